@@ -1,4 +1,5 @@
 fn main() {
+    #[cfg(feature = "winsock")]
     windows::build!(
         Windows::Win32::Networking::WinSock::*,
         Windows::Win32::System::SystemServices::*,
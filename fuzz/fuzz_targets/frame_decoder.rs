@@ -0,0 +1,34 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use online_game_programming::frame::FrameDecoder;
+
+fuzz_target!(|data: &[u8]| {
+    let (sizes, payload) = data.split_at(data.len().min(8));
+    let mut decoder = FrameDecoder::with_max_frame_size(usize::MAX);
+    let mut frames = vec![];
+    let mut rest = payload;
+    for size in sizes.iter().cycle().take(payload.len() + 1) {
+        let (chunk, remaining) = rest.split_at((*size as usize).min(rest.len()));
+        decoder.push(chunk);
+        rest = remaining;
+        while let Some(frame) = decoder.next_frame().expect("Frames are unbounded.") {
+            assert!(!frame.contains(&0));
+            frames.push(frame);
+        }
+        if rest.is_empty() {
+            break;
+        }
+    }
+    decoder.push(rest);
+    while let Some(frame) = decoder.next_frame().expect("Frames are unbounded.") {
+        frames.push(frame);
+    }
+
+    let terminated = payload.iter().filter(|b| **b == 0).count();
+    assert_eq!(frames.len(), terminated);
+    let rejoined = frames
+        .iter()
+        .flat_map(|frame| frame.iter().copied().chain(std::iter::once(0)))
+        .collect::<Vec<_>>();
+    assert!(payload.starts_with(&rejoined));
+});
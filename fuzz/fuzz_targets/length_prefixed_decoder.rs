@@ -0,0 +1,41 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use online_game_programming::frame::{length_prefixed, LengthPrefixedDecoder};
+
+fuzz_target!(|data: &[u8]| {
+    let (sizes, payload) = data.split_at(data.len().min(8));
+    let frames = payload
+        .chunks(16)
+        .map(|chunk| chunk.to_vec())
+        .collect::<Vec<_>>();
+    let stream = frames
+        .iter()
+        .flat_map(|frame| length_prefixed(frame))
+        .collect::<Vec<_>>();
+
+    let mut decoder = LengthPrefixedDecoder::with_max_frame_size(16);
+    let mut decoded = vec![];
+    let mut rest = stream.as_slice();
+    for size in sizes.iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (chunk, remaining) = rest.split_at((*size as usize).clamp(1, rest.len()));
+        decoder.push(chunk);
+        rest = remaining;
+        while let Some(frame) = decoder.next_frame().expect("Chunks fit the frame limit.") {
+            decoded.push(frame);
+        }
+    }
+    if sizes.is_empty() {
+        decoder.push(rest);
+        while let Some(frame) = decoder.next_frame().expect("Chunks fit the frame limit.") {
+            decoded.push(frame);
+        }
+    }
+    assert_eq!(decoded, frames);
+
+    let mut garbage = LengthPrefixedDecoder::new();
+    garbage.push(data);
+    while let Ok(Some(_)) = garbage.next_frame() {}
+});
@@ -0,0 +1,141 @@
+use crate::announcements::{parse_announcement, parse_interval, Schedule};
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdminCommand {
+    Broadcast(String),
+    Kick(u32),
+    Ban {
+        target: String,
+        reason: String,
+    },
+    Unban(String),
+    Stats(String),
+    Clients,
+    IpStats(Option<String>),
+    ReportMatch {
+        room: String,
+        scores: Vec<(String, i64)>,
+    },
+    StartMatch(String),
+    Score {
+        room: String,
+        player: String,
+        points: i64,
+    },
+    EndMatch(String),
+    Snapshot,
+    Transfer {
+        client_id: Option<u32>,
+        peer: String,
+    },
+    Announce {
+        schedule: Schedule,
+        text: String,
+    },
+    Announcements,
+    CancelAnnouncement(u32),
+    Countdown {
+        delay: Duration,
+        label: String,
+    },
+    Events,
+    CancelEvent(u32),
+    Shutdown,
+}
+
+impl AdminCommand {
+    pub fn parse(input: &str) -> Option<AdminCommand> {
+        let input = input.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        let (command, args) = match input.find(char::is_whitespace) {
+            Some(index) => (&input[..index], input[index..].trim()),
+            None => (input, ""),
+        };
+        match command {
+            "broadcast" | "say" if !args.is_empty() => {
+                Some(AdminCommand::Broadcast(args.to_string()))
+            }
+            "kick" => args.parse::<u32>().ok().map(AdminCommand::Kick),
+            "ban" if !args.is_empty() => {
+                let mut parts = args.splitn(2, char::is_whitespace);
+                let target = parts.next().unwrap_or_default().to_string();
+                let reason = parts.next().unwrap_or_default().trim().to_string();
+                Some(AdminCommand::Ban { target, reason })
+            }
+            "unban" if !args.is_empty() => Some(AdminCommand::Unban(args.to_string())),
+            "stats" if !args.is_empty() => Some(AdminCommand::Stats(args.to_string())),
+            "clients" => Some(AdminCommand::Clients),
+            "ipstats" if args.is_empty() => Some(AdminCommand::IpStats(None)),
+            "ipstats" => Some(AdminCommand::IpStats(Some(args.to_string()))),
+            "startmatch" if !args.is_empty() => Some(AdminCommand::StartMatch(args.to_string())),
+            "score" => {
+                let mut parts = args.split_whitespace();
+                match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                    (Some(room), Some(player), Some(points), None) => Some(AdminCommand::Score {
+                        room: room.to_string(),
+                        player: player.to_string(),
+                        points: points.parse().ok()?,
+                    }),
+                    _ => None,
+                }
+            }
+            "endmatch" if !args.is_empty() => Some(AdminCommand::EndMatch(args.to_string())),
+            "snapshot" => Some(AdminCommand::Snapshot),
+            "transfer" => {
+                let mut parts = args.split_whitespace();
+                let client_id = match parts.next()? {
+                    "all" => None,
+                    id => Some(id.parse().ok()?),
+                };
+                match (parts.next(), parts.next()) {
+                    (Some(peer), None) => Some(AdminCommand::Transfer {
+                        client_id,
+                        peer: peer.to_string(),
+                    }),
+                    _ => None,
+                }
+            }
+            "announce" => parse_announcement(args)
+                .map(|(schedule, text)| AdminCommand::Announce { schedule, text }),
+            "announcements" => Some(AdminCommand::Announcements),
+            "unannounce" => args
+                .parse::<u32>()
+                .ok()
+                .map(AdminCommand::CancelAnnouncement),
+            "countdown" => {
+                let mut parts = args.splitn(2, char::is_whitespace);
+                let delay = parse_interval(parts.next()?)?;
+                let label = parts.next()?.trim();
+                if label.is_empty() {
+                    None
+                } else {
+                    Some(AdminCommand::Countdown {
+                        delay,
+                        label: label.to_string(),
+                    })
+                }
+            }
+            "events" => Some(AdminCommand::Events),
+            "cancelevent" => args.parse::<u32>().ok().map(AdminCommand::CancelEvent),
+            "shutdown" => Some(AdminCommand::Shutdown),
+            "match" => {
+                let mut parts = args.split_whitespace();
+                let room = parts.next()?.to_string();
+                let scores = parts
+                    .map(|entry| {
+                        let mut fields = entry.splitn(2, ':');
+                        let name = fields.next()?.to_string();
+                        let score = fields.next()?.parse::<i64>().ok()?;
+                        Some((name, score))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if scores.is_empty() {
+                    None
+                } else {
+                    Some(AdminCommand::ReportMatch { room, scores })
+                }
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use super::AdminCommand;
+#[cfg(feature = "winsock")]
+use crate::bindings::Windows::Win32::Networking::WinSock::WSACleanup;
+use crate::context::ServerContext;
+use crate::events::ServerEvent;
+use crate::ip_stats::DEFAULT_TOP as IP_STATS_TOP;
+use crate::leaderboard::MatchStanding;
+use crate::scoreboard::GameEvent;
+use crate::session::{finish_match, send_prioritized, send_text, start_match};
+use crate::snapshot::Snapshotter;
+use crate::transport::SendPriority;
+use std::sync::mpsc::Receiver;
+use std::time::SystemTime;
+
+pub fn save_snapshot(snapshotter: Option<&Snapshotter>) {
+    match snapshotter.map(Snapshotter::save) {
+        Some(Ok(snapshot)) => println!(
+            "サーバーの状態を保存しました（ルーム{}、セッション{}、BAN{}）\n",
+            snapshot.rooms.len(),
+            snapshot.sessions.len(),
+            snapshot.bans.len()
+        ),
+        Some(Err(e)) => eprintln!("サーバーの状態の保存に失敗しました：{}\n", e),
+        None => eprintln!("SNAPSHOT_PATHが未設定のため、状態を保存できません\n"),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn store_ban(context: &ServerContext, target: &str, reason: &str) {
+    match context.storage.as_ref() {
+        Some(storage) => match storage.add_ban(target, reason, None) {
+            Ok(()) => {
+                context.ip_stats.record_ban(target);
+                println!("{}をBANしました\n", target)
+            }
+            Err(e) => eprintln!("BANの保存に失敗しました：{}\n", e),
+        },
+        None => eprintln!("ストレージが無効のため、BANできません\n"),
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn store_ban(_context: &ServerContext, _target: &str, _reason: &str) {
+    eprintln!("ストレージが無効のため、BANできません\n");
+}
+
+#[cfg(feature = "sqlite")]
+fn remove_ban(context: &ServerContext, target: &str) {
+    if let Some(storage) = context.storage.as_ref() {
+        match storage.remove_ban(target) {
+            Ok(true) => println!("{}のBANを解除しました\n", target),
+            Ok(false) => println!("{}はBANされていません\n", target),
+            Err(e) => eprintln!("BANの解除に失敗しました：{}\n", e),
+        }
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn remove_ban(_context: &ServerContext, _target: &str) {}
+
+fn transfer_clients(context: &ServerContext, client_id: Option<u32>, peer: &str) {
+    let transfer = match context.transfer.as_ref() {
+        Some(transfer) => transfer,
+        None => {
+            eprintln!("転送サービスが無効のため、クライアントを移動できません\n");
+            return;
+        }
+    };
+    let peer = match transfer.peer(peer) {
+        Some(peer) => peer,
+        None => {
+            eprintln!("転送先{}が見つかりません\n", peer);
+            return;
+        }
+    };
+    for (id, _, _, _) in context.clients.connected() {
+        if client_id.is_some_and(|client_id| client_id != id) {
+            continue;
+        }
+        let client = match context.clients.get(id) {
+            Some(client) => client,
+            None => continue,
+        };
+        match transfer.transfer_client(peer, &client) {
+            Ok(_) => println!("クライアント{}を{}へ転送しました\n", id, &peer.id),
+            Err(e) => eprintln!("クライアント{}の転送に失敗しました：{}\n", id, e),
+        }
+    }
+}
+
+pub fn spawn_admin_handler(commands: Receiver<AdminCommand>, context: ServerContext) {
+    std::thread::spawn(move || {
+        for command in commands.iter() {
+            match &command {
+                AdminCommand::Ban { target, reason } => store_ban(&context, target, reason),
+                AdminCommand::Unban(target) => {
+                    remove_ban(&context, target);
+                    continue;
+                }
+                AdminCommand::Stats(name) => {
+                    let stats = context.accounts.as_ref().map(|accounts| {
+                        accounts
+                            .find_account(name)
+                            .and_then(|account| match account {
+                                Some(account) => accounts.stats(account.id).map(Some),
+                                None => Ok(None),
+                            })
+                    });
+                    match stats {
+                        Some(Ok(Some(stats))) => println!(
+                            "{}：試合数{}、勝利数{}、合計スコア{}\n",
+                            name, stats.matches_played, stats.wins, stats.total_score
+                        ),
+                        Some(Ok(None)) => println!("アカウント{}は存在しません\n", name),
+                        Some(Err(e)) => eprintln!("統計の取得に失敗しました：{}\n", e),
+                        None => eprintln!("ストレージが無効のため、統計を取得できません\n"),
+                    }
+                    for (client_id, _, latency) in context
+                        .clients
+                        .latencies()
+                        .into_iter()
+                        .filter(|(_, client_name, _)| client_name == name)
+                    {
+                        println!(
+                            "クライアント{}の通信遅延：現在{}ms、平均{}ms、p50 {}ms、p95 {}ms、p99 {}ms\n",
+                            client_id,
+                            latency.current.as_millis(),
+                            latency.average.as_millis(),
+                            latency.p50.as_millis(),
+                            latency.p95.as_millis(),
+                            latency.p99.as_millis()
+                        );
+                    }
+                    continue;
+                }
+                AdminCommand::ReportMatch { room, scores } => {
+                    let leaderboard = match context.leaderboard.as_ref() {
+                        Some(leaderboard) => leaderboard,
+                        None => {
+                            eprintln!("ストレージが無効のため、試合結果を記録できません\n");
+                            continue;
+                        }
+                    };
+                    let standings = scores
+                        .iter()
+                        .map(|(name, score)| match leaderboard.find_account_id(name) {
+                            Ok(Some(account_id)) => Ok(MatchStanding {
+                                account_id,
+                                score: *score,
+                            }),
+                            Ok(None) => Err(format!("アカウント{}は存在しません", name)),
+                            Err(e) => Err(e.to_string()),
+                        })
+                        .collect::<Result<Vec<_>, _>>();
+                    match standings {
+                        Ok(standings) => {
+                            println!("{}の試合結果を受信しました\n", room);
+                            let _ = context.events.send(ServerEvent::MatchEnded {
+                                room: room.clone(),
+                                standings,
+                            });
+                        }
+                        Err(e) => eprintln!("試合結果を記録できません：{}\n", e),
+                    }
+                    continue;
+                }
+                AdminCommand::StartMatch(room) => {
+                    start_match(&context, room);
+                    println!("ルーム{}の試合を開始しました\n", room);
+                    continue;
+                }
+                AdminCommand::Score {
+                    room,
+                    player,
+                    points,
+                } => {
+                    let account_id = context
+                        .leaderboard
+                        .as_ref()
+                        .and_then(|leaderboard| leaderboard.find_account_id(player).ok().flatten());
+                    let event = GameEvent::Scored {
+                        player: player.clone(),
+                        account_id,
+                        points: *points,
+                    };
+                    if context.scoreboards.record(room, event) {
+                        println!("{}に{}点を加算しました\n", player, points);
+                    } else {
+                        eprintln!("ルーム{}では試合が行われていません\n", room);
+                    }
+                    continue;
+                }
+                AdminCommand::EndMatch(room) => {
+                    if finish_match(&context, room) {
+                        println!("ルーム{}の試合を終了しました\n", room);
+                    } else {
+                        eprintln!("ルーム{}では試合が行われていません\n", room);
+                    }
+                    continue;
+                }
+                AdminCommand::Clients => {
+                    let clients = context.clients.locations();
+                    if clients.is_empty() {
+                        println!("接続中のクライアントはいません\n");
+                    }
+                    for (client_id, address, name, location) in clients {
+                        match location {
+                            Some(location) => println!(
+                                "クライアント{}：{}（{}、{}）\n",
+                                client_id, name, address, location
+                            ),
+                            None => {
+                                println!("クライアント{}：{}（{}）\n", client_id, name, address)
+                            }
+                        }
+                    }
+                    continue;
+                }
+                AdminCommand::IpStats(address) => {
+                    let records = match address {
+                        Some(address) => context
+                            .ip_stats
+                            .get(address)
+                            .map(|record| vec![(address.clone(), record)])
+                            .unwrap_or_default(),
+                        None => context.ip_stats.top(IP_STATS_TOP),
+                    };
+                    if records.is_empty() {
+                        println!("IP統計はありません\n");
+                    }
+                    for (address, record) in records {
+                        println!(
+                            "{}：接続{}回、失敗{}回、受信{}バイト、BAN{}回（初回{}、最終{}）\n",
+                            address,
+                            record.connects,
+                            record.failures,
+                            record.bytes_received,
+                            record.bans,
+                            record.first_seen,
+                            record.last_seen
+                        );
+                    }
+                    continue;
+                }
+                AdminCommand::Snapshot => {
+                    save_snapshot(context.snapshotter.as_ref());
+                    continue;
+                }
+                AdminCommand::Transfer { client_id, peer } => {
+                    transfer_clients(&context, *client_id, peer);
+                    continue;
+                }
+                AdminCommand::Announce { schedule, text } => {
+                    let id = context
+                        .announcements
+                        .add(*schedule, text.clone(), SystemTime::now());
+                    println!(
+                        "告知{}を登録しました（{}）：{}\n",
+                        id,
+                        schedule.describe(),
+                        text
+                    );
+                    continue;
+                }
+                AdminCommand::Announcements => {
+                    let announcements = context.announcements.list();
+                    if announcements.is_empty() {
+                        println!("登録されている告知はありません\n");
+                    }
+                    for announcement in announcements {
+                        println!(
+                            "告知{}（{}）：{}\n",
+                            announcement.id,
+                            announcement.schedule.describe(),
+                            &announcement.text
+                        );
+                    }
+                    continue;
+                }
+                AdminCommand::CancelAnnouncement(id) => {
+                    if context.announcements.remove(*id) {
+                        println!("告知{}を取り消しました\n", id);
+                    } else {
+                        eprintln!("告知{}は登録されていません\n", id);
+                    }
+                    continue;
+                }
+                AdminCommand::Countdown { delay, label } => {
+                    let event = context
+                        .clock
+                        .schedule(*delay, label.clone(), SystemTime::now());
+                    println!(
+                        "予定イベント{}をティック{}に登録しました：{}\n",
+                        event.id, event.tick, &event.label
+                    );
+                    for (_, _, transport, _) in context.clients.connected() {
+                        send_text(&transport, &event.encode());
+                    }
+                    continue;
+                }
+                AdminCommand::Events => {
+                    let events = context.clock.upcoming();
+                    if events.is_empty() {
+                        println!("登録されている予定イベントはありません\n");
+                    }
+                    for event in events {
+                        println!(
+                            "予定イベント{}（ティック{}、現在{}）：{}\n",
+                            event.id,
+                            event.tick,
+                            context.clock.tick(),
+                            &event.label
+                        );
+                    }
+                    continue;
+                }
+                AdminCommand::CancelEvent(id) => {
+                    if context.clock.cancel(*id) {
+                        println!("予定イベント{}を取り消しました\n", id);
+                        for (_, _, transport, _) in context.clients.connected() {
+                            send_text(&transport, &format!("EVENT_CANCEL {}", id));
+                        }
+                    } else {
+                        eprintln!("予定イベント{}は登録されていません\n", id);
+                    }
+                    continue;
+                }
+                AdminCommand::Shutdown => {
+                    println!("管理コマンドでサーバーを終了します\n");
+                    for (_, _, transport, _) in context.clients.connected() {
+                        send_prioritized(
+                            &transport,
+                            "[Server] Server is shutting down.",
+                            SendPriority::Control,
+                        );
+                        transport.shutdown();
+                    }
+                    save_snapshot(context.snapshotter.as_ref());
+                    if let Err(e) = context.ip_stats.save() {
+                        eprintln!("IP統計の保存に失敗しました：{}\n", e);
+                    }
+                    #[cfg(feature = "winsock")]
+                    unsafe {
+                        WSACleanup();
+                    }
+                    std::process::exit(0);
+                }
+                _ => {}
+            }
+
+            for (client_id, ip_address, transport, _) in context.clients.connected() {
+                let kick = match &command {
+                    AdminCommand::Broadcast(text) => {
+                        send_text(&transport, &format!("[Server] {}", text));
+                        false
+                    }
+                    AdminCommand::Kick(id) => *id == client_id,
+                    AdminCommand::Ban { target, .. } => *target == ip_address,
+                    AdminCommand::Unban(_)
+                    | AdminCommand::Stats(_)
+                    | AdminCommand::Clients
+                    | AdminCommand::IpStats(_)
+                    | AdminCommand::ReportMatch { .. }
+                    | AdminCommand::Snapshot
+                    | AdminCommand::Transfer { .. }
+                    | AdminCommand::Announce { .. }
+                    | AdminCommand::Announcements
+                    | AdminCommand::CancelAnnouncement(_)
+                    | AdminCommand::Countdown { .. }
+                    | AdminCommand::Events
+                    | AdminCommand::CancelEvent(_)
+                    | AdminCommand::StartMatch(_)
+                    | AdminCommand::Score { .. }
+                    | AdminCommand::EndMatch(_)
+                    | AdminCommand::Shutdown => false,
+                };
+                if kick {
+                    println!("管理コマンドでクライアント{}を切断します\n", client_id);
+                    send_prioritized(&transport, "Kicked by server.", SendPriority::Control);
+                    transport.shutdown();
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,180 @@
+use crate::clients::ClientRegistry;
+use crate::events::escape_json;
+use crate::leaderboard::{entries_to_json, Leaderboard, DEFAULT_RADIUS, DEFAULT_TOP};
+use crate::metrics::Metrics;
+use crate::p2p::{relay_stats_to_json, RelayServer};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::JoinHandle;
+
+const MAX_REQUEST_SIZE: usize = 8192;
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn json(body: String) -> Self {
+        HttpResponse { status: 200, body }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        HttpResponse {
+            status,
+            body: format!("{{\"error\":\"{}\"}}", message),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "Unknown",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AdminApi {
+    pub leaderboard: Option<Leaderboard>,
+    pub metrics: Option<Metrics>,
+    pub relay: Option<RelayServer>,
+    pub clients: Option<ClientRegistry>,
+}
+
+fn latencies_to_json(clients: &ClientRegistry) -> String {
+    let entries = clients
+        .latencies()
+        .iter()
+        .map(|(client_id, name, summary)| {
+            format!(
+                "{{\"client_id\":{},\"name\":\"{}\",\"latency\":{}}}",
+                client_id,
+                escape_json(name),
+                summary.to_json()
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", entries.join(","))
+}
+
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| {
+        let mut parts = pair.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(k), Some(v)) if k == key => Some(v),
+            _ => None,
+        }
+    })
+}
+
+impl AdminApi {
+    pub fn route(&self, method: &str, path: &str, query: &str) -> HttpResponse {
+        if method != "GET" {
+            return HttpResponse::error(405, "method not allowed");
+        }
+        let segments = path
+            .trim_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        match segments.as_slice() {
+            ["leaderboard"] => self.leaderboard_top(query),
+            ["leaderboard", name] => self.leaderboard_around(name, query),
+            ["metrics"] => match self.metrics.as_ref() {
+                Some(metrics) => HttpResponse::json(metrics.to_json()),
+                None => HttpResponse::error(503, "metrics unavailable"),
+            },
+            ["relay"] => match self.relay.as_ref() {
+                Some(relay) => HttpResponse::json(relay_stats_to_json(&relay.stats())),
+                None => HttpResponse::error(503, "relay unavailable"),
+            },
+            ["latency"] => match self.clients.as_ref() {
+                Some(clients) => HttpResponse::json(latencies_to_json(clients)),
+                None => HttpResponse::error(503, "clients unavailable"),
+            },
+            _ => HttpResponse::error(404, "not found"),
+        }
+    }
+
+    fn leaderboard_top(&self, query: &str) -> HttpResponse {
+        let leaderboard = match self.leaderboard.as_ref() {
+            Some(leaderboard) => leaderboard,
+            None => return HttpResponse::error(503, "leaderboard unavailable"),
+        };
+        let limit = query_param(query, "limit")
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_TOP);
+        match leaderboard.top(limit) {
+            Ok(entries) => HttpResponse::json(entries_to_json(&entries)),
+            Err(_) => HttpResponse::error(500, "storage error"),
+        }
+    }
+
+    fn leaderboard_around(&self, name: &str, query: &str) -> HttpResponse {
+        let leaderboard = match self.leaderboard.as_ref() {
+            Some(leaderboard) => leaderboard,
+            None => return HttpResponse::error(503, "leaderboard unavailable"),
+        };
+        let radius = query_param(query, "radius")
+            .and_then(|radius| radius.parse().ok())
+            .unwrap_or(DEFAULT_RADIUS);
+        match leaderboard.around_name(name, radius) {
+            Ok(Some(entries)) => HttpResponse::json(entries_to_json(&entries)),
+            Ok(None) => HttpResponse::error(404, "account not found"),
+            Err(_) => HttpResponse::error(500, "storage error"),
+        }
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut request = Vec::with_capacity(1024);
+        let mut buffer = [0_u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        let request = String::from_utf8_lossy(&request);
+        let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+        let response = match (request_line.next(), request_line.next()) {
+            (Some(method), Some(target)) => {
+                let mut target = target.splitn(2, '?');
+                let path = target.next().unwrap_or_default();
+                let query = target.next().unwrap_or_default();
+                self.route(method, path, query)
+            }
+            _ => HttpResponse::error(400, "bad request"),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.body.len(),
+            response.body
+        )
+    }
+
+    pub fn spawn(self, address: &str) -> std::io::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(address)?;
+        Ok(std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let api = self.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = api.handle_connection(stream) {
+                        eprintln!("管理APIのリクエスト処理に失敗しました：{}", e);
+                    }
+                });
+            }
+        }))
+    }
+}
@@ -0,0 +1,6 @@
+mod command;
+mod handler;
+mod http;
+pub use command::*;
+pub use handler::*;
+pub use http::*;
@@ -0,0 +1,239 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    Every(Duration),
+    DailyAt(u64),
+}
+
+pub(crate) fn parse_interval(input: &str) -> Option<Duration> {
+    let (digits, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => input.split_at(index),
+        None => (input, "s"),
+    };
+    let value = digits.parse::<u64>().ok().filter(|value| *value > 0)?;
+    let seconds = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+fn parse_time_of_day(input: &str) -> Option<u64> {
+    let (hours, minutes) = input.split_once(':')?;
+    let hours = hours.parse::<u64>().ok().filter(|hours| *hours < 24)?;
+    let minutes = minutes
+        .parse::<u64>()
+        .ok()
+        .filter(|minutes| *minutes < 60)?;
+    Some(hours * 60 * 60 + minutes * 60)
+}
+
+impl Schedule {
+    pub fn parse(kind: &str, value: &str) -> Option<Schedule> {
+        match kind {
+            "every" => parse_interval(value).map(Schedule::Every),
+            "at" => parse_time_of_day(value).map(Schedule::DailyAt),
+            _ => None,
+        }
+    }
+
+    fn next_after(&self, now: SystemTime) -> SystemTime {
+        match *self {
+            Schedule::Every(interval) => now + interval,
+            Schedule::DailyAt(time_of_day) => {
+                let seconds = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                let today = seconds - seconds % SECONDS_PER_DAY;
+                let mut next = today + time_of_day;
+                if next <= seconds {
+                    next += SECONDS_PER_DAY;
+                }
+                UNIX_EPOCH + Duration::from_secs(next)
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Schedule::Every(interval) => format!("every {}s", interval.as_secs()),
+            Schedule::DailyAt(time_of_day) => format!(
+                "at {:02}:{:02} UTC",
+                time_of_day / (60 * 60),
+                time_of_day / 60 % 60
+            ),
+        }
+    }
+}
+
+pub fn parse_announcements(input: &str) -> Vec<(Schedule, String)> {
+    input
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let announcement = parse_announcement(entry);
+            if announcement.is_none() {
+                eprintln!("告知の設定を解析できませんでした：{}\n", entry);
+            }
+            announcement
+        })
+        .collect()
+}
+
+pub fn parse_announcement(input: &str) -> Option<(Schedule, String)> {
+    let mut parts = input.trim().splitn(3, char::is_whitespace);
+    let schedule = Schedule::parse(parts.next()?, parts.next()?)?;
+    let text = parts.next()?.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some((schedule, text.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Announcement {
+    pub id: u32,
+    pub schedule: Schedule,
+    pub text: String,
+    next_due: SystemTime,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    announcements: Vec<Announcement>,
+    next_id: u32,
+}
+
+#[derive(Clone, Default)]
+pub struct AnnouncementScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+impl AnnouncementScheduler {
+    pub fn new() -> Self {
+        AnnouncementScheduler::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SchedulerState> {
+        self.state.lock().expect("Failed to lock announcements.")
+    }
+
+    pub fn add(&self, schedule: Schedule, text: String, now: SystemTime) -> u32 {
+        let mut state = self.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.announcements.push(Announcement {
+            id,
+            schedule,
+            text,
+            next_due: schedule.next_after(now),
+        });
+        id
+    }
+
+    pub fn remove(&self, id: u32) -> bool {
+        let mut state = self.lock();
+        let before = state.announcements.len();
+        state
+            .announcements
+            .retain(|announcement| announcement.id != id);
+        state.announcements.len() != before
+    }
+
+    pub fn list(&self) -> Vec<Announcement> {
+        self.lock().announcements.clone()
+    }
+
+    pub fn due(&self, now: SystemTime) -> Vec<String> {
+        self.lock()
+            .announcements
+            .iter_mut()
+            .filter(|announcement| announcement.next_due <= now)
+            .map(|announcement| {
+                announcement.next_due = announcement.schedule.next_after(now);
+                announcement.text.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn schedules_are_parsed_from_admin_syntax() {
+        assert_eq!(
+            parse_announcement("every 10m Restart in ten minutes"),
+            Some((
+                Schedule::Every(Duration::from_secs(600)),
+                "Restart in ten minutes".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_announcement("at 18:30 イベント開始"),
+            Some((
+                Schedule::DailyAt(18 * 3600 + 30 * 60),
+                "イベント開始".to_string()
+            ))
+        );
+        assert_eq!(parse_announcement("every 0s nothing"), None);
+        assert_eq!(parse_announcement("at 24:00 late"), None);
+        assert_eq!(parse_announcement("every 5m"), None);
+        assert_eq!(
+            Schedule::DailyAt(9 * 3600 + 5 * 60).describe(),
+            "at 09:05 UTC"
+        );
+        assert_eq!(
+            parse_announcements("every 1h Drink water; ;at 7:00 Morning"),
+            vec![
+                (
+                    Schedule::Every(Duration::from_secs(3600)),
+                    "Drink water".to_string()
+                ),
+                (Schedule::DailyAt(7 * 3600), "Morning".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn announcements_fire_when_due_and_reschedule() {
+        let scheduler = AnnouncementScheduler::new();
+        let start = at(SECONDS_PER_DAY * 10 + 12 * 3600);
+        let every = scheduler.add(
+            Schedule::Every(Duration::from_secs(60)),
+            "tick".to_string(),
+            start,
+        );
+        scheduler.add(
+            Schedule::DailyAt(12 * 3600 + 120),
+            "noon".to_string(),
+            start,
+        );
+
+        assert!(scheduler.due(start + Duration::from_secs(59)).is_empty());
+        assert_eq!(scheduler.due(start + Duration::from_secs(60)), vec!["tick"]);
+        assert_eq!(
+            scheduler.due(start + Duration::from_secs(120)),
+            vec!["tick", "noon"]
+        );
+        assert!(scheduler.due(start + Duration::from_secs(150)).is_empty());
+
+        assert!(scheduler.remove(every));
+        assert!(!scheduler.remove(every));
+        assert!(scheduler.due(start + Duration::from_secs(180)).is_empty());
+        assert_eq!(
+            scheduler.due(start + Duration::from_secs(SECONDS_PER_DAY + 120)),
+            vec!["noon"]
+        );
+    }
+}
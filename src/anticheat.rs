@@ -0,0 +1,376 @@
+use crate::config::env_or;
+use crate::snapshot::escape_field;
+use crate::storage::unix_now;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const INPUT_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct AntiCheatConfig {
+    pub tick_interval: Duration,
+    pub max_distance_per_tick: Option<f64>,
+    pub max_inputs_per_second: Option<usize>,
+    pub cooldowns: HashMap<String, Duration>,
+    pub kick_after: Option<u32>,
+    pub evidence: Option<PathBuf>,
+}
+
+impl AntiCheatConfig {
+    pub fn from_env(tick_interval: Duration) -> Option<Self> {
+        let config = AntiCheatConfig {
+            tick_interval,
+            max_distance_per_tick: env_or("ANTICHEAT_MAX_DISTANCE", "")
+                .parse()
+                .ok()
+                .filter(|distance: &f64| *distance > 0.0),
+            max_inputs_per_second: env_or("ANTICHEAT_MAX_INPUT_RATE", "")
+                .parse()
+                .ok()
+                .filter(|rate| *rate > 0),
+            cooldowns: parse_cooldowns(&env_or("ANTICHEAT_COOLDOWNS", "")),
+            kick_after: env_or("ANTICHEAT_KICK_AFTER", "")
+                .parse()
+                .ok()
+                .filter(|count| *count > 0),
+            evidence: std::env::var("ANTICHEAT_EVIDENCE").ok().map(PathBuf::from),
+        };
+        if config.max_distance_per_tick.is_none()
+            && config.max_inputs_per_second.is_none()
+            && config.cooldowns.is_empty()
+        {
+            return None;
+        }
+        Some(config)
+    }
+}
+
+pub fn parse_cooldowns(input: &str) -> HashMap<String, Duration> {
+    input
+        .split(',')
+        .filter_map(|entry| {
+            let (ability, millis) = entry.trim().split_once(':')?;
+            Some((
+                ability.to_string(),
+                Duration::from_millis(millis.parse().ok()?),
+            ))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AbilityCommand(pub String);
+
+impl AbilityCommand {
+    pub fn parse(input: &str) -> Option<AbilityCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":ability", Some(ability), None) => Some(AbilityCommand(ability.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    Speed {
+        distance: f64,
+        allowed: f64,
+    },
+    InputRate {
+        inputs: usize,
+        limit: usize,
+    },
+    Cooldown {
+        ability: String,
+        remaining: Duration,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Speed { distance, allowed } => write!(
+                f,
+                "moved {:.1} units where at most {:.1} were possible",
+                distance, allowed
+            ),
+            Violation::InputRate { inputs, limit } => write!(
+                f,
+                "sent {} inputs within a second (limit {})",
+                inputs, limit
+            ),
+            Violation::Cooldown { ability, remaining } => write!(
+                f,
+                "used {} {}ms before its cooldown ended",
+                ability,
+                remaining.as_millis()
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Allowed,
+    Flagged(Violation),
+    Kick(Violation),
+}
+
+#[derive(Debug)]
+struct History {
+    last_move: Instant,
+    inputs: VecDeque<Instant>,
+    abilities: HashMap<String, Instant>,
+    violations: u32,
+}
+
+impl History {
+    fn new(last_move: Instant) -> Self {
+        History {
+            last_move,
+            inputs: VecDeque::new(),
+            abilities: HashMap::new(),
+            violations: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AntiCheat {
+    config: AntiCheatConfig,
+    players: Arc<Mutex<HashMap<u32, History>>>,
+    evidence: Option<Arc<Mutex<File>>>,
+}
+
+impl AntiCheat {
+    pub fn new(config: AntiCheatConfig) -> std::io::Result<Self> {
+        let evidence = match config.evidence.as_ref() {
+            Some(path) => Some(Arc::new(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            ))),
+            None => None,
+        };
+        Ok(AntiCheat {
+            config,
+            players: Arc::new(Mutex::new(HashMap::new())),
+            evidence,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u32, History>> {
+        self.players
+            .lock()
+            .expect("Failed to lock anti-cheat history.")
+    }
+
+    pub fn track(&self, client_id: u32, now: Instant) {
+        self.lock().insert(client_id, History::new(now));
+    }
+
+    pub fn forget(&self, client_id: u32) {
+        self.lock().remove(&client_id);
+    }
+
+    pub fn check_move(
+        &self,
+        client_id: u32,
+        player: &str,
+        from: (i32, i32),
+        to: (i32, i32),
+        now: Instant,
+    ) -> Verdict {
+        let tick = self.config.tick_interval;
+        let mut players = self.lock();
+        let history = players
+            .entry(client_id)
+            .or_insert_with(|| History::new(now.checked_sub(tick).unwrap_or(now)));
+        let violation = self.check_input_rate(history, now).or_else(|| {
+            let max_distance = self.config.max_distance_per_tick?;
+            let elapsed = now.saturating_duration_since(history.last_move);
+            let ticks = (elapsed.as_secs_f64() / tick.as_secs_f64().max(f64::EPSILON))
+                .ceil()
+                .max(1.0);
+            let allowed = max_distance * ticks;
+            let distance =
+                (f64::from(to.0) - f64::from(from.0)).hypot(f64::from(to.1) - f64::from(from.1));
+            (distance > allowed).then_some(Violation::Speed { distance, allowed })
+        });
+        if violation.is_none() {
+            history.last_move = now;
+        }
+        self.judge(client_id, player, history, violation)
+    }
+
+    pub fn check_ability(
+        &self,
+        client_id: u32,
+        player: &str,
+        ability: &str,
+        now: Instant,
+    ) -> Verdict {
+        let mut players = self.lock();
+        let history = players
+            .entry(client_id)
+            .or_insert_with(|| History::new(now));
+        let violation = self.check_input_rate(history, now).or_else(|| {
+            let cooldown = self.config.cooldowns.get(ability).copied()?;
+            let ready_at = *history.abilities.get(ability)? + cooldown;
+            (now < ready_at).then(|| Violation::Cooldown {
+                ability: ability.to_string(),
+                remaining: ready_at - now,
+            })
+        });
+        if violation.is_none() {
+            history.abilities.insert(ability.to_string(), now);
+        }
+        self.judge(client_id, player, history, violation)
+    }
+
+    fn check_input_rate(&self, history: &mut History, now: Instant) -> Option<Violation> {
+        let limit = self.config.max_inputs_per_second?;
+        while history
+            .inputs
+            .front()
+            .is_some_and(|input| now.saturating_duration_since(*input) >= INPUT_WINDOW)
+        {
+            history.inputs.pop_front();
+        }
+        history.inputs.push_back(now);
+        (history.inputs.len() > limit).then_some(Violation::InputRate {
+            inputs: history.inputs.len(),
+            limit,
+        })
+    }
+
+    fn judge(
+        &self,
+        client_id: u32,
+        player: &str,
+        history: &mut History,
+        violation: Option<Violation>,
+    ) -> Verdict {
+        let violation = match violation {
+            Some(violation) => violation,
+            None => return Verdict::Allowed,
+        };
+        history.violations += 1;
+        self.record(client_id, player, history.violations, &violation);
+        match self.config.kick_after {
+            Some(kick_after) if history.violations >= kick_after => Verdict::Kick(violation),
+            _ => Verdict::Flagged(violation),
+        }
+    }
+
+    fn record(&self, client_id: u32, player: &str, count: u32, violation: &Violation) {
+        let evidence = match self.evidence.as_ref() {
+            Some(evidence) => evidence,
+            None => return,
+        };
+        let line = format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            unix_now(),
+            client_id,
+            escape_field(player),
+            count,
+            escape_field(&violation.to_string())
+        );
+        let mut file = evidence
+            .lock()
+            .expect("Failed to lock anti-cheat evidence.");
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            eprintln!("不正行為の証拠の記録に失敗しました：{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AntiCheatConfig {
+        AntiCheatConfig {
+            tick_interval: Duration::from_millis(100),
+            max_distance_per_tick: Some(5.0),
+            max_inputs_per_second: Some(4),
+            cooldowns: parse_cooldowns("dash:2000, blink:x"),
+            kick_after: Some(3),
+            evidence: None,
+        }
+    }
+
+    #[test]
+    fn impossible_moves_and_abilities_are_flagged_then_kicked() {
+        assert_eq!(
+            AbilityCommand::parse(":ability dash\0"),
+            Some(AbilityCommand("dash".to_string()))
+        );
+        assert_eq!(config().cooldowns.len(), 1);
+        let anticheat = AntiCheat::new(config()).expect("Failed to create anti-cheat.");
+        let start = Instant::now();
+        anticheat.track(1, start);
+
+        let at = |millis| start + Duration::from_millis(millis);
+        assert_eq!(
+            anticheat.check_move(1, "alice", (0, 0), (3, 4), at(100)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            anticheat.check_move(1, "alice", (3, 4), (13, 4), at(300)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            anticheat.check_move(1, "alice", (13, 4), (513, 4), at(400)),
+            Verdict::Flagged(Violation::Speed {
+                distance: 500.0,
+                allowed: 5.0
+            })
+        );
+        assert_eq!(
+            anticheat.check_ability(1, "alice", "dash", at(1500)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            anticheat.check_ability(1, "alice", "dash", at(2000)),
+            Verdict::Flagged(Violation::Cooldown {
+                ability: "dash".to_string(),
+                remaining: Duration::from_millis(1500)
+            })
+        );
+        assert_eq!(
+            anticheat.check_ability(1, "alice", "blink", at(2100)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            anticheat.check_ability(1, "alice", "blink", at(2200)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            anticheat.check_ability(1, "alice", "blink", at(2300)),
+            Verdict::Kick(Violation::InputRate {
+                inputs: 5,
+                limit: 4
+            })
+        );
+
+        anticheat.forget(1);
+        assert_eq!(
+            anticheat.check_move(1, "alice", (0, 0), (5, 0), at(5000)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            anticheat.check_move(1, "alice", (i32::MIN, 0), (i32::MAX, 0), at(5500)),
+            Verdict::Flagged(Violation::Speed {
+                distance: f64::from(u32::MAX),
+                allowed: 25.0
+            })
+        );
+    }
+}
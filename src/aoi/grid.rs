@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+pub struct SpatialGrid {
+    cell_size: i32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: i32) -> Self {
+        SpatialGrid {
+            cell_size: cell_size.max(1),
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, position: (i32, i32)) -> (i32, i32) {
+        (
+            position.0.div_euclid(self.cell_size),
+            position.1.div_euclid(self.cell_size),
+        )
+    }
+
+    pub fn insert(&mut self, index: usize, position: (i32, i32)) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    pub fn query(&self, position: (i32, i32), radius: i32) -> Vec<usize> {
+        let (min_x, min_y) = self.cell((
+            position.0.saturating_sub(radius),
+            position.1.saturating_sub(radius),
+        ));
+        let (max_x, max_y) = self.cell((
+            position.0.saturating_add(radius),
+            position.1.saturating_add(radius),
+        ));
+        let mut found = (min_x..=max_x)
+            .flat_map(|x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        found
+    }
+
+    pub fn occupied_cells(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_only_visit_neighbouring_cells() {
+        let mut grid = SpatialGrid::new(10);
+        let positions = [(0, 0), (9, 9), (10, 0), (-1, -1), (35, 35), (-25, 0)];
+        for (index, position) in positions.iter().enumerate() {
+            grid.insert(index, *position);
+        }
+        assert_eq!(grid.occupied_cells(), 5);
+        assert_eq!(grid.query((5, 5), 5), vec![0, 1, 2]);
+        assert_eq!(grid.query((0, 0), 1), vec![0, 1, 3]);
+        assert_eq!(grid.query((-20, 0), 4), vec![5]);
+        assert_eq!(grid.query((35, 35), 1), vec![4]);
+        assert!(grid.query((100, 100), 10).is_empty());
+    }
+}
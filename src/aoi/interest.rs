@@ -0,0 +1,202 @@
+use super::SpatialGrid;
+use crate::config::env_or;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug)]
+pub struct AoiConfig {
+    pub radius: i32,
+    pub cell_size: i32,
+}
+
+impl AoiConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("AOI_RADIUS")
+            .ok()
+            .and_then(|radius| radius.parse().ok())
+            .filter(|radius| *radius > 0)
+            .map(|radius| AoiConfig {
+                radius,
+                cell_size: env_or("AOI_CELL_SIZE", "")
+                    .parse()
+                    .ok()
+                    .filter(|cell_size| *cell_size > 0)
+                    .unwrap_or(radius),
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entity {
+    pub id: u32,
+    pub name: String,
+    pub position: (i32, i32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AoiEvent {
+    Enter(Entity),
+    Leave(u32),
+}
+
+impl AoiEvent {
+    pub fn encode(&self) -> String {
+        match self {
+            AoiEvent::Enter(entity) => format!(
+                "ENTER {} {} {} {}",
+                entity.id, entity.name, entity.position.0, entity.position.1
+            ),
+            AoiEvent::Leave(id) => format!("LEAVE {}", id),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterestUpdate {
+    pub observer: u32,
+    pub events: Vec<AoiEvent>,
+    pub snapshot: Vec<Entity>,
+}
+
+impl InterestUpdate {
+    pub fn snapshot_line(&self) -> Option<String> {
+        if self.snapshot.is_empty() {
+            return None;
+        }
+        Some(
+            self.snapshot
+                .iter()
+                .fold("SNAP".to_string(), |line, entity| {
+                    format!(
+                        "{} {}:{},{}",
+                        line, entity.id, entity.position.0, entity.position.1
+                    )
+                }),
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct InterestManager {
+    radius: i32,
+    cell_size: i32,
+    visible: Arc<Mutex<HashMap<u32, HashSet<u32>>>>,
+}
+
+impl InterestManager {
+    pub fn new(radius: i32) -> Self {
+        InterestManager::with_cell_size(radius, radius)
+    }
+
+    pub fn with_cell_size(radius: i32, cell_size: i32) -> Self {
+        InterestManager {
+            radius,
+            cell_size,
+            visible: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn relevant(&self, observer: &Entity, other: &Entity) -> bool {
+        let dx = (observer.position.0 - other.position.0) as i64;
+        let dy = (observer.position.1 - other.position.1) as i64;
+        let radius = self.radius as i64;
+        observer.id != other.id && dx * dx + dy * dy <= radius * radius
+    }
+
+    pub fn update(&self, entities: &[Entity]) -> Vec<InterestUpdate> {
+        let mut visible = self.visible.lock().expect("Failed to lock interest sets.");
+        visible.retain(|observer, _| entities.iter().any(|entity| entity.id == *observer));
+        let mut grid = SpatialGrid::new(self.cell_size);
+        for (index, entity) in entities.iter().enumerate() {
+            grid.insert(index, entity.position);
+        }
+        let mut updates = vec![];
+        for observer in entities.iter() {
+            let snapshot = grid
+                .query(observer.position, self.radius)
+                .into_iter()
+                .map(|index| &entities[index])
+                .filter(|other| self.relevant(observer, other))
+                .cloned()
+                .collect::<Vec<_>>();
+            let now_visible = snapshot
+                .iter()
+                .map(|entity| entity.id)
+                .collect::<HashSet<_>>();
+            let previous = visible.insert(observer.id, now_visible).unwrap_or_default();
+            let mut events = snapshot
+                .iter()
+                .filter(|entity| !previous.contains(&entity.id))
+                .cloned()
+                .map(AoiEvent::Enter)
+                .collect::<Vec<_>>();
+            let mut left = previous
+                .iter()
+                .filter(|id| !snapshot.iter().any(|entity| entity.id == **id))
+                .copied()
+                .collect::<Vec<_>>();
+            left.sort_unstable();
+            events.extend(left.into_iter().map(AoiEvent::Leave));
+            if !events.is_empty() || !snapshot.is_empty() {
+                updates.push(InterestUpdate {
+                    observer: observer.id,
+                    events,
+                    snapshot,
+                });
+            }
+        }
+        updates
+    }
+
+    pub fn forget(&self, observer: u32) {
+        self.visible
+            .lock()
+            .expect("Failed to lock interest sets.")
+            .remove(&observer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(id: u32, x: i32, y: i32) -> Entity {
+        Entity {
+            id,
+            name: format!("p{}", id),
+            position: (x, y),
+        }
+    }
+
+    #[test]
+    fn observers_only_see_entities_within_their_radius() {
+        let interest = InterestManager::new(10);
+        let updates = interest.update(&[entity(0, 0, 0), entity(1, 6, 8), entity(2, 50, 50)]);
+        assert_eq!(
+            updates,
+            vec![
+                InterestUpdate {
+                    observer: 0,
+                    events: vec![AoiEvent::Enter(entity(1, 6, 8))],
+                    snapshot: vec![entity(1, 6, 8)],
+                },
+                InterestUpdate {
+                    observer: 1,
+                    events: vec![AoiEvent::Enter(entity(0, 0, 0))],
+                    snapshot: vec![entity(0, 0, 0)],
+                },
+            ]
+        );
+        assert_eq!(updates[0].snapshot_line(), Some("SNAP 1:6,8".to_string()));
+
+        let updates = interest.update(&[entity(0, 0, 0), entity(1, 7, 8), entity(2, 50, 50)]);
+        assert_eq!(updates[0].events, vec![AoiEvent::Leave(1)]);
+        assert_eq!(updates[0].snapshot_line(), None);
+        assert_eq!(AoiEvent::Leave(1).encode(), "LEAVE 1");
+        assert_eq!(updates.len(), 2);
+
+        let updates = interest.update(&[entity(0, 0, 0), entity(2, 3, 4)]);
+        assert_eq!(updates[0].events, vec![AoiEvent::Enter(entity(2, 3, 4))]);
+        assert_eq!(AoiEvent::Enter(entity(2, 3, 4)).encode(), "ENTER 2 p2 3 4");
+    }
+}
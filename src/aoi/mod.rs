@@ -0,0 +1,4 @@
+mod grid;
+mod interest;
+pub use grid::*;
+pub use interest::*;
@@ -0,0 +1,125 @@
+use online_game_programming::attachment::{Attachment, AttachmentAssembler, AttachmentError};
+use online_game_programming::codec::{codec_by_name, AttachmentRef, ChatBody, JsonCodec, Message};
+use online_game_programming::config::{env_or, ServerConfig};
+use online_game_programming::rooms::DEFAULT_ROOM;
+use online_game_programming::server::{ClientHandle, NetServer};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+fn share_attachment(
+    client: &ClientHandle,
+    result: Result<Option<Attachment>, AttachmentError>,
+    next_id: &AtomicU32,
+    chunk_size: usize,
+) {
+    let mut attachment = match result {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return,
+        Err(e) => {
+            let _ = client.send(&Message::Reply {
+                ok: false,
+                text: e.to_string(),
+            });
+            return;
+        }
+    };
+    attachment.reference.id = next_id.fetch_add(1, Ordering::SeqCst);
+    println!(
+        "{}（{}）が添付ファイル{}を共有しました（{}バイト）\n",
+        client.id(),
+        client.address(),
+        &attachment.reference.name,
+        attachment.reference.size
+    );
+    for message in attachment.messages(DEFAULT_ROOM, Some(client.id().to_string()), chunk_size) {
+        client.broadcast(&message);
+    }
+}
+
+pub fn codec_chat() -> bool {
+    let config = ServerConfig::from_env();
+    let codec = codec_by_name(&env_or("CODEC", "json")).unwrap_or_else(|| Arc::new(JsonCodec));
+    let policy = config.attachments.clone();
+    let assemblers = Mutex::new(HashMap::<usize, AttachmentAssembler>::new());
+    let next_attachment_id = AtomicU32::new(1);
+
+    let mut builder = NetServer::builder();
+    if let Some(layers) = config.layers.clone() {
+        builder = builder.layers(layers);
+    }
+    if let Some(compression) = config.compression.clone() {
+        builder = builder.compression(compression);
+    }
+    builder
+        .bind(config.port)
+        .transport(config.transport)
+        .codec(codec)
+        .max_clients(config.max_clients)
+        .on_message(move |client, message| match message {
+            Message::Chat { room, body, .. } => {
+                let body = match body {
+                    ChatBody::Plain(text) => ChatBody::parse(&text),
+                    ChatBody::Notice(text) => ChatBody::Plain(text),
+                    body => body,
+                };
+                let sender = client.id().to_string();
+                println!(
+                    "{}（{}）：{}\n",
+                    client.id(),
+                    client.address(),
+                    body.render(Some(&sender))
+                );
+                let message = Message::Chat {
+                    room,
+                    sender: Some(sender),
+                    body,
+                };
+                client.broadcast(&message);
+            }
+            Message::AttachmentOffer {
+                id,
+                name,
+                mime,
+                size,
+            } => {
+                let result = assemblers
+                    .lock()
+                    .expect("Failed to lock attachment assemblers.")
+                    .entry(client.id())
+                    .or_insert_with(|| AttachmentAssembler::new(policy.clone()))
+                    .offer(AttachmentRef {
+                        id,
+                        name,
+                        mime,
+                        size,
+                    });
+                share_attachment(client, result, &next_attachment_id, policy.chunk_size);
+            }
+            Message::AttachmentChunk { id, index, data } => {
+                let result = assemblers
+                    .lock()
+                    .expect("Failed to lock attachment assemblers.")
+                    .get_mut(&client.id())
+                    .ok_or(AttachmentError::UnknownTransfer(id))
+                    .and_then(|assembler| assembler.push(id, index, &data));
+                share_attachment(client, result, &next_attachment_id, policy.chunk_size);
+            }
+            Message::End => {
+                println!("{}", "終了コマンドを受信しました\n");
+                let _ = client.send(&Message::Reply {
+                    ok: true,
+                    text: "Bye!".to_string(),
+                });
+                client.close();
+            }
+            _ => {
+                let _ = client.send(&Message::Reply {
+                    ok: false,
+                    text: "Unsupported message.".to_string(),
+                });
+            }
+        })
+        .build()
+        .run()
+}
@@ -1,2 +1,12 @@
+mod codec_chat;
+mod physics_demo;
+mod trade_sync;
 mod unit_05;
-pub use unit_05::*;
\ No newline at end of file
+mod unit_05_select;
+mod urgent_data;
+pub use codec_chat::*;
+pub use physics_demo::*;
+pub use trade_sync::*;
+pub use unit_05::*;
+pub use unit_05_select::*;
+pub use urgent_data::*;
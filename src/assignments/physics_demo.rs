@@ -0,0 +1,260 @@
+use online_game_programming::clients::ClientRegistry;
+use online_game_programming::config::ServerConfig;
+use online_game_programming::physics::{DeltaEncoder, PhysicsCommand, PhysicsConfig, World};
+#[cfg(feature = "winsock")]
+use online_game_programming::server::startup_wsa;
+use online_game_programming::server::{spawn_ticker, ClientContext, Flow, ServerHandler};
+use online_game_programming::session::ClientPool;
+use online_game_programming::transport::{PriorityTransport, SendPriority, Transport};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const PROFILE_INTERVAL: Duration = Duration::from_secs(5);
+
+struct Viewer {
+    body: u32,
+    transport: Arc<dyn Transport>,
+    encoder: DeltaEncoder,
+}
+
+struct TickProfile {
+    since: Instant,
+    ticks: u32,
+    step: Duration,
+    broadcast: Duration,
+    slowest: Duration,
+    bytes: usize,
+}
+
+impl TickProfile {
+    fn new(now: Instant) -> Self {
+        TickProfile {
+            since: now,
+            ticks: 0,
+            step: Duration::ZERO,
+            broadcast: Duration::ZERO,
+            slowest: Duration::ZERO,
+            bytes: 0,
+        }
+    }
+
+    fn record(&mut self, step: Duration, broadcast: Duration, bytes: usize, bodies: usize) {
+        self.ticks += 1;
+        self.step += step;
+        self.broadcast += broadcast;
+        self.slowest = self.slowest.max(step + broadcast);
+        self.bytes += bytes;
+        let now = Instant::now();
+        if now.saturating_duration_since(self.since) < PROFILE_INTERVAL {
+            return;
+        }
+        println!(
+            "{}ティック：ステップ平均{:?}、配信平均{:?}、最長{:?}、{}体、{}バイト送信\n",
+            self.ticks,
+            self.step / self.ticks,
+            self.broadcast / self.ticks,
+            self.slowest,
+            bodies,
+            self.bytes
+        );
+        *self = TickProfile::new(now);
+    }
+}
+
+struct PhysicsHandler {
+    clients: ClientRegistry,
+    config: PhysicsConfig,
+    world: Mutex<World>,
+    viewers: Mutex<HashMap<u32, Viewer>>,
+    profile: Mutex<TickProfile>,
+}
+
+impl PhysicsHandler {
+    fn new(clients: ClientRegistry, config: PhysicsConfig) -> Self {
+        PhysicsHandler {
+            clients,
+            world: Mutex::new(World::new(config.clone())),
+            config,
+            viewers: Mutex::new(HashMap::new()),
+            profile: Mutex::new(TickProfile::new(Instant::now())),
+        }
+    }
+
+    fn lock_world(&self) -> std::sync::MutexGuard<'_, World> {
+        self.world.lock().expect("Failed to lock physics world.")
+    }
+
+    fn lock_viewers(&self) -> std::sync::MutexGuard<'_, HashMap<u32, Viewer>> {
+        self.viewers
+            .lock()
+            .expect("Failed to lock physics viewers.")
+    }
+}
+
+impl ServerHandler for PhysicsHandler {
+    fn on_client_connected(&self, client: &ClientContext) {
+        let body = {
+            let mut world = self.lock_world();
+            if world.bodies().len() >= self.config.max_bodies {
+                None
+            } else {
+                Some(world.spawn_next())
+            }
+        };
+        let body = match body {
+            Some(body) => body,
+            None => {
+                client.send_text("ERR The world is full.");
+                client.transport.close();
+                return;
+            }
+        };
+        println!(
+            "クライアント{}（{}）に物体{}を割り当てました\n",
+            client.id, &client.address, body
+        );
+        let _ = client
+            .transport
+            .send_text_prioritized(&format!("BODY {}", body), SendPriority::Control);
+        self.lock_viewers().insert(
+            client.id,
+            Viewer {
+                body,
+                transport: client.transport.clone(),
+                encoder: DeltaEncoder::new(),
+            },
+        );
+    }
+
+    fn on_message(&self, client: &ClientContext, message: &str) -> Flow {
+        if message.starts_with(":end") {
+            println!("終了コマンドを受信しました\n");
+            client.send_text("Bye!");
+            return Flow::Disconnect;
+        }
+        let body = match self.lock_viewers().get(&client.id) {
+            Some(viewer) => viewer.body,
+            None => return Flow::Disconnect,
+        };
+        match PhysicsCommand::parse(message) {
+            Some(PhysicsCommand::Push(dx, dy)) => {
+                self.lock_world().push(body, (dx, dy));
+            }
+            Some(PhysicsCommand::Spawn) => {
+                let mut world = self.lock_world();
+                if world.bodies().len() >= self.config.max_bodies {
+                    client.send_text("ERR The world is full.");
+                } else {
+                    client.send_text(&format!("OK {}", world.spawn_next()));
+                }
+            }
+            Some(PhysicsCommand::Resync) => {
+                if let Some(viewer) = self.lock_viewers().get_mut(&client.id) {
+                    viewer.encoder.reset();
+                }
+            }
+            None => client.send_text("ERR Usage: :push <dx> <dy> | :spawn | :resync | :end"),
+        }
+        Flow::Continue
+    }
+
+    fn on_client_disconnected(&self, client: &ClientContext) {
+        println!(
+            "クライアント{}（{}）が切断しました\n",
+            client.id, &client.address
+        );
+        if let Some(viewer) = self.lock_viewers().remove(&client.id) {
+            self.lock_world().remove(viewer.body);
+        }
+        if let Some(socket_client) = self.clients.get(client.id) {
+            let mut client_lock = socket_client
+                .write()
+                .expect("Failed to lock socket client.");
+            client_lock.transport = None;
+            client_lock.connected_at = None;
+        }
+    }
+
+    fn on_tick(&self) {
+        let started = Instant::now();
+        let (tick, bodies) = {
+            let mut world = self.lock_world();
+            world.step(self.config.tick_interval.as_secs_f64());
+            (world.tick(), world.bodies().to_vec())
+        };
+        let stepped = Instant::now();
+
+        let mut bytes = 0;
+        for viewer in self.lock_viewers().values_mut() {
+            if let Some(line) = viewer.encoder.encode(tick, &bodies) {
+                bytes += line.len() + 1;
+                let _ = viewer
+                    .transport
+                    .send_text_prioritized(&line, SendPriority::GameState);
+            }
+        }
+        self.profile
+            .lock()
+            .expect("Failed to lock tick profile.")
+            .record(stepped - started, stepped.elapsed(), bytes, bodies.len());
+    }
+}
+
+pub fn physics_demo() -> bool {
+    #[cfg(feature = "winsock")]
+    if !unsafe { startup_wsa() } {
+        return false;
+    }
+
+    let config = ServerConfig::from_env();
+    let physics = PhysicsConfig::from_env();
+    let listener = match config
+        .transport
+        .bind_address(config.bind.as_deref(), config.port)
+    {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "{}のリスナーを起動できませんでした：{}\n",
+                config.transport.name(),
+                e
+            );
+            return false;
+        }
+    };
+    println!(
+        "物理演算サーバーが起動しました。（{}×{}、{:?}ごと、初期{}体）\n",
+        physics.width, physics.height, physics.tick_interval, physics.initial_bodies
+    );
+
+    let mut client_pool = ClientPool::new(config.max_clients);
+    client_pool.max_frame_size = config.max_frame_size;
+    let tick_interval = physics.tick_interval;
+    let handler: Arc<dyn ServerHandler> =
+        Arc::new(PhysicsHandler::new(client_pool.clients.clone(), physics));
+    spawn_ticker(handler.clone(), tick_interval);
+
+    loop {
+        let (transport, address) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("クライアントと接続失敗。エラー：{}\n", e);
+                continue;
+            }
+        };
+        println!(
+            "クライアントが接続してきました！：IPAddress({})\n",
+            &address
+        );
+
+        let client = client_pool.clients.find_empty();
+        {
+            let mut client_lock = client.write().expect("Failed to lock client socket.");
+            client_lock.address = address;
+            client_lock.connected_at = Some(Instant::now());
+            client_lock.transport = Some(Arc::new(PriorityTransport::new(transport)));
+        }
+        client_pool.start_messaging(handler.clone(), client);
+    }
+}
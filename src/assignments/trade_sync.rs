@@ -0,0 +1,145 @@
+use online_game_programming::codec::{codec_by_name, JsonCodec, Message};
+use online_game_programming::config::{env_or, ServerConfig};
+use online_game_programming::server::{ClientHandle, NetServer};
+use online_game_programming::trade::{
+    encode_items, parse_items, starting_items_from_env, Trade, TradeBook, TradeResult,
+};
+use std::sync::{Arc, Mutex};
+
+const PROPOSE_USAGE: &str = "Usage: propose <player> <item:count,...|-> <item:count,...|->";
+
+fn command(name: &str, args: Vec<String>) -> Message {
+    Message::Command {
+        name: name.to_string(),
+        args,
+    }
+}
+
+fn reply(client: &ClientHandle, ok: bool, text: String) {
+    let _ = client.send(&Message::Reply { ok, text });
+}
+
+fn reply_with(client: &ClientHandle, result: TradeResult<String>) {
+    match result {
+        Ok(text) => reply(client, true, text),
+        Err(e) => reply(client, false, e.to_string()),
+    }
+}
+
+fn send_inventory(client: &ClientHandle, book: &TradeBook, player: usize) {
+    if let Some(items) = book.inventory(player) {
+        let _ = client.send_to(player, &command("inventory", vec![encode_items(items)]));
+    }
+}
+
+fn cancel(client: &ClientHandle, trade: &Trade) {
+    println!("取引#{}がキャンセルされました\n", trade.id);
+    let _ = client.send_to(
+        trade.counterparty(client.id()),
+        &command("cancel", vec![trade.id.to_string()]),
+    );
+}
+
+fn propose(client: &ClientHandle, book: &mut TradeBook, args: &[String]) {
+    let (to, give, want) = match args {
+        [to, give, want] => (to.parse().ok(), parse_items(give), parse_items(want)),
+        _ => (None, None, None),
+    };
+    let (to, give, want) = match (to, give, want) {
+        (Some(to), Some(give), Some(want)) => (to, give, want),
+        _ => return reply(client, false, PROPOSE_USAGE.to_string()),
+    };
+    reply_with(
+        client,
+        book.propose(client.id(), to, give, want).map(|trade| {
+            println!(
+                "{}が{}に取引#{}を提案しました（{} ⇔ {}）\n",
+                trade.from,
+                trade.to,
+                trade.id,
+                encode_items(&trade.give),
+                encode_items(&trade.want)
+            );
+            let request = vec![
+                trade.id.to_string(),
+                trade.from.to_string(),
+                encode_items(&trade.give),
+                encode_items(&trade.want),
+            ];
+            let _ = client.send_to(trade.to, &command("trade", request));
+            format!("PROPOSED {}", trade.id)
+        }),
+    );
+}
+
+fn accept(client: &ClientHandle, book: &mut TradeBook, id: u32) {
+    let trade = match book.accept(client.id(), id) {
+        Ok(trade) => trade,
+        Err(e) => return reply(client, false, e.to_string()),
+    };
+    println!(
+        "取引#{}が成立しました（{}と{}）\n",
+        trade.id, trade.from, trade.to
+    );
+    for player in [trade.from, trade.to].iter() {
+        let _ = client.send_to(*player, &command("commit", vec![trade.id.to_string()]));
+        send_inventory(client, book, *player);
+    }
+    reply(client, true, format!("ACCEPTED {}", trade.id));
+}
+
+pub fn trade_sync() -> bool {
+    let config = ServerConfig::from_env();
+    let codec = codec_by_name(&env_or("CODEC", "json")).unwrap_or_else(|| Arc::new(JsonCodec));
+    let starting_items = starting_items_from_env();
+    let book = Arc::new(Mutex::new(TradeBook::new()));
+    let departures = book.clone();
+
+    NetServer::builder()
+        .bind(config.port)
+        .transport(config.transport)
+        .codec(codec)
+        .max_clients(config.max_clients)
+        .on_message(move |client, message| {
+            let mut book = book.lock().expect("Failed to lock trade book.");
+            if book.inventory(client.id()).is_none() {
+                book.join(client.id(), starting_items.clone());
+                send_inventory(client, &book, client.id());
+            }
+            match message {
+                Message::Command { name, args } => match (name.as_str(), args.as_slice()) {
+                    ("inventory", []) => send_inventory(client, &book, client.id()),
+                    ("propose", args) => propose(client, &mut book, args),
+                    ("accept", [id]) => match id.parse() {
+                        Ok(id) => accept(client, &mut book, id),
+                        Err(_) => reply(client, false, "Usage: accept <trade>".to_string()),
+                    },
+                    ("decline", [id]) => match id.parse() {
+                        Ok(id) => reply_with(
+                            client,
+                            book.decline(client.id(), id).map(|trade| {
+                                cancel(client, &trade);
+                                format!("DECLINED {}", trade.id)
+                            }),
+                        ),
+                        Err(_) => reply(client, false, "Usage: decline <trade>".to_string()),
+                    },
+                    _ => reply(client, false, "Unknown trade command.".to_string()),
+                },
+                Message::End => {
+                    println!("{}", "終了コマンドを受信しました\n");
+                    reply(client, true, "Bye!".to_string());
+                    client.close();
+                }
+                _ => reply(client, false, "Unsupported message.".to_string()),
+            }
+        })
+        .on_disconnect(move |client| {
+            let mut book = departures.lock().expect("Failed to lock trade book.");
+            for trade in book.leave(client.id()).iter() {
+                cancel(client, trade);
+            }
+        })
+        .build()
+        .run()
+}
@@ -1,272 +1,594 @@
-use crate::bindings::Windows::Win32::NetworkManagement::IpHelper::AF_INET;
-use crate::bindings::Windows::Win32::Networking::WinSock::{
-    accept, bind, closesocket, htons, listen, recv, send, socket, WSACleanup, WSAData,
-    WSAGetLastError, WSAStartup, IN_ADDR, IN_ADDR_0, SEND_FLAGS, SOCKADDR, SOCKADDR_IN, SOCKET,
-    SOCKET_ERROR, SOCK_STREAM, SOMAXCONN,
+use online_game_programming::admin::{save_snapshot, spawn_admin_handler, AdminApi};
+use online_game_programming::announcements::AnnouncementScheduler;
+use online_game_programming::anticheat::AntiCheat;
+use online_game_programming::aoi::InterestManager;
+use online_game_programming::bindings::Windows::Win32::Networking::WinSock::WSACleanup;
+use online_game_programming::bots::{connect_bot, ReadyBot};
+#[cfg(feature = "tls")]
+use online_game_programming::bridge::DiscordBridge;
+use online_game_programming::bridge::{MqttBridge, RedisRelay, WebhookSender};
+use online_game_programming::bus::MessageBus;
+use online_game_programming::chat_log::ChatLog;
+use online_game_programming::clock::ServerClock;
+use online_game_programming::cluster::ClusterNode;
+use online_game_programming::config::ServerConfig;
+use online_game_programming::console;
+use online_game_programming::context::ServerContext;
+use online_game_programming::events::{EventDispatcher, ServerEvent};
+use online_game_programming::geoip::GeoIp;
+use online_game_programming::guilds::Guilds;
+use online_game_programming::identity::Identity;
+use online_game_programming::ip_stats::IpStats;
+use online_game_programming::layers::{CompressedTransport, LayeredTransport};
+use online_game_programming::leaderboard::Leaderboard;
+use online_game_programming::metrics::Metrics;
+use online_game_programming::notify::{HttpNotifier, Notifier};
+use online_game_programming::p2p::{MeshRegistry, RelayServer};
+use online_game_programming::playback::{Playback, ReplayTransport};
+use online_game_programming::plugins::PluginRegistry;
+use online_game_programming::query::QueryServer;
+use online_game_programming::recorder::{PacketKind, Recorder};
+use online_game_programming::rooms::Rooms;
+use online_game_programming::scoreboard::Scoreboards;
+use online_game_programming::server::{spawn_ticker, startup_wsa, ClientContext, ServerHandler};
+use online_game_programming::session::{
+    spawn_relay_delivery, spawn_whisper_delivery, ChatHandler, ClientPool,
+};
+use online_game_programming::snapshot::Snapshotter;
+use online_game_programming::storage::{open_stores, Storage};
+use online_game_programming::throttle::{AcceptThrottle, Rejection};
+use online_game_programming::transfer::TransferService;
+#[cfg(feature = "chaos")]
+use online_game_programming::transport::ChaosTransport;
+use online_game_programming::transport::{
+    EncodingTransport, NetemTransport, PlainTextTransport, PriorityTransport, Transport,
+};
+use online_game_programming::zone::ZoneNode;
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime};
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE};
+use winapi::um::consoleapi::{GetConsoleMode, SetConsoleCtrlHandler, SetConsoleMode};
+use winapi::um::processenv::GetStdHandle;
+use winapi::um::winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+use winapi::um::wincon::{
+    CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
 };
-use crate::bindings::Windows::Win32::System::SystemServices::{CHAR, PSTR};
-use std::sync::{Arc, RwLock};
-use winapi::shared::minwindef::MAKEWORD;
-use winapi::shared::ws2def::INADDR_ANY;
-use winapi::um::winsock2::INVALID_SOCKET;
-
-const PORT: u16 = 7000;
-const CLIENT_ADDR_SIZE: usize = std::mem::size_of::<SOCKADDR_IN>();
-const BUFFER_SIZE: usize = 2048;
-const RECV_PREFIX: &str = "受信データ：";
-const DEFAULT_MAX_CLIENTS: usize = 10;
 
-#[derive(Clone)]
-struct Client {
-    pub id: u32,
-    pub addr: SOCKADDR_IN,
-    pub socket: SOCKET,
-}
+static SHUTDOWN_SNAPSHOT: OnceLock<Snapshotter> = OnceLock::new();
 
-impl Default for Client {
-    fn default() -> Self {
-        Client {
-            id: 0,
-            addr: SOCKADDR_IN {
-                sin_family: 0,
-                sin_port: 0,
-                sin_addr: IN_ADDR {
-                    S_un: IN_ADDR_0 { S_addr: 0 },
-                },
-                sin_zero: [CHAR(0); 8],
-            },
-            socket: SOCKET(INVALID_SOCKET),
+unsafe extern "system" fn console_ctrl_handler(ctrl_type: DWORD) -> BOOL {
+    if matches!(
+        ctrl_type,
+        CTRL_C_EVENT | CTRL_BREAK_EVENT | CTRL_CLOSE_EVENT
+    ) {
+        if let Some(snapshotter) = SHUTDOWN_SNAPSHOT.get() {
+            save_snapshot(Some(snapshotter));
         }
     }
+    FALSE
 }
 
-unsafe fn check_socket_error(result: i32, msg: &str) -> bool {
-    if result == SOCKET_ERROR {
-        eprintln!("{}", msg);
-        eprintln!("Error: {}", WSAGetLastError().0);
-        WSACleanup();
-        false
-    } else {
-        true
-    }
+unsafe fn enable_virtual_terminal() -> bool {
+    [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE].iter().all(|handle| {
+        let handle = GetStdHandle(*handle);
+        let mut mode: DWORD = 0;
+        GetConsoleMode(handle, &mut mode) != FALSE
+            && SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != FALSE
+    })
 }
 
-struct ClientPool {
-    pub socket_clients: Vec<Arc<RwLock<Client>>>,
-    pub socket_client_threads: Vec<std::thread::JoinHandle<()>>,
-}
+pub unsafe fn unit_05() -> bool {
+    if !startup_wsa() {
+        return false;
+    }
 
-impl ClientPool {
-    pub fn new(pool_size: usize) -> Self {
-        let mut client = Client::default();
-        let mut client_vec = vec![];
-        client_vec.resize_with(pool_size, || {
-            let inner_client = client.clone();
-            client.id += 1;
-            Arc::new(RwLock::new(inner_client))
-        });
-        ClientPool {
-            socket_clients: client_vec,
-            socket_client_threads: Vec::with_capacity(pool_size),
+    let config = ServerConfig::from_env();
+    console::set_color(config.console_color && enable_virtual_terminal());
+    let listener = match config
+        .transport
+        .bind_address(config.bind.as_deref(), config.port)
+    {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "{}のリスナーを起動できませんでした：{}\n",
+                config.transport.name(),
+                e
+            );
+            WSACleanup();
+            return false;
         }
-    }
+    };
 
-    pub fn find_empty_client(&mut self) -> Arc<RwLock<Client>> {
-        self.socket_clients
-            .iter()
-            .find(|c| {
-                c.try_read()
-                    .expect("Failed to lock socket client.")
-                    .socket
-                    .0
-                    == INVALID_SOCKET
-            })
-            .cloned()
-            .unwrap_or_else(|| {
-                self.socket_clients
-                    .push(Arc::new(RwLock::new(Client::default())));
-                self.socket_clients
-                    .last()
-                    .cloned()
-                    .expect("There are no available socket clients.")
-            })
+    println!("サーバーが起動しました。\n");
+    if config.plain_text {
+        println!("プレーンテキストモード（改行区切り）で通信します。\n");
+        if config.layers.is_some() || config.compression.is_some() {
+            eprintln!("プレーンテキストモードではレイヤーと圧縮を無効にします\n");
+        }
     }
 
-    pub unsafe fn start_messaging(
-        &mut self,
-        socket_client: Arc<RwLock<Client>>,
-        mut server_msg: String,
-        other_clients: Vec<Arc<RwLock<Client>>>,
-    ) {
-        self.socket_client_threads.push(std::thread::spawn(move || {
-            {
-                let client_lock = socket_client.read().expect("Failed to lock socket client.");
-                send(
-                    &client_lock.socket,
-                    PSTR(server_msg.as_mut_ptr()),
-                    (server_msg.chars().count() as i32) + 1,
-                    SEND_FLAGS(0),
+    let mut client_pool = ClientPool::new(config.max_clients);
+    client_pool.max_frame_size = config.max_frame_size;
+
+    let mut dispatcher = EventDispatcher::new();
+    let storage = match Storage::open_from_env() {
+        Ok(storage) => {
+            dispatcher.add_listener(storage.clone());
+            Some(storage)
+        }
+        Err(e) => {
+            eprintln!("データベースを開けませんでした：{}\n", e);
+            None
+        }
+    };
+    let stores = storage
+        .as_ref()
+        .and_then(|storage| match open_stores(storage) {
+            Ok(stores) => Some(stores),
+            Err(e) => {
+                eprintln!("アカウントストアを開けませんでした：{}\n", e);
+                None
+            }
+        });
+    let accounts = stores.as_ref().map(|(accounts, _)| accounts.clone());
+    let guilds = stores.map(|(accounts, guilds)| Guilds::new(guilds, accounts));
+    let bus = MessageBus::new();
+    let leaderboard = accounts.clone().map(Leaderboard::new);
+    if let Some(leaderboard) = leaderboard.as_ref() {
+        leaderboard.subscribe(&bus);
+    }
+    let mut admin_commands = None;
+    if let Some(mqtt_config) = config.mqtt.clone() {
+        match MqttBridge::connect(mqtt_config) {
+            Ok((bridge, commands)) => {
+                println!("MQTTブローカーに接続しました。\n");
+                dispatcher.add_listener(bridge);
+                admin_commands = Some(commands);
+            }
+            Err(e) => eprintln!("MQTTブローカーへの接続に失敗しました：{}\n", e),
+        }
+    }
+    if let Some(redis_config) = config.redis.clone() {
+        match RedisRelay::connect(redis_config) {
+            Ok((relay, messages)) => {
+                println!("Redisに接続しました。\n");
+                dispatcher.add_listener(relay);
+                spawn_relay_delivery(messages, client_pool.clients.clone());
+            }
+            Err(e) => eprintln!("Redisへの接続に失敗しました：{}\n", e),
+        }
+    }
+    #[cfg(feature = "tls")]
+    if let Some(discord_config) = config.discord.clone() {
+        let room = discord_config.room.clone();
+        match DiscordBridge::connect(discord_config, client_pool.clients.clone()) {
+            Ok((bridge, messages)) => {
+                println!("Discordとルーム{}を中継します。\n", &room);
+                dispatcher.add_listener(bridge);
+                spawn_relay_delivery(messages, client_pool.clients.clone());
+            }
+            Err(e) => eprintln!("Discordへの接続に失敗しました：{}\n", e),
+        }
+    }
+    if let Some(webhook_config) = config.webhooks.clone() {
+        println!(
+            "Webhookを{}件登録しました（イベント：{}）\n",
+            webhook_config.urls.len(),
+            webhook_config.events.join(",")
+        );
+        dispatcher.add_listener(WebhookSender::spawn(webhook_config));
+    }
+    let cluster = config.cluster.clone().and_then(|cluster_config| {
+        let clients = client_pool.clients.clone();
+        let bind = cluster_config.bind.clone();
+        match ClusterNode::start(cluster_config, move || clients.nicknames()) {
+            Ok((cluster, messages)) => {
+                println!(
+                    "クラスタに参加しました（ノード{}、{}）\n",
+                    cluster.node_id(),
+                    &bind
                 );
+                spawn_whisper_delivery(messages, client_pool.clients.clone(), cluster.clone());
+                Some(cluster)
             }
-
-            let mut recv_buffer = [0_u8; BUFFER_SIZE];
-            'outer_loop: loop {
-                if let Ok(client_lock) = socket_client.try_read() {
-                    let recv_size = recv(
-                        &client_lock.socket,
-                        PSTR(recv_buffer.as_mut_ptr()),
-                        recv_buffer.len() as i32,
-                        0,
-                    );
-                    let mut incoming_message =
-                        String::from_utf8_lossy(&recv_buffer[..(recv_size as usize)]).to_string();
-                    println!("{}{}", RECV_PREFIX, &incoming_message);
-                    if incoming_message.starts_with(":end") {
-                        println!("{}", "終了コマンドを受信しました\n");
-                        let mut bye_message = "Bye!\0".to_string();
-                        send(
-                            &client_lock.socket,
-                            PSTR(bye_message.as_mut_ptr()),
-                            bye_message.len() as i32,
-                            SEND_FLAGS(0),
-                        );
-                        break 'outer_loop;
-                    }
-
+            Err(e) => {
+                eprintln!("クラスタの起動に失敗しました：{}\n", e);
+                None
+            }
+        }
+    });
+    let zone = config
+        .zone
+        .clone()
+        .and_then(|zone_config| match ZoneNode::start(zone_config) {
+            Ok(zone) => {
+                println!(
+                    "ゾーン{}を担当します（ハンドオフ：{}）\n",
+                    zone.zone_id(),
+                    zone.local_addr()
+                );
+                Some(zone)
+            }
+            Err(e) => {
+                eprintln!("ゾーンの起動に失敗しました：{}\n", e);
+                None
+            }
+        });
+    let transfer = match zone.as_ref() {
+        Some(zone) => Some(zone.transfer()),
+        None => config.transfer.clone().and_then(|transfer_config| {
+            match TransferService::start(transfer_config) {
+                Ok(transfer) => {
                     println!(
-                        "{} -> {}：{}\n",
-                        client_lock.id, client_lock.id, &incoming_message
+                        "転送サービスを{}で起動しました（サーバー{}）\n",
+                        transfer.local_addr(),
+                        transfer.server_id()
                     );
-                    send(
-                        &client_lock.socket,
-                        PSTR(incoming_message.as_mut_ptr()),
-                        incoming_message.len() as i32,
-                        SEND_FLAGS(0),
-                    );
-
-                    for client in other_clients.iter() {
-                        if let Ok(other_client_lock) = client.try_read() {
-                            if other_client_lock.socket.0 == INVALID_SOCKET {
-                                continue;
-                            }
-                            println!(
-                                "{} -> {}：{}\n",
-                                client_lock.id, other_client_lock.id, &incoming_message
-                            );
-                            send(
-                                &other_client_lock.socket,
-                                PSTR(incoming_message.as_mut_ptr()),
-                                incoming_message.len() as i32,
-                                SEND_FLAGS(0),
-                            );
-                        }
-                    }
+                    Some(transfer)
+                }
+                Err(e) => {
+                    eprintln!("転送サービスの起動に失敗しました：{}\n", e);
+                    None
                 }
             }
-
-            {
-                let mut client_lock = socket_client
-                    .try_write()
-                    .expect("Failed to lock socket client.");
-                let result = closesocket(&client_lock.socket);
-                check_socket_error(result, "切断に失敗しました。");
-                client_lock.socket.0 = INVALID_SOCKET;
-            }
-        }));
+        }),
+    };
+    let chat_log =
+        config
+            .chat_log
+            .clone()
+            .and_then(|chat_log_config| match ChatLog::open(chat_log_config) {
+                Ok(chat_log) => Some(chat_log),
+                Err(e) => {
+                    eprintln!("チャットログを開けませんでした：{}\n", e);
+                    None
+                }
+            });
+    if let Some(chat_log) = chat_log.clone() {
+        dispatcher.add_listener(chat_log);
     }
-}
-
-unsafe fn startup_wsa() -> bool {
-    let version = MAKEWORD(2, 2);
-    let mut wsa_data = WSAData::default();
-    let result = WSAStartup(version, &mut wsa_data as *mut _);
-    if result != 0 {
-        eprintln!(
-            "WSAStartup failed to initialize with error: {}\n",
-            WSAGetLastError().0
-        );
-        false
-    } else {
-        true
+    let rooms = Rooms::new(config.room_history);
+    rooms.subscribe(&bus);
+    let metrics = Metrics::new();
+    metrics.subscribe(&bus);
+    dispatcher.add_listener(bus);
+    let (events, dispatcher_thread) = dispatcher.spawn();
+    let identity = Identity::new(accounts.clone(), config.require_login);
+    let snapshotter = config.snapshot.clone().map(|snapshot_config| {
+        Snapshotter::new(
+            snapshot_config,
+            rooms.clone(),
+            identity.clone(),
+            storage.clone(),
+        )
+    });
+    if let Some(snapshotter) = snapshotter.as_ref() {
+        match snapshotter.restore() {
+            Ok(Some(snapshot)) => println!(
+                "{}に保存された状態を復元しました（ルーム{}、セッション{}、BAN{}）\n",
+                snapshot.saved_at,
+                snapshot.rooms.len(),
+                snapshot.sessions.len(),
+                snapshot.bans.len()
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("サーバーの状態を復元できませんでした：{}\n", e),
+        }
+        let _ = SHUTDOWN_SNAPSHOT.set(snapshotter.clone());
+        SetConsoleCtrlHandler(Some(console_ctrl_handler), 1);
     }
-}
-
-unsafe fn create_and_bind_socket() -> Option<SOCKET> {
-    let addr = SOCKADDR_IN {
-        sin_family: AF_INET.0 as u16,
-        sin_port: htons(PORT),
-        sin_addr: IN_ADDR {
-            S_un: IN_ADDR_0 { S_addr: INADDR_ANY },
-        },
-        sin_zero: [CHAR(0); 8],
+    let recorder =
+        config
+            .record_dir
+            .as_ref()
+            .and_then(|directory| match Recorder::create(directory) {
+                Ok(recorder) => {
+                    println!("セッションを{}に記録します。\n", recorder.path().display());
+                    Some(recorder)
+                }
+                Err(e) => {
+                    eprintln!("セッション記録ファイルを作成できませんでした：{}\n", e);
+                    None
+                }
+            });
+    let anticheat = config.anticheat.clone().and_then(|anticheat_config| {
+        match AntiCheat::new(anticheat_config) {
+            Ok(anticheat) => {
+                println!("不正行為の検知を有効にしました\n");
+                Some(anticheat)
+            }
+            Err(e) => {
+                eprintln!("不正行為の証拠ファイルを開けませんでした：{}\n", e);
+                None
+            }
+        }
+    });
+    let ip_stats = IpStats::from_env().unwrap_or_else(|e| {
+        eprintln!("IP統計を読み込めませんでした：{}\n", e);
+        IpStats::new()
+    });
+    let context = ServerContext {
+        clients: client_pool.clients.clone(),
+        rooms,
+        mesh: MeshRegistry::new(),
+        identity,
+        storage,
+        accounts,
+        leaderboard,
+        guilds,
+        chat_log,
+        recorder,
+        snapshotter,
+        metrics,
+        ip_stats,
+        idle_timeout: config.idle_timeout,
+        afk_timeout: config.afk_timeout,
+        pre_auth_timeout: config.throttle.pre_auth_timeout,
+        pow_difficulty: config.pow_difficulty,
+        heartbeat_interval: config.heartbeat_interval,
+        skip_afk_in_ready_checks: config.afk_skip_ready,
+        cluster,
+        zone,
+        transfer,
+        interest: config
+            .aoi
+            .as_ref()
+            .map(|aoi| InterestManager::with_cell_size(aoi.radius, aoi.cell_size)),
+        anticheat,
+        announcements: AnnouncementScheduler::new(),
+        notifier: config.push.clone().map(|push_config| {
+            println!("プッシュ通知を{}へ送信します\n", &push_config.url);
+            Arc::new(HttpNotifier::spawn(push_config)) as Arc<dyn Notifier>
+        }),
+        clock: ServerClock::new(config.tick_interval),
+        scoreboards: Scoreboards::new(config.scoreboard_interval),
+        events,
     };
-    let socket = socket(AF_INET.0 as i32, SOCK_STREAM as i32, 0);
-    if socket.0 == INVALID_SOCKET {
-        eprintln!("ソケットの生成に失敗しました：{}\n", WSAGetLastError().0);
-        WSACleanup();
-        None
-    } else {
-        let result = bind(
-            &socket,
-            &addr as *const _ as *const SOCKADDR,
-            std::mem::size_of::<SOCKADDR_IN>() as i32,
-        );
-        if !check_socket_error(result, "Socket binding failed.") {
-            None
-        } else {
-            Some(socket)
+    for (schedule, text) in config.announcements.iter() {
+        context
+            .announcements
+            .add(*schedule, text.clone(), SystemTime::now());
+    }
+    if let Some(playback_config) = config.playback.as_ref() {
+        match Playback::load(&playback_config.path) {
+            Ok(playback) => {
+                println!(
+                    "{}を再生します（パケット{}件）\n",
+                    playback_config.path.display(),
+                    playback.packets.len()
+                );
+                let handler =
+                    ChatHandler::new(context.clone(), PluginRegistry::from_names(&config.plugins));
+                let delivered =
+                    playback.replay(&handler, playback_config.timing, |client_id, address| {
+                        let client = context.clients.find_empty();
+                        let mut client_lock =
+                            client.write().expect("Failed to lock client socket.");
+                        let transport: Arc<dyn Transport> =
+                            Arc::new(ReplayTransport::new(client_id));
+                        client_lock.address = address.to_string();
+                        client_lock.connected_at = Some(Instant::now());
+                        client_lock.transport = Some(transport.clone());
+                        Some(ClientContext {
+                            id: client_lock.id,
+                            address: address.to_string(),
+                            transport,
+                        })
+                    });
+                println!("再生が完了しました（メッセージ{}件）\n", delivered);
+            }
+            Err(e) => eprintln!("記録ファイルを読み込めませんでした：{}\n", e),
         }
+        drop(context);
+        let _ = dispatcher_thread.join();
+        drop(listener);
+        WSACleanup();
+        return true;
     }
-}
-
-pub unsafe fn unit_05() -> bool {
-    if !startup_wsa() {
-        return false;
+    if let Some(commands) = admin_commands {
+        spawn_admin_handler(commands, context.clone());
+    }
+    let relay =
+        config
+            .relay
+            .clone()
+            .and_then(|relay_config| match RelayServer::spawn(relay_config) {
+                Ok(relay) => {
+                    println!("リレーサーバーを{}で起動しました。\n", relay.local_addr());
+                    Some(relay)
+                }
+                Err(e) => {
+                    eprintln!("リレーサーバーの起動に失敗しました：{}\n", e);
+                    None
+                }
+            });
+    if let Some(address) = config.admin_http.as_ref() {
+        let api = AdminApi {
+            leaderboard: context.leaderboard.clone(),
+            metrics: Some(context.metrics.clone()),
+            relay,
+            clients: Some(context.clients.clone()),
+        };
+        match api.spawn(address) {
+            Ok(_) => println!("管理APIを{}で起動しました。\n", address),
+            Err(e) => eprintln!("管理APIの起動に失敗しました：{}\n", e),
+        }
     }
 
-    let server_socket = create_and_bind_socket().expect("Failed to create server socket.");
-    let result = listen(&server_socket, SOMAXCONN as i32);
-    if !check_socket_error(result, "Socket failed to start listening.") {
-        return false;
+    if let Some(query_config) = config.query.clone() {
+        let rules = vec![
+            ("transport".to_string(), config.transport.name().to_string()),
+            (
+                "require_login".to_string(),
+                config.require_login.to_string(),
+            ),
+            (
+                "max_frame_size".to_string(),
+                config.max_frame_size.to_string(),
+            ),
+            (
+                "pow_difficulty".to_string(),
+                config.pow_difficulty.unwrap_or(0).to_string(),
+            ),
+        ];
+        match QueryServer::spawn(
+            query_config,
+            context.clients.clone(),
+            config.max_clients,
+            rules,
+        ) {
+            Ok(query) => println!(
+                "サーバー情報クエリを{}で受け付けます。\n",
+                query.local_addr()
+            ),
+            Err(e) => eprintln!("サーバー情報クエリの起動に失敗しました：{}\n", e),
+        }
     }
 
-    println!("サーバーが起動しました。\n");
-    let server_msg = "Hello".to_string();
+    let geoip = config
+        .geoip
+        .as_ref()
+        .and_then(|geoip_config| match GeoIp::open(geoip_config) {
+            Ok(geoip) => {
+                println!(
+                    "GeoIPデータベース{}を読み込みました。\n",
+                    geoip_config.database.display()
+                );
+                Some(geoip)
+            }
+            Err(e) => {
+                eprintln!("GeoIPデータベースを開けませんでした：{}\n", e);
+                None
+            }
+        });
 
-    let mut client_pool = ClientPool::new(DEFAULT_MAX_CLIENTS);
+    let handler: Arc<dyn ServerHandler> = Arc::new(ChatHandler::new(
+        context.clone(),
+        PluginRegistry::from_names(&config.plugins),
+    ));
+    spawn_ticker(handler.clone(), config.tick_interval);
+    if let Some(bots) = config.bots.as_ref() {
+        println!("ルーム{}にボットを{}体追加します\n", &bots.room, bots.count);
+        for _ in 0..bots.count {
+            let client = connect_bot(&context.clients, Box::new(ReadyBot::new(&bots.room)));
+            let (client_id, address) = {
+                let client_lock = client.read().expect("Failed to lock client socket.");
+                (client_lock.id, client_lock.address.clone())
+            };
+            let _ = context
+                .events
+                .send(ServerEvent::ClientJoined { client_id, address });
+            client_pool.start_messaging(handler.clone(), client);
+        }
+    }
+    let mut throttle = AcceptThrottle::new(config.throttle.clone(), Instant::now());
 
     loop {
-        let client = client_pool.find_empty_client();
-        let mut client_addr_size = CLIENT_ADDR_SIZE;
-        let mut client_lock = client.try_write().expect("Failed to lock client socket.");
-        let accepted_socket = accept(
-            &server_socket,
-            &mut client_lock.addr as *mut _ as *mut SOCKADDR,
-            &mut client_addr_size as *mut _ as *mut i32,
-        );
-        client_lock.socket = accepted_socket;
-
-        if client_lock.socket.0 == INVALID_SOCKET {
-            eprintln!("クライアントと接続失敗。エラー：{}\n", WSAGetLastError().0);
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = config.chaos.as_ref() {
+            std::thread::sleep(chaos.sample_accept_delay());
+        }
+        let (mut transport, address) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("クライアントと接続失敗。エラー：{}\n", e);
+                continue;
+            }
+        };
+        let peer = transport.peer_addr();
+        let endpoint = peer.map_or_else(|| address.clone(), |peer| peer.to_string());
+        context.ip_stats.record_connect(&address);
+        if let Err(rejection) =
+            throttle.admit(context.clients.connections_from(&address), Instant::now())
+        {
+            match rejection {
+                Rejection::RateLimited => {
+                    println!(
+                        "接続が集中しているため、{}からの接続を拒否しました\n",
+                        &address
+                    )
+                }
+                Rejection::TooManyConnections(max) => println!(
+                    "{}からの同時接続数が上限（{}）に達したため、接続を拒否しました\n",
+                    &address, max
+                ),
+            }
+            context.ip_stats.record_failure(&address);
+            transport.close();
             continue;
         }
+        if let Some(netem) = config.netem.clone() {
+            transport = Arc::new(NetemTransport::new(transport, netem));
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = config.chaos.clone() {
+            transport = Arc::new(ChaosTransport::new(transport, chaos));
+        }
+        if config.plain_text {
+            transport = Arc::new(PlainTextTransport::new(transport));
+        }
+        if let Some(layers) = config.layers.clone().filter(|_| !config.plain_text) {
+            transport = Arc::new(LayeredTransport::new(transport, layers));
+        }
+        if let Some(compression) = config.compression.clone().filter(|_| !config.plain_text) {
+            match CompressedTransport::negotiate(transport.clone(), compression) {
+                Ok(compressed) => transport = Arc::new(compressed),
+                Err(e) => {
+                    eprintln!("圧縮方式の交渉に失敗しました：{}\n", e);
+                    context.ip_stats.record_failure(&address);
+                    transport.close();
+                    continue;
+                }
+            }
+        }
+        transport = Arc::new(EncodingTransport::new(transport, config.text_encoding));
+        transport = Arc::new(PriorityTransport::new(transport));
 
-        let ip_address = format!(
-            "クライアントが接続してきました！：IPAddress({}.{}.{}.{})\n",
-            client_lock.addr.sin_addr.S_un.S_un_b.s_b1,
-            client_lock.addr.sin_addr.S_un.S_un_b.s_b2,
-            client_lock.addr.sin_addr.S_un.S_un_b.s_b3,
-            client_lock.addr.sin_addr.S_un.S_un_b.s_b4,
-        );
+        let location = geoip.as_ref().and_then(|geoip| geoip.lookup(&address));
+        let ip_address = match location.as_ref() {
+            Some(location) => format!(
+                "クライアントが接続してきました！：IPAddress({})（{}）\n",
+                &endpoint, location
+            ),
+            None => format!(
+                "クライアントが接続してきました！：IPAddress({})\n",
+                &endpoint
+            ),
+        };
         println!("{}", &ip_address);
+
+        if let Some(ban) = context
+            .storage
+            .as_ref()
+            .and_then(|storage| storage.find_ban(&address).ok().flatten())
+        {
+            println!("BANされたクライアントの接続を拒否しました：{}\n", &address);
+            let notice = match ban.expires_at {
+                Some(expires_at) => format!("You are banned until {}: {}", expires_at, ban.reason),
+                None => format!("You are banned: {}", ban.reason),
+            };
+            let _ = transport.send_text(&notice);
+            context.ip_stats.record_failure(&address);
+            transport.close();
+            continue;
+        }
+
+        let client = context.clients.find_empty();
+        let mut client_lock = client.write().expect("Failed to lock client socket.");
+        client_lock.address = address.clone();
+        client_lock.peer = peer;
+        client_lock.location = location;
+        client_lock.connected_at = Some(Instant::now());
+        client_lock.transport = Some(transport);
         let client_id = client_lock.id;
+        if let Some(recorder) = context.recorder.as_ref() {
+            recorder.record(client_id, PacketKind::Join, &address);
+        }
+        let _ = context
+            .events
+            .send(ServerEvent::ClientJoined { client_id, address });
         drop(client_lock);
-        let other_clients = client_pool
-            .socket_clients
-            .clone()
-            .into_iter()
-            .filter(|c| c.try_read().expect("Failed to lock client socket.").id != client_id)
-            .collect::<Vec<_>>();
-        client_pool.start_messaging(client, server_msg.clone(), other_clients);
+        client_pool.start_messaging(handler.clone(), client);
     }
 }
@@ -0,0 +1,218 @@
+use online_game_programming::anticheat::AntiCheat;
+use online_game_programming::bindings::Windows::Win32::Networking::WinSock::{
+    closesocket, listen, WSACleanup, WSAGetLastError, SOCKET, SOCKET_ERROR, SOMAXCONN,
+};
+use online_game_programming::clients::ClientRegistry;
+use online_game_programming::clock::ServerClock;
+use online_game_programming::config::ServerConfig;
+use online_game_programming::context::ServerContext;
+use online_game_programming::events::{EventDispatcher, ServerEvent};
+use online_game_programming::plugins::PluginRegistry;
+use online_game_programming::scoreboard::Scoreboards;
+use online_game_programming::server::{
+    accept_client, client_ip, create_and_bind_socket, socket_addr, startup_wsa, ClientContext,
+    ClientSession, Flow, ServerHandler,
+};
+use online_game_programming::session::ChatHandler;
+use online_game_programming::transport::{
+    EncodingTransport, SocketTransport, Transport, HALF_CLOSE_TIMEOUT,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winapi::um::winsock2::{fd_set, select, timeval, FD_SETSIZE};
+
+const BUFFER_SIZE: usize = 2048;
+
+struct Connection {
+    socket: SOCKET,
+    session: ClientSession,
+    closing: Option<Instant>,
+}
+
+fn read_set(sockets: impl Iterator<Item = SOCKET>) -> fd_set {
+    let mut set = fd_set {
+        fd_count: 0,
+        fd_array: [0; FD_SETSIZE],
+    };
+    for socket in sockets.take(FD_SETSIZE) {
+        set.fd_array[set.fd_count as usize] = socket.0;
+        set.fd_count += 1;
+    }
+    set
+}
+
+fn is_set(set: &fd_set, socket: SOCKET) -> bool {
+    set.fd_array[..set.fd_count as usize].contains(&socket.0)
+}
+
+fn timeout(duration: Duration) -> timeval {
+    timeval {
+        tv_sec: duration.as_secs() as _,
+        tv_usec: duration.subsec_micros() as _,
+    }
+}
+
+unsafe fn accept_session(
+    listener: SOCKET,
+    context: &ServerContext,
+    handler: &ChatHandler,
+    config: &ServerConfig,
+) -> Option<Connection> {
+    let (socket, address) = match accept_client(&listener) {
+        Some(accepted) => accepted,
+        None => {
+            eprintln!("クライアントと接続失敗。エラー：{}\n", WSAGetLastError().0);
+            return None;
+        }
+    };
+    let peer = socket_addr(&address);
+    let address = client_ip(&address);
+    let transport: Arc<dyn Transport> = Arc::new(EncodingTransport::new(
+        Arc::new(SocketTransport::new(socket)),
+        config.text_encoding,
+    ));
+    println!("クライアントが接続してきました！：IPAddress({})\n", peer);
+    context.ip_stats.record_connect(&address);
+
+    let client = context.clients.find_empty();
+    let mut client_lock = client.write().expect("Failed to lock client socket.");
+    client_lock.address = address.clone();
+    client_lock.peer = Some(peer);
+    client_lock.connected_at = Some(Instant::now());
+    client_lock.transport = Some(transport.clone());
+    let client_id = client_lock.id;
+    drop(client_lock);
+    let _ = context.events.send(ServerEvent::ClientJoined {
+        client_id,
+        address: address.clone(),
+    });
+
+    let client = ClientContext {
+        id: client_id,
+        address,
+        transport,
+    };
+    handler.on_client_connected(&client);
+    Some(Connection {
+        socket,
+        session: ClientSession::new(client, config.max_frame_size),
+        closing: None,
+    })
+}
+
+pub unsafe fn unit_05_select() -> bool {
+    if !startup_wsa() {
+        return false;
+    }
+
+    let config = ServerConfig::from_env();
+    let listener = match create_and_bind_socket(config.port) {
+        Some(listener) => listener,
+        None => return false,
+    };
+    if listen(listener, SOMAXCONN as i32) == SOCKET_ERROR {
+        eprintln!("listenに失敗しました：{}\n", WSAGetLastError().0);
+        closesocket(listener);
+        WSACleanup();
+        return false;
+    }
+
+    println!("サーバーが起動しました。（select）\n");
+
+    let clients = ClientRegistry::new(config.max_clients);
+    let (events, _dispatcher_thread) = EventDispatcher::new().spawn();
+    let mut context = ServerContext::new(clients, events);
+    context.idle_timeout = config.idle_timeout;
+    context.afk_timeout = config.afk_timeout;
+    context.pre_auth_timeout = config.throttle.pre_auth_timeout;
+    context.pow_difficulty = config.pow_difficulty;
+    context.heartbeat_interval = config.heartbeat_interval;
+    context.clock = ServerClock::new(config.tick_interval);
+    context.scoreboards = Scoreboards::new(config.scoreboard_interval);
+    context.anticheat = config.anticheat.clone().and_then(|anticheat_config| {
+        AntiCheat::new(anticheat_config)
+            .map_err(|e| eprintln!("不正行為の証拠ファイルを開けませんでした：{}\n", e))
+            .ok()
+    });
+    let handler = ChatHandler::new(context.clone(), PluginRegistry::from_names(&config.plugins));
+
+    // The listener needs one of the FD_SETSIZE entries, so stop accepting once
+    // the remaining entries are taken and let the backlog wait.
+    let capacity = config.max_clients.min(FD_SETSIZE - 1);
+    let mut connections: Vec<Connection> = Vec::with_capacity(capacity);
+    let mut recv_buffer = [0_u8; BUFFER_SIZE];
+    let mut next_tick = Instant::now() + config.tick_interval;
+
+    loop {
+        let accepting = connections.len() < capacity;
+        let mut readable = read_set(
+            Some(listener)
+                .filter(|_| accepting)
+                .into_iter()
+                .chain(connections.iter().map(|connection| connection.socket)),
+        );
+        let wait = timeout(next_tick.saturating_duration_since(Instant::now()));
+        let ready = select(
+            0,
+            &mut readable,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &wait,
+        );
+        if ready == SOCKET_ERROR {
+            eprintln!("selectに失敗しました：{}\n", WSAGetLastError().0);
+            break;
+        }
+
+        if Instant::now() >= next_tick {
+            handler.on_tick();
+            next_tick = Instant::now() + config.tick_interval;
+        }
+
+        if accepting && is_set(&readable, listener) {
+            if let Some(connection) = accept_session(listener, &context, &handler, &config) {
+                connections.push(connection);
+            }
+        }
+
+        let now = Instant::now();
+        connections.retain_mut(|connection| {
+            let transport = connection.session.client.transport.clone();
+            let flow = if !is_set(&readable, connection.socket) {
+                match connection.closing {
+                    Some(deadline) if deadline <= now => Flow::Disconnect,
+                    _ => return true,
+                }
+            } else {
+                match transport.receive(&mut recv_buffer) {
+                    Ok(0) | Err(_) => Flow::Disconnect,
+                    Ok(_) if connection.closing.is_some() => return true,
+                    Ok(recv_size) => {
+                        let flow = connection
+                            .session
+                            .deliver(&handler, &recv_buffer[..recv_size]);
+                        if flow == Flow::Disconnect && transport.shutdown_send() {
+                            connection.closing = Some(now + HALF_CLOSE_TIMEOUT);
+                            return true;
+                        }
+                        flow
+                    }
+                }
+            };
+            if flow == Flow::Continue {
+                return true;
+            }
+            transport.close();
+            handler.on_client_disconnected(&connection.session.client);
+            false
+        });
+    }
+
+    for connection in connections {
+        connection.session.client.transport.close();
+        handler.on_client_disconnected(&connection.session.client);
+    }
+    closesocket(listener);
+    WSACleanup();
+    false
+}
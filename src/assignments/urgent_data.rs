@@ -0,0 +1,135 @@
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::os::windows::io::AsRawSocket;
+use winapi::shared::minwindef::BOOL;
+use winapi::shared::ws2def::{SOL_SOCKET, SO_OOBINLINE};
+use winapi::um::winsock2::{
+    fd_set, recv, select, send, setsockopt, timeval, FD_SETSIZE, MSG_OOB, SOCKET, SOCKET_ERROR,
+};
+
+const BUFFER_SIZE: usize = 64;
+
+fn raw(stream: &TcpStream) -> SOCKET {
+    stream.as_raw_socket() as SOCKET
+}
+
+fn check(result: i32) -> std::io::Result<i32> {
+    if result == SOCKET_ERROR {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+unsafe fn send_urgent(stream: &TcpStream, byte: u8) -> std::io::Result<()> {
+    check(send(
+        raw(stream),
+        &byte as *const u8 as *const _,
+        1,
+        MSG_OOB,
+    ))
+    .map(|_| ())
+}
+
+unsafe fn receive_urgent(stream: &TcpStream) -> std::io::Result<u8> {
+    let mut byte = 0_u8;
+    check(recv(
+        raw(stream),
+        &mut byte as *mut u8 as *mut _,
+        1,
+        MSG_OOB,
+    ))?;
+    Ok(byte)
+}
+
+unsafe fn wait_for_urgent(stream: &TcpStream) -> std::io::Result<bool> {
+    let mut exceptional = fd_set {
+        fd_count: 1,
+        fd_array: [0; FD_SETSIZE],
+    };
+    exceptional.fd_array[0] = raw(stream);
+    let timeout = timeval {
+        tv_sec: 1,
+        tv_usec: 0,
+    };
+    let ready = check(select(
+        0,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        &mut exceptional,
+        &timeout,
+    ))?;
+    Ok(ready > 0)
+}
+
+unsafe fn set_oob_inline(stream: &TcpStream, enabled: bool) -> std::io::Result<()> {
+    let value = enabled as BOOL;
+    check(setsockopt(
+        raw(stream),
+        SOL_SOCKET,
+        SO_OOBINLINE,
+        &value as *const BOOL as *const _,
+        std::mem::size_of::<BOOL>() as i32,
+    ))
+    .map(|_| ())
+}
+
+unsafe fn demonstrate(client: &TcpStream, server: &TcpStream) -> std::io::Result<()> {
+    println!("--- 帯域外データ（MSG_OOB） ---\n");
+    (&*client).write_all(b"normal")?;
+    send_urgent(client, b'!')?;
+    if wait_for_urgent(server)? {
+        println!("selectの例外セットで緊急データの到着を検出しました\n");
+    }
+    let urgent = receive_urgent(server)?;
+    println!("MSG_OOBで受信した緊急データ：{}\n", urgent as char);
+    let mut buffer = [0_u8; BUFFER_SIZE];
+    let size = (&*server).read(&mut buffer)?;
+    println!(
+        "通常のrecvで受信したデータ：{}\n",
+        String::from_utf8_lossy(&buffer[..size])
+    );
+
+    println!("--- SO_OOBINLINE ---\n");
+    set_oob_inline(server, true)?;
+    (&*client).write_all(b"abc")?;
+    send_urgent(client, b'X')?;
+    (&*client).write_all(b"def")?;
+    client.shutdown(Shutdown::Write)?;
+    let mut inline = vec![];
+    (&*server).read_to_end(&mut inline)?;
+    println!(
+        "緊急データを含めて通常のrecvで受信したデータ：{}\n",
+        String::from_utf8_lossy(&inline)
+    );
+    Ok(())
+}
+
+pub unsafe fn urgent_data() -> bool {
+    let listener = match TcpListener::bind(("127.0.0.1", 0)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("リスナーを起動できませんでした：{}\n", e);
+            return false;
+        }
+    };
+    let connected = listener
+        .local_addr()
+        .and_then(TcpStream::connect)
+        .and_then(|client| listener.accept().map(|(server, _)| (client, server)));
+    let (client, server) = match connected {
+        Ok(connected) => connected,
+        Err(e) => {
+            eprintln!("ループバック接続に失敗しました：{}\n", e);
+            return false;
+        }
+    };
+
+    match demonstrate(&client, &server) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("緊急データの送受信に失敗しました：{}\n", e);
+            false
+        }
+    }
+}
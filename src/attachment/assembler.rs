@@ -0,0 +1,215 @@
+use super::{AttachmentError, AttachmentPolicy};
+use crate::codec::{AttachmentRef, ChatBody, Message};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attachment {
+    pub reference: AttachmentRef,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn messages(&self, room: &str, sender: Option<String>, chunk_size: usize) -> Vec<Message> {
+        let reference = &self.reference;
+        let mut messages = vec![Message::AttachmentOffer {
+            id: reference.id,
+            name: reference.name.clone(),
+            mime: reference.mime.clone(),
+            size: reference.size,
+        }];
+        messages.extend(
+            self.data
+                .chunks(chunk_size.max(1))
+                .enumerate()
+                .map(|(index, chunk)| Message::AttachmentChunk {
+                    id: reference.id,
+                    index: index as u32,
+                    data: chunk.to_vec(),
+                }),
+        );
+        messages.push(Message::Chat {
+            room: room.to_string(),
+            sender,
+            body: ChatBody::Attachment(reference.clone()),
+        });
+        messages
+    }
+}
+
+struct PendingAttachment {
+    reference: AttachmentRef,
+    next_index: u32,
+    data: Vec<u8>,
+}
+
+pub struct AttachmentAssembler {
+    policy: AttachmentPolicy,
+    pending: HashMap<u32, PendingAttachment>,
+}
+
+impl AttachmentAssembler {
+    pub fn new(policy: AttachmentPolicy) -> Self {
+        AttachmentAssembler {
+            policy,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn offer(
+        &mut self,
+        reference: AttachmentRef,
+    ) -> Result<Option<Attachment>, AttachmentError> {
+        self.policy
+            .check(&reference.mime, reference.size as usize)?;
+        if reference.size == 0 {
+            self.pending.remove(&reference.id);
+            return Ok(Some(Attachment {
+                reference,
+                data: vec![],
+            }));
+        }
+        self.pending.insert(
+            reference.id,
+            PendingAttachment {
+                data: Vec::with_capacity(reference.size as usize),
+                reference,
+                next_index: 0,
+            },
+        );
+        Ok(None)
+    }
+
+    pub fn push(
+        &mut self,
+        id: u32,
+        index: u32,
+        data: &[u8],
+    ) -> Result<Option<Attachment>, AttachmentError> {
+        let pending = self
+            .pending
+            .get_mut(&id)
+            .ok_or(AttachmentError::UnknownTransfer(id))?;
+        let error = if index != pending.next_index {
+            Some(AttachmentError::OutOfOrder {
+                expected: pending.next_index,
+                received: index,
+            })
+        } else if pending.data.len() + data.len() > pending.reference.size as usize {
+            Some(AttachmentError::Overflow(id))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.pending.remove(&id);
+            return Err(error);
+        }
+        pending.data.extend_from_slice(data);
+        pending.next_index += 1;
+        if pending.data.len() < pending.reference.size as usize {
+            return Ok(None);
+        }
+        Ok(self.pending.remove(&id).map(|pending| Attachment {
+            reference: pending.reference,
+            data: pending.data,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(id: u32, mime: &str, size: u32) -> AttachmentRef {
+        AttachmentRef {
+            id,
+            name: "cat.png".to_string(),
+            mime: mime.to_string(),
+            size,
+        }
+    }
+
+    fn policy() -> AttachmentPolicy {
+        AttachmentPolicy {
+            max_size: 8,
+            chunk_size: 3,
+            allowed_types: vec!["image/*".to_string(), "text/plain".to_string()],
+        }
+    }
+
+    #[test]
+    fn chunks_are_reassembled_in_order() {
+        let attachment = Attachment {
+            reference: reference(1, "image/png", 7),
+            data: b"meow!!!".to_vec(),
+        };
+        let messages = attachment.messages("lobby", Some("alice".to_string()), 3);
+        assert_eq!(messages.len(), 5);
+
+        let mut assembler = AttachmentAssembler::new(policy());
+        let mut completed = None;
+        for message in messages {
+            completed = match message {
+                Message::AttachmentOffer {
+                    id,
+                    name,
+                    mime,
+                    size,
+                } => assembler
+                    .offer(AttachmentRef {
+                        id,
+                        name,
+                        mime,
+                        size,
+                    })
+                    .expect("The offer was rejected."),
+                Message::AttachmentChunk { id, index, data } => assembler
+                    .push(id, index, &data)
+                    .expect("The chunk was rejected."),
+                Message::Chat { body, .. } => {
+                    assert_eq!(body, ChatBody::Attachment(reference(1, "image/png", 7)));
+                    completed
+                }
+                _ => unreachable!(),
+            };
+        }
+        assert_eq!(completed, Some(attachment));
+    }
+
+    #[test]
+    fn policy_and_protocol_violations_are_rejected() {
+        let mut assembler = AttachmentAssembler::new(policy());
+        assert_eq!(
+            assembler.offer(reference(1, "image/png", 9)),
+            Err(AttachmentError::TooLarge(8))
+        );
+        assert_eq!(
+            assembler.offer(reference(1, "application/zip", 4)),
+            Err(AttachmentError::TypeNotAllowed(
+                "application/zip".to_string()
+            ))
+        );
+        assert_eq!(
+            assembler.push(2, 0, b"hi"),
+            Err(AttachmentError::UnknownTransfer(2))
+        );
+
+        assert_eq!(assembler.offer(reference(3, "TEXT/PLAIN", 4)), Ok(None));
+        assert_eq!(
+            assembler.push(3, 1, b"hi"),
+            Err(AttachmentError::OutOfOrder {
+                expected: 0,
+                received: 1
+            })
+        );
+        assert_eq!(
+            assembler.push(3, 0, b"hi"),
+            Err(AttachmentError::UnknownTransfer(3))
+        );
+
+        assert_eq!(assembler.offer(reference(4, "image/gif", 4)), Ok(None));
+        assert_eq!(
+            assembler.push(4, 0, b"hello"),
+            Err(AttachmentError::Overflow(4))
+        );
+    }
+}
@@ -0,0 +1,4 @@
+mod assembler;
+mod policy;
+pub use assembler::*;
+pub use policy::*;
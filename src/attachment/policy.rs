@@ -0,0 +1,101 @@
+use crate::config::env_or;
+use std::fmt::{Display, Formatter};
+
+pub const DEFAULT_MAX_ATTACHMENT_SIZE: usize = 256 * 1024;
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+pub const DEFAULT_ATTACHMENT_TYPES: &str = "image/png,image/jpeg,image/gif,text/plain";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttachmentError {
+    TooLarge(usize),
+    TypeNotAllowed(String),
+    UnknownTransfer(u32),
+    OutOfOrder { expected: u32, received: u32 },
+    Overflow(u32),
+}
+
+impl Display for AttachmentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttachmentError::TooLarge(max_size) => {
+                write!(f, "Attachments must be at most {} bytes.", max_size)
+            }
+            AttachmentError::TypeNotAllowed(mime) => {
+                write!(f, "Attachments of type {} are not allowed.", mime)
+            }
+            AttachmentError::UnknownTransfer(id) => write!(f, "Unknown attachment {}.", id),
+            AttachmentError::OutOfOrder { expected, received } => write!(
+                f,
+                "Expected attachment chunk {} but received {}.",
+                expected, received
+            ),
+            AttachmentError::Overflow(id) => {
+                write!(f, "Attachment {} is larger than announced.", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttachmentError {}
+
+#[derive(Clone, Debug)]
+pub struct AttachmentPolicy {
+    pub max_size: usize,
+    pub chunk_size: usize,
+    pub allowed_types: Vec<String>,
+}
+
+impl Default for AttachmentPolicy {
+    fn default() -> Self {
+        AttachmentPolicy {
+            max_size: DEFAULT_MAX_ATTACHMENT_SIZE,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            allowed_types: parse_types(DEFAULT_ATTACHMENT_TYPES),
+        }
+    }
+}
+
+impl AttachmentPolicy {
+    pub fn from_env() -> Self {
+        AttachmentPolicy {
+            max_size: env_or("ATTACHMENT_MAX_BYTES", "")
+                .parse()
+                .unwrap_or(DEFAULT_MAX_ATTACHMENT_SIZE),
+            chunk_size: env_or("ATTACHMENT_CHUNK_BYTES", "")
+                .parse()
+                .ok()
+                .filter(|chunk_size| *chunk_size > 0)
+                .unwrap_or(DEFAULT_CHUNK_SIZE),
+            allowed_types: parse_types(&env_or("ATTACHMENT_TYPES", DEFAULT_ATTACHMENT_TYPES)),
+        }
+    }
+
+    pub fn check(&self, mime: &str, size: usize) -> Result<(), AttachmentError> {
+        if size > self.max_size {
+            return Err(AttachmentError::TooLarge(self.max_size));
+        }
+        let mime = mime.to_ascii_lowercase();
+        let allowed = self
+            .allowed_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(family) => mime
+                    .split_once('/')
+                    .is_some_and(|(prefix, _)| prefix == family),
+                None => *allowed == mime,
+            });
+        if allowed {
+            Ok(())
+        } else {
+            Err(AttachmentError::TypeNotAllowed(mime))
+        }
+    }
+}
+
+fn parse_types(types: &str) -> Vec<String> {
+    types
+        .split(',')
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .filter(|mime| !mime.is_empty())
+        .collect()
+}
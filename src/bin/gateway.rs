@@ -0,0 +1,18 @@
+use online_game_programming::gateway::{Gateway, GatewayConfig};
+
+fn main() {
+    let config = GatewayConfig::from_env();
+    if config.backends.is_empty() {
+        eprintln!("GATEWAY_BACKENDSにバックエンドが設定されていません。\n");
+        std::process::exit(1);
+    }
+    let gateway = Gateway::bind(&config).expect("Failed to bind the gateway.");
+    println!(
+        "ゲートウェイを{}で起動しました（バックエンド：{}）\n",
+        gateway
+            .local_addr()
+            .expect("Failed to read gateway address."),
+        config.backends.join(", ")
+    );
+    gateway.run();
+}
@@ -0,0 +1,273 @@
+use online_game_programming::config::env_or;
+use rand::Rng;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::windows::io::AsRawHandle;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winapi::shared::minwindef::DWORD;
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::processthreadsapi::GetProcessHandleCount;
+use winapi::um::psapi::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+use winapi::um::tlhelp32::{
+    CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+};
+use winapi::um::winnt::HANDLE;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct SoakConfig {
+    server: PathBuf,
+    duration: Duration,
+    bots: usize,
+    sample_interval: Duration,
+    warmup: Duration,
+    max_thread_growth: i64,
+    max_handle_growth: i64,
+    max_memory_growth: i64,
+}
+
+impl SoakConfig {
+    fn from_env() -> Self {
+        let server = std::env::var("SOAK_SERVER")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                std::env::current_exe()
+                    .expect("Failed to locate the soak binary.")
+                    .with_file_name("online_game_programming.exe")
+            });
+        SoakConfig {
+            server,
+            duration: Duration::from_secs(
+                env_or("SOAK_DURATION_SECS", "")
+                    .parse()
+                    .unwrap_or(4 * 60 * 60),
+            ),
+            bots: env_or("SOAK_BOTS", "").parse().unwrap_or(16),
+            sample_interval: Duration::from_secs(
+                env_or("SOAK_SAMPLE_SECS", "").parse().unwrap_or(60),
+            ),
+            warmup: Duration::from_secs(env_or("SOAK_WARMUP_SECS", "").parse().unwrap_or(120)),
+            max_thread_growth: env_or("SOAK_MAX_THREAD_GROWTH", "").parse().unwrap_or(8),
+            max_handle_growth: env_or("SOAK_MAX_HANDLE_GROWTH", "").parse().unwrap_or(64),
+            max_memory_growth: env_or("SOAK_MAX_MEMORY_GROWTH_KB", "")
+                .parse::<i64>()
+                .unwrap_or(32 * 1024)
+                * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    elapsed: Duration,
+    working_set: usize,
+    handles: u32,
+    threads: u32,
+}
+
+fn thread_count(process_id: DWORD) -> u32 {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return 0;
+        }
+        let mut entry: THREADENTRY32 = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as DWORD;
+        let mut count = 0;
+        let mut found = Thread32First(snapshot, &mut entry);
+        while found != 0 {
+            if entry.th32OwnerProcessID == process_id {
+                count += 1;
+            }
+            found = Thread32Next(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+        count
+    }
+}
+
+fn sample(server: &Child, started: Instant) -> Sample {
+    let process = server.as_raw_handle() as HANDLE;
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    let mut handles = 0;
+    unsafe {
+        K32GetProcessMemoryInfo(
+            process,
+            &mut counters,
+            std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as DWORD,
+        );
+        GetProcessHandleCount(process, &mut handles);
+    }
+    Sample {
+        elapsed: started.elapsed(),
+        working_set: counters.WorkingSetSize,
+        handles,
+        threads: thread_count(server.id()),
+    }
+}
+
+fn start_server(config: &SoakConfig) -> (Child, u16, PathBuf) {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Failed to find a free port.")
+        .port();
+    let directory = std::env::temp_dir().join(format!("ogp-soak-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).expect("Failed to create soak directory.");
+
+    let mut child = Command::new(&config.server)
+        .env("SERVER_PORT", port.to_string())
+        .env("DATABASE_PATH", directory.join("soak.db"))
+        .env_remove("DATABASE_URL")
+        .env_remove("RECORD_DIR")
+        .env_remove("PLAYBACK_PATH")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start server.");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("Server has no stdout."));
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = stdout
+            .read_line(&mut line)
+            .expect("Failed to read server output.");
+        assert!(read > 0, "Server exited before it started listening.");
+        if line.contains("サーバーが起動しました") {
+            break;
+        }
+    }
+    std::thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::sink()));
+    (child, port, directory)
+}
+
+#[derive(Default)]
+struct BotStats {
+    sessions: AtomicU64,
+    messages: AtomicU64,
+    failures: AtomicU64,
+}
+
+fn run_session(port: u16, bot: usize, stats: &BotStats) -> std::io::Result<()> {
+    let mut rng = rand::thread_rng();
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut buffer = [0_u8; 2048];
+    let _ = stream.read(&mut buffer)?;
+
+    for sequence in 0..rng.gen_range(1..20) {
+        stream.write_all(format!("bot{} message{}\0", bot, sequence).as_bytes())?;
+        stats.messages.fetch_add(1, Ordering::Relaxed);
+        let _ = stream.read(&mut buffer)?;
+        std::thread::sleep(Duration::from_millis(rng.gen_range(10..200)));
+    }
+
+    if rng.gen_bool(0.5) {
+        stream.write_all(b":end\0")?;
+        let _ = stream.read(&mut buffer);
+    }
+    stats.sessions.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+fn spawn_bot(port: u16, bot: usize, stats: Arc<BotStats>, running: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        while running.load(Ordering::Relaxed) {
+            if run_session(port, bot, &stats).is_err() {
+                stats.failures.fetch_add(1, Ordering::Relaxed);
+            }
+            let pause = rand::thread_rng().gen_range(50..500);
+            std::thread::sleep(Duration::from_millis(pause));
+        }
+    });
+}
+
+fn main() {
+    let config = SoakConfig::from_env();
+    let (mut server, port, directory) = start_server(&config);
+    println!(
+        "ソークテストを開始しました（ボット{}、{}秒間、ポート{}）\n",
+        config.bots,
+        config.duration.as_secs(),
+        port
+    );
+
+    let stats = Arc::new(BotStats::default());
+    let running = Arc::new(AtomicBool::new(true));
+    for bot in 0..config.bots {
+        spawn_bot(port, bot, stats.clone(), running.clone());
+    }
+
+    println!("elapsed_secs,working_set_kb,handles,threads,sessions,messages,failures");
+    let started = Instant::now();
+    let mut baseline = None;
+    let mut last = sample(&server, started);
+    while started.elapsed() < config.duration {
+        std::thread::sleep(config.sample_interval.min(config.duration));
+        if let Ok(Some(status)) = server.try_wait() {
+            eprintln!("サーバーが異常終了しました：{}\n", status);
+            std::process::exit(2);
+        }
+        last = sample(&server, started);
+        println!(
+            "{},{},{},{},{},{},{}",
+            last.elapsed.as_secs(),
+            last.working_set / 1024,
+            last.handles,
+            last.threads,
+            stats.sessions.load(Ordering::Relaxed),
+            stats.messages.load(Ordering::Relaxed),
+            stats.failures.load(Ordering::Relaxed)
+        );
+        if baseline.is_none() && last.elapsed >= config.warmup {
+            baseline = Some(last);
+        }
+    }
+
+    running.store(false, Ordering::Relaxed);
+    let _ = server.kill();
+    let _ = server.wait();
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let baseline = match baseline {
+        Some(baseline) if baseline.elapsed < last.elapsed => baseline,
+        _ => {
+            println!("ウォームアップ後のサンプルが足りないため、リークを判定できません。\n");
+            return;
+        }
+    };
+    let thread_growth = last.threads as i64 - baseline.threads as i64;
+    let handle_growth = last.handles as i64 - baseline.handles as i64;
+    let memory_growth = last.working_set as i64 - baseline.working_set as i64;
+    println!(
+        "\nウォームアップ後の増加：スレッド{}、ハンドル{}、ワーキングセット{}KB\n",
+        thread_growth,
+        handle_growth,
+        memory_growth / 1024
+    );
+
+    let mut leaked = false;
+    if thread_growth > config.max_thread_growth {
+        eprintln!("スレッド数が増え続けています（+{}）\n", thread_growth);
+        leaked = true;
+    }
+    if handle_growth > config.max_handle_growth {
+        eprintln!("ハンドル数が増え続けています（+{}）\n", handle_growth);
+        leaked = true;
+    }
+    if memory_growth > config.max_memory_growth {
+        eprintln!(
+            "ワーキングセットが増え続けています（+{}KB）\n",
+            memory_growth / 1024
+        );
+        leaked = true;
+    }
+    if leaked {
+        std::process::exit(1);
+    }
+    println!("リークは検出されませんでした。\n");
+}
@@ -0,0 +1,208 @@
+use crate::clients::{ClientRegistry, SharedClient};
+use crate::config::env_or;
+use crate::rooms::{valid_room_name, DEFAULT_ROOM};
+use crate::transport::Transport;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+#[derive(Clone, Debug)]
+pub struct BotConfig {
+    pub room: String,
+    pub count: usize,
+}
+
+impl BotConfig {
+    pub fn from_env() -> Option<Self> {
+        let count = env_or("BOT_COUNT", "")
+            .parse()
+            .ok()
+            .filter(|count| *count > 0)?;
+        let room = env_or("BOT_ROOM", DEFAULT_ROOM);
+        if !valid_room_name(&room) {
+            return None;
+        }
+        Some(BotConfig { room, count })
+    }
+}
+
+pub trait BotBehavior: Send {
+    fn on_connected(&mut self, name: &str) -> Vec<String>;
+    fn on_message(&mut self, message: &str) -> Vec<String>;
+}
+
+pub struct ReadyBot {
+    room: String,
+}
+
+impl ReadyBot {
+    pub fn new(room: &str) -> Self {
+        ReadyBot {
+            room: room.to_string(),
+        }
+    }
+}
+
+impl BotBehavior for ReadyBot {
+    fn on_connected(&mut self, _name: &str) -> Vec<String> {
+        vec![format!(":join {}", &self.room), ":ready".to_string()]
+    }
+
+    fn on_message(&mut self, message: &str) -> Vec<String> {
+        match message.strip_prefix("FINAL ") {
+            Some(result) if result.split_whitespace().next() == Some(self.room.as_str()) => {
+                vec!["gg".to_string(), ":ready".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Default)]
+struct Outbox {
+    pending: VecDeque<u8>,
+    closed: bool,
+}
+
+pub struct BotTransport {
+    behavior: Mutex<Box<dyn BotBehavior>>,
+    outbox: Mutex<Outbox>,
+    changed: Condvar,
+}
+
+impl BotTransport {
+    pub fn new(name: &str, mut behavior: Box<dyn BotBehavior>) -> Self {
+        let commands = behavior.on_connected(name);
+        let transport = BotTransport {
+            behavior: Mutex::new(behavior),
+            outbox: Mutex::new(Outbox::default()),
+            changed: Condvar::new(),
+        };
+        transport.queue(&commands);
+        transport
+    }
+
+    fn lock_outbox(&self) -> std::sync::MutexGuard<'_, Outbox> {
+        self.outbox.lock().expect("Failed to lock bot outbox.")
+    }
+
+    fn queue(&self, commands: &[String]) {
+        let mut outbox = self.lock_outbox();
+        if outbox.closed {
+            return;
+        }
+        for command in commands.iter() {
+            outbox.pending.extend(command.as_bytes());
+            outbox.pending.push_back(0);
+        }
+        self.changed.notify_all();
+    }
+}
+
+impl Transport for BotTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let mut outbox = self.lock_outbox();
+        while outbox.pending.is_empty() && !outbox.closed {
+            outbox = self
+                .changed
+                .wait(outbox)
+                .expect("Failed to lock bot outbox.");
+        }
+        let size = outbox.pending.len().min(buffer.len());
+        for (slot, byte) in buffer.iter_mut().zip(outbox.pending.drain(..size)) {
+            *slot = byte;
+        }
+        Ok(size)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        if self.lock_outbox().closed {
+            return Err(Error::new(ErrorKind::BrokenPipe, "the bot has left"));
+        }
+        let replies = {
+            let mut behavior = self.behavior.lock().expect("Failed to lock bot behavior.");
+            let mut replies = vec![];
+            for message in data
+                .split(|b| *b == 0)
+                .filter(|message| !message.is_empty())
+            {
+                let message = String::from_utf8_lossy(message);
+                match message.strip_prefix("PING ") {
+                    Some(nonce) => replies.push(format!(":pong {}", nonce)),
+                    None => replies.extend(behavior.on_message(&message)),
+                }
+            }
+            replies
+        };
+        self.queue(&replies);
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.lock_outbox().closed = true;
+        self.changed.notify_all();
+    }
+
+    fn close(&self) {
+        self.shutdown();
+    }
+}
+
+pub fn connect_bot(clients: &ClientRegistry, behavior: Box<dyn BotBehavior>) -> SharedClient {
+    let client = clients.find_empty();
+    {
+        let mut client_lock = client.write().expect("Failed to lock socket client.");
+        let name = format!("Bot{}", client_lock.id);
+        client_lock.address = format!("bot:{}", client_lock.id);
+        client_lock.transport = Some(Arc::new(BotTransport::new(&name, behavior)));
+        client_lock.nickname = Some(name);
+        client_lock.bot = true;
+        client_lock.connected_at = Some(Instant::now());
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(transport: &BotTransport) -> Vec<String> {
+        let mut buffer = [0_u8; 256];
+        let size = transport.receive(&mut buffer).expect("Failed to receive.");
+        buffer[..size]
+            .split(|b| *b == 0)
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| String::from_utf8_lossy(frame).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn bots_answer_heartbeats_and_ready_up_after_each_match() {
+        let transport = BotTransport::new("Bot3", Box::new(ReadyBot::new("red")));
+        assert_eq!(frames(&transport), vec![":join red", ":ready"]);
+
+        transport
+            .send(b"OK red\0PING 42\0FINAL blue 1.alice:3\0")
+            .expect("Failed to send.");
+        transport
+            .send_text("FINAL red 1.Bot3:2 2.alice:1")
+            .expect("Failed to send.");
+        assert_eq!(frames(&transport), vec![":pong 42", "gg", ":ready"]);
+
+        let reader = Arc::new(transport);
+        let blocked = {
+            let reader = reader.clone();
+            std::thread::spawn(move || reader.receive(&mut [0_u8; 16]))
+        };
+        reader.close();
+        assert_eq!(
+            blocked
+                .join()
+                .expect("Reader panicked.")
+                .expect("Failed to receive."),
+            0
+        );
+        assert!(reader.send_text("PING 7").is_err());
+    }
+}
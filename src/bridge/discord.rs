@@ -0,0 +1,367 @@
+use super::RelayedMessage;
+use crate::clients::ClientRegistry;
+use crate::config::{env_millis, env_or};
+use crate::events::{EventListener, ServerEvent};
+use crate::rooms::DEFAULT_ROOM;
+use native_tls::TlsConnector;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError};
+use std::time::Duration;
+
+const API_HOST: &str = "discord.com";
+const API_PREFIX: &str = "/api/v10";
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_CONTENT_LENGTH: usize = 2000;
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+const QUEUE_SIZE: usize = 256;
+
+#[derive(Clone, Debug)]
+pub struct DiscordConfig {
+    pub token: String,
+    pub channel_id: String,
+    pub room: String,
+    pub tag: String,
+    pub poll_interval: Duration,
+}
+
+impl DiscordConfig {
+    pub fn from_env() -> Option<Self> {
+        let token = std::env::var("DISCORD_BOT_TOKEN").ok()?;
+        let channel_id = std::env::var("DISCORD_CHANNEL_ID").ok()?;
+        Some(DiscordConfig {
+            token,
+            channel_id,
+            room: env_or("DISCORD_ROOM", DEFAULT_ROOM),
+            tag: env_or("DISCORD_TAG", "Discord"),
+            poll_interval: env_millis("DISCORD_POLL_MS")
+                .filter(|interval| !interval.is_zero())
+                .unwrap_or(DEFAULT_POLL_INTERVAL),
+        })
+    }
+
+    fn messages_path(&self) -> String {
+        format!("{}/channels/{}/messages", API_PREFIX, self.channel_id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordAuthor {
+    username: String,
+    #[serde(default)]
+    global_name: Option<String>,
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordMessage {
+    id: String,
+    #[serde(default)]
+    content: String,
+    author: DiscordAuthor,
+    #[serde(default)]
+    webhook_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimit {
+    retry_after: f64,
+}
+
+fn snowflake(id: &str) -> u64 {
+    id.parse().unwrap_or(0)
+}
+
+fn parse_messages(
+    body: &str,
+    config: &DiscordConfig,
+    after: &mut Option<String>,
+) -> serde_json::Result<Vec<RelayedMessage>> {
+    let mut messages = serde_json::from_str::<Vec<DiscordMessage>>(body)?;
+    messages.sort_by_key(|message| snowflake(&message.id));
+    let mut relayed = vec![];
+    for message in messages.into_iter() {
+        if after
+            .as_ref()
+            .map(|after| snowflake(&message.id) <= snowflake(after))
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        *after = Some(message.id.clone());
+        if message.author.bot || message.webhook_id.is_some() || message.content.trim().is_empty() {
+            continue;
+        }
+        let author = message
+            .author
+            .global_name
+            .unwrap_or(message.author.username);
+        relayed.push(RelayedMessage {
+            origin: config.tag.to_lowercase(),
+            room: config.room.clone(),
+            client_id: 0,
+            message: format!("[{}] {}：{}", &config.tag, author, message.content.trim()),
+        });
+    }
+    Ok(relayed)
+}
+
+fn outgoing_content(name: &str, message: &str) -> String {
+    let content = format!("**{}**: {}", name.replace('*', "\\*"), message);
+    match content.char_indices().nth(MAX_CONTENT_LENGTH) {
+        Some((index, _)) => content[..index].to_string(),
+        None => content,
+    }
+}
+
+fn read_body<R: BufRead>(reader: &mut R, chunked: bool) -> std::io::Result<Vec<u8>> {
+    let mut body = vec![];
+    if !chunked {
+        reader.read_to_end(&mut body)?;
+        return Ok(body);
+    }
+    loop {
+        let mut size = String::new();
+        reader.read_line(&mut size)?;
+        let size = usize::from_str_radix(size.trim().split(';').next().unwrap_or(""), 16)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Malformed chunk size."))?;
+        if size == 0 {
+            return Ok(body);
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut terminator = String::new();
+        reader.read_line(&mut terminator)?;
+    }
+}
+
+fn request(
+    token: &str,
+    method: &str,
+    path: &str,
+    payload: Option<&str>,
+) -> std::io::Result<(u16, String)> {
+    let connector = TlsConnector::new().map_err(Error::other)?;
+    let socket = TcpStream::connect((API_HOST, 443))?;
+    socket.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    socket.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut stream = connector
+        .connect(API_HOST, socket)
+        .map_err(|e| Error::other(format!("TLS error: {}", e)))?;
+    let payload = payload.unwrap_or_default();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bot {}\r\nUser-Agent: DiscordBot (online_game_programming, {})\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        API_HOST,
+        token,
+        env!("CARGO_PKG_VERSION"),
+        payload.len(),
+        payload
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Malformed HTTP status line."))?;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let header = header.to_ascii_lowercase();
+        if header.starts_with("transfer-encoding:") && header.contains("chunked") {
+            chunked = true;
+        }
+    }
+    let body = read_body(&mut reader, chunked)?;
+    Ok((status, String::from_utf8_lossy(&body).to_string()))
+}
+
+fn call(token: &str, method: &str, path: &str, payload: Option<&str>) -> std::io::Result<String> {
+    for _ in 0..MAX_RATE_LIMIT_RETRIES {
+        let (status, body) = request(token, method, path, payload)?;
+        match status {
+            200..=299 => return Ok(body),
+            429 => {
+                let retry_after = serde_json::from_str::<RateLimit>(&body)
+                    .map(|limit| limit.retry_after)
+                    .unwrap_or(1.0);
+                std::thread::sleep(Duration::from_secs_f64(retry_after.clamp(0.0, 60.0)));
+            }
+            _ => {
+                return Err(Error::other(format!(
+                    "Discord responded with {}: {}",
+                    status, body
+                )))
+            }
+        }
+    }
+    Err(Error::other("Discord rate limit did not clear."))
+}
+
+fn poll_channel(config: DiscordConfig, mut after: Option<String>, sender: Sender<RelayedMessage>) {
+    loop {
+        std::thread::sleep(config.poll_interval);
+        let path = match after.as_ref() {
+            Some(after) => format!("{}?after={}&limit=50", config.messages_path(), after),
+            None => format!("{}?limit=1", config.messages_path()),
+        };
+        let body = match call(&config.token, "GET", &path, None) {
+            Ok(body) => body,
+            Err(e) => {
+                eprintln!("Discordからの受信に失敗しました：{}\n", e);
+                continue;
+            }
+        };
+        match parse_messages(&body, &config, &mut after) {
+            Ok(messages) => {
+                for message in messages.into_iter() {
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(e) => eprintln!("Discordのメッセージを解析できませんでした：{}\n", e),
+        }
+    }
+}
+
+fn post_messages(config: DiscordConfig, receiver: Receiver<String>) {
+    let path = config.messages_path();
+    for content in receiver.iter() {
+        let payload = serde_json::json!({
+            "content": content,
+            "allowed_mentions": { "parse": [] },
+        })
+        .to_string();
+        if let Err(e) = call(&config.token, "POST", &path, Some(&payload)) {
+            eprintln!("Discordへの送信に失敗しました：{}\n", e);
+        }
+    }
+}
+
+pub struct DiscordBridge {
+    room: String,
+    clients: ClientRegistry,
+    sender: SyncSender<String>,
+}
+
+impl DiscordBridge {
+    pub fn connect(
+        config: DiscordConfig,
+        clients: ClientRegistry,
+    ) -> std::io::Result<(DiscordBridge, Receiver<RelayedMessage>)> {
+        let latest = call(
+            &config.token,
+            "GET",
+            &format!("{}?limit=1", config.messages_path()),
+            None,
+        )?;
+        let mut after = None;
+        parse_messages(&latest, &config, &mut after)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        let (message_sender, message_receiver) = channel();
+        let poll_config = config.clone();
+        std::thread::spawn(move || poll_channel(poll_config, after, message_sender));
+
+        let (sender, receiver) = sync_channel(QUEUE_SIZE);
+        let room = config.room.clone();
+        std::thread::spawn(move || post_messages(config, receiver));
+        Ok((
+            DiscordBridge {
+                room,
+                clients,
+                sender,
+            },
+            message_receiver,
+        ))
+    }
+}
+
+impl EventListener for DiscordBridge {
+    fn on_event(&mut self, event: &ServerEvent) {
+        if let ServerEvent::Chat {
+            client_id,
+            room,
+            message,
+            ..
+        } = event
+        {
+            if *room != self.room {
+                return;
+            }
+            let name = self
+                .clients
+                .get(*client_id)
+                .map(|client| {
+                    client
+                        .read()
+                        .expect("Failed to lock socket client.")
+                        .display_name()
+                })
+                .unwrap_or_else(|| format!("Guest{}", client_id));
+            if let Err(TrySendError::Full(_)) =
+                self.sender.try_send(outgoing_content(&name, message))
+            {
+                eprintln!("Discordへの送信待ちが溢れたため、メッセージを破棄しました\n");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discord_messages_are_relayed_in_order_without_echoing_bots() {
+        let config = DiscordConfig {
+            token: "token".to_string(),
+            channel_id: "42".to_string(),
+            room: "lobby".to_string(),
+            tag: "Discord".to_string(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        };
+        let body = r#"[
+            {"id": "1003", "content": "gg", "author": {"username": "bob", "global_name": "Bob"}},
+            {"id": "1002", "content": "bridged", "author": {"username": "ogp", "bot": true}},
+            {"id": "1001", "content": "hi all ", "author": {"username": "alice"}},
+            {"id": "999", "content": "old", "author": {"username": "carol"}}
+        ]"#;
+        let mut after = Some("1000".to_string());
+
+        let relayed = parse_messages(body, &config, &mut after).expect("Failed to parse.");
+
+        assert_eq!(
+            relayed
+                .iter()
+                .map(|message| message.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["[Discord] alice：hi all", "[Discord] Bob：gg"]
+        );
+        assert_eq!(relayed[0].room, "lobby");
+        assert_eq!(after.as_deref(), Some("1003"));
+        assert_eq!(
+            outgoing_content("*alice*", "hello"),
+            "**\\*alice\\***: hello"
+        );
+        assert_eq!(
+            outgoing_content("alice", &"あ".repeat(3000))
+                .chars()
+                .count(),
+            MAX_CONTENT_LENGTH
+        );
+    }
+}
@@ -0,0 +1,10 @@
+#[cfg(feature = "tls")]
+mod discord;
+mod mqtt;
+mod redis;
+mod webhook;
+#[cfg(feature = "tls")]
+pub use discord::*;
+pub use mqtt::*;
+pub use redis::*;
+pub use webhook::*;
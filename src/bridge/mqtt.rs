@@ -0,0 +1,216 @@
+use crate::admin::AdminCommand;
+use crate::config::env_or;
+use crate::events::{EventListener, ServerEvent};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const PROTOCOL_NAME: &str = "MQTT";
+const PROTOCOL_LEVEL: u8 = 4;
+const CLEAN_SESSION: u8 = 0x02;
+const KEEP_ALIVE_SECS: u16 = 60;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const PINGREQ: u8 = 0xc0;
+const DISCONNECT: u8 = 0xe0;
+
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    pub broker: String,
+    pub client_id: String,
+    pub topic_prefix: String,
+}
+
+impl MqttConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("MQTT_BROKER").ok().map(|broker| MqttConfig {
+            broker,
+            client_id: env_or("MQTT_CLIENT_ID", "online_game_programming"),
+            topic_prefix: env_or("MQTT_TOPIC_PREFIX", "ogp"),
+        })
+    }
+
+    pub fn event_topic(&self, event: &ServerEvent) -> String {
+        format!("{}/events/{}", self.topic_prefix, event.name())
+    }
+
+    pub fn control_topic(&self) -> String {
+        format!("{}/control", self.topic_prefix)
+    }
+}
+
+pub struct MqttBridge {
+    config: MqttConfig,
+    stream: Arc<Mutex<TcpStream>>,
+}
+
+impl MqttBridge {
+    pub fn connect(config: MqttConfig) -> std::io::Result<(MqttBridge, Receiver<AdminCommand>)> {
+        let mut stream = TcpStream::connect(&config.broker)?;
+        stream.write_all(&connect_packet(&config.client_id))?;
+
+        let mut connack = [0_u8; 4];
+        stream.read_exact(&mut connack)?;
+        if connack[0] != CONNACK || connack[3] != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("MQTT broker refused the connection: {}", connack[3]),
+            ));
+        }
+
+        stream.write_all(&subscribe_packet(1, &config.control_topic()))?;
+
+        let (command_sender, command_receiver) = channel();
+        let reader = stream.try_clone()?;
+        let control_topic = config.control_topic();
+        std::thread::spawn(move || read_control_messages(reader, control_topic, command_sender));
+
+        let stream = Arc::new(Mutex::new(stream));
+        let pinger = stream.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_secs((KEEP_ALIVE_SECS / 2) as u64));
+            let mut stream = pinger.lock().expect("Failed to lock MQTT stream.");
+            if stream.write_all(&[PINGREQ, 0]).is_err() {
+                break;
+            }
+        });
+
+        Ok((MqttBridge { config, stream }, command_receiver))
+    }
+
+    pub fn publish(&self, topic: &str, payload: &[u8]) -> std::io::Result<()> {
+        let mut stream = self.stream.lock().expect("Failed to lock MQTT stream.");
+        stream.write_all(&publish_packet(topic, payload))
+    }
+}
+
+impl EventListener for MqttBridge {
+    fn on_event(&mut self, event: &ServerEvent) {
+        let topic = self.config.event_topic(event);
+        if let Err(e) = self.publish(&topic, event.to_json().as_bytes()) {
+            eprintln!("MQTTへの送信に失敗しました：{}", e);
+        }
+    }
+}
+
+impl Drop for MqttBridge {
+    fn drop(&mut self) {
+        if let Ok(mut stream) = self.stream.lock() {
+            let _ = stream.write_all(&[DISCONNECT, 0]);
+        }
+    }
+}
+
+fn read_control_messages(
+    mut stream: TcpStream,
+    control_topic: String,
+    command_sender: Sender<AdminCommand>,
+) {
+    loop {
+        let mut header = [0_u8; 1];
+        if stream.read_exact(&mut header).is_err() {
+            break;
+        }
+        let body = match read_remaining_length(&mut stream).and_then(|length| {
+            let mut body = vec![0_u8; length];
+            stream.read_exact(&mut body).map(|_| body)
+        }) {
+            Ok(body) => body,
+            Err(_) => break,
+        };
+
+        if header[0] & 0xf0 != PUBLISH || body.len() < 2 {
+            continue;
+        }
+        let topic_length = u16::from_be_bytes([body[0], body[1]]) as usize;
+        if body.len() < 2 + topic_length {
+            continue;
+        }
+        let topic = String::from_utf8_lossy(&body[2..2 + topic_length]);
+        let qos = (header[0] >> 1) & 0x03;
+        let payload_start = 2 + topic_length + if qos > 0 { 2 } else { 0 };
+        if topic != control_topic || body.len() < payload_start {
+            continue;
+        }
+
+        let payload = String::from_utf8_lossy(&body[payload_start..]);
+        match AdminCommand::parse(&payload) {
+            Some(command) => {
+                if command_sender.send(command).is_err() {
+                    break;
+                }
+            }
+            None => eprintln!("不明な管理コマンドです：{}", payload),
+        }
+    }
+}
+
+fn read_remaining_length(stream: &mut TcpStream) -> std::io::Result<usize> {
+    let mut length = 0_usize;
+    let mut multiplier = 1_usize;
+    for _ in 0..4 {
+        let mut byte = [0_u8; 1];
+        stream.read_exact(&mut byte)?;
+        length += (byte[0] & 0x7f) as usize * multiplier;
+        if byte[0] & 0x80 == 0 {
+            return Ok(length);
+        }
+        multiplier *= 128;
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "Malformed MQTT remaining length.",
+    ))
+}
+
+fn encode_packet(packet_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![packet_type];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn push_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+fn connect_packet(client_id: &str) -> Vec<u8> {
+    let mut body = vec![];
+    push_string(&mut body, PROTOCOL_NAME);
+    body.push(PROTOCOL_LEVEL);
+    body.push(CLEAN_SESSION);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    push_string(&mut body, client_id);
+    encode_packet(CONNECT, &body)
+}
+
+fn subscribe_packet(packet_id: u16, topic: &str) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    push_string(&mut body, topic);
+    body.push(0);
+    encode_packet(SUBSCRIBE, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    push_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    encode_packet(PUBLISH, &body)
+}
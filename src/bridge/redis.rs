@@ -0,0 +1,231 @@
+use crate::config::env_or;
+use crate::events::{EventListener, ServerEvent};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug)]
+pub struct RedisConfig {
+    pub address: String,
+    pub channel: String,
+    pub instance_id: String,
+}
+
+impl RedisConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("REDIS_ADDRESS")
+            .ok()
+            .map(|address| RedisConfig {
+                address,
+                channel: env_or("REDIS_CHANNEL", "ogp:chat"),
+                instance_id: std::env::var("INSTANCE_ID").unwrap_or_else(|_| default_instance_id()),
+            })
+    }
+}
+
+pub fn default_instance_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!("{}-{:08x}", std::process::id(), nanos)
+}
+
+#[derive(Clone, Debug)]
+pub struct RelayedMessage {
+    pub origin: String,
+    pub room: String,
+    pub client_id: u32,
+    pub message: String,
+}
+
+impl RelayedMessage {
+    fn encode(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.origin, self.room, self.client_id, self.message
+        )
+    }
+
+    fn decode(payload: &str) -> Option<RelayedMessage> {
+        let mut fields = payload.splitn(4, '\t');
+        Some(RelayedMessage {
+            origin: fields.next()?.to_string(),
+            room: fields.next()?.to_string(),
+            client_id: fields.next()?.parse().ok()?,
+            message: fields.next()?.to_string(),
+        })
+    }
+}
+
+#[derive(Debug)]
+enum RespValue {
+    Simple,
+    Error(String),
+    Integer,
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<RespValue>>),
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "Redis connection closed.",
+        ));
+    }
+    Ok(line.trim_end_matches("\r\n").to_string())
+}
+
+fn read_value<R: BufRead>(reader: &mut R) -> std::io::Result<RespValue> {
+    let line = read_line(reader)?;
+    let (kind, rest) = line.split_at(line.len().min(1));
+    let parse_length = || {
+        rest.parse::<i64>()
+            .map_err(|_| invalid_data("Malformed RESP length."))
+    };
+    match kind {
+        "+" => Ok(RespValue::Simple),
+        "-" => Ok(RespValue::Error(rest.to_string())),
+        ":" => parse_length().map(|_| RespValue::Integer),
+        "$" => {
+            let length = parse_length()?;
+            if length < 0 {
+                return Ok(RespValue::Bulk(None));
+            }
+            let mut data = vec![0_u8; length as usize + 2];
+            reader.read_exact(&mut data)?;
+            data.truncate(length as usize);
+            Ok(RespValue::Bulk(Some(data)))
+        }
+        "*" => {
+            let length = parse_length()?;
+            if length < 0 {
+                return Ok(RespValue::Array(None));
+            }
+            let items = (0..length)
+                .map(|_| read_value(reader))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            Ok(RespValue::Array(Some(items)))
+        }
+        _ => Err(invalid_data("Unknown RESP type.")),
+    }
+}
+
+fn encode_command(args: &[&[u8]]) -> Vec<u8> {
+    let mut command = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        command.extend_from_slice(arg);
+        command.extend_from_slice(b"\r\n");
+    }
+    command
+}
+
+pub struct RedisRelay {
+    config: RedisConfig,
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl RedisRelay {
+    pub fn connect(config: RedisConfig) -> std::io::Result<(RedisRelay, Receiver<RelayedMessage>)> {
+        let stream = TcpStream::connect(&config.address)?;
+        let reader = BufReader::new(stream.try_clone()?);
+
+        let mut subscriber = TcpStream::connect(&config.address)?;
+        subscriber.write_all(&encode_command(&[b"SUBSCRIBE", config.channel.as_bytes()]))?;
+        let (message_sender, message_receiver) = channel();
+        let instance_id = config.instance_id.clone();
+        let subscriber_reader = BufReader::new(subscriber);
+        std::thread::spawn(move || {
+            read_subscription(subscriber_reader, instance_id, message_sender)
+        });
+
+        Ok((
+            RedisRelay {
+                config,
+                stream,
+                reader,
+            },
+            message_receiver,
+        ))
+    }
+
+    pub fn publish(&mut self, room: &str, client_id: u32, message: &str) -> std::io::Result<()> {
+        let payload = RelayedMessage {
+            origin: self.config.instance_id.clone(),
+            room: room.to_string(),
+            client_id,
+            message: message.to_string(),
+        }
+        .encode();
+        self.stream.write_all(&encode_command(&[
+            b"PUBLISH",
+            self.config.channel.as_bytes(),
+            payload.as_bytes(),
+        ]))?;
+        match read_value(&mut self.reader)? {
+            RespValue::Error(e) => Err(std::io::Error::other(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl EventListener for RedisRelay {
+    fn on_event(&mut self, event: &ServerEvent) {
+        if let ServerEvent::Chat {
+            client_id,
+            room,
+            message,
+            ..
+        } = event
+        {
+            if let Err(e) = self.publish(room, *client_id, message) {
+                eprintln!("Redisへの送信に失敗しました：{}", e);
+            }
+        }
+    }
+}
+
+fn read_subscription(
+    mut reader: BufReader<TcpStream>,
+    instance_id: String,
+    message_sender: Sender<RelayedMessage>,
+) {
+    loop {
+        let items = match read_value(&mut reader) {
+            Ok(RespValue::Array(Some(items))) => items,
+            Ok(RespValue::Error(e)) => {
+                eprintln!("Redisの購読に失敗しました：{}", e);
+                break;
+            }
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("Redisとの接続が切れました：{}", e);
+                break;
+            }
+        };
+        let payload = match items.as_slice() {
+            [RespValue::Bulk(Some(kind)), _, RespValue::Bulk(Some(payload))]
+                if kind.as_slice() == b"message" =>
+            {
+                String::from_utf8_lossy(payload).to_string()
+            }
+            _ => continue,
+        };
+        let relayed = match RelayedMessage::decode(&payload) {
+            Some(relayed) if relayed.origin != instance_id => relayed,
+            _ => continue,
+        };
+        if message_sender.send(relayed).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::config::{env_millis, env_or};
+use crate::events::{EventListener, ServerEvent};
+use crate::identity::hex;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::time::Duration;
+
+const DEFAULT_EVENTS: &str = "join,leave,match";
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const QUEUE_SIZE: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    pub events: Vec<String>,
+    pub secret: Option<String>,
+    pub max_attempts: u32,
+    pub backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Option<Self> {
+        let urls = env_or("WEBHOOK_URLS", "")
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            return None;
+        }
+        let defaults = WebhookConfig::new(urls);
+        Some(WebhookConfig {
+            events: env_or("WEBHOOK_EVENTS", DEFAULT_EVENTS)
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            secret: std::env::var("WEBHOOK_SECRET").ok(),
+            max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", "")
+                .parse()
+                .ok()
+                .filter(|attempts| *attempts > 0)
+                .unwrap_or(defaults.max_attempts),
+            backoff: env_millis("WEBHOOK_BACKOFF_MS").unwrap_or(defaults.backoff),
+            timeout: env_millis("WEBHOOK_TIMEOUT_MS").unwrap_or(defaults.timeout),
+            ..defaults
+        })
+    }
+
+    pub fn new(urls: Vec<String>) -> Self {
+        WebhookConfig {
+            urls,
+            events: DEFAULT_EVENTS.split(',').map(str::to_string).collect(),
+            secret: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: DEFAULT_BACKOFF,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn wants(&self, event: &ServerEvent) -> bool {
+        self.events
+            .iter()
+            .any(|name| name == "*" || name == event.name())
+    }
+
+    fn signature(&self, body: &str) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts any key length.");
+        mac.update(body.as_bytes());
+        Some(format!("sha256={}", hex(&mac.finalize().into_bytes())))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct WebhookUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl WebhookUrl {
+    fn parse(url: &str) -> std::io::Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            Error::new(
+                ErrorKind::Unsupported,
+                format!("Only http:// webhooks are supported: {}", url),
+            )
+        })?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| Error::new(ErrorKind::InvalidInput, "Invalid webhook port."))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Webhook host is empty.",
+            ));
+        }
+        Ok(WebhookUrl {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+fn post(
+    url: &WebhookUrl,
+    event: &str,
+    body: &str,
+    signature: Option<&str>,
+    timeout: Duration,
+) -> std::io::Result<u16> {
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Webhook host did not resolve."))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let signature = signature
+        .map(|signature| format!("X-Webhook-Signature: {}\r\n", signature))
+        .unwrap_or_default();
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: online_game_programming\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nX-Webhook-Event: {}\r\n{}Connection: close\r\n\r\n{}",
+        &url.path,
+        &url.host,
+        url.port,
+        body.len(),
+        event,
+        signature,
+        body
+    );
+    stream.write_all(request.as_bytes())?;
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Malformed HTTP status line."))
+}
+
+fn retryable(status: u16) -> bool {
+    status == 408 || status == 429 || status >= 500
+}
+
+pub(crate) fn deliver(
+    config: &WebhookConfig,
+    url: &str,
+    event: &str,
+    body: &str,
+) -> std::io::Result<u32> {
+    let target = WebhookUrl::parse(url)?;
+    let signature = config.signature(body);
+    let mut backoff = config.backoff;
+    let mut attempt = 1;
+    loop {
+        let error = match post(&target, event, body, signature.as_deref(), config.timeout) {
+            Ok(status) if (200..300).contains(&status) => return Ok(attempt),
+            Ok(status) if !retryable(status) => {
+                return Err(Error::other(format!("Webhook rejected with {}", status)))
+            }
+            Ok(status) => Error::other(format!("Webhook responded with {}", status)),
+            Err(e) => e,
+        };
+        if attempt >= config.max_attempts {
+            return Err(error);
+        }
+        eprintln!(
+            "Webhook {}への送信に失敗しました（{}回目）：{}、{}ms後に再試行します\n",
+            url,
+            attempt,
+            error,
+            backoff.as_millis()
+        );
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        attempt += 1;
+    }
+}
+
+fn run_deliveries(config: WebhookConfig, receiver: Receiver<(&'static str, String)>) {
+    for (event, body) in receiver.iter() {
+        for url in config.urls.iter() {
+            if let Err(e) = deliver(&config, url, event, &body) {
+                eprintln!(
+                    "Webhook {}へのイベント{}の送信を諦めました：{}\n",
+                    url, event, e
+                );
+            }
+        }
+    }
+}
+
+pub struct WebhookSender {
+    config: WebhookConfig,
+    sender: SyncSender<(&'static str, String)>,
+}
+
+impl WebhookSender {
+    pub fn spawn(config: WebhookConfig) -> Self {
+        let (sender, receiver) = sync_channel(QUEUE_SIZE);
+        let worker_config = config.clone();
+        std::thread::spawn(move || run_deliveries(worker_config, receiver));
+        WebhookSender { config, sender }
+    }
+}
+
+impl EventListener for WebhookSender {
+    fn on_event(&mut self, event: &ServerEvent) {
+        if !self.config.wants(event) {
+            return;
+        }
+        match self.sender.try_send((event.name(), event.to_json())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                eprintln!("Webhookの送信待ちが溢れたため、イベントを破棄しました\n")
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn failed_deliveries_are_retried_until_the_endpoint_accepts() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind.");
+        let url = format!(
+            "http://{}/hooks/ogp",
+            listener.local_addr().expect("Address.")
+        );
+        let endpoint = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for status in ["503 Service Unavailable", "204 No Content"].iter() {
+                let (mut stream, _) = listener.accept().expect("Failed to accept.");
+                let mut reader = BufReader::new(stream.try_clone().expect("Failed to clone."));
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).expect("Failed to read.");
+                    if let Some(value) = line.strip_prefix("Content-Length: ") {
+                        length = value.trim().parse().expect("Invalid length.");
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0_u8; length];
+                reader.read_exact(&mut body).expect("Failed to read body.");
+                requests.push(request + &String::from_utf8_lossy(&body));
+                write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status)
+                    .expect("Failed to respond.");
+            }
+            requests
+        });
+        let config = WebhookConfig {
+            urls: vec![url.clone()],
+            events: vec!["leave".to_string()],
+            secret: Some("s3cret".to_string()),
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+            timeout: Duration::from_secs(5),
+        };
+        let event = ServerEvent::ClientLeft { client_id: 7 };
+        assert!(config.wants(&event));
+        assert!(!config.wants(&ServerEvent::ClientJoined {
+            client_id: 7,
+            address: "127.0.0.1".to_string(),
+        }));
+
+        let body = event.to_json();
+        let attempts = deliver(&config, &url, event.name(), &body).expect("Failed to deliver.");
+        assert_eq!(attempts, 2);
+        let requests = endpoint.join().expect("Endpoint panicked.");
+        assert!(requests[1].starts_with("POST /hooks/ogp HTTP/1.1\r\n"));
+        assert!(requests[1].contains("X-Webhook-Event: leave\r\n"));
+        assert!(requests[1].contains(&format!(
+            "X-Webhook-Signature: {}\r\n",
+            config.signature(&body).expect("Missing signature.")
+        )));
+        assert!(requests[1].ends_with("{\"event\":\"leave\",\"client_id\":7}"));
+
+        assert!(WebhookUrl::parse("https://example.com/hook").is_err());
+        assert_eq!(
+            WebhookUrl::parse("http://example.com").expect("Failed to parse."),
+            WebhookUrl {
+                host: "example.com".to_string(),
+                port: 80,
+                path: "/".to_string(),
+            }
+        );
+    }
+}
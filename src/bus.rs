@@ -0,0 +1,177 @@
+use crate::events::{EventListener, ServerEvent};
+use crate::identity::AccountId;
+use crate::leaderboard::MatchStanding;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+type Subscriber = Arc<dyn Fn(&dyn Any) + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub struct ClientJoined {
+    pub client_id: u32,
+    pub address: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientLeft {
+    pub client_id: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LoggedIn {
+    pub client_id: u32,
+    pub account_id: AccountId,
+    pub name: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatPosted {
+    pub client_id: u32,
+    pub account_id: Option<AccountId>,
+    pub room: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchEnded {
+    pub room: String,
+    pub standings: Vec<MatchStanding>,
+}
+
+#[derive(Clone, Default)]
+pub struct MessageBus {
+    subscribers: Arc<RwLock<HashMap<TypeId, Vec<Subscriber>>>>,
+}
+
+impl MessageBus {
+    pub fn new() -> Self {
+        MessageBus::default()
+    }
+
+    pub fn subscribe<E, F>(&self, handler: F)
+    where
+        E: Any,
+        F: Fn(&E) + Send + Sync + 'static,
+    {
+        let subscriber: Subscriber = Arc::new(move |event: &dyn Any| {
+            if let Some(event) = event.downcast_ref::<E>() {
+                handler(event);
+            }
+        });
+        self.subscribers
+            .write()
+            .expect("Failed to lock bus subscribers.")
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push(subscriber);
+    }
+
+    pub fn publish<E: Any>(&self, event: &E) -> usize {
+        let subscribers = self
+            .subscribers
+            .read()
+            .expect("Failed to lock bus subscribers.")
+            .get(&TypeId::of::<E>())
+            .cloned()
+            .unwrap_or_default();
+        for subscriber in subscribers.iter() {
+            subscriber(event);
+        }
+        subscribers.len()
+    }
+}
+
+impl EventListener for MessageBus {
+    fn on_event(&mut self, event: &ServerEvent) {
+        match event.clone() {
+            ServerEvent::ClientJoined { client_id, address } => {
+                self.publish(&ClientJoined { client_id, address })
+            }
+            ServerEvent::ClientLeft { client_id } => self.publish(&ClientLeft { client_id }),
+            ServerEvent::LoggedIn {
+                client_id,
+                account_id,
+                name,
+            } => self.publish(&LoggedIn {
+                client_id,
+                account_id,
+                name,
+            }),
+            ServerEvent::Chat {
+                client_id,
+                account_id,
+                room,
+                message,
+            } => self.publish(&ChatPosted {
+                client_id,
+                account_id,
+                room,
+                message,
+            }),
+            ServerEvent::MatchEnded { room, standings } => {
+                self.publish(&MatchEnded { room, standings })
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn subscribers_only_receive_their_event_type() {
+        let bus = MessageBus::new();
+        let joined = Arc::new(Mutex::new(vec![]));
+        let left = Arc::new(Mutex::new(vec![]));
+        let sink = joined.clone();
+        bus.subscribe(move |event: &ClientJoined| {
+            sink.lock()
+                .expect("Failed to lock events.")
+                .push(event.client_id)
+        });
+        let sink = left.clone();
+        bus.subscribe(move |event: &ClientLeft| {
+            sink.lock()
+                .expect("Failed to lock events.")
+                .push(event.client_id)
+        });
+
+        assert_eq!(
+            bus.publish(&ClientJoined {
+                client_id: 1,
+                address: "127.0.0.1".to_string(),
+            }),
+            1
+        );
+        assert_eq!(bus.publish(&ClientLeft { client_id: 2 }), 1);
+        assert_eq!(bus.publish(&"unsubscribed"), 0);
+
+        assert_eq!(*joined.lock().expect("Failed to lock events."), vec![1]);
+        assert_eq!(*left.lock().expect("Failed to lock events."), vec![2]);
+    }
+
+    #[test]
+    fn server_events_are_republished_as_typed_events() {
+        let mut bus = MessageBus::new();
+        let rooms = Arc::new(Mutex::new(vec![]));
+        let sink = rooms.clone();
+        bus.subscribe(move |event: &ChatPosted| {
+            sink.lock()
+                .expect("Failed to lock events.")
+                .push(event.room.clone())
+        });
+
+        bus.on_event(&ServerEvent::Chat {
+            client_id: 0,
+            account_id: None,
+            room: "red".to_string(),
+            message: "hi".to_string(),
+        });
+        bus.on_event(&ServerEvent::ClientLeft { client_id: 0 });
+
+        assert_eq!(*rooms.lock().expect("Failed to lock events."), vec!["red"]);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::env_or;
+use crate::events::{EventListener, ServerEvent};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_BYTES: u64 = 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+
+#[derive(Clone, Debug)]
+pub struct ChatLogConfig {
+    pub directory: PathBuf,
+    pub max_bytes: u64,
+    pub max_files: usize,
+    pub replay_lines: usize,
+}
+
+impl ChatLogConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("CHAT_LOG_DIR")
+            .ok()
+            .map(|directory| ChatLogConfig {
+                directory: PathBuf::from(directory),
+                max_bytes: env_or("CHAT_LOG_MAX_BYTES", "")
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_BYTES),
+                max_files: env_or("CHAT_LOG_MAX_FILES", "")
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_FILES),
+                replay_lines: env_or("CHAT_LOG_REPLAY", "").parse().unwrap_or(0),
+            })
+    }
+}
+
+struct OpenLog {
+    file: File,
+    size: u64,
+}
+
+#[derive(Clone)]
+pub struct ChatLog {
+    config: ChatLogConfig,
+    files: Arc<Mutex<HashMap<String, OpenLog>>>,
+}
+
+impl ChatLog {
+    pub fn open(config: ChatLogConfig) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&config.directory)?;
+        Ok(ChatLog {
+            config,
+            files: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn replay(&self, room: &str) -> Vec<String> {
+        if self.config.replay_lines == 0 {
+            return vec![];
+        }
+        self.tail(room, self.config.replay_lines)
+            .into_iter()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let _timestamp = fields.next()?;
+                let client_id = fields.next()?;
+                let message = fields.next()?;
+                Some(format!("{}：{}", client_id, message))
+            })
+            .collect()
+    }
+
+    fn log_path(&self, room: &str, generation: usize) -> PathBuf {
+        let room = room
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        if generation == 0 {
+            self.config.directory.join(format!("{}.log", room))
+        } else {
+            self.config
+                .directory
+                .join(format!("{}.{}.log", room, generation))
+        }
+    }
+
+    fn open_log(&self, room: &str) -> std::io::Result<OpenLog> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path(room, 0))?;
+        let size = file.metadata()?.len();
+        Ok(OpenLog { file, size })
+    }
+
+    fn rotate(&self, room: &str) -> std::io::Result<()> {
+        let oldest = self.log_path(room, self.config.max_files);
+        if oldest.exists() {
+            std::fs::remove_file(oldest)?;
+        }
+        for generation in (0..self.config.max_files).rev() {
+            let from = self.log_path(room, generation);
+            if from.exists() {
+                std::fs::rename(from, self.log_path(room, generation + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn append(&self, room: &str, client_id: u32, message: &str) -> std::io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let message = message.replace(['\r', '\n'], " ");
+        let line = format!("{}\t{}\t{}\n", timestamp, client_id, message);
+
+        let mut files = self.files.lock().expect("Failed to lock chat log.");
+        if !files.contains_key(room) {
+            files.insert(room.to_string(), self.open_log(room)?);
+        }
+        let needs_rotation = files
+            .get(room)
+            .map(|log| log.size > 0 && log.size + line.len() as u64 > self.config.max_bytes)
+            .unwrap_or(false);
+        if needs_rotation {
+            files.remove(room);
+            self.rotate(room)?;
+            files.insert(room.to_string(), self.open_log(room)?);
+        }
+
+        let log = files.get_mut(room).expect("Chat log was not opened.");
+        log.file.write_all(line.as_bytes())?;
+        log.size += line.len() as u64;
+        Ok(())
+    }
+
+    pub fn tail(&self, room: &str, count: usize) -> Vec<String> {
+        let _files = self.files.lock().expect("Failed to lock chat log.");
+        let mut lines = Vec::with_capacity(count);
+        for generation in 0..=self.config.max_files {
+            if lines.len() >= count {
+                break;
+            }
+            let file = match File::open(self.log_path(room, generation)) {
+                Ok(file) => file,
+                Err(_) => break,
+            };
+            let older = BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .collect::<Vec<_>>();
+            let take = (count - lines.len()).min(older.len());
+            let mut older = older[older.len() - take..].to_vec();
+            older.append(&mut lines);
+            lines = older;
+        }
+        lines
+    }
+}
+
+impl EventListener for ChatLog {
+    fn on_event(&mut self, event: &ServerEvent) {
+        if let ServerEvent::Chat {
+            client_id,
+            room,
+            message,
+            ..
+        } = event
+        {
+            if let Err(e) = self.append(room, *client_id, message) {
+                eprintln!("チャットログの書き込みに失敗しました：{}", e);
+            }
+        }
+    }
+}
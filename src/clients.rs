@@ -0,0 +1,576 @@
+use crate::aoi::Entity;
+use crate::geoip::GeoLocation;
+use crate::identity::AccountId;
+use crate::latency::{LatencySummary, LatencyTracker};
+use crate::pow::Challenge;
+use crate::presence::Presence;
+use crate::rooms::DEFAULT_ROOM;
+use crate::transport::Transport;
+use std::net::SocketAddr;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct Client {
+    pub id: u32,
+    pub address: String,
+    pub peer: Option<SocketAddr>,
+    pub location: Option<GeoLocation>,
+    pub transport: Option<Arc<dyn Transport>>,
+    pub account_id: Option<AccountId>,
+    pub nickname: Option<String>,
+    pub room: String,
+    pub position: (i32, i32),
+    pub connected_at: Option<Instant>,
+    pub last_active: Option<Instant>,
+    pub afk: bool,
+    pub ready: bool,
+    pub presence: Presence,
+    pub friends: Vec<String>,
+    pub spectating: Option<String>,
+    pub bot: bool,
+    pub latency: LatencyTracker,
+    pub challenge: Option<Challenge>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client {
+            id: 0,
+            address: String::new(),
+            peer: None,
+            location: None,
+            transport: None,
+            account_id: None,
+            nickname: None,
+            room: DEFAULT_ROOM.to_string(),
+            position: (0, 0),
+            connected_at: None,
+            last_active: None,
+            afk: false,
+            ready: false,
+            presence: Presence::Online,
+            friends: vec![],
+            spectating: None,
+            bot: false,
+            latency: LatencyTracker::default(),
+            challenge: None,
+        }
+    }
+}
+
+impl Client {
+    pub fn display_name(&self) -> String {
+        self.nickname
+            .clone()
+            .unwrap_or_else(|| format!("Guest{}", self.id))
+    }
+
+    pub fn endpoint(&self) -> String {
+        match self.peer {
+            Some(peer) => peer.to_string(),
+            None => self.address.clone(),
+        }
+    }
+}
+
+pub type SharedClient = Arc<RwLock<Client>>;
+pub type ConnectedClient = (u32, String, Arc<dyn Transport>, String);
+
+#[derive(Clone)]
+pub struct ClientRegistry {
+    clients: Arc<RwLock<Vec<SharedClient>>>,
+}
+
+impl ClientRegistry {
+    pub fn new(pool_size: usize) -> Self {
+        let clients = (0..pool_size)
+            .map(|id| {
+                Arc::new(RwLock::new(Client {
+                    id: id as u32,
+                    ..Client::default()
+                }))
+            })
+            .collect();
+        ClientRegistry {
+            clients: Arc::new(RwLock::new(clients)),
+        }
+    }
+
+    pub fn find_empty(&self) -> SharedClient {
+        let mut clients = self
+            .clients
+            .write()
+            .expect("Failed to lock socket clients.");
+        clients
+            .iter()
+            .find(|c| {
+                c.read()
+                    .expect("Failed to lock socket client.")
+                    .transport
+                    .is_none()
+            })
+            .cloned()
+            .unwrap_or_else(|| {
+                let client = Client {
+                    id: clients.len() as u32,
+                    ..Client::default()
+                };
+                clients.push(Arc::new(RwLock::new(client)));
+                clients
+                    .last()
+                    .cloned()
+                    .expect("There are no available socket clients.")
+            })
+    }
+
+    pub fn get(&self, client_id: u32) -> Option<SharedClient> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .get(client_id as usize)
+            .cloned()
+    }
+
+    pub fn reclaim(&self, client_id: u32, transport: &Arc<dyn Transport>) -> bool {
+        let mut clients = self.clients.write().unwrap_or_else(PoisonError::into_inner);
+        let slot = match clients.get_mut(client_id as usize) {
+            Some(slot) => slot,
+            None => return false,
+        };
+        let still_ours = slot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .transport
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, transport));
+        if still_ours {
+            *slot = Arc::new(RwLock::new(Client {
+                id: client_id,
+                ..Client::default()
+            }));
+        }
+        still_ours
+    }
+
+    pub fn others(&self, client_id: u32) -> Vec<SharedClient> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .enumerate()
+            .filter(|(id, _)| *id as u32 != client_id)
+            .map(|(_, client)| client.clone())
+            .collect()
+    }
+
+    pub fn connected(&self) -> Vec<ConnectedClient> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.clone().map(|transport| {
+                    (
+                        client_lock.id,
+                        client_lock.address.clone(),
+                        transport,
+                        client_lock.room.clone(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn entities(&self) -> Vec<(Entity, Arc<dyn Transport>)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.clone().map(|transport| {
+                    (
+                        Entity {
+                            id: client_lock.id,
+                            name: client_lock.display_name(),
+                            position: client_lock.position,
+                        },
+                        transport,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn touch(&self, client_id: u32) -> bool {
+        match self.get(client_id) {
+            Some(client) => {
+                let mut client_lock = client.write().expect("Failed to lock socket client.");
+                client_lock.last_active = Some(Instant::now());
+                std::mem::replace(&mut client_lock.afk, false)
+            }
+            None => false,
+        }
+    }
+
+    pub fn mark_afk(&self, threshold: Duration) -> Vec<(u32, String, String)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let mut client_lock = c.write().expect("Failed to lock socket client.");
+                let inactive = client_lock
+                    .last_active
+                    .is_some_and(|last_active| last_active.elapsed() > threshold);
+                if client_lock.transport.is_none() || client_lock.afk || !inactive {
+                    return None;
+                }
+                client_lock.afk = true;
+                Some((
+                    client_lock.id,
+                    client_lock.display_name(),
+                    client_lock.room.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    pub fn in_room(&self, room: &str) -> Vec<SharedClient> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.is_some() && client_lock.room == room
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn audience(&self, room: &str) -> Vec<Arc<dyn Transport>> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match client_lock.transport.as_ref() {
+                    Some(transport)
+                        if client_lock.room == room
+                            || client_lock.spectating.as_deref() == Some(room) =>
+                    {
+                        Some(transport.clone())
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    pub fn presence_of(&self, name: &str) -> Option<Presence> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .find_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match client_lock.transport {
+                    Some(_) if client_lock.display_name() == name => Some(client_lock.presence),
+                    _ => None,
+                }
+            })
+    }
+
+    pub fn watchers(&self, name: &str) -> Vec<Arc<dyn Transport>> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match client_lock.transport.as_ref() {
+                    Some(transport) if client_lock.friends.iter().any(|friend| friend == name) => {
+                        Some(transport.clone())
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    pub fn idle(&self, timeout: Duration) -> Vec<(u32, Arc<dyn Transport>)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match (client_lock.transport.as_ref(), client_lock.last_active) {
+                    (Some(transport), Some(last_active)) if last_active.elapsed() > timeout => {
+                        Some((client_lock.id, transport.clone()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    pub fn unauthenticated(&self, timeout: Duration) -> Vec<(u32, Arc<dyn Transport>)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match (client_lock.transport.as_ref(), client_lock.connected_at) {
+                    (Some(transport), Some(connected_at))
+                        if client_lock.account_id.is_none()
+                            && !client_lock.bot
+                            && connected_at.elapsed() > timeout =>
+                    {
+                        Some((client_lock.id, transport.clone()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    pub fn connections_from(&self, address: &str) -> usize {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.is_some() && client_lock.address == address
+            })
+            .count()
+    }
+
+    pub fn start_heartbeats(
+        &self,
+        interval: Duration,
+        now: Instant,
+    ) -> Vec<(Arc<dyn Transport>, u64)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let mut client_lock = c.write().expect("Failed to lock socket client.");
+                let transport = client_lock.transport.clone()?;
+                if !client_lock.latency.probe_due(now, interval) {
+                    return None;
+                }
+                Some((transport, client_lock.latency.start_probe(now)))
+            })
+            .collect()
+    }
+
+    pub fn complete_ping(&self, client_id: u32, nonce: u64, now: Instant) -> Option<Duration> {
+        self.get(client_id)?
+            .write()
+            .expect("Failed to lock socket client.")
+            .latency
+            .complete_probe(nonce, now)
+    }
+
+    pub fn latencies(&self) -> Vec<(u32, String, LatencySummary)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.as_ref()?;
+                let summary = client_lock.latency.summary()?;
+                Some((client_lock.id, client_lock.display_name(), summary))
+            })
+            .collect()
+    }
+
+    pub fn locations(&self) -> Vec<(u32, String, String, Option<GeoLocation>)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.as_ref()?;
+                Some((
+                    client_lock.id,
+                    client_lock.endpoint(),
+                    client_lock.display_name(),
+                    client_lock.location.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    pub fn find_by_nickname(&self, nickname: &str) -> Option<Arc<dyn Transport>> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .find_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match client_lock.nickname.as_deref() {
+                    Some(name) if name == nickname => client_lock.transport.clone(),
+                    _ => None,
+                }
+            })
+    }
+
+    pub fn find_by_account(&self, account_id: AccountId) -> Option<(Arc<dyn Transport>, Presence)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .find_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                match client_lock.transport.as_ref() {
+                    Some(transport) if client_lock.account_id == Some(account_id) => {
+                        Some((transport.clone(), client_lock.presence))
+                    }
+                    _ => None,
+                }
+            })
+    }
+
+    pub fn players(&self, now: Instant) -> Vec<(String, Duration)> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock.transport.as_ref()?;
+                let connected = client_lock
+                    .connected_at
+                    .map_or(Duration::ZERO, |connected_at| {
+                        now.saturating_duration_since(connected_at)
+                    });
+                Some((client_lock.display_name(), connected))
+            })
+            .collect()
+    }
+
+    pub fn nicknames(&self) -> Vec<String> {
+        self.clients
+            .read()
+            .expect("Failed to lock socket clients.")
+            .iter()
+            .filter_map(|c| {
+                let client_lock = c.read().expect("Failed to lock socket client.");
+                client_lock
+                    .transport
+                    .as_ref()
+                    .and(client_lock.nickname.clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn slots_are_reused_before_the_registry_grows() {
+        let registry = ClientRegistry::new(1);
+        let first = registry.find_empty();
+        first
+            .write()
+            .expect("Failed to lock socket client.")
+            .transport = Some(Arc::new(MockTransport::new()));
+
+        let second = registry.find_empty();
+
+        assert_eq!(second.read().expect("Failed to lock socket client.").id, 1);
+        assert!(Arc::ptr_eq(
+            &registry.get(1).expect("Client 1 is missing."),
+            &second
+        ));
+        assert_eq!(registry.others(1).len(), 1);
+        assert_eq!(registry.connected().len(), 1);
+        assert!(Arc::ptr_eq(&registry.find_empty(), &second));
+    }
+
+    #[test]
+    fn poisoned_slots_are_reclaimed_only_by_their_own_session() {
+        let registry = ClientRegistry::new(2);
+        let transport: Arc<dyn Transport> = Arc::new(MockTransport::new());
+        let client = registry.find_empty();
+        client
+            .write()
+            .expect("Failed to lock socket client.")
+            .transport = Some(transport.clone());
+        let _ = std::thread::spawn(move || {
+            let _client_lock = client.write().expect("Failed to lock socket client.");
+            panic!("session crashed while holding its slot");
+        })
+        .join();
+        assert!(registry
+            .get(0)
+            .expect("Client 0 is missing.")
+            .read()
+            .is_err());
+
+        let stranger: Arc<dyn Transport> = Arc::new(MockTransport::new());
+        assert!(!registry.reclaim(0, &stranger));
+        assert!(!registry.reclaim(9, &transport));
+        assert!(registry.reclaim(0, &transport));
+        assert!(!registry.reclaim(0, &transport));
+
+        let reclaimed = registry.find_empty();
+        let client_lock = reclaimed.read().expect("Failed to lock socket client.");
+        assert_eq!(client_lock.id, 0);
+        assert!(client_lock.transport.is_none());
+    }
+
+    #[test]
+    fn only_connected_clients_past_the_timeout_are_idle() {
+        let registry = ClientRegistry::new(3);
+        for id in 0..3 {
+            let client = registry.get(id).expect("Client is missing.");
+            let mut client_lock = client.write().expect("Failed to lock socket client.");
+            client_lock.last_active = Some(Instant::now() - Duration::from_secs(60));
+            if id != 2 {
+                client_lock.transport = Some(Arc::new(MockTransport::new()));
+            }
+        }
+        registry.touch(1);
+
+        let idle = registry.idle(Duration::from_secs(30));
+        assert_eq!(idle.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn listings_show_the_source_port_when_the_peer_is_known() {
+        let registry = ClientRegistry::new(2);
+        for id in 0..2 {
+            let client = registry.get(id).expect("Client is missing.");
+            let mut client_lock = client.write().expect("Failed to lock socket client.");
+            client_lock.address = "192.0.2.7".to_string();
+            client_lock.transport = Some(Arc::new(MockTransport::new()));
+            if id == 0 {
+                client_lock.peer = "192.0.2.7:52100".parse().ok();
+            }
+        }
+
+        assert_eq!(
+            registry
+                .locations()
+                .into_iter()
+                .map(|(_, endpoint, _, _)| endpoint)
+                .collect::<Vec<_>>(),
+            vec!["192.0.2.7:52100", "192.0.2.7"]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TimeCommand {
+    Time,
+    Events,
+}
+
+impl TimeCommand {
+    pub fn parse(input: &str) -> Option<TimeCommand> {
+        match input.trim_matches(|c: char| c.is_whitespace() || c == '\0') {
+            ":time" | "/time" => Some(TimeCommand::Time),
+            ":events" | "/events" => Some(TimeCommand::Events),
+            _ => None,
+        }
+    }
+}
+
+fn unix_millis(now: SystemTime) -> u64 {
+    now.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServerTime {
+    pub unix_ms: u64,
+    pub tick: u64,
+    pub tick_interval: Duration,
+}
+
+impl ServerTime {
+    pub fn encode(&self) -> String {
+        format!(
+            "TIME {} {} {}",
+            self.unix_ms,
+            self.tick,
+            self.tick_interval.as_millis()
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    pub id: u32,
+    pub tick: u64,
+    pub unix_ms: u64,
+    pub label: String,
+}
+
+impl ScheduledEvent {
+    pub fn encode(&self) -> String {
+        format!(
+            "EVENT {} {} {} {}",
+            self.id, self.tick, self.unix_ms, self.label
+        )
+    }
+
+    pub fn encode_start(&self) -> String {
+        format!("EVENT_START {} {} {}", self.id, self.tick, self.label)
+    }
+}
+
+#[derive(Default)]
+struct ClockState {
+    events: Vec<ScheduledEvent>,
+    next_id: u32,
+}
+
+#[derive(Clone)]
+pub struct ServerClock {
+    tick: Arc<AtomicU64>,
+    tick_interval: Duration,
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl ServerClock {
+    pub fn new(tick_interval: Duration) -> Self {
+        ServerClock {
+            tick: Arc::new(AtomicU64::new(0)),
+            tick_interval: tick_interval.max(Duration::from_millis(1)),
+            state: Arc::new(Mutex::new(ClockState::default())),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ClockState> {
+        self.state.lock().expect("Failed to lock server clock.")
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick.load(Ordering::SeqCst)
+    }
+
+    pub fn now(&self, now: SystemTime) -> ServerTime {
+        ServerTime {
+            unix_ms: unix_millis(now),
+            tick: self.tick(),
+            tick_interval: self.tick_interval,
+        }
+    }
+
+    pub fn advance(&self) -> Vec<ScheduledEvent> {
+        let tick = self.tick.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut state = self.lock();
+        let (due, pending) = state.events.drain(..).partition(|event| event.tick <= tick);
+        state.events = pending;
+        due
+    }
+
+    pub fn schedule(&self, delay: Duration, label: String, now: SystemTime) -> ScheduledEvent {
+        let interval = self.tick_interval.as_millis();
+        let ticks = delay.as_millis().div_ceil(interval).max(1) as u64;
+        let mut state = self.lock();
+        state.next_id += 1;
+        let current = self.tick();
+        let event = ScheduledEvent {
+            id: state.next_id,
+            tick: current + ticks,
+            unix_ms: unix_millis(now) + ticks * interval as u64,
+            label,
+        };
+        state.events.push(event.clone());
+        state.events.sort_by_key(|event| event.tick);
+        event
+    }
+
+    pub fn cancel(&self, id: u32) -> bool {
+        let mut state = self.lock();
+        let before = state.events.len();
+        state.events.retain(|event| event.id != id);
+        state.events.len() != before
+    }
+
+    pub fn upcoming(&self) -> Vec<ScheduledEvent> {
+        self.lock().events.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_fire_on_the_tick_they_were_scheduled_for() {
+        let clock = ServerClock::new(Duration::from_millis(100));
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        clock.advance();
+        assert_eq!(clock.now(now).encode(), "TIME 1000000 1 100".to_string());
+
+        let start = clock.schedule(Duration::from_millis(250), "Match start".to_string(), now);
+        let round = clock.schedule(Duration::from_millis(100), "Round".to_string(), now);
+        let cancelled = clock.schedule(Duration::ZERO, "Warmup".to_string(), now);
+        assert_eq!(start.encode(), "EVENT 1 4 1000300 Match start");
+        assert_eq!(round.tick, 2);
+        assert_eq!(cancelled.tick, 2);
+        assert_eq!(
+            clock
+                .upcoming()
+                .iter()
+                .map(|event| event.id)
+                .collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+        assert!(clock.cancel(cancelled.id));
+        assert!(!clock.cancel(cancelled.id));
+
+        assert_eq!(clock.advance(), vec![round]);
+        assert_eq!(clock.advance(), vec![]);
+        let fired = clock.advance();
+        assert_eq!(fired, vec![start]);
+        assert_eq!(fired[0].encode_start(), "EVENT_START 1 4 Match start");
+        assert!(clock.upcoming().is_empty());
+        assert_eq!(TimeCommand::parse("/time\0"), Some(TimeCommand::Time));
+        assert_eq!(TimeCommand::parse(":events"), Some(TimeCommand::Events));
+        assert_eq!(TimeCommand::parse(":time now"), None);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::bridge::default_instance_id;
+use crate::config::{env_millis, env_or};
+use std::time::Duration;
+
+pub const DEFAULT_GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
+pub const DEFAULT_PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct ClusterConfig {
+    pub node_id: String,
+    pub bind: String,
+    pub seeds: Vec<String>,
+    pub gossip_interval: Duration,
+    pub peer_timeout: Duration,
+}
+
+impl ClusterConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("CLUSTER_BIND")
+            .ok()
+            .map(|bind| ClusterConfig {
+                node_id: std::env::var("INSTANCE_ID").unwrap_or_else(|_| default_instance_id()),
+                bind,
+                seeds: env_or("CLUSTER_PEERS", "")
+                    .split(',')
+                    .map(|peer| peer.trim().to_string())
+                    .filter(|peer| !peer.is_empty())
+                    .collect(),
+                gossip_interval: env_millis("CLUSTER_GOSSIP_MS").unwrap_or(DEFAULT_GOSSIP_INTERVAL),
+                peer_timeout: env_millis("CLUSTER_PEER_TIMEOUT_MS").unwrap_or(DEFAULT_PEER_TIMEOUT),
+            })
+    }
+}
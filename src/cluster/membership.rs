@@ -0,0 +1,201 @@
+use super::{Whisper, WhisperReceipt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClusterMessage {
+    Gossip {
+        node_id: String,
+        members: Vec<(String, SocketAddr)>,
+        presence: Vec<String>,
+    },
+    Whisper(Whisper),
+    Receipt(WhisperReceipt),
+}
+
+#[derive(Clone, Debug)]
+pub struct Member {
+    pub address: SocketAddr,
+    pub last_seen: Instant,
+    pub presence: Vec<String>,
+}
+
+pub struct Membership {
+    node_id: String,
+    seeds: Vec<SocketAddr>,
+    members: HashMap<String, Member>,
+    timeout: Duration,
+}
+
+impl Membership {
+    pub fn new(node_id: &str, seeds: Vec<SocketAddr>, timeout: Duration) -> Self {
+        Membership {
+            node_id: node_id.to_string(),
+            seeds,
+            members: HashMap::new(),
+            timeout,
+        }
+    }
+
+    pub fn gossip(&self, presence: Vec<String>) -> ClusterMessage {
+        ClusterMessage::Gossip {
+            node_id: self.node_id.clone(),
+            members: self.members(),
+            presence,
+        }
+    }
+
+    pub fn targets(&self) -> Vec<SocketAddr> {
+        let mut targets = self
+            .members
+            .values()
+            .map(|member| member.address)
+            .collect::<Vec<_>>();
+        for seed in self.seeds.iter() {
+            if !targets.contains(seed) {
+                targets.push(*seed);
+            }
+        }
+        targets
+    }
+
+    pub fn merge(
+        &mut self,
+        from: SocketAddr,
+        node_id: &str,
+        members: &[(String, SocketAddr)],
+        presence: Vec<String>,
+        now: Instant,
+    ) -> Vec<String> {
+        let mut discovered = vec![];
+        if node_id == self.node_id {
+            return discovered;
+        }
+        if !self.members.contains_key(node_id) {
+            discovered.push(node_id.to_string());
+        }
+        self.members.insert(
+            node_id.to_string(),
+            Member {
+                address: from,
+                last_seen: now,
+                presence,
+            },
+        );
+        for (id, address) in members.iter() {
+            if *id == self.node_id || self.members.contains_key(id) {
+                continue;
+            }
+            discovered.push(id.clone());
+            self.members.insert(
+                id.clone(),
+                Member {
+                    address: *address,
+                    last_seen: now,
+                    presence: vec![],
+                },
+            );
+        }
+        discovered
+    }
+
+    pub fn expire(&mut self, now: Instant) -> Vec<String> {
+        let timeout = self.timeout;
+        let expired = self
+            .members
+            .iter()
+            .filter(|(_, member)| now.duration_since(member.last_seen) > timeout)
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        for id in expired.iter() {
+            self.members.remove(id);
+        }
+        expired
+    }
+
+    pub fn members(&self) -> Vec<(String, SocketAddr)> {
+        let mut members = self
+            .members
+            .iter()
+            .map(|(id, member)| (id.clone(), member.address))
+            .collect::<Vec<_>>();
+        members.sort();
+        members
+    }
+
+    pub fn locate(&self, name: &str) -> Option<(String, SocketAddr)> {
+        self.members
+            .iter()
+            .find(|(_, member)| member.presence.iter().any(|present| present == name))
+            .map(|(id, member)| (id.clone(), member.address))
+    }
+
+    pub fn presence(&self) -> Vec<(String, String)> {
+        let mut presence = self
+            .members
+            .iter()
+            .flat_map(|(id, member)| {
+                member
+                    .presence
+                    .iter()
+                    .map(move |name| (id.clone(), name.clone()))
+            })
+            .collect::<Vec<_>>();
+        presence.sort();
+        presence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn gossip_discovers_members_transitively_and_expires_silent_ones() {
+        let start = Instant::now();
+        let mut membership = Membership::new("a", vec![address(7101)], Duration::from_secs(5));
+        assert_eq!(membership.targets(), vec![address(7101)]);
+
+        let discovered = membership.merge(
+            address(7101),
+            "b",
+            &[
+                ("a".to_string(), address(7100)),
+                ("c".to_string(), address(7102)),
+            ],
+            vec!["bob".to_string()],
+            start,
+        );
+        assert_eq!(discovered, vec!["b", "c"]);
+        assert_eq!(
+            membership.locate("bob"),
+            Some(("b".to_string(), address(7101)))
+        );
+        assert_eq!(membership.locate("carol"), None);
+
+        membership.merge(
+            address(7102),
+            "c",
+            &[],
+            vec!["carol".to_string()],
+            start + Duration::from_secs(4),
+        );
+        assert_eq!(
+            membership.presence(),
+            vec![
+                ("b".to_string(), "bob".to_string()),
+                ("c".to_string(), "carol".to_string())
+            ]
+        );
+
+        assert_eq!(membership.expire(start + Duration::from_secs(6)), vec!["b"]);
+        assert_eq!(membership.members(), vec![("c".to_string(), address(7102))]);
+        assert_eq!(membership.targets(), vec![address(7102), address(7101)]);
+    }
+}
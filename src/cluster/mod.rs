@@ -0,0 +1,8 @@
+mod config;
+mod membership;
+mod node;
+mod whisper;
+pub use config::*;
+pub use membership::*;
+pub use node::*;
+pub use whisper::*;
@@ -0,0 +1,229 @@
+use super::{ClusterConfig, ClusterMessage, DirectMessage, Membership, Whisper, WhisperReceipt};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+const MAX_DATAGRAM: usize = 65536;
+
+#[derive(Clone)]
+pub struct ClusterNode {
+    node_id: String,
+    socket: Arc<UdpSocket>,
+    membership: Arc<Mutex<Membership>>,
+}
+
+impl ClusterNode {
+    pub fn start<F>(
+        config: ClusterConfig,
+        presence: F,
+    ) -> std::io::Result<(Self, Receiver<DirectMessage>)>
+    where
+        F: Fn() -> Vec<String> + Send + 'static,
+    {
+        let socket = Arc::new(UdpSocket::bind(config.bind.as_str())?);
+        socket.set_read_timeout(Some(config.gossip_interval))?;
+        let seeds = config
+            .seeds
+            .iter()
+            .filter_map(|seed| match seed.to_socket_addrs() {
+                Ok(mut addresses) => addresses.next(),
+                Err(e) => {
+                    eprintln!("クラスタノード{}を解決できませんでした：{}\n", seed, e);
+                    None
+                }
+            })
+            .collect();
+        let node = ClusterNode {
+            node_id: config.node_id.clone(),
+            socket,
+            membership: Arc::new(Mutex::new(Membership::new(
+                &config.node_id,
+                seeds,
+                config.peer_timeout,
+            ))),
+        };
+
+        let (messages, received) = channel();
+        let gossip_node = node.clone();
+        std::thread::spawn(move || gossip_node.run(config, presence, messages));
+        Ok((node, received))
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn locate(&self, name: &str) -> Option<String> {
+        self.lock().locate(name).map(|(node_id, _)| node_id)
+    }
+
+    pub fn presence(&self) -> Vec<(String, String)> {
+        self.lock().presence()
+    }
+
+    pub fn whisper(&self, whisper: Whisper) -> std::io::Result<bool> {
+        let target = whisper.target.clone();
+        self.route(&target, &ClusterMessage::Whisper(whisper))
+    }
+
+    pub fn receipt(&self, receipt: WhisperReceipt) -> std::io::Result<bool> {
+        let sender = receipt.sender.clone();
+        self.route(&sender, &ClusterMessage::Receipt(receipt))
+    }
+
+    fn route(&self, name: &str, message: &ClusterMessage) -> std::io::Result<bool> {
+        let address = match self.lock().locate(name) {
+            Some((_, address)) => address,
+            None => return Ok(false),
+        };
+        self.send(message, address)?;
+        Ok(true)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Membership> {
+        self.membership
+            .lock()
+            .expect("Failed to lock cluster membership.")
+    }
+
+    fn send(&self, message: &ClusterMessage, address: SocketAddr) -> std::io::Result<()> {
+        let payload = serde_json::to_vec(message)?;
+        self.socket.send_to(&payload, address).map(|_| ())
+    }
+
+    fn run<F>(self, config: ClusterConfig, presence: F, messages: Sender<DirectMessage>)
+    where
+        F: Fn() -> Vec<String>,
+    {
+        let mut buffer = vec![0_u8; MAX_DATAGRAM];
+        let mut last_gossip = None::<Instant>;
+        loop {
+            if last_gossip.is_none_or(|sent| sent.elapsed() >= config.gossip_interval) {
+                let (gossip, targets) = {
+                    let mut membership = self.lock();
+                    for node_id in membership.expire(Instant::now()) {
+                        println!("クラスタノード{}との接続が途絶えました\n", node_id);
+                    }
+                    (membership.gossip(presence()), membership.targets())
+                };
+                for target in targets {
+                    if let Err(e) = self.send(&gossip, target) {
+                        eprintln!("{}へのゴシップ送信に失敗しました：{}\n", target, e);
+                    }
+                }
+                last_gossip = Some(Instant::now());
+            }
+
+            let (size, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            match serde_json::from_slice::<ClusterMessage>(&buffer[..size]) {
+                Ok(ClusterMessage::Gossip {
+                    node_id,
+                    members,
+                    presence,
+                }) => {
+                    let discovered =
+                        self.lock()
+                            .merge(from, &node_id, &members, presence, Instant::now());
+                    for node_id in discovered {
+                        println!("クラスタノード{}を検出しました\n", node_id);
+                    }
+                }
+                Ok(ClusterMessage::Whisper(whisper)) => {
+                    let _ = messages.send(DirectMessage::Whisper(whisper));
+                }
+                Ok(ClusterMessage::Receipt(receipt)) => {
+                    let _ = messages.send(DirectMessage::Receipt(receipt));
+                }
+                Err(e) => eprintln!(
+                    "{}から不正なクラスタメッセージを受信しました：{}\n",
+                    from, e
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::ReceiptStatus;
+    use std::time::Duration;
+
+    fn config(node_id: &str, seeds: Vec<String>) -> ClusterConfig {
+        ClusterConfig {
+            node_id: node_id.to_string(),
+            bind: "127.0.0.1:0".to_string(),
+            seeds,
+            gossip_interval: Duration::from_millis(20),
+            peer_timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn wait_until<F: Fn() -> bool>(condition: F) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "The cluster did not converge.");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn nodes_share_presence_and_route_whispers() {
+        let (first, first_messages) =
+            ClusterNode::start(config("first", vec![]), || vec!["alice".to_string()])
+                .expect("Failed to start the first node.");
+        let seed = format!(
+            "127.0.0.1:{}",
+            first.local_addr().expect("Failed to read address.").port()
+        );
+        let (second, messages) =
+            ClusterNode::start(config("second", vec![seed]), || vec!["bob".to_string()])
+                .expect("Failed to start the second node.");
+
+        wait_until(|| first.locate("bob").is_some() && second.locate("alice").is_some());
+        assert_eq!(first.locate("bob"), Some("second".to_string()));
+        assert_eq!(
+            second.presence(),
+            vec![("first".to_string(), "alice".to_string())]
+        );
+
+        let whisper = Whisper {
+            id: 1,
+            origin: first.node_id().to_string(),
+            sender: "alice".to_string(),
+            target: "bob".to_string(),
+            text: "psst".to_string(),
+        };
+        assert!(first.whisper(whisper.clone()).expect("Failed to whisper."));
+        assert_eq!(
+            messages
+                .recv_timeout(Duration::from_secs(5))
+                .expect("The whisper was not routed."),
+            DirectMessage::Whisper(whisper.clone())
+        );
+        let receipt = whisper.receipt(ReceiptStatus::Read);
+        assert!(second
+            .receipt(receipt.clone())
+            .expect("Failed to send receipt."));
+        assert_eq!(
+            first_messages
+                .recv_timeout(Duration::from_secs(5))
+                .expect("The receipt was not routed."),
+            DirectMessage::Receipt(receipt)
+        );
+        assert!(!first
+            .whisper(Whisper {
+                target: "nobody".to_string(),
+                ..whisper
+            })
+            .expect("Failed to whisper."));
+    }
+}
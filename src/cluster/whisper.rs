@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WhisperCommand {
+    pub target: String,
+    pub text: String,
+}
+
+impl WhisperCommand {
+    pub fn parse(input: &str) -> Option<WhisperCommand> {
+        let input = input.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        let (command, rest) = input.split_once(char::is_whitespace)?;
+        if command != ":whisper" && command != ":w" {
+            return None;
+        }
+        let (target, text) = rest.trim_start().split_once(char::is_whitespace)?;
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        Some(WhisperCommand {
+            target: target.to_string(),
+            text: text.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadCommand {
+    pub sender: String,
+    pub id: u64,
+}
+
+impl ReadCommand {
+    pub fn parse(input: &str) -> Option<ReadCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+            (":read", Some(sender), Some(id), None) => Some(ReadCommand {
+                sender: sender.to_string(),
+                id: id.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Whisper {
+    #[serde(default)]
+    pub id: u64,
+    pub origin: String,
+    pub sender: String,
+    pub target: String,
+    pub text: String,
+}
+
+impl Whisper {
+    pub fn format(&self) -> String {
+        format!("[Whisper #{}] {}：{}", self.id, self.sender, self.text)
+    }
+
+    pub fn confirmation(&self) -> String {
+        format!("[Whisper -> {} #{}] {}", self.target, self.id, self.text)
+    }
+
+    pub fn receipt(&self, status: ReceiptStatus) -> WhisperReceipt {
+        WhisperReceipt {
+            id: self.id,
+            status,
+            sender: self.sender.clone(),
+            reader: self.target.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceiptStatus {
+    Delivered,
+    Read,
+    Notified,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhisperReceipt {
+    pub id: u64,
+    pub status: ReceiptStatus,
+    pub sender: String,
+    pub reader: String,
+}
+
+impl WhisperReceipt {
+    pub fn encode(&self) -> String {
+        let status = match self.status {
+            ReceiptStatus::Delivered => "DELIVERED",
+            ReceiptStatus::Read => "READ",
+            ReceiptStatus::Notified => "NOTIFIED",
+        };
+        format!("{} {} {}", status, self.id, self.reader)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DirectMessage {
+    Whisper(Whisper),
+    Receipt(WhisperReceipt),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whisper_commands_need_a_target_and_text() {
+        assert_eq!(
+            WhisperCommand::parse(":whisper alice  hello there\0"),
+            Some(WhisperCommand {
+                target: "alice".to_string(),
+                text: "hello there".to_string(),
+            })
+        );
+        assert_eq!(
+            WhisperCommand::parse(":w bob hi").map(|command| command.target),
+            Some("bob".to_string())
+        );
+        assert_eq!(WhisperCommand::parse(":whisper alice"), None);
+        assert_eq!(WhisperCommand::parse(":whisper alice   "), None);
+        assert_eq!(WhisperCommand::parse(":whispers alice hi"), None);
+    }
+
+    #[test]
+    fn receipts_carry_the_whisper_correlation_id() {
+        let whisper = Whisper {
+            id: 7,
+            origin: String::new(),
+            sender: "alice".to_string(),
+            target: "bob".to_string(),
+            text: "psst".to_string(),
+        };
+        assert_eq!(whisper.format(), "[Whisper #7] alice：psst");
+        assert_eq!(whisper.confirmation(), "[Whisper -> bob #7] psst");
+        assert_eq!(
+            whisper.receipt(ReceiptStatus::Delivered).encode(),
+            "DELIVERED 7 bob"
+        );
+        assert_eq!(
+            ReadCommand::parse(":read alice 7\0"),
+            Some(ReadCommand {
+                sender: "alice".to_string(),
+                id: 7,
+            })
+        );
+        assert_eq!(ReadCommand::parse(":read alice seven"), None);
+        assert_eq!(ReadCommand::parse(":read alice"), None);
+    }
+}
@@ -0,0 +1,18 @@
+use super::{Codec, CodecResult, Message};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn encode(&self, message: &Message) -> CodecResult<Vec<u8>> {
+        Ok(bincode::serialize(message)?)
+    }
+
+    fn decode(&self, data: &[u8]) -> CodecResult<Message> {
+        Ok(bincode::deserialize(data)?)
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum CodecError {
+    Bincode(bincode::Error),
+    Json(serde_json::Error),
+    MessagePackEncode(rmp_serde::encode::Error),
+    MessagePackDecode(rmp_serde::decode::Error),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::Bincode(e) => write!(f, "bincode error: {}", e),
+            CodecError::Json(e) => write!(f, "JSON error: {}", e),
+            CodecError::MessagePackEncode(e) => write!(f, "MessagePack encode error: {}", e),
+            CodecError::MessagePackDecode(e) => write!(f, "MessagePack decode error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<bincode::Error> for CodecError {
+    fn from(e: bincode::Error) -> Self {
+        CodecError::Bincode(e)
+    }
+}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(e: serde_json::Error) -> Self {
+        CodecError::Json(e)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for CodecError {
+    fn from(e: rmp_serde::encode::Error) -> Self {
+        CodecError::MessagePackEncode(e)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for CodecError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        CodecError::MessagePackDecode(e)
+    }
+}
+
+pub type CodecResult<T> = Result<T, CodecError>;
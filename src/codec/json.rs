@@ -0,0 +1,18 @@
+use super::{Codec, CodecResult, Message};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode(&self, message: &Message) -> CodecResult<Vec<u8>> {
+        Ok(serde_json::to_vec(message)?)
+    }
+
+    fn decode(&self, data: &[u8]) -> CodecResult<Message> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
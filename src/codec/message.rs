@@ -0,0 +1,52 @@
+use super::ChatBody;
+use crate::transport::SendPriority;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    Hello {
+        motd: String,
+    },
+    Chat {
+        room: String,
+        sender: Option<String>,
+        body: ChatBody,
+    },
+    Join {
+        room: String,
+    },
+    Command {
+        name: String,
+        args: Vec<String>,
+    },
+    Reply {
+        ok: bool,
+        text: String,
+    },
+    End,
+    AttachmentOffer {
+        id: u32,
+        name: String,
+        mime: String,
+        size: u32,
+    },
+    AttachmentChunk {
+        id: u32,
+        index: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl Message {
+    pub fn priority(&self) -> SendPriority {
+        match self {
+            Message::Hello { .. }
+            | Message::Join { .. }
+            | Message::Command { .. }
+            | Message::Reply { .. }
+            | Message::End => SendPriority::Control,
+            Message::Chat { .. } => SendPriority::Chat,
+            Message::AttachmentOffer { .. } | Message::AttachmentChunk { .. } => SendPriority::Bulk,
+        }
+    }
+}
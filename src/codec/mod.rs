@@ -0,0 +1,223 @@
+mod binary;
+mod error;
+mod json;
+mod message;
+mod msgpack;
+mod rich;
+pub use binary::*;
+pub use error::*;
+pub use json::*;
+pub use message::*;
+pub use msgpack::*;
+pub use rich::*;
+
+use std::sync::Arc;
+
+pub trait Codec: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn encode(&self, message: &Message) -> CodecResult<Vec<u8>>;
+    fn decode(&self, data: &[u8]) -> CodecResult<Message>;
+}
+
+pub fn codec_by_name(name: &str) -> Option<Arc<dyn Codec>> {
+    match name {
+        "bincode" => Some(Arc::new(BincodeCodec)),
+        "json" => Some(Arc::new(JsonCodec)),
+        "msgpack" | "messagepack" => Some(Arc::new(MessagePackCodec)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn golden_cases() -> Vec<(&'static str, Message)> {
+        vec![
+            (
+                "hello",
+                Message::Hello {
+                    motd: "Hello".to_string(),
+                },
+            ),
+            (
+                "chat",
+                Message::Chat {
+                    room: "lobby".to_string(),
+                    sender: Some("alice".to_string()),
+                    body: ChatBody::Plain("こんにちは、世界".to_string()),
+                },
+            ),
+            (
+                "chat_anonymous",
+                Message::Chat {
+                    room: "red".to_string(),
+                    sender: None,
+                    body: ChatBody::Plain("hello".to_string()),
+                },
+            ),
+            (
+                "chat_emote",
+                Message::Chat {
+                    room: "lobby".to_string(),
+                    sender: Some("alice".to_string()),
+                    body: ChatBody::Emote("waves".to_string()),
+                },
+            ),
+            (
+                "chat_notice",
+                Message::Chat {
+                    room: "lobby".to_string(),
+                    sender: None,
+                    body: ChatBody::Notice("Server restarting soon.".to_string()),
+                },
+            ),
+            (
+                "chat_formatted",
+                Message::Chat {
+                    room: "lobby".to_string(),
+                    sender: Some("bob".to_string()),
+                    body: ChatBody::Formatted(vec![
+                        Span {
+                            text: "gg ".to_string(),
+                            style: SpanStyle::Normal,
+                        },
+                        Span {
+                            text: "well played".to_string(),
+                            style: SpanStyle::Bold,
+                        },
+                    ]),
+                },
+            ),
+            (
+                "join",
+                Message::Join {
+                    room: "red".to_string(),
+                },
+            ),
+            (
+                "command",
+                Message::Command {
+                    name: "top".to_string(),
+                    args: vec!["10".to_string()],
+                },
+            ),
+            (
+                "reply",
+                Message::Reply {
+                    ok: false,
+                    text: "Invalid name or password.".to_string(),
+                },
+            ),
+            ("end", Message::End),
+            (
+                "attachment_offer",
+                Message::AttachmentOffer {
+                    id: 1,
+                    name: "cat.png".to_string(),
+                    mime: "image/png".to_string(),
+                    size: 4,
+                },
+            ),
+            (
+                "attachment_chunk",
+                Message::AttachmentChunk {
+                    id: 1,
+                    index: 0,
+                    data: vec![0x89, b'P', b'N', b'G'],
+                },
+            ),
+            (
+                "chat_attachment",
+                Message::Chat {
+                    room: "lobby".to_string(),
+                    sender: Some("alice".to_string()),
+                    body: ChatBody::Attachment(AttachmentRef {
+                        id: 1,
+                        name: "cat.png".to_string(),
+                        mime: "image/png".to_string(),
+                        size: 4,
+                    }),
+                },
+            ),
+        ]
+    }
+
+    fn codecs() -> Vec<(&'static str, Arc<dyn Codec>)> {
+        ["bincode", "json", "msgpack"]
+            .iter()
+            .map(|name| (*name, codec_by_name(name).expect("Unknown codec.")))
+            .collect()
+    }
+
+    fn golden_path(case: &str, extension: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("golden")
+            .join("codec")
+            .join(format!("{}.{}", case, extension))
+    }
+
+    #[test]
+    fn messages_round_trip_through_every_codec() {
+        for (_, codec) in codecs() {
+            for (case, message) in golden_cases() {
+                let encoded = codec.encode(&message).expect("Failed to encode message.");
+                let decoded = codec.decode(&encoded).expect("Failed to decode message.");
+                assert_eq!(decoded, message, "{} round trip of {}", codec.name(), case);
+            }
+        }
+    }
+
+    #[test]
+    fn wire_format_matches_golden_files() {
+        let update = std::env::var("UPDATE_GOLDEN").is_ok();
+        for (extension, codec) in codecs() {
+            for (case, message) in golden_cases() {
+                let path = golden_path(case, extension);
+                let encoded = codec.encode(&message).expect("Failed to encode message.");
+                if update {
+                    std::fs::write(&path, &encoded).expect("Failed to write golden file.");
+                    continue;
+                }
+                let golden = std::fs::read(&path).unwrap_or_else(|e| {
+                    panic!("Failed to read golden file {}: {}", path.display(), e)
+                });
+                assert_eq!(
+                    encoded,
+                    golden,
+                    "{} encoding of {} no longer matches {}; rerun with UPDATE_GOLDEN=1 if the wire change is intended",
+                    codec.name(),
+                    case,
+                    path.display()
+                );
+                assert_eq!(
+                    codec
+                        .decode(&golden)
+                        .expect("Failed to decode golden file."),
+                    message,
+                    "{} can no longer decode {}",
+                    codec.name(),
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        for (_, codec) in codecs() {
+            assert!(
+                codec.decode(&[]).is_err(),
+                "{} accepted no data",
+                codec.name()
+            );
+            assert!(
+                codec.decode(&[0xff, 0xff, 0xff, 0xff]).is_err(),
+                "{} accepted garbage",
+                codec.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use super::{Codec, CodecResult, Message};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn encode(&self, message: &Message) -> CodecResult<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(message)?)
+    }
+
+    fn decode(&self, data: &[u8]) -> CodecResult<Message> {
+        Ok(rmp_serde::from_slice(data)?)
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpanStyle {
+    Normal,
+    Bold,
+    Italic,
+    Code,
+}
+
+impl SpanStyle {
+    fn from_marker(marker: char) -> Option<SpanStyle> {
+        match marker {
+            '*' => Some(SpanStyle::Bold),
+            '_' => Some(SpanStyle::Italic),
+            '`' => Some(SpanStyle::Code),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentRef {
+    pub id: u32,
+    pub name: String,
+    pub mime: String,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatBody {
+    Plain(String),
+    Emote(String),
+    Notice(String),
+    Formatted(Vec<Span>),
+    Attachment(AttachmentRef),
+}
+
+impl ChatBody {
+    pub fn parse(input: &str) -> ChatBody {
+        if let Some(action) = input.strip_prefix("/me ") {
+            return ChatBody::Emote(action.trim().to_string());
+        }
+        let spans = parse_spans(input);
+        if spans.iter().all(|span| span.style == SpanStyle::Normal) {
+            ChatBody::Plain(input.to_string())
+        } else {
+            ChatBody::Formatted(spans)
+        }
+    }
+
+    pub fn plain_text(&self) -> String {
+        match self {
+            ChatBody::Plain(text) | ChatBody::Emote(text) | ChatBody::Notice(text) => text.clone(),
+            ChatBody::Formatted(spans) => spans.iter().map(|span| span.text.as_str()).collect(),
+            ChatBody::Attachment(attachment) => format!(
+                "[Attachment] {} ({}, {} bytes)",
+                &attachment.name, &attachment.mime, attachment.size
+            ),
+        }
+    }
+
+    pub fn render(&self, sender: Option<&str>) -> String {
+        match (self, sender) {
+            (ChatBody::Emote(action), Some(sender)) => format!("* {} {}", sender, action),
+            (ChatBody::Emote(action), None) => format!("* {}", action),
+            (ChatBody::Notice(text), _) => format!("[Server] {}", text),
+            (body, Some(sender)) => format!("{}：{}", sender, body.plain_text()),
+            (body, None) => body.plain_text(),
+        }
+    }
+}
+
+fn parse_spans(input: &str) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut normal = String::new();
+    let mut rest = input;
+    while let Some(marker) = rest.chars().next() {
+        let styled = SpanStyle::from_marker(marker).and_then(|style| {
+            let inner = &rest[marker.len_utf8()..];
+            inner
+                .find(marker)
+                .filter(|end| {
+                    let text = &inner[..*end];
+                    !text.is_empty() && text.trim() == text
+                })
+                .map(|end| (style, &inner[..end], &inner[end + marker.len_utf8()..]))
+        });
+        match styled {
+            Some((style, text, remaining)) => {
+                if !normal.is_empty() {
+                    spans.push(Span {
+                        text: std::mem::take(&mut normal),
+                        style: SpanStyle::Normal,
+                    });
+                }
+                spans.push(Span {
+                    text: text.to_string(),
+                    style,
+                });
+                rest = remaining;
+            }
+            None => {
+                normal.push(marker);
+                rest = &rest[marker.len_utf8()..];
+            }
+        }
+    }
+    if !normal.is_empty() {
+        spans.push(Span {
+            text: normal,
+            style: SpanStyle::Normal,
+        });
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: SpanStyle) -> Span {
+        Span {
+            text: text.to_string(),
+            style,
+        }
+    }
+
+    #[test]
+    fn markup_is_parsed_into_rich_bodies() {
+        assert_eq!(
+            ChatBody::parse("hello"),
+            ChatBody::Plain("hello".to_string())
+        );
+        assert_eq!(
+            ChatBody::parse("/me waves"),
+            ChatBody::Emote("waves".to_string())
+        );
+        assert_eq!(
+            ChatBody::parse("これは*太字*と_斜体_と`code`"),
+            ChatBody::Formatted(vec![
+                span("これは", SpanStyle::Normal),
+                span("太字", SpanStyle::Bold),
+                span("と", SpanStyle::Normal),
+                span("斜体", SpanStyle::Italic),
+                span("と", SpanStyle::Normal),
+                span("code", SpanStyle::Code),
+            ])
+        );
+        assert_eq!(
+            ChatBody::parse("2 * 3 ** 4"),
+            ChatBody::Plain("2 * 3 ** 4".to_string())
+        );
+    }
+
+    #[test]
+    fn bodies_render_to_the_text_protocol() {
+        assert_eq!(
+            ChatBody::Emote("waves".to_string()).render(Some("alice")),
+            "* alice waves"
+        );
+        assert_eq!(
+            ChatBody::Notice("Restarting soon.".to_string()).render(None),
+            "[Server] Restarting soon."
+        );
+        assert_eq!(
+            ChatBody::parse("*hi* all").render(Some("bob")),
+            "bob：hi all"
+        );
+    }
+}
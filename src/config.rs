@@ -0,0 +1,170 @@
+use crate::announcements::{parse_announcements, Schedule};
+use crate::anticheat::AntiCheatConfig;
+use crate::aoi::AoiConfig;
+use crate::attachment::AttachmentPolicy;
+use crate::bots::BotConfig;
+#[cfg(feature = "tls")]
+use crate::bridge::DiscordConfig;
+use crate::bridge::{MqttConfig, RedisConfig, WebhookConfig};
+use crate::chat_log::ChatLogConfig;
+use crate::cluster::ClusterConfig;
+use crate::console;
+use crate::frame::DEFAULT_MAX_FRAME_SIZE;
+use crate::geoip::GeoIpConfig;
+use crate::layers::{CompressionConfig, Pipeline};
+use crate::notify::PushConfig;
+use crate::p2p::RelayConfig;
+use crate::playback::PlaybackConfig;
+use crate::pow;
+use crate::query::QueryConfig;
+use crate::rooms::DEFAULT_HISTORY_SIZE;
+use crate::server::{DEFAULT_MAX_CLIENTS, DEFAULT_TICK_INTERVAL};
+use crate::snapshot::SnapshotConfig;
+use crate::throttle::ThrottleConfig;
+use crate::transfer::TransferConfig;
+#[cfg(feature = "chaos")]
+use crate::transport::ChaosConfig;
+use crate::transport::{NetemConfig, TextEncoding, TransportKind};
+use crate::zone::ZoneConfig;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 7000;
+
+#[derive(Clone, Debug, Default)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub bind: Option<String>,
+    pub max_clients: usize,
+    pub max_frame_size: usize,
+    pub transport: TransportKind,
+    pub tick_interval: Duration,
+    pub mqtt: Option<MqttConfig>,
+    pub chat_log: Option<ChatLogConfig>,
+    pub redis: Option<RedisConfig>,
+    pub webhooks: Option<WebhookConfig>,
+    pub push: Option<PushConfig>,
+    #[cfg(feature = "tls")]
+    pub discord: Option<DiscordConfig>,
+    pub require_login: bool,
+    pub admin_http: Option<String>,
+    pub room_history: usize,
+    pub snapshot: Option<SnapshotConfig>,
+    pub record_dir: Option<PathBuf>,
+    pub playback: Option<PlaybackConfig>,
+    pub netem: Option<NetemConfig>,
+    pub layers: Option<Pipeline>,
+    pub compression: Option<CompressionConfig>,
+    pub plugins: Vec<String>,
+    pub cluster: Option<ClusterConfig>,
+    pub relay: Option<RelayConfig>,
+    pub idle_timeout: Option<Duration>,
+    pub afk_timeout: Option<Duration>,
+    pub heartbeat_interval: Option<Duration>,
+    pub afk_skip_ready: bool,
+    pub scoreboard_interval: Option<Duration>,
+    pub bots: Option<BotConfig>,
+    pub anticheat: Option<AntiCheatConfig>,
+    pub zone: Option<ZoneConfig>,
+    pub transfer: Option<TransferConfig>,
+    pub aoi: Option<AoiConfig>,
+    pub attachments: AttachmentPolicy,
+    pub text_encoding: TextEncoding,
+    pub plain_text: bool,
+    pub console_color: bool,
+    pub announcements: Vec<(Schedule, String)>,
+    pub geoip: Option<GeoIpConfig>,
+    pub throttle: ThrottleConfig,
+    pub pow_difficulty: Option<u32>,
+    pub query: Option<QueryConfig>,
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<ChaosConfig>,
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        let port = env_or("SERVER_PORT", "").parse().unwrap_or(DEFAULT_PORT);
+        let tick_interval = env_millis("TICK_INTERVAL_MS").unwrap_or(DEFAULT_TICK_INTERVAL);
+        ServerConfig {
+            port,
+            bind: std::env::var("SERVER_BIND").ok(),
+            max_clients: env_or("MAX_CLIENTS", "")
+                .parse()
+                .unwrap_or(DEFAULT_MAX_CLIENTS),
+            max_frame_size: env_or("MAX_FRAME_SIZE", "")
+                .parse()
+                .ok()
+                .filter(|size| *size > 0)
+                .unwrap_or(DEFAULT_MAX_FRAME_SIZE),
+            transport: TransportKind::from_name(&env_or("TRANSPORT", "tcp")).unwrap_or_default(),
+            tick_interval,
+            mqtt: MqttConfig::from_env(),
+            chat_log: ChatLogConfig::from_env(),
+            redis: RedisConfig::from_env(),
+            webhooks: WebhookConfig::from_env(),
+            push: PushConfig::from_env(),
+            #[cfg(feature = "tls")]
+            discord: DiscordConfig::from_env(),
+            require_login: env_or("REQUIRE_LOGIN", "false") == "true",
+            admin_http: std::env::var("ADMIN_HTTP_ADDRESS").ok(),
+            room_history: env_or("ROOM_HISTORY_SIZE", "")
+                .parse()
+                .unwrap_or(DEFAULT_HISTORY_SIZE),
+            snapshot: SnapshotConfig::from_env(),
+            record_dir: std::env::var("RECORD_DIR").ok().map(PathBuf::from),
+            playback: PlaybackConfig::from_env(),
+            netem: NetemConfig::from_env(),
+            layers: Pipeline::from_env(),
+            compression: CompressionConfig::from_env(),
+            plugins: env_or("PLUGINS", "")
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            cluster: ClusterConfig::from_env(),
+            relay: RelayConfig::from_env(),
+            idle_timeout: env_millis("IDLE_TIMEOUT_MS").filter(|timeout| !timeout.is_zero()),
+            afk_timeout: env_millis("AFK_TIMEOUT_MS").filter(|timeout| !timeout.is_zero()),
+            heartbeat_interval: env_millis("HEARTBEAT_INTERVAL_MS")
+                .filter(|interval| !interval.is_zero()),
+            afk_skip_ready: env_or("AFK_SKIP_READY", "false") == "true",
+            scoreboard_interval: env_millis("SCOREBOARD_INTERVAL_MS")
+                .filter(|interval| !interval.is_zero()),
+            bots: BotConfig::from_env(),
+            anticheat: AntiCheatConfig::from_env(tick_interval),
+            zone: ZoneConfig::from_env(),
+            transfer: TransferConfig::from_env(),
+            aoi: AoiConfig::from_env(),
+            attachments: AttachmentPolicy::from_env(),
+            text_encoding: TextEncoding::from_name(&env_or("TEXT_ENCODING", "utf-8"))
+                .unwrap_or_default(),
+            plain_text: env_or("PLAIN_TEXT", "false") == "true",
+            console_color: console::color_from_env(),
+            announcements: parse_announcements(&env_or("ANNOUNCEMENTS", "")),
+            geoip: GeoIpConfig::from_env(),
+            throttle: ThrottleConfig::from_env(),
+            pow_difficulty: pow::difficulty_from_env(),
+            query: QueryConfig::from_env(port),
+            #[cfg(feature = "chaos")]
+            chaos: ChaosConfig::from_env(),
+        }
+    }
+}
+
+pub fn env_or(key: &str, default: &str) -> String {
+    std::env::var(key).unwrap_or_else(|_| default.to_string())
+}
+
+pub fn env_millis(key: &str) -> Option<Duration> {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_millis)
+}
+
+pub fn env_probability(key: &str) -> f64 {
+    env_or(key, "0")
+        .parse::<f64>()
+        .unwrap_or(0.0)
+        .clamp(0.0, 1.0)
+}
@@ -0,0 +1,129 @@
+use crate::config::env_or;
+use std::fmt::Arguments;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const RESET: &str = "\x1b[0m";
+const SYSTEM_COLOR: &str = "\x1b[90m";
+const ERROR_COLOR: &str = "\x1b[31m";
+const PALETTE: [&str; 6] = [
+    "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m", "\x1b[93m",
+];
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind<'a> {
+    System,
+    Error,
+    Chat { client_id: u32, room: &'a str },
+}
+
+pub fn color_from_env() -> bool {
+    match env_or("CONSOLE_COLOR", "auto").as_str() {
+        "always" | "true" => true,
+        "never" | "false" => false,
+        _ => std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+    }
+}
+
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::SeqCst);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::SeqCst)
+}
+
+fn palette_color(key: u64) -> &'static str {
+    PALETTE[(key % PALETTE.len() as u64) as usize]
+}
+
+fn room_color(room: &str) -> &'static str {
+    palette_color(room.bytes().fold(0_u64, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as u64)
+    }))
+}
+
+pub fn render(kind: LineKind<'_>, text: &str, color: bool) -> String {
+    let body = text.trim_end_matches('\n');
+    let newlines = &text[body.len()..];
+    let line = match (kind, color) {
+        (LineKind::Chat { room, .. }, false) => format!("[{}] {}", room, body),
+        (_, false) => body.to_string(),
+        (LineKind::System, true) => format!("{}{}{}", SYSTEM_COLOR, body, RESET),
+        (LineKind::Error, true) => format!("{}{}{}", ERROR_COLOR, body, RESET),
+        (LineKind::Chat { client_id, room }, true) => format!(
+            "{}[{}]{} {}{}{}",
+            room_color(room),
+            room,
+            RESET,
+            palette_color(client_id as u64),
+            body,
+            RESET
+        ),
+    };
+    format!("{}{}", line, newlines)
+}
+
+pub fn system(args: Arguments<'_>) {
+    println!(
+        "{}",
+        render(LineKind::System, &args.to_string(), color_enabled())
+    );
+}
+
+pub fn error(args: Arguments<'_>) {
+    eprintln!(
+        "{}",
+        render(LineKind::Error, &args.to_string(), color_enabled())
+    );
+}
+
+pub fn chat(client_id: u32, room: &str, args: Arguments<'_>) {
+    println!(
+        "{}",
+        render(
+            LineKind::Chat { client_id, room },
+            &args.to_string(),
+            color_enabled()
+        )
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_tagged_and_colored_by_kind() {
+        let chat = LineKind::Chat {
+            client_id: 1,
+            room: "lobby",
+        };
+        assert_eq!(render(chat, "1 -> 2：hi\n", false), "[lobby] 1 -> 2：hi\n");
+        assert_eq!(
+            render(LineKind::System, "接続しました\n", false),
+            "接続しました\n"
+        );
+        assert_eq!(
+            render(LineKind::Error, "失敗しました\n", true),
+            "\x1b[31m失敗しました\x1b[0m\n"
+        );
+
+        let colored = render(chat, "1 -> 2：hi", true);
+        assert!(colored.starts_with(room_color("lobby")));
+        assert!(colored.contains(&format!("{}1 -> 2：hi{}", PALETTE[1], RESET)));
+        assert_ne!(
+            render(chat, "hi", true),
+            render(
+                LineKind::Chat {
+                    client_id: 2,
+                    room: "lobby"
+                },
+                "hi",
+                true
+            )
+        );
+    }
+}
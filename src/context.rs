@@ -0,0 +1,98 @@
+use crate::announcements::AnnouncementScheduler;
+use crate::anticheat::AntiCheat;
+use crate::aoi::InterestManager;
+use crate::chat_log::ChatLog;
+use crate::clients::ClientRegistry;
+use crate::clock::ServerClock;
+use crate::cluster::ClusterNode;
+use crate::events::EventSender;
+use crate::guilds::Guilds;
+use crate::identity::Identity;
+use crate::ip_stats::IpStats;
+use crate::leaderboard::Leaderboard;
+use crate::metrics::Metrics;
+use crate::notify::Notifier;
+use crate::p2p::MeshRegistry;
+use crate::recorder::Recorder;
+use crate::rooms::Rooms;
+use crate::scoreboard::Scoreboards;
+use crate::server::DEFAULT_TICK_INTERVAL;
+use crate::snapshot::Snapshotter;
+use crate::storage::AccountStore;
+#[cfg(feature = "sqlite")]
+use crate::storage::Storage;
+use crate::transfer::TransferService;
+use crate::zone::ZoneNode;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct ServerContext {
+    pub clients: ClientRegistry,
+    pub rooms: Rooms,
+    pub mesh: MeshRegistry,
+    pub identity: Identity,
+    #[cfg(feature = "sqlite")]
+    pub storage: Option<Storage>,
+    pub accounts: Option<Arc<dyn AccountStore>>,
+    pub leaderboard: Option<Leaderboard>,
+    pub guilds: Option<Guilds>,
+    pub chat_log: Option<ChatLog>,
+    pub recorder: Option<Recorder>,
+    pub snapshotter: Option<Snapshotter>,
+    pub metrics: Metrics,
+    pub ip_stats: IpStats,
+    pub idle_timeout: Option<Duration>,
+    pub afk_timeout: Option<Duration>,
+    pub pre_auth_timeout: Option<Duration>,
+    pub pow_difficulty: Option<u32>,
+    pub heartbeat_interval: Option<Duration>,
+    pub skip_afk_in_ready_checks: bool,
+    pub cluster: Option<ClusterNode>,
+    pub zone: Option<ZoneNode>,
+    pub transfer: Option<TransferService>,
+    pub interest: Option<InterestManager>,
+    pub anticheat: Option<AntiCheat>,
+    pub announcements: AnnouncementScheduler,
+    pub notifier: Option<Arc<dyn Notifier>>,
+    pub clock: ServerClock,
+    pub scoreboards: Scoreboards,
+    pub events: EventSender,
+}
+
+impl ServerContext {
+    pub fn new(clients: ClientRegistry, events: EventSender) -> Self {
+        ServerContext {
+            clients,
+            rooms: Rooms::new(crate::rooms::DEFAULT_HISTORY_SIZE),
+            mesh: MeshRegistry::new(),
+            identity: Identity::new(None, false),
+            #[cfg(feature = "sqlite")]
+            storage: None,
+            accounts: None,
+            leaderboard: None,
+            guilds: None,
+            chat_log: None,
+            recorder: None,
+            snapshotter: None,
+            metrics: Metrics::new(),
+            ip_stats: IpStats::new(),
+            idle_timeout: None,
+            afk_timeout: None,
+            pre_auth_timeout: None,
+            pow_difficulty: None,
+            heartbeat_interval: None,
+            skip_afk_in_ready_checks: false,
+            cluster: None,
+            zone: None,
+            transfer: None,
+            interest: None,
+            anticheat: None,
+            announcements: AnnouncementScheduler::new(),
+            notifier: None,
+            clock: ServerClock::new(DEFAULT_TICK_INTERVAL),
+            scoreboards: Scoreboards::new(None),
+            events,
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::identity::AccountId;
+use crate::leaderboard::MatchStanding;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+#[derive(Clone, Debug)]
+pub enum ServerEvent {
+    ClientJoined {
+        client_id: u32,
+        address: String,
+    },
+    ClientLeft {
+        client_id: u32,
+    },
+    LoggedIn {
+        client_id: u32,
+        account_id: AccountId,
+        name: String,
+    },
+    Chat {
+        client_id: u32,
+        account_id: Option<AccountId>,
+        room: String,
+        message: String,
+    },
+    MatchEnded {
+        room: String,
+        standings: Vec<MatchStanding>,
+    },
+}
+
+impl ServerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerEvent::ClientJoined { .. } => "join",
+            ServerEvent::ClientLeft { .. } => "leave",
+            ServerEvent::LoggedIn { .. } => "login",
+            ServerEvent::Chat { .. } => "chat",
+            ServerEvent::MatchEnded { .. } => "match",
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            ServerEvent::ClientJoined { client_id, address } => format!(
+                "{{\"event\":\"{}\",\"client_id\":{},\"address\":\"{}\"}}",
+                self.name(),
+                client_id,
+                escape_json(address)
+            ),
+            ServerEvent::ClientLeft { client_id } => format!(
+                "{{\"event\":\"{}\",\"client_id\":{}}}",
+                self.name(),
+                client_id
+            ),
+            ServerEvent::LoggedIn {
+                client_id,
+                account_id,
+                name,
+            } => format!(
+                "{{\"event\":\"{}\",\"client_id\":{},\"account_id\":{},\"name\":\"{}\"}}",
+                self.name(),
+                client_id,
+                account_id,
+                escape_json(name)
+            ),
+            ServerEvent::Chat {
+                client_id,
+                account_id,
+                room,
+                message,
+            } => format!(
+                "{{\"event\":\"{}\",\"client_id\":{},\"account_id\":{},\"room\":\"{}\",\"message\":\"{}\"}}",
+                self.name(),
+                client_id,
+                account_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "null".to_string()),
+                escape_json(room),
+                escape_json(message)
+            ),
+            ServerEvent::MatchEnded { room, standings } => format!(
+                "{{\"event\":\"{}\",\"room\":\"{}\",\"standings\":[{}]}}",
+                self.name(),
+                escape_json(room),
+                standings
+                    .iter()
+                    .map(|standing| format!(
+                        "{{\"account_id\":{},\"score\":{}}}",
+                        standing.account_id, standing.score
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
+pub fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => {}
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub trait EventListener: Send {
+    fn on_event(&mut self, event: &ServerEvent);
+}
+
+pub type EventSender = Sender<ServerEvent>;
+
+#[derive(Default)]
+pub struct EventDispatcher {
+    listeners: Vec<Box<dyn EventListener>>,
+}
+
+impl EventDispatcher {
+    pub fn new() -> Self {
+        EventDispatcher::default()
+    }
+
+    pub fn add_listener<T: EventListener + 'static>(&mut self, listener: T) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn spawn(mut self) -> (EventSender, JoinHandle<()>) {
+        let (sender, receiver) = channel::<ServerEvent>();
+        let handle = std::thread::spawn(move || {
+            for event in receiver.iter() {
+                for listener in self.listeners.iter_mut() {
+                    listener.on_event(&event);
+                }
+            }
+        });
+        (sender, handle)
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::{Display, Formatter};
+
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    Oversized { size: usize, max: usize },
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::Oversized { size, max } => write!(
+                f,
+                "a frame of {} bytes exceeds the {}-byte limit",
+                size, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    max_frame_size: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        FrameDecoder::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        FrameDecoder::default()
+    }
+
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        FrameDecoder {
+            buffer: vec![],
+            max_frame_size,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        let end = match self.buffer.iter().position(|b| *b == 0) {
+            Some(end) => end,
+            None => return check_size(self.buffer.len(), self.max_frame_size).map(|_| None),
+        };
+        check_size(end, self.max_frame_size)?;
+        let mut frame = self.buffer.drain(..=end).collect::<Vec<_>>();
+        frame.pop();
+        Ok(Some(frame))
+    }
+}
+
+fn check_size(size: usize, max: usize) -> Result<(), FrameError> {
+    if size > max {
+        return Err(FrameError::Oversized { size, max });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Utf8Decoder::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> String {
+        self.pending.extend_from_slice(data);
+        let mut text = String::new();
+        let mut start = 0;
+        loop {
+            match std::str::from_utf8(&self.pending[start..]) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    start = self.pending.len();
+                    break;
+                }
+                Err(e) => {
+                    let valid_up_to = start + e.valid_up_to();
+                    text.push_str(
+                        std::str::from_utf8(&self.pending[start..valid_up_to])
+                            .expect("Prefix was validated."),
+                    );
+                    match e.error_len() {
+                        Some(length) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            start = valid_up_to + length;
+                        }
+                        None => {
+                            start = valid_up_to;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending.drain(..start);
+        text
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct TextFrameDecoder {
+    utf8: Utf8Decoder,
+    buffer: String,
+    max_frame_size: usize,
+}
+
+impl Default for TextFrameDecoder {
+    fn default() -> Self {
+        TextFrameDecoder::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl TextFrameDecoder {
+    pub fn new() -> Self {
+        TextFrameDecoder::default()
+    }
+
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        TextFrameDecoder {
+            utf8: Utf8Decoder::new(),
+            buffer: String::new(),
+            max_frame_size,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        let text = self.utf8.push(data);
+        self.buffer.push_str(&text);
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<String>, FrameError> {
+        let end = match self.buffer.find('\0') {
+            Some(end) => end,
+            None => return check_size(self.buffer.len(), self.max_frame_size).map(|_| None),
+        };
+        check_size(end, self.max_frame_size)?;
+        let mut frame = self.buffer.drain(..=end).collect::<String>();
+        frame.pop();
+        Ok(Some(frame))
+    }
+}
+
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+pub fn length_prefixed(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(LENGTH_PREFIX_SIZE + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[derive(Debug)]
+pub struct LengthPrefixedDecoder {
+    buffer: Vec<u8>,
+    max_frame_size: usize,
+}
+
+impl Default for LengthPrefixedDecoder {
+    fn default() -> Self {
+        LengthPrefixedDecoder::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl LengthPrefixedDecoder {
+    pub fn new() -> Self {
+        LengthPrefixedDecoder::default()
+    }
+
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        LengthPrefixedDecoder {
+            buffer: vec![],
+            max_frame_size,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        let prefix = match self.buffer.get(..LENGTH_PREFIX_SIZE) {
+            Some(prefix) => prefix,
+            None => return Ok(None),
+        };
+        let size = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        check_size(size, self.max_frame_size)?;
+        if self.buffer.len() < LENGTH_PREFIX_SIZE + size {
+            return Ok(None);
+        }
+        let frame = self.buffer[LENGTH_PREFIX_SIZE..LENGTH_PREFIX_SIZE + size].to_vec();
+        self.buffer.drain(..LENGTH_PREFIX_SIZE + size);
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_byte_characters_split_across_reads_are_reassembled() {
+        let bytes = "こんにちは\0".as_bytes();
+        let mut decoder = TextFrameDecoder::new();
+        for byte in bytes.iter() {
+            decoder.push(std::slice::from_ref(byte));
+        }
+        assert_eq!(decoder.next_frame(), Ok(Some("こんにちは".to_string())));
+        assert_eq!(decoder.next_frame(), Ok(None));
+
+        let mut utf8 = Utf8Decoder::new();
+        assert_eq!(utf8.push(&bytes[..4]), "こ");
+        assert!(utf8.has_pending());
+        assert_eq!(utf8.push(&bytes[4..6]), "ん");
+        assert!(!utf8.has_pending());
+        assert_eq!(utf8.push(&[b'a', 0xff, b'b', 0xe3]), "a\u{fffd}b");
+        assert_eq!(utf8.push(&[0x81, 0x82]), "あ");
+    }
+
+    #[test]
+    fn frames_past_the_size_limit_are_rejected_before_buffering() {
+        let mut decoder = LengthPrefixedDecoder::with_max_frame_size(8);
+        decoder.push(&length_prefixed(b"12345678"));
+        assert_eq!(decoder.next_frame(), Ok(Some(b"12345678".to_vec())));
+        decoder.push(&[0xff, 0xff, 0xff, 0xff, b'x']);
+        assert_eq!(
+            decoder.next_frame(),
+            Err(FrameError::Oversized {
+                size: u32::MAX as usize,
+                max: 8
+            })
+        );
+
+        let mut text = TextFrameDecoder::with_max_frame_size(4);
+        text.push(b"ping\0pong");
+        assert_eq!(text.next_frame(), Ok(Some("ping".to_string())));
+        assert_eq!(text.next_frame(), Ok(None));
+        text.push(b"!");
+        assert_eq!(
+            text.next_frame(),
+            Err(FrameError::Oversized { size: 5, max: 4 })
+        );
+
+        let mut raw = FrameDecoder::with_max_frame_size(2);
+        raw.push(b"abc\0");
+        assert_eq!(
+            raw.next_frame(),
+            Err(FrameError::Oversized { size: 3, max: 2 })
+        );
+    }
+}
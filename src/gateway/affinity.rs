@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MAX_PENDING_FRAME: usize = 64 * 1024;
+const MIN_TOKEN_LENGTH: usize = 32;
+
+pub fn resume_token(frame: &str) -> Option<&str> {
+    let mut parts = frame
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .split_whitespace();
+    match (parts.next()?, parts.next(), parts.next()) {
+        (":resume", Some(token), None) => Some(token),
+        _ => None,
+    }
+}
+
+pub fn issued_token(frame: &str) -> Option<&str> {
+    let mut parts = frame
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .split_whitespace();
+    match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+        ("OK", Some(_), Some(token), None)
+            if token.len() >= MIN_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            Some(token)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+pub struct FrameScanner {
+    pending: Vec<u8>,
+}
+
+impl FrameScanner {
+    pub fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(data);
+        let mut frames = vec![];
+        while let Some(end) = self.pending.iter().position(|b| *b == 0) {
+            let frame = self.pending.drain(..=end).collect::<Vec<_>>();
+            frames.push(String::from_utf8_lossy(&frame[..end]).to_string());
+        }
+        if self.pending.len() > MAX_PENDING_FRAME {
+            self.pending.clear();
+        }
+        frames
+    }
+}
+
+#[derive(Clone)]
+pub struct SessionAffinity {
+    ttl: Duration,
+    routes: Arc<Mutex<HashMap<String, (String, Instant)>>>,
+}
+
+impl SessionAffinity {
+    pub fn new(ttl: Duration) -> Self {
+        SessionAffinity {
+            ttl,
+            routes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (String, Instant)>> {
+        self.routes.lock().expect("Failed to lock session routes.")
+    }
+
+    pub fn record(&self, token: &str, backend: &str) {
+        let mut routes = self.lock();
+        let now = Instant::now();
+        let ttl = self.ttl;
+        routes.retain(|_, (_, recorded_at)| now.duration_since(*recorded_at) < ttl);
+        routes.insert(token.to_string(), (backend.to_string(), now));
+    }
+
+    pub fn lookup(&self, token: &str) -> Option<String> {
+        let mut routes = self.lock();
+        match routes.get(token) {
+            Some((_, recorded_at)) if recorded_at.elapsed() >= self.ttl => {
+                routes.remove(token);
+                None
+            }
+            Some((backend, _)) => Some(backend.clone()),
+            None => None,
+        }
+    }
+
+    pub fn forget(&self, token: &str) {
+        self.lock().remove(token);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_sniffed_from_frames_and_expire() {
+        let token = "ab".repeat(32);
+        let mut scanner = FrameScanner::default();
+        let reply = format!("Hello\0OK alice {}\0OK mar", token);
+        assert_eq!(
+            scanner.push(reply.as_bytes()),
+            vec!["Hello".to_string(), format!("OK alice {}", token)]
+        );
+        assert_eq!(scanner.push(b"ket\0"), vec!["OK market"]);
+        assert_eq!(
+            issued_token(&format!("OK alice {}", token)),
+            Some(token.as_str())
+        );
+        assert_eq!(issued_token("OK market"), None);
+        assert_eq!(issued_token("OK alice not-a-token"), None);
+        assert_eq!(
+            resume_token(&format!(":resume {}\0", token)),
+            Some(token.as_str())
+        );
+        assert_eq!(resume_token(":login alice secret"), None);
+
+        let affinity = SessionAffinity::new(Duration::from_millis(50));
+        affinity.record(&token, "10.0.0.1:7000");
+        assert_eq!(affinity.lookup(&token), Some("10.0.0.1:7000".to_string()));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(affinity.lookup(&token), None);
+        assert!(affinity.is_empty());
+    }
+}
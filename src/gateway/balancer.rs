@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub struct Backend {
+    pub address: String,
+    active: AtomicUsize,
+    healthy: AtomicBool,
+}
+
+impl Backend {
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub fn healthy(&self) -> bool {
+        self.healthy.load(Ordering::SeqCst)
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::SeqCst);
+    }
+}
+
+pub struct BackendLease {
+    backend: Arc<Backend>,
+}
+
+impl BackendLease {
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+}
+
+impl Drop for BackendLease {
+    fn drop(&mut self) {
+        self.backend.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Clone)]
+pub struct Balancer {
+    backends: Arc<Vec<Arc<Backend>>>,
+}
+
+impl Balancer {
+    pub fn new(addresses: &[String]) -> Self {
+        Balancer {
+            backends: Arc::new(
+                addresses
+                    .iter()
+                    .map(|address| {
+                        Arc::new(Backend {
+                            address: address.clone(),
+                            active: AtomicUsize::new(0),
+                            healthy: AtomicBool::new(true),
+                        })
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn candidates(&self) -> Vec<Arc<Backend>> {
+        let mut candidates = self.backends.iter().cloned().collect::<Vec<_>>();
+        candidates.sort_by_key(|backend| (!backend.healthy(), backend.active()));
+        candidates
+    }
+
+    pub fn lease(&self, backend: &Arc<Backend>) -> BackendLease {
+        backend.active.fetch_add(1, Ordering::SeqCst);
+        BackendLease {
+            backend: backend.clone(),
+        }
+    }
+
+    pub fn stats(&self) -> Vec<(String, usize, bool)> {
+        self.backends
+            .iter()
+            .map(|backend| (backend.address.clone(), backend.active(), backend.healthy()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(candidates: &[Arc<Backend>]) -> Vec<&str> {
+        candidates
+            .iter()
+            .map(|backend| backend.address.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn candidates_prefer_healthy_backends_with_the_fewest_connections() {
+        let balancer = Balancer::new(&["a".to_string(), "b".to_string(), "c".to_string()]);
+        let first = balancer.lease(&balancer.candidates()[0]);
+        assert_eq!(first.backend().address, "a");
+        let second = balancer.lease(&balancer.candidates()[0]);
+        assert_eq!(second.backend().address, "b");
+
+        balancer.candidates()[0].set_healthy(false);
+        assert_eq!(addresses(&balancer.candidates()), vec!["a", "b", "c"]);
+        let third = balancer.lease(&balancer.candidates()[0]);
+        assert_eq!(third.backend().address, "a");
+
+        drop(first);
+        drop(second);
+        assert_eq!(
+            balancer.stats(),
+            vec![
+                ("a".to_string(), 1, true),
+                ("b".to_string(), 0, true),
+                ("c".to_string(), 0, false)
+            ]
+        );
+    }
+}
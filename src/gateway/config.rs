@@ -0,0 +1,33 @@
+use crate::config::{env_millis, env_or};
+use std::time::Duration;
+
+pub const DEFAULT_GATEWAY_BIND: &str = "0.0.0.0:7000";
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+pub const DEFAULT_STICKY_WAIT: Duration = Duration::from_millis(250);
+pub const DEFAULT_AFFINITY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Debug)]
+pub struct GatewayConfig {
+    pub bind: String,
+    pub backends: Vec<String>,
+    pub connect_timeout: Duration,
+    pub sticky_wait: Duration,
+    pub affinity_ttl: Duration,
+}
+
+impl GatewayConfig {
+    pub fn from_env() -> Self {
+        GatewayConfig {
+            bind: env_or("GATEWAY_BIND", DEFAULT_GATEWAY_BIND),
+            backends: env_or("GATEWAY_BACKENDS", "")
+                .split(',')
+                .map(|backend| backend.trim().to_string())
+                .filter(|backend| !backend.is_empty())
+                .collect(),
+            connect_timeout: env_millis("GATEWAY_CONNECT_TIMEOUT_MS")
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+            sticky_wait: env_millis("GATEWAY_STICKY_WAIT_MS").unwrap_or(DEFAULT_STICKY_WAIT),
+            affinity_ttl: env_millis("GATEWAY_AFFINITY_TTL_MS").unwrap_or(DEFAULT_AFFINITY_TTL),
+        }
+    }
+}
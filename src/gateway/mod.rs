@@ -0,0 +1,8 @@
+mod affinity;
+mod balancer;
+mod config;
+mod proxy;
+pub use affinity::*;
+pub use balancer::*;
+pub use config::*;
+pub use proxy::*;
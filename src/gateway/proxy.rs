@@ -0,0 +1,422 @@
+use super::{
+    issued_token, resume_token, Backend, Balancer, FrameScanner, GatewayConfig, SessionAffinity,
+};
+use crate::transport::unix_path;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+const PIPE_BUFFER_SIZE: usize = 4096;
+
+#[derive(Clone)]
+struct Router {
+    balancer: Balancer,
+    affinity: SessionAffinity,
+    connect_timeout: Duration,
+    sticky_wait: Duration,
+}
+
+pub struct Gateway {
+    listener: TcpListener,
+    router: Router,
+}
+
+impl Gateway {
+    pub fn bind(config: &GatewayConfig) -> std::io::Result<Self> {
+        Ok(Gateway {
+            listener: TcpListener::bind(config.bind.as_str())?,
+            router: Router {
+                balancer: Balancer::new(&config.backends),
+                affinity: SessionAffinity::new(config.affinity_ttl),
+                connect_timeout: config.connect_timeout,
+                sticky_wait: config.sticky_wait,
+            },
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn balancer(&self) -> Balancer {
+        self.router.balancer.clone()
+    }
+
+    pub fn affinity(&self) -> SessionAffinity {
+        self.router.affinity.clone()
+    }
+
+    pub fn run(&self) {
+        for stream in self.listener.incoming() {
+            let client = match stream {
+                Ok(client) => client,
+                Err(e) => {
+                    eprintln!("クライアント接続の受け付けに失敗しました：{}\n", e);
+                    continue;
+                }
+            };
+            let router = self.router.clone();
+            std::thread::spawn(move || {
+                let peer = client
+                    .peer_addr()
+                    .map(|address| address.to_string())
+                    .unwrap_or_default();
+                if let Err(e) = router.forward(client) {
+                    eprintln!("{}の転送に失敗しました：{}\n", peer, e);
+                }
+            });
+        }
+    }
+}
+
+enum BackendStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl BackendStream {
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        match self {
+            BackendStream::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => stream.shutdown(how),
+        }
+    }
+}
+
+impl Read for &BackendStream {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            BackendStream::Tcp(stream) => (&*stream).read(buffer),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => (&*stream).read(buffer),
+        }
+    }
+}
+
+impl Write for &BackendStream {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        match self {
+            BackendStream::Tcp(stream) => (&*stream).write(data),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => (&*stream).write(data),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            BackendStream::Tcp(stream) => (&*stream).flush(),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => (&*stream).flush(),
+        }
+    }
+}
+
+fn connect(backend: &Backend, timeout: Duration) -> std::io::Result<BackendStream> {
+    if let Some(path) = unix_path(&backend.address) {
+        #[cfg(unix)]
+        return Ok(BackendStream::Unix(UnixStream::connect(path)?));
+        #[cfg(not(unix))]
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Unix domain socket {} is not available on this target.",
+                path
+            ),
+        ));
+    }
+    let address = backend
+        .address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Backend address did not resolve."))?;
+    Ok(BackendStream::Tcp(TcpStream::connect_timeout(
+        &address, timeout,
+    )?))
+}
+
+impl Router {
+    fn read_greeting(&self, client: &TcpStream) -> std::io::Result<Vec<u8>> {
+        let mut buffer = vec![0_u8; PIPE_BUFFER_SIZE];
+        client.set_read_timeout(Some(self.sticky_wait))?;
+        let size = match (&*client).read(&mut buffer) {
+            Ok(size) => size,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => 0,
+            Err(e) => return Err(e),
+        };
+        client.set_read_timeout(None)?;
+        buffer.truncate(size);
+        Ok(buffer)
+    }
+
+    fn forward(&self, client: TcpStream) -> std::io::Result<()> {
+        let greeting = self.read_greeting(&client)?;
+        let token = FrameScanner::default()
+            .push(&greeting)
+            .first()
+            .and_then(|frame| resume_token(frame).map(|token| token.to_string()));
+        let sticky = token
+            .as_deref()
+            .and_then(|token| self.affinity.lookup(token));
+        let mut candidates = self.balancer.candidates();
+        if let Some(address) = sticky.as_ref() {
+            if let Some(index) = candidates
+                .iter()
+                .position(|backend| &backend.address == address)
+            {
+                let backend = candidates.remove(index);
+                candidates.insert(0, backend);
+            }
+        }
+
+        for backend in candidates {
+            let server = match connect(&backend, self.connect_timeout) {
+                Ok(server) => server,
+                Err(e) => {
+                    if backend.healthy() {
+                        eprintln!("バックエンド{}に接続できません：{}\n", &backend.address, e);
+                    }
+                    backend.set_healthy(false);
+                    continue;
+                }
+            };
+            backend.set_healthy(true);
+            if let (Some(token), Some(address)) = (token.as_deref(), sticky.as_ref()) {
+                if *address != backend.address {
+                    println!(
+                        "セッションを保持するバックエンド{}が使えないため{}に振り分けます\n",
+                        address, &backend.address
+                    );
+                    self.affinity.forget(token);
+                }
+            }
+            let _lease = self.balancer.lease(&backend);
+            println!(
+                "{}をバックエンド{}に振り分けました（接続数：{}）\n",
+                client.peer_addr()?,
+                &backend.address,
+                backend.active()
+            );
+            (&server).write_all(&greeting)?;
+            self.pipe(client, server, &backend.address)?;
+            return Ok(());
+        }
+        let _ = client.shutdown(Shutdown::Both);
+        Err(Error::new(
+            ErrorKind::ConnectionRefused,
+            "No backend is available.",
+        ))
+    }
+
+    fn pipe(&self, client: TcpStream, server: BackendStream, backend: &str) -> std::io::Result<()> {
+        let client = Arc::new(client);
+        let server = Arc::new(server);
+        let upstream = {
+            let (client, server) = (client.clone(), server.clone());
+            std::thread::spawn(move || {
+                let _ = std::io::copy(&mut &*client, &mut &*server);
+                let _ = server.shutdown(Shutdown::Write);
+            })
+        };
+        let mut scanner = FrameScanner::default();
+        let mut buffer = [0_u8; PIPE_BUFFER_SIZE];
+        loop {
+            let size = match (&*server).read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => size,
+            };
+            for frame in scanner.push(&buffer[..size]) {
+                if let Some(token) = issued_token(&frame) {
+                    self.affinity.record(token, backend);
+                }
+            }
+            if (&*client).write_all(&buffer[..size]).is_err() {
+                break;
+            }
+        }
+        let _ = client.shutdown(Shutdown::Both);
+        let _ = server.shutdown(Shutdown::Both);
+        upstream
+            .join()
+            .map_err(|_| Error::other("Gateway pipe thread panicked."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn spawn_backend(tag: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind backend.");
+        let address = listener
+            .local_addr()
+            .expect("Failed to read address.")
+            .to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                std::thread::spawn(move || {
+                    let mut stream = stream;
+                    let mut buffer = [0_u8; 64];
+                    while let Ok(size) = stream.read(&mut buffer) {
+                        if size == 0 {
+                            break;
+                        }
+                        let reply = format!("{}:{}", tag, String::from_utf8_lossy(&buffer[..size]));
+                        if stream.write_all(reply.as_bytes()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        address
+    }
+
+    fn round_trip(stream: &mut TcpStream, text: &str) -> String {
+        stream.write_all(text.as_bytes()).expect("Failed to send.");
+        let mut buffer = [0_u8; 64];
+        let size = stream.read(&mut buffer).expect("Failed to receive.");
+        String::from_utf8_lossy(&buffer[..size]).to_string()
+    }
+
+    #[test]
+    fn connections_spread_across_live_backends_by_load() {
+        let dead = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to reserve an address.")
+            .to_string();
+        let gateway = Gateway::bind(&GatewayConfig {
+            bind: "127.0.0.1:0".to_string(),
+            backends: vec![dead, spawn_backend("a"), spawn_backend("b")],
+            connect_timeout: Duration::from_millis(500),
+            sticky_wait: Duration::from_millis(50),
+            affinity_ttl: Duration::from_secs(60),
+        })
+        .expect("Failed to bind the gateway.");
+        let address = gateway.local_addr().expect("Failed to read address.");
+        let balancer = gateway.balancer();
+        std::thread::spawn(move || gateway.run());
+
+        let wait_for_active = |expected: usize| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while balancer
+                .stats()
+                .iter()
+                .map(|(_, active, _)| active)
+                .sum::<usize>()
+                != expected
+            {
+                assert!(Instant::now() < deadline, "The gateway did not settle.");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        let mut first = TcpStream::connect(address).expect("Failed to connect.");
+        assert_eq!(round_trip(&mut first, "hello"), "a:hello");
+        wait_for_active(1);
+        let mut second = TcpStream::connect(address).expect("Failed to connect.");
+        assert_eq!(round_trip(&mut second, "hello"), "b:hello");
+        wait_for_active(2);
+
+        let stats = balancer.stats();
+        assert!(!stats[0].2);
+        assert_eq!((stats[1].1, stats[2].1), (1, 1));
+
+        drop(first);
+        wait_for_active(1);
+        let mut third = TcpStream::connect(address).expect("Failed to connect.");
+        assert_eq!(round_trip(&mut third, "again"), "a:again");
+    }
+
+    fn spawn_session_backend(tag: char) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind backend.");
+        let address = listener
+            .local_addr()
+            .expect("Failed to read address.")
+            .to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                std::thread::spawn(move || {
+                    let mut stream = stream;
+                    let mut scanner = FrameScanner::default();
+                    let mut buffer = [0_u8; 256];
+                    while let Ok(size) = stream.read(&mut buffer) {
+                        if size == 0 {
+                            break;
+                        }
+                        for frame in scanner.push(&buffer[..size]) {
+                            let reply = if frame.starts_with(":login ") {
+                                format!("OK alice {}\0", tag.to_string().repeat(64))
+                            } else if resume_token(&frame).is_some() {
+                                format!("RESUMED {}\0", tag)
+                            } else {
+                                format!("{}:{}\0", tag, frame)
+                            };
+                            let _ = stream.write_all(reply.as_bytes());
+                        }
+                    }
+                });
+            }
+        });
+        address
+    }
+
+    fn receive_frame(stream: &mut TcpStream) -> String {
+        let mut scanner = FrameScanner::default();
+        let mut buffer = [0_u8; 256];
+        loop {
+            let size = stream.read(&mut buffer).expect("Failed to receive.");
+            assert!(size > 0, "The gateway closed the connection.");
+            if let Some(frame) = scanner.push(&buffer[..size]).into_iter().next() {
+                return frame;
+            }
+        }
+    }
+
+    #[test]
+    fn resuming_clients_return_to_the_backend_holding_their_session() {
+        let gateway = Gateway::bind(&GatewayConfig {
+            bind: "127.0.0.1:0".to_string(),
+            backends: vec![spawn_session_backend('a'), spawn_session_backend('b')],
+            connect_timeout: Duration::from_millis(500),
+            sticky_wait: Duration::from_millis(50),
+            affinity_ttl: Duration::from_secs(60),
+        })
+        .expect("Failed to bind the gateway.");
+        let address = gateway.local_addr().expect("Failed to read address.");
+        let (balancer, affinity) = (gateway.balancer(), gateway.affinity());
+        std::thread::spawn(move || gateway.run());
+
+        let mut first = TcpStream::connect(address).expect("Failed to connect.");
+        first
+            .write_all(b":login alice secret\0")
+            .expect("Failed to send.");
+        let token = "a".repeat(64);
+        assert_eq!(receive_frame(&mut first), format!("OK alice {}", token));
+        assert_eq!(affinity.lookup(&token), Some(balancer.stats()[0].0.clone()));
+        drop(first);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while balancer.stats()[0].1 != 0 {
+            assert!(Instant::now() < deadline, "The gateway did not settle.");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let mut busy = TcpStream::connect(address).expect("Failed to connect.");
+        busy.write_all(b"hi\0").expect("Failed to send.");
+        assert_eq!(receive_frame(&mut busy), "a:hi");
+
+        let mut resumed = TcpStream::connect(address).expect("Failed to connect.");
+        resumed
+            .write_all(format!(":resume {}\0", token).as_bytes())
+            .expect("Failed to send.");
+        assert_eq!(receive_frame(&mut resumed), "RESUMED a");
+
+        let mut fresh = TcpStream::connect(address).expect("Failed to connect.");
+        fresh.write_all(b"hi\0").expect("Failed to send.");
+        assert_eq!(receive_frame(&mut fresh), "b:hi");
+    }
+}
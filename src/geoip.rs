@@ -0,0 +1,136 @@
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::path::PathBuf;
+#[cfg(feature = "geoip")]
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct GeoIpConfig {
+    pub database: PathBuf,
+}
+
+impl GeoIpConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("GEOIP_DATABASE")
+            .ok()
+            .filter(|database| !database.is_empty())
+            .map(|database| GeoIpConfig {
+                database: PathBuf::from(database),
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeoLocation {
+    pub country: String,
+    pub region: Option<String>,
+}
+
+impl Display for GeoLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.region.as_ref() {
+            Some(region) => write!(f, "{} / {}", &self.country, region),
+            None => write!(f, "{}", &self.country),
+        }
+    }
+}
+
+pub fn routable(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast())
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                || (ip.segments()[0] & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GeoIp {
+    #[cfg(feature = "geoip")]
+    reader: Arc<maxminddb::Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    #[cfg(feature = "geoip")]
+    pub fn open(config: &GeoIpConfig) -> std::io::Result<Self> {
+        maxminddb::Reader::open_readfile(&config.database)
+            .map(|reader| GeoIp {
+                reader: Arc::new(reader),
+            })
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    pub fn open(_config: &GeoIpConfig) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "geoip feature is disabled",
+        ))
+    }
+
+    pub fn lookup(&self, address: &str) -> Option<GeoLocation> {
+        let ip = address.parse::<IpAddr>().ok().filter(|ip| routable(*ip))?;
+        self.lookup_ip(ip)
+    }
+
+    #[cfg(feature = "geoip")]
+    fn lookup_ip(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let city = self.reader.lookup::<maxminddb::geoip2::City>(ip).ok()?;
+        let country = city.country.and_then(|country| country.iso_code)?;
+        let region = city
+            .subdivisions
+            .and_then(|subdivisions| subdivisions.into_iter().next())
+            .and_then(|subdivision| {
+                subdivision
+                    .names
+                    .and_then(|names| names.get("en").copied())
+                    .or(subdivision.iso_code)
+            });
+        Some(GeoLocation {
+            country: country.to_string(),
+            region: region.map(str::to_string),
+        })
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    fn lookup_ip(&self, _ip: IpAddr) -> Option<GeoLocation> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_addresses_are_not_looked_up_and_locations_format_with_region() {
+        for address in ["127.0.0.1", "10.0.0.5", "192.168.1.20", "::1", "fd00::1"] {
+            assert!(!routable(address.parse().expect("Invalid address.")));
+        }
+        assert!(routable("203.0.113.7".parse().expect("Invalid address.")));
+        assert!(routable("2001:db8::1".parse().expect("Invalid address.")));
+
+        let location = GeoLocation {
+            country: "JP".to_string(),
+            region: Some("Tokyo".to_string()),
+        };
+        assert_eq!(location.to_string(), "JP / Tokyo");
+        assert_eq!(
+            GeoLocation {
+                region: None,
+                ..location
+            }
+            .to_string(),
+            "JP"
+        );
+    }
+}
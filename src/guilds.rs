@@ -0,0 +1,618 @@
+use crate::identity::AccountId;
+use crate::presence::Presence;
+use crate::storage::{AccountStore, GuildMemberRecord, GuildRecord, GuildStore, StorageError};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+pub const MAX_GUILD_NAME_LENGTH: usize = 24;
+pub const MAX_GUILD_MEMBERS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GuildRole {
+    Member,
+    Officer,
+    Leader,
+}
+
+impl GuildRole {
+    pub fn parse(input: &str) -> Option<GuildRole> {
+        match input.to_ascii_lowercase().as_str() {
+            "member" => Some(GuildRole::Member),
+            "officer" => Some(GuildRole::Officer),
+            "leader" => Some(GuildRole::Leader),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GuildRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuildRole::Member => write!(f, "member"),
+            GuildRole::Officer => write!(f, "officer"),
+            GuildRole::Leader => write!(f, "leader"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GuildCommand {
+    Create(String),
+    Invite(String),
+    Accept(String),
+    Kick(String),
+    Role(String, GuildRole),
+    Leave,
+    Members,
+    Chat(String),
+}
+
+impl GuildCommand {
+    pub fn parse(input: &str) -> Option<GuildCommand> {
+        let input = input.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        if let Some(text) = input.strip_prefix(":g ") {
+            let text = text.trim();
+            if text.is_empty() {
+                return None;
+            }
+            return Some(GuildCommand::Chat(text.to_string()));
+        }
+        let mut parts = input.split_whitespace();
+        if parts.next()? != ":guild" {
+            return None;
+        }
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (None, ..) | (Some("members"), None, ..) => Some(GuildCommand::Members),
+            (Some("create"), Some(name), None, _) => Some(GuildCommand::Create(name.to_string())),
+            (Some("invite"), Some(name), None, _) => Some(GuildCommand::Invite(name.to_string())),
+            (Some("accept"), Some(name), None, _) => Some(GuildCommand::Accept(name.to_string())),
+            (Some("kick"), Some(name), None, _) => Some(GuildCommand::Kick(name.to_string())),
+            (Some("role"), Some(name), Some(role), None) => {
+                GuildRole::parse(role).map(|role| GuildCommand::Role(name.to_string(), role))
+            }
+            (Some("leave"), None, ..) => Some(GuildCommand::Leave),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GuildError {
+    InvalidName,
+    NameTaken,
+    AlreadyInGuild,
+    PlayerInGuild(String),
+    NotInGuild,
+    NotMember(String),
+    UnknownGuild,
+    UnknownPlayer(String),
+    NotInvited,
+    GuildFull,
+    PermissionDenied,
+    LeaderMustHandOver,
+    Storage(StorageError),
+}
+
+impl Display for GuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuildError::InvalidName => write!(
+                f,
+                "Guild names must be 1-{} alphanumeric characters.",
+                MAX_GUILD_NAME_LENGTH
+            ),
+            GuildError::NameTaken => write!(f, "That guild name is already taken."),
+            GuildError::AlreadyInGuild => write!(f, "You are already in a guild."),
+            GuildError::PlayerInGuild(name) => write!(f, "{} is already in a guild.", name),
+            GuildError::NotInGuild => write!(f, "You are not in a guild."),
+            GuildError::NotMember(name) => write!(f, "{} is not in your guild.", name),
+            GuildError::UnknownGuild => write!(f, "No such guild."),
+            GuildError::UnknownPlayer(name) => write!(f, "No account named {}.", name),
+            GuildError::NotInvited => write!(f, "You have not been invited to that guild."),
+            GuildError::GuildFull => {
+                write!(f, "Guilds can have at most {} members.", MAX_GUILD_MEMBERS)
+            }
+            GuildError::PermissionDenied => write!(f, "Your guild role does not allow that."),
+            GuildError::LeaderMustHandOver => {
+                write!(f, "Make another member leader before leaving.")
+            }
+            GuildError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<StorageError> for GuildError {
+    fn from(e: StorageError) -> Self {
+        GuildError::Storage(e)
+    }
+}
+
+pub type GuildResult<T> = Result<T, GuildError>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Membership {
+    pub guild: GuildRecord,
+    pub role: GuildRole,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuildMember {
+    pub account_id: AccountId,
+    pub name: String,
+    pub role: GuildRole,
+}
+
+impl From<GuildMemberRecord> for GuildMember {
+    fn from(record: GuildMemberRecord) -> Self {
+        GuildMember {
+            account_id: AccountId(record.account_id),
+            name: record.name,
+            role: GuildRole::parse(&record.role).unwrap_or(GuildRole::Member),
+        }
+    }
+}
+
+pub fn valid_guild_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_GUILD_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+pub fn roster(guild: &str, members: &[(GuildMember, Option<Presence>)]) -> String {
+    std::iter::once(format!("GUILD {}", guild))
+        .chain(members.iter().map(|(member, presence)| {
+            format!(
+                "{}:{}:{}",
+                &member.name,
+                member.role,
+                presence
+                    .map(|presence| presence.to_string())
+                    .unwrap_or_else(|| "offline".to_string())
+            )
+        }))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn guild_message(guild: &str, sender: &str, text: &str) -> String {
+    format!("[Guild {}] {}：{}", guild, sender, text)
+}
+
+pub fn guild_notice(guild: &str, text: &str) -> String {
+    format!("[Guild {}] {}", guild, text)
+}
+
+#[derive(Clone)]
+pub struct Guilds {
+    store: Arc<dyn GuildStore>,
+    accounts: Arc<dyn AccountStore>,
+}
+
+impl Guilds {
+    pub fn new(store: Arc<dyn GuildStore>, accounts: Arc<dyn AccountStore>) -> Self {
+        Guilds { store, accounts }
+    }
+
+    pub fn membership(&self, account_id: AccountId) -> GuildResult<Option<Membership>> {
+        Ok(self
+            .store
+            .guild_membership(account_id.0)?
+            .map(|(guild, role)| Membership {
+                guild,
+                role: GuildRole::parse(&role).unwrap_or(GuildRole::Member),
+            }))
+    }
+
+    pub fn members(&self, guild_id: i64) -> GuildResult<Vec<GuildMember>> {
+        Ok(self
+            .store
+            .guild_members(guild_id)?
+            .into_iter()
+            .map(GuildMember::from)
+            .collect())
+    }
+
+    fn require_membership(&self, account_id: AccountId) -> GuildResult<Membership> {
+        self.membership(account_id)?.ok_or(GuildError::NotInGuild)
+    }
+
+    fn find_player(&self, name: &str) -> GuildResult<AccountId> {
+        self.accounts
+            .find_account(name)?
+            .map(|account| AccountId(account.id))
+            .ok_or_else(|| GuildError::UnknownPlayer(name.to_string()))
+    }
+
+    fn find_member(&self, membership: &Membership, name: &str) -> GuildResult<GuildMember> {
+        let account_id = self.find_player(name)?;
+        self.members(membership.guild.id)?
+            .into_iter()
+            .find(|member| member.account_id == account_id)
+            .ok_or_else(|| GuildError::NotMember(name.to_string()))
+    }
+
+    pub fn create(&self, founder: AccountId, name: &str) -> GuildResult<GuildRecord> {
+        if !valid_guild_name(name) {
+            return Err(GuildError::InvalidName);
+        }
+        if self.membership(founder)?.is_some() {
+            return Err(GuildError::AlreadyInGuild);
+        }
+        if self.store.find_guild(name)?.is_some() {
+            return Err(GuildError::NameTaken);
+        }
+        let id = self
+            .store
+            .create_guild(name, founder.0, &GuildRole::Leader.to_string())?;
+        Ok(GuildRecord {
+            id,
+            name: name.to_string(),
+        })
+    }
+
+    pub fn invite(&self, inviter: AccountId, name: &str) -> GuildResult<GuildRecord> {
+        let membership = self.require_membership(inviter)?;
+        if membership.role < GuildRole::Officer {
+            return Err(GuildError::PermissionDenied);
+        }
+        let account_id = self.find_player(name)?;
+        if self.membership(account_id)?.is_some() {
+            return Err(GuildError::PlayerInGuild(name.to_string()));
+        }
+        if self.store.guild_members(membership.guild.id)?.len() >= MAX_GUILD_MEMBERS {
+            return Err(GuildError::GuildFull);
+        }
+        self.store
+            .add_guild_invite(membership.guild.id, account_id.0, inviter.0)?;
+        Ok(membership.guild)
+    }
+
+    pub fn accept(&self, account_id: AccountId, name: &str) -> GuildResult<GuildRecord> {
+        if self.membership(account_id)?.is_some() {
+            return Err(GuildError::AlreadyInGuild);
+        }
+        let guild = self
+            .store
+            .find_guild(name)?
+            .ok_or(GuildError::UnknownGuild)?;
+        if self.store.guild_members(guild.id)?.len() >= MAX_GUILD_MEMBERS {
+            return Err(GuildError::GuildFull);
+        }
+        if !self.store.take_guild_invite(guild.id, account_id.0)? {
+            return Err(GuildError::NotInvited);
+        }
+        self.store
+            .add_guild_member(guild.id, account_id.0, &GuildRole::Member.to_string())?;
+        Ok(guild)
+    }
+
+    pub fn kick(&self, actor: AccountId, name: &str) -> GuildResult<(GuildRecord, GuildMember)> {
+        let membership = self.require_membership(actor)?;
+        let target = self.find_member(&membership, name)?;
+        if membership.role < GuildRole::Officer || target.role >= membership.role {
+            return Err(GuildError::PermissionDenied);
+        }
+        self.store
+            .remove_guild_member(membership.guild.id, target.account_id.0)?;
+        Ok((membership.guild, target))
+    }
+
+    pub fn set_role(
+        &self,
+        actor: AccountId,
+        name: &str,
+        role: GuildRole,
+    ) -> GuildResult<(GuildRecord, GuildMember)> {
+        let membership = self.require_membership(actor)?;
+        let target = self.find_member(&membership, name)?;
+        if target.account_id == actor {
+            return Err(GuildError::PermissionDenied);
+        }
+        let guild_id = membership.guild.id;
+        if role == GuildRole::Leader {
+            if membership.role != GuildRole::Leader {
+                return Err(GuildError::PermissionDenied);
+            }
+            self.store
+                .set_guild_role(guild_id, target.account_id.0, &role.to_string())?;
+            self.store
+                .set_guild_role(guild_id, actor.0, &GuildRole::Officer.to_string())?;
+        } else {
+            if target.role >= membership.role || role >= membership.role {
+                return Err(GuildError::PermissionDenied);
+            }
+            self.store
+                .set_guild_role(guild_id, target.account_id.0, &role.to_string())?;
+        }
+        Ok((membership.guild, GuildMember { role, ..target }))
+    }
+
+    pub fn leave(&self, account_id: AccountId) -> GuildResult<(GuildRecord, bool)> {
+        let membership = self.require_membership(account_id)?;
+        let guild_id = membership.guild.id;
+        if membership.role == GuildRole::Leader {
+            if self.store.guild_members(guild_id)?.len() > 1 {
+                return Err(GuildError::LeaderMustHandOver);
+            }
+            self.store.delete_guild(guild_id)?;
+            return Ok((membership.guild, true));
+        }
+        self.store.remove_guild_member(guild_id, account_id.0)?;
+        Ok((membership.guild, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{
+        AccountRecord, LeaderboardEntry, MatchParticipant, PlayerStats, StorageResult,
+    };
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryState {
+        guilds: Vec<GuildRecord>,
+        members: Vec<(i64, GuildMemberRecord)>,
+        invites: Vec<(i64, i64)>,
+    }
+
+    struct MemoryStore {
+        accounts: Vec<&'static str>,
+        state: Mutex<MemoryState>,
+    }
+
+    impl MemoryStore {
+        fn lock(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+            self.state.lock().expect("Failed to lock guild state.")
+        }
+    }
+
+    impl AccountStore for MemoryStore {
+        fn create_account(&self, _: &str, _: &str) -> StorageResult<i64> {
+            Ok(0)
+        }
+
+        fn find_account(&self, name: &str) -> StorageResult<Option<AccountRecord>> {
+            Ok(self
+                .accounts
+                .iter()
+                .position(|known| *known == name)
+                .map(|index| AccountRecord {
+                    id: index as i64 + 1,
+                    name: name.to_string(),
+                    password_hash: String::new(),
+                    created_at: 0,
+                }))
+        }
+
+        fn record_stats(&self, _: i64, _: bool, _: i64) -> StorageResult<()> {
+            Ok(())
+        }
+
+        fn stats(&self, _: i64) -> StorageResult<PlayerStats> {
+            Ok(PlayerStats::default())
+        }
+
+        fn record_match(&self, _: &str, _: &[MatchParticipant]) -> StorageResult<i64> {
+            Ok(0)
+        }
+
+        fn leaderboard_page(&self, _: i64, _: i64) -> StorageResult<Vec<LeaderboardEntry>> {
+            Ok(vec![])
+        }
+
+        fn leaderboard_rank(&self, _: i64) -> StorageResult<Option<i64>> {
+            Ok(None)
+        }
+    }
+
+    impl GuildStore for MemoryStore {
+        fn create_guild(&self, name: &str, founder: i64, role: &str) -> StorageResult<i64> {
+            let id = self.lock().guilds.len() as i64 + 1;
+            self.lock().guilds.push(GuildRecord {
+                id,
+                name: name.to_string(),
+            });
+            self.add_guild_member(id, founder, role)?;
+            Ok(id)
+        }
+
+        fn find_guild(&self, name: &str) -> StorageResult<Option<GuildRecord>> {
+            Ok(self
+                .lock()
+                .guilds
+                .iter()
+                .find(|guild| guild.name.eq_ignore_ascii_case(name))
+                .cloned())
+        }
+
+        fn guild_membership(
+            &self,
+            account_id: i64,
+        ) -> StorageResult<Option<(GuildRecord, String)>> {
+            let state = self.lock();
+            Ok(state
+                .members
+                .iter()
+                .find(|(_, member)| member.account_id == account_id)
+                .and_then(|(guild_id, member)| {
+                    state
+                        .guilds
+                        .iter()
+                        .find(|guild| guild.id == *guild_id)
+                        .map(|guild| (guild.clone(), member.role.clone()))
+                }))
+        }
+
+        fn guild_members(&self, guild_id: i64) -> StorageResult<Vec<GuildMemberRecord>> {
+            Ok(self
+                .lock()
+                .members
+                .iter()
+                .filter(|(id, _)| *id == guild_id)
+                .map(|(_, member)| member.clone())
+                .collect())
+        }
+
+        fn add_guild_member(
+            &self,
+            guild_id: i64,
+            account_id: i64,
+            role: &str,
+        ) -> StorageResult<()> {
+            let mut state = self.lock();
+            state.invites.retain(|(_, invited)| *invited != account_id);
+            state.members.push((
+                guild_id,
+                GuildMemberRecord {
+                    account_id,
+                    name: self.accounts[account_id as usize - 1].to_string(),
+                    role: role.to_string(),
+                },
+            ));
+            Ok(())
+        }
+
+        fn set_guild_role(
+            &self,
+            guild_id: i64,
+            account_id: i64,
+            role: &str,
+        ) -> StorageResult<bool> {
+            let mut state = self.lock();
+            match state
+                .members
+                .iter_mut()
+                .find(|(id, member)| *id == guild_id && member.account_id == account_id)
+            {
+                Some((_, member)) => {
+                    member.role = role.to_string();
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        fn remove_guild_member(&self, guild_id: i64, account_id: i64) -> StorageResult<bool> {
+            let mut state = self.lock();
+            let before = state.members.len();
+            state
+                .members
+                .retain(|(id, member)| !(*id == guild_id && member.account_id == account_id));
+            Ok(state.members.len() < before)
+        }
+
+        fn delete_guild(&self, guild_id: i64) -> StorageResult<()> {
+            let mut state = self.lock();
+            state.guilds.retain(|guild| guild.id != guild_id);
+            state.members.retain(|(id, _)| *id != guild_id);
+            state.invites.retain(|(id, _)| *id != guild_id);
+            Ok(())
+        }
+
+        fn add_guild_invite(&self, guild_id: i64, account_id: i64, _: i64) -> StorageResult<()> {
+            self.lock().invites.push((guild_id, account_id));
+            Ok(())
+        }
+
+        fn take_guild_invite(&self, guild_id: i64, account_id: i64) -> StorageResult<bool> {
+            let mut state = self.lock();
+            let before = state.invites.len();
+            state
+                .invites
+                .retain(|invite| *invite != (guild_id, account_id));
+            Ok(state.invites.len() < before)
+        }
+    }
+
+    #[test]
+    fn roles_gate_invites_kicks_and_leadership_handover() {
+        assert_eq!(
+            GuildCommand::parse(":guild role bob Officer\0"),
+            Some(GuildCommand::Role("bob".to_string(), GuildRole::Officer))
+        );
+        assert_eq!(GuildCommand::parse(":guild"), Some(GuildCommand::Members));
+        assert_eq!(
+            GuildCommand::parse(":g  raid at nine "),
+            Some(GuildCommand::Chat("raid at nine".to_string()))
+        );
+        assert_eq!(GuildCommand::parse(":g "), None);
+        assert_eq!(GuildCommand::parse(":guild role bob king"), None);
+
+        let store = Arc::new(MemoryStore {
+            accounts: vec!["alice", "bob", "carol"],
+            state: Mutex::new(MemoryState::default()),
+        });
+        let guilds = Guilds::new(store.clone(), store);
+        let (alice, bob, carol) = (AccountId(1), AccountId(2), AccountId(3));
+
+        let knights = guilds.create(alice, "Knights").expect("Failed to create.");
+        assert!(matches!(
+            guilds.create(bob, "knights"),
+            Err(GuildError::NameTaken)
+        ));
+        assert!(matches!(
+            guilds.accept(bob, "Knights"),
+            Err(GuildError::NotInvited)
+        ));
+        guilds.invite(alice, "bob").expect("Failed to invite.");
+        assert_eq!(
+            guilds.accept(bob, "knights").expect("Failed to accept."),
+            knights
+        );
+        assert!(matches!(
+            guilds.invite(bob, "carol"),
+            Err(GuildError::PermissionDenied)
+        ));
+        guilds
+            .set_role(alice, "bob", GuildRole::Officer)
+            .expect("Failed to promote.");
+        guilds.invite(bob, "carol").expect("Failed to invite.");
+        guilds.accept(carol, "Knights").expect("Failed to accept.");
+        assert!(matches!(
+            guilds.kick(bob, "alice"),
+            Err(GuildError::PermissionDenied)
+        ));
+        assert!(matches!(
+            guilds.leave(alice),
+            Err(GuildError::LeaderMustHandOver)
+        ));
+
+        guilds
+            .set_role(alice, "bob", GuildRole::Leader)
+            .expect("Failed to hand over.");
+        let (_, kicked) = guilds.kick(bob, "carol").expect("Failed to kick.");
+        assert_eq!(kicked.name, "carol");
+        assert_eq!(
+            roster(
+                &knights.name,
+                &guilds
+                    .members(knights.id)
+                    .expect("Failed to list.")
+                    .into_iter()
+                    .zip(vec![Some(Presence::Busy), None])
+                    .collect::<Vec<_>>()
+            ),
+            "GUILD Knights alice:officer:busy bob:leader:offline"
+        );
+        assert!(guilds
+            .membership(carol)
+            .expect("Failed to look up.")
+            .is_none());
+        assert_eq!(
+            guilds.leave(alice).expect("Failed to leave."),
+            (knights.clone(), false)
+        );
+        assert_eq!(
+            guilds.leave(bob).expect("Failed to leave."),
+            (knights, true)
+        );
+        assert!(matches!(
+            guilds.accept(carol, "Knights"),
+            Err(GuildError::UnknownGuild)
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::storage::{unix_now, AccountStore, StorageError};
+use hmac::Hmac;
+use rand::Rng;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+const PBKDF2_ROUNDS: u32 = 10_000;
+const SALT_SIZE: usize = 16;
+const HASH_SIZE: usize = 32;
+const TOKEN_SIZE: usize = 32;
+const SESSION_TTL_SECS: i64 = 24 * 60 * 60;
+const MIN_PASSWORD_LENGTH: usize = 4;
+const MAX_NAME_LENGTH: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AccountId(pub i64);
+
+impl Display for AccountId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub token: String,
+    pub account_id: AccountId,
+    pub name: String,
+    pub expires_at: i64,
+}
+
+#[derive(Debug)]
+pub enum IdentityError {
+    InvalidName,
+    PasswordTooShort,
+    NameTaken,
+    InvalidCredentials,
+    SessionExpired,
+    Unavailable,
+    Storage(StorageError),
+}
+
+impl Display for IdentityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentityError::InvalidName => write!(
+                f,
+                "Names must be 1-{} alphanumeric characters.",
+                MAX_NAME_LENGTH
+            ),
+            IdentityError::PasswordTooShort => write!(
+                f,
+                "Passwords must be at least {} characters.",
+                MIN_PASSWORD_LENGTH
+            ),
+            IdentityError::NameTaken => write!(f, "That name is already registered."),
+            IdentityError::InvalidCredentials => write!(f, "Invalid name or password."),
+            IdentityError::SessionExpired => write!(f, "The session has expired."),
+            IdentityError::Unavailable => write!(f, "Accounts are not available."),
+            IdentityError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<StorageError> for IdentityError {
+    fn from(e: StorageError) -> Self {
+        IdentityError::Storage(e)
+    }
+}
+
+pub enum AuthCommand<'a> {
+    Register { name: &'a str, password: &'a str },
+    Login { name: &'a str, password: &'a str },
+    Resume { token: &'a str },
+}
+
+impl<'a> AuthCommand<'a> {
+    pub fn parse(input: &'a str) -> Option<AuthCommand<'a>> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":register", Some(name), Some(password)) => {
+                Some(AuthCommand::Register { name, password })
+            }
+            (":login", Some(name), Some(password)) => Some(AuthCommand::Login { name, password }),
+            (":resume", Some(token), None) => Some(AuthCommand::Resume { token }),
+            _ => None,
+        }
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_password(password: &str, salt: &[u8], rounds: u32) -> String {
+    let mut hash = [0_u8; HASH_SIZE];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, rounds, &mut hash);
+    hex(&hash)
+}
+
+pub fn encode_password(password: &str) -> String {
+    let salt = rand::thread_rng().gen::<[u8; SALT_SIZE]>();
+    format!(
+        "pbkdf2-sha256${}${}${}",
+        PBKDF2_ROUNDS,
+        hex(&salt),
+        hash_password(password, &salt, PBKDF2_ROUNDS)
+    )
+}
+
+pub fn verify_password(password: &str, encoded: &str) -> bool {
+    let mut fields = encoded.split('$');
+    let (rounds, salt, expected) =
+        match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some("pbkdf2-sha256"), Some(rounds), Some(salt), Some(expected)) => {
+                (rounds, salt, expected)
+            }
+            _ => return false,
+        };
+    let rounds = match rounds.parse::<u32>() {
+        Ok(rounds) => rounds,
+        Err(_) => return false,
+    };
+    let salt = (0..salt.len())
+        .step_by(2)
+        .filter_map(|i| salt.get(i..i + 2))
+        .filter_map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect::<Vec<_>>();
+    let actual = hash_password(password, &salt, rounds);
+    actual.len() == expected.len()
+        && actual
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0_u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_LENGTH
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[derive(Clone)]
+pub struct Identity {
+    accounts: Option<Arc<dyn AccountStore>>,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    pub require_login: bool,
+}
+
+impl Identity {
+    pub fn new(accounts: Option<Arc<dyn AccountStore>>, require_login: bool) -> Self {
+        Identity {
+            accounts,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            require_login,
+        }
+    }
+
+    fn accounts(&self) -> Result<&Arc<dyn AccountStore>, IdentityError> {
+        self.accounts.as_ref().ok_or(IdentityError::Unavailable)
+    }
+
+    fn issue_session(&self, account_id: AccountId, name: &str) -> Session {
+        let token = hex(&rand::thread_rng().gen::<[u8; TOKEN_SIZE]>());
+        let session = Session {
+            token: token.clone(),
+            account_id,
+            name: name.to_string(),
+            expires_at: unix_now() + SESSION_TTL_SECS,
+        };
+        let mut sessions = self.sessions.lock().expect("Failed to lock sessions.");
+        let now = unix_now();
+        sessions.retain(|_, s| s.expires_at > now);
+        sessions.insert(token, session.clone());
+        session
+    }
+
+    pub fn register(&self, name: &str, password: &str) -> Result<Session, IdentityError> {
+        if !valid_name(name) {
+            return Err(IdentityError::InvalidName);
+        }
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            return Err(IdentityError::PasswordTooShort);
+        }
+        let accounts = self.accounts()?;
+        if accounts.find_account(name)?.is_some() {
+            return Err(IdentityError::NameTaken);
+        }
+        let account_id = match accounts.create_account(name, &encode_password(password)) {
+            Ok(account_id) => account_id,
+            Err(e) if e.is_unique_violation() => return Err(IdentityError::NameTaken),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(self.issue_session(AccountId(account_id), name))
+    }
+
+    pub fn login(&self, name: &str, password: &str) -> Result<Session, IdentityError> {
+        match self.accounts()?.find_account(name)? {
+            Some(account) if verify_password(password, &account.password_hash) => {
+                Ok(self.issue_session(AccountId(account.id), &account.name))
+            }
+            _ => Err(IdentityError::InvalidCredentials),
+        }
+    }
+
+    pub fn resume(&self, token: &str) -> Result<Session, IdentityError> {
+        let mut sessions = self.sessions.lock().expect("Failed to lock sessions.");
+        match sessions.get(token) {
+            Some(session) if session.expires_at > unix_now() => Ok(session.clone()),
+            Some(_) => {
+                sessions.remove(token);
+                Err(IdentityError::SessionExpired)
+            }
+            None => Err(IdentityError::SessionExpired),
+        }
+    }
+
+    pub fn sessions(&self) -> Vec<Session> {
+        let now = unix_now();
+        self.sessions
+            .lock()
+            .expect("Failed to lock sessions.")
+            .values()
+            .filter(|session| session.expires_at > now)
+            .cloned()
+            .collect()
+    }
+
+    pub fn restore_sessions(&self, restored: Vec<Session>) {
+        let now = unix_now();
+        let mut sessions = self.sessions.lock().expect("Failed to lock sessions.");
+        for session in restored.into_iter().filter(|s| s.expires_at > now) {
+            sessions.insert(session.token.clone(), session);
+        }
+    }
+
+    pub fn handle(&self, command: AuthCommand<'_>) -> Result<Session, IdentityError> {
+        match command {
+            AuthCommand::Register { name, password } => self.register(name, password),
+            AuthCommand::Login { name, password } => self.login(name, password),
+            AuthCommand::Resume { token } => self.resume(token),
+        }
+    }
+}
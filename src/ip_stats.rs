@@ -0,0 +1,185 @@
+use crate::storage::unix_now;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TOP: usize = 10;
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpRecord {
+    pub connects: u64,
+    pub failures: u64,
+    pub bytes_received: u64,
+    pub bans: u64,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+#[derive(Default)]
+struct IpStatsState {
+    records: HashMap<String, IpRecord>,
+    dirty: bool,
+    saved_at: Option<Instant>,
+}
+
+#[derive(Clone, Default)]
+pub struct IpStats {
+    path: Option<PathBuf>,
+    state: Arc<Mutex<IpStatsState>>,
+}
+
+impl IpStats {
+    pub fn new() -> Self {
+        IpStats::default()
+    }
+
+    pub fn from_env() -> std::io::Result<Self> {
+        match std::env::var("IP_STATS_PATH") {
+            Ok(path) => IpStats::open(PathBuf::from(path)),
+            Err(_) => Ok(IpStats::new()),
+        }
+    }
+
+    pub fn open(path: PathBuf) -> std::io::Result<Self> {
+        let records = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(IpStats {
+            path: Some(path),
+            state: Arc::new(Mutex::new(IpStatsState {
+                records,
+                ..IpStatsState::default()
+            })),
+        })
+    }
+
+    fn update(&self, address: &str, update: impl FnOnce(&mut IpRecord)) {
+        let mut state = self.state.lock().expect("Failed to lock IP statistics.");
+        let now = unix_now();
+        let record = state
+            .records
+            .entry(address.to_string())
+            .or_insert_with(|| IpRecord {
+                first_seen: now,
+                ..IpRecord::default()
+            });
+        record.last_seen = now;
+        update(record);
+        state.dirty = true;
+    }
+
+    pub fn record_connect(&self, address: &str) {
+        self.update(address, |record| record.connects += 1);
+    }
+
+    pub fn record_failure(&self, address: &str) {
+        self.update(address, |record| record.failures += 1);
+    }
+
+    pub fn record_bytes(&self, address: &str, bytes: usize) {
+        self.update(address, |record| record.bytes_received += bytes as u64);
+    }
+
+    pub fn record_ban(&self, address: &str) {
+        self.update(address, |record| record.bans += 1);
+    }
+
+    pub fn get(&self, address: &str) -> Option<IpRecord> {
+        self.state
+            .lock()
+            .expect("Failed to lock IP statistics.")
+            .records
+            .get(address)
+            .cloned()
+    }
+
+    pub fn top(&self, limit: usize) -> Vec<(String, IpRecord)> {
+        let mut records = self
+            .state
+            .lock()
+            .expect("Failed to lock IP statistics.")
+            .records
+            .iter()
+            .map(|(address, record)| (address.clone(), record.clone()))
+            .collect::<Vec<_>>();
+        records.sort_by(|(a_address, a), (b_address, b)| {
+            (b.connects + b.failures)
+                .cmp(&(a.connects + a.failures))
+                .then_with(|| a_address.cmp(b_address))
+        });
+        records.truncate(limit);
+        records
+    }
+
+    pub fn save(&self) -> std::io::Result<bool> {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        let mut state = self.state.lock().expect("Failed to lock IP statistics.");
+        let data = serde_json::to_vec(&state.records)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, data)?;
+        state.dirty = false;
+        state.saved_at = Some(Instant::now());
+        Ok(true)
+    }
+
+    pub fn flush(&self, now: Instant) -> std::io::Result<bool> {
+        {
+            let state = self.state.lock().expect("Failed to lock IP statistics.");
+            let due = state
+                .saved_at
+                .is_none_or(|saved_at| now.duration_since(saved_at) >= SAVE_INTERVAL);
+            if !state.dirty || !due {
+                return Ok(false);
+            }
+        }
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_kept_per_address_and_survive_a_reload() {
+        let path = std::env::temp_dir().join(format!("ogp-ip-stats-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let stats = IpStats::open(path.clone()).expect("Failed to open IP statistics.");
+        stats.record_connect("10.0.0.1");
+        stats.record_connect("10.0.0.1");
+        stats.record_bytes("10.0.0.1", 12);
+        stats.record_connect("10.0.0.2");
+        stats.record_failure("10.0.0.2");
+        stats.record_failure("10.0.0.2");
+        stats.record_ban("10.0.0.2");
+
+        let record = stats.get("10.0.0.1").expect("Missing IP record.");
+        assert_eq!((record.connects, record.bytes_received), (2, 12));
+        assert!(record.first_seen <= record.last_seen);
+        assert_eq!(
+            stats
+                .top(1)
+                .into_iter()
+                .map(|(address, _)| address)
+                .collect::<Vec<_>>(),
+            vec!["10.0.0.2"]
+        );
+        assert!(stats.flush(Instant::now()).expect("Failed to save."));
+        assert!(!stats.flush(Instant::now()).expect("Failed to save."));
+
+        let reloaded = IpStats::open(path.clone()).expect("Failed to reload.");
+        assert_eq!(reloaded.get("10.0.0.2"), stats.get("10.0.0.2"));
+        assert_eq!(reloaded.get("10.0.0.3"), None);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+const MAX_SAMPLES: usize = 64;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PingCommand {
+    Ping,
+    Pong(u64),
+}
+
+impl PingCommand {
+    pub fn parse(input: &str) -> Option<PingCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":ping" | "/ping", None, None) => Some(PingCommand::Ping),
+            (":pong", Some(nonce), None) => nonce.parse().ok().map(PingCommand::Pong),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencySummary {
+    pub current: Duration,
+    pub smoothed: Duration,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub samples: usize,
+}
+
+impl LatencySummary {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"current_ms\":{},\"smoothed_ms\":{},\"average_ms\":{},\"p50_ms\":{},\"p95_ms\":{},\"p99_ms\":{},\"samples\":{}}}",
+            self.current.as_millis(),
+            self.smoothed.as_millis(),
+            self.average.as_millis(),
+            self.p50.as_millis(),
+            self.p95.as_millis(),
+            self.p99.as_millis(),
+            self.samples
+        )
+    }
+}
+
+impl Display for LatencySummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RTT {}ms (avg {}ms, p95 {}ms)",
+            self.current.as_millis(),
+            self.average.as_millis(),
+            self.p95.as_millis()
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LatencyTracker {
+    samples: VecDeque<Duration>,
+    smoothed: Option<Duration>,
+    pending: Option<(u64, Instant)>,
+    last_probe: Option<Instant>,
+    next_nonce: u64,
+}
+
+impl LatencyTracker {
+    pub fn probe_due(&self, now: Instant, interval: Duration) -> bool {
+        self.last_probe
+            .is_none_or(|last_probe| now.duration_since(last_probe) >= interval)
+    }
+
+    pub fn start_probe(&mut self, now: Instant) -> u64 {
+        self.next_nonce += 1;
+        self.pending = Some((self.next_nonce, now));
+        self.last_probe = Some(now);
+        self.next_nonce
+    }
+
+    pub fn complete_probe(&mut self, nonce: u64, now: Instant) -> Option<Duration> {
+        match self.pending {
+            Some((pending, sent_at)) if pending == nonce => {
+                self.pending = None;
+                let rtt = now.duration_since(sent_at);
+                self.record(rtt);
+                Some(rtt)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn record(&mut self, rtt: Duration) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rtt);
+        self.smoothed = Some(match self.smoothed {
+            Some(smoothed) => (smoothed * 7 + rtt) / 8,
+            None => rtt,
+        });
+    }
+
+    fn percentile(sorted: &[Duration], percentile: usize) -> Duration {
+        let rank = (sorted.len() * percentile).div_ceil(100).max(1);
+        sorted[rank - 1]
+    }
+
+    pub fn summary(&self) -> Option<LatencySummary> {
+        let current = *self.samples.back()?;
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort();
+        Some(LatencySummary {
+            current,
+            smoothed: self.smoothed.unwrap_or(current),
+            average: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p50: LatencyTracker::percentile(&sorted, 50),
+            p95: LatencyTracker::percentile(&sorted, 95),
+            p99: LatencyTracker::percentile(&sorted, 99),
+            samples: sorted.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn probes_are_matched_by_nonce_and_summarised() {
+        let start = Instant::now();
+        let mut tracker = LatencyTracker::default();
+        assert!(tracker.probe_due(start, millis(100)));
+        assert_eq!(tracker.summary(), None);
+
+        let nonce = tracker.start_probe(start);
+        assert!(!tracker.probe_due(start + millis(50), millis(100)));
+        assert_eq!(tracker.complete_probe(nonce + 1, start + millis(10)), None);
+        assert_eq!(
+            tracker.complete_probe(nonce, start + millis(40)),
+            Some(millis(40))
+        );
+        assert_eq!(tracker.complete_probe(nonce, start + millis(50)), None);
+        for rtt in 1..=99 {
+            tracker.record(millis(rtt));
+        }
+
+        let summary = tracker.summary().expect("Missing latency summary.");
+        assert_eq!(summary.samples, MAX_SAMPLES);
+        assert_eq!(summary.current, millis(99));
+        assert_eq!(summary.p50, millis(67));
+        assert_eq!(summary.p99, millis(99));
+        assert_eq!(summary.average, millis(67) + millis(1) / 2);
+        assert!(summary.to_string().starts_with("RTT 99ms (avg 67ms"));
+        assert_eq!(PingCommand::parse("/ping\0"), Some(PingCommand::Ping));
+        assert_eq!(PingCommand::parse(":pong 7"), Some(PingCommand::Pong(7)));
+        assert_eq!(PingCommand::parse(":pong x"), None);
+    }
+}
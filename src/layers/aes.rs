@@ -0,0 +1,87 @@
+use super::{invalid_data, Layer};
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rand::Rng;
+use std::convert::TryFrom;
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+
+pub struct AesGcmLayer {
+    cipher: Aes256Gcm,
+}
+
+impl AesGcmLayer {
+    pub fn new(key: &[u8; KEY_SIZE]) -> Self {
+        AesGcmLayer {
+            cipher: Aes256Gcm::new(&Key::from(*key)),
+        }
+    }
+
+    pub fn from_env() -> std::io::Result<Self> {
+        let key = std::env::var("LAYER_KEY").unwrap_or_default();
+        let key = (0..key.len())
+            .step_by(2)
+            .filter_map(|i| key.get(i..i + 2))
+            .map(|byte| u8::from_str_radix(byte, 16))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .and_then(|key| <[u8; KEY_SIZE]>::try_from(key).ok())
+            .ok_or_else(|| invalid_data("LAYER_KEY must be 64 hex characters"))?;
+        Ok(AesGcmLayer::new(&key))
+    }
+}
+
+impl Layer for AesGcmLayer {
+    fn name(&self) -> &str {
+        "aes"
+    }
+
+    fn encode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        let mut nonce = [0_u8; NONCE_SIZE];
+        rand::thread_rng().fill(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::from(nonce), data.as_slice())
+            .map_err(|_| invalid_data("failed to encrypt the message"))?;
+        let mut encoded = nonce.to_vec();
+        encoded.extend_from_slice(&ciphertext);
+        Ok(encoded)
+    }
+
+    fn decode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        if data.len() < NONCE_SIZE {
+            return Err(invalid_data("the encrypted message is truncated"));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+        let nonce = <[u8; NONCE_SIZE]>::try_from(nonce).map_err(invalid_data)?;
+        self.cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| invalid_data("failed to decrypt the message"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tampered_or_foreign_messages_are_rejected() {
+        let layer = AesGcmLayer::new(&[1; KEY_SIZE]);
+        let mut encrypted = layer
+            .encode(b"secret".to_vec())
+            .expect("Failed to encrypt.");
+
+        assert_eq!(
+            layer.decode(encrypted.clone()).expect("Failed to decrypt."),
+            b"secret"
+        );
+        assert!(AesGcmLayer::new(&[2; KEY_SIZE])
+            .decode(encrypted.clone())
+            .is_err());
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(layer.decode(encrypted).is_err());
+        assert!(layer.decode(vec![0; 4]).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use super::{invalid_data, Layer, Lz4Layer, ZlibLayer};
+use crate::config::env_or;
+use crate::frame::{length_prefixed, LengthPrefixedDecoder};
+use crate::transport::Transport;
+use std::sync::{Arc, Mutex, RwLock};
+
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 256;
+
+const BUFFER_SIZE: usize = 2048;
+const FLAG_RAW: u8 = 0;
+const FLAG_ZLIB: u8 = 1;
+const FLAG_LZ4: u8 = 2;
+const FLAG_OFFER: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    Zlib,
+    Lz4,
+}
+
+impl CompressionAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "zlib" => Some(CompressionAlgorithm::Zlib),
+            "lz4" => Some(CompressionAlgorithm::Lz4),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Zlib => "zlib",
+            CompressionAlgorithm::Lz4 => "lz4",
+        }
+    }
+
+    fn flag(&self) -> u8 {
+        match self {
+            CompressionAlgorithm::Zlib => FLAG_ZLIB,
+            CompressionAlgorithm::Lz4 => FLAG_LZ4,
+        }
+    }
+
+    fn from_flag(flag: u8) -> Option<Self> {
+        match flag {
+            FLAG_ZLIB => Some(CompressionAlgorithm::Zlib),
+            FLAG_LZ4 => Some(CompressionAlgorithm::Lz4),
+            _ => None,
+        }
+    }
+
+    fn layer(&self) -> Box<dyn Layer> {
+        match self {
+            CompressionAlgorithm::Zlib => Box::new(ZlibLayer::default()),
+            CompressionAlgorithm::Lz4 => Box::new(Lz4Layer),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressionConfig {
+    pub algorithms: Vec<CompressionAlgorithm>,
+    pub threshold: usize,
+}
+
+impl CompressionConfig {
+    pub fn from_env() -> Option<Self> {
+        let algorithms = env_or("COMPRESSION", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let algorithm = CompressionAlgorithm::from_name(name);
+                if algorithm.is_none() {
+                    eprintln!("圧縮方式{}には対応していません\n", name);
+                }
+                algorithm
+            })
+            .collect::<Vec<_>>();
+        if algorithms.is_empty() {
+            return None;
+        }
+        Some(CompressionConfig {
+            algorithms,
+            threshold: env_or("COMPRESSION_THRESHOLD", "")
+                .parse()
+                .unwrap_or(DEFAULT_COMPRESSION_THRESHOLD),
+        })
+    }
+}
+
+pub fn offer_frame(algorithms: &[CompressionAlgorithm]) -> Vec<u8> {
+    let names = algorithms
+        .iter()
+        .map(CompressionAlgorithm::name)
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut frame = vec![FLAG_OFFER];
+    frame.extend_from_slice(names.as_bytes());
+    frame
+}
+
+fn parse_offer(payload: &[u8]) -> Vec<CompressionAlgorithm> {
+    String::from_utf8_lossy(payload)
+        .split(',')
+        .filter_map(CompressionAlgorithm::from_name)
+        .collect()
+}
+
+struct Inbound {
+    decoder: LengthPrefixedDecoder,
+    pending: Vec<u8>,
+}
+
+pub struct CompressedTransport {
+    inner: Arc<dyn Transport>,
+    config: CompressionConfig,
+    negotiated: RwLock<Option<CompressionAlgorithm>>,
+    inbound: Mutex<Inbound>,
+}
+
+impl CompressedTransport {
+    pub fn negotiate(
+        inner: Arc<dyn Transport>,
+        config: CompressionConfig,
+    ) -> std::io::Result<Self> {
+        inner.send(&length_prefixed(&offer_frame(&config.algorithms)))?;
+        Ok(CompressedTransport {
+            inner,
+            config,
+            negotiated: RwLock::new(None),
+            inbound: Mutex::new(Inbound {
+                decoder: LengthPrefixedDecoder::new(),
+                pending: vec![],
+            }),
+        })
+    }
+
+    pub fn negotiated(&self) -> Option<CompressionAlgorithm> {
+        *self
+            .negotiated
+            .read()
+            .expect("Failed to lock negotiated compression.")
+    }
+
+    fn encode_frame(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        if let Some(algorithm) = self
+            .negotiated()
+            .filter(|_| data.len() >= self.config.threshold)
+        {
+            let compressed = algorithm.layer().encode(data.to_vec())?;
+            if compressed.len() < data.len() {
+                let mut frame = vec![algorithm.flag()];
+                frame.extend_from_slice(&compressed);
+                return Ok(frame);
+            }
+        }
+        let mut frame = vec![FLAG_RAW];
+        frame.extend_from_slice(data);
+        Ok(frame)
+    }
+
+    fn decode_frame(&self, frame: Vec<u8>) -> std::io::Result<Option<Vec<u8>>> {
+        match frame.split_first() {
+            None => Err(invalid_data("the compressed frame is empty")),
+            Some((&FLAG_RAW, payload)) => Ok(Some(payload.to_vec())),
+            Some((&FLAG_OFFER, payload)) => {
+                let offered = parse_offer(payload);
+                *self
+                    .negotiated
+                    .write()
+                    .expect("Failed to lock negotiated compression.") = self
+                    .config
+                    .algorithms
+                    .iter()
+                    .find(|algorithm| offered.contains(algorithm))
+                    .copied();
+                Ok(None)
+            }
+            Some((&flag, payload)) => match CompressionAlgorithm::from_flag(flag)
+                .filter(|algorithm| self.config.algorithms.contains(algorithm))
+            {
+                Some(algorithm) => algorithm.layer().decode(payload.to_vec()).map(Some),
+                None => Err(invalid_data("the frame uses an unsupported compression")),
+            },
+        }
+    }
+}
+
+impl Transport for CompressedTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let mut inbound = self
+            .inbound
+            .lock()
+            .expect("Failed to lock compressed transport.");
+        let mut recv_buffer = [0_u8; BUFFER_SIZE];
+        while inbound.pending.is_empty() {
+            if let Some(frame) = inbound.decoder.next_frame().map_err(invalid_data)? {
+                if let Some(payload) = self.decode_frame(frame)? {
+                    inbound.pending = payload;
+                }
+                continue;
+            }
+            match self.inner.receive(&mut recv_buffer)? {
+                0 => return Ok(0),
+                size => inbound.decoder.push(&recv_buffer[..size]),
+            }
+        }
+        let size = inbound.pending.len().min(buffer.len());
+        buffer[..size].copy_from_slice(&inbound.pending[..size]);
+        inbound.pending.drain(..size);
+        Ok(size)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let frame = self.encode_frame(data)?;
+        self.inner.send(&length_prefixed(&frame))?;
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.inner.shutdown_send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{MemoryTransport, MockTransport};
+
+    fn config(algorithms: &[CompressionAlgorithm], threshold: usize) -> CompressionConfig {
+        CompressionConfig {
+            algorithms: algorithms.to_vec(),
+            threshold,
+        }
+    }
+
+    #[test]
+    fn large_frames_are_flagged_with_the_negotiated_algorithm() {
+        let inner = Arc::new(MockTransport::new());
+        inner
+            .script_read(&length_prefixed(&offer_frame(&[
+                CompressionAlgorithm::Zlib,
+            ])))
+            .script_read(&length_prefixed(b"\0hi\0"));
+        let transport = CompressedTransport::negotiate(
+            inner.clone(),
+            config(&[CompressionAlgorithm::Lz4, CompressionAlgorithm::Zlib], 16),
+        )
+        .expect("Failed to negotiate.");
+        let snapshot = "{\"x\":0,\"y\":0}".repeat(20);
+
+        transport.send_text(&snapshot).expect("Failed to send.");
+        let mut buffer = [0_u8; 16];
+        let size = transport.receive(&mut buffer).expect("Failed to receive.");
+        transport.send_text(&snapshot).expect("Failed to send.");
+        transport.send_text("ok").expect("Failed to send.");
+
+        let frames = inner.writes();
+        assert_eq!(&buffer[..size], b"hi\0");
+        assert_eq!(transport.negotiated(), Some(CompressionAlgorithm::Zlib));
+        assert_eq!(
+            frames[0][4..],
+            offer_frame(&[CompressionAlgorithm::Lz4, CompressionAlgorithm::Zlib])[..]
+        );
+        assert_eq!(frames[1][4], FLAG_RAW);
+        assert_eq!(frames[2][4], FLAG_ZLIB);
+        assert!(frames[2].len() < frames[1].len() / 4);
+        assert_eq!(frames[3][4], FLAG_RAW);
+    }
+
+    #[test]
+    fn both_ends_exchange_compressed_text() {
+        let (client, server) = MemoryTransport::pair();
+        let client = CompressedTransport::negotiate(
+            Arc::new(client),
+            config(&[CompressionAlgorithm::Lz4], 0),
+        )
+        .expect("Failed to negotiate.");
+        let server = CompressedTransport::negotiate(
+            Arc::new(server),
+            config(&[CompressionAlgorithm::Zlib, CompressionAlgorithm::Lz4], 0),
+        )
+        .expect("Failed to negotiate.");
+        let mut buffer = [0_u8; 1024];
+
+        client.send_text("hello").expect("Failed to send.");
+        let size = server.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"hello\0");
+        let message = "gg ".repeat(100);
+        server.send_text(&message).expect("Failed to send.");
+        let mut received = vec![];
+        while received.len() < message.len() + 1 {
+            let size = client.receive(&mut buffer).expect("Failed to receive.");
+            received.extend_from_slice(&buffer[..size]);
+        }
+
+        assert_eq!(received, format!("{}\0", message).into_bytes());
+        assert_eq!(server.negotiated(), Some(CompressionAlgorithm::Lz4));
+        assert_eq!(client.negotiated(), Some(CompressionAlgorithm::Lz4));
+        assert!(CompressionAlgorithm::from_name("brotli").is_none());
+    }
+}
@@ -0,0 +1,125 @@
+use super::{invalid_data, Pipeline};
+use crate::frame::{length_prefixed, LengthPrefixedDecoder};
+use crate::transport::Transport;
+use std::sync::{Arc, Mutex};
+
+const BUFFER_SIZE: usize = 2048;
+
+struct Inbound {
+    decoder: LengthPrefixedDecoder,
+    pending: Vec<u8>,
+}
+
+pub struct LayeredTransport {
+    inner: Arc<dyn Transport>,
+    pipeline: Pipeline,
+    inbound: Mutex<Inbound>,
+}
+
+impl LayeredTransport {
+    pub fn new(inner: Arc<dyn Transport>, pipeline: Pipeline) -> Self {
+        LayeredTransport {
+            inner,
+            pipeline,
+            inbound: Mutex::new(Inbound {
+                decoder: LengthPrefixedDecoder::new(),
+                pending: vec![],
+            }),
+        }
+    }
+}
+
+impl Transport for LayeredTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let mut inbound = self
+            .inbound
+            .lock()
+            .expect("Failed to lock layered transport.");
+        let mut recv_buffer = [0_u8; BUFFER_SIZE];
+        while inbound.pending.is_empty() {
+            if let Some(frame) = inbound.decoder.next_frame().map_err(invalid_data)? {
+                inbound.pending = self.pipeline.decode(frame)?;
+                continue;
+            }
+            match self.inner.receive(&mut recv_buffer)? {
+                0 => return Ok(0),
+                size => inbound.decoder.push(&recv_buffer[..size]),
+            }
+        }
+        let size = inbound.pending.len().min(buffer.len());
+        buffer[..size].copy_from_slice(&inbound.pending[..size]);
+        inbound.pending.drain(..size);
+        Ok(size)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let encoded = self.pipeline.encode(data.to_vec())?;
+        self.inner.send(&length_prefixed(&encoded))?;
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.inner.shutdown_send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{AesGcmLayer, ZlibLayer};
+    use crate::transport::MemoryTransport;
+
+    fn pipeline() -> Pipeline {
+        Pipeline::new()
+            .layer(Arc::new(ZlibLayer::default()))
+            .layer(Arc::new(AesGcmLayer::new(&[9; 32])))
+    }
+
+    #[test]
+    fn both_ends_exchange_text_through_the_stack() {
+        let (client, server) = MemoryTransport::pair();
+        let client = LayeredTransport::new(Arc::new(client), pipeline());
+        let server = LayeredTransport::new(Arc::new(server), pipeline());
+
+        client.send_text("hello").expect("Failed to send.");
+        client.send_text("again").expect("Failed to send.");
+        let mut buffer = [0_u8; 4];
+        let mut received = vec![];
+        while received.len() < 12 {
+            let size = server.receive(&mut buffer).expect("Failed to receive.");
+            received.extend_from_slice(&buffer[..size]);
+        }
+        server.send_text("pong").expect("Failed to send.");
+        let size = client.receive(&mut buffer).expect("Failed to receive.");
+
+        assert_eq!(received, b"hello\0again\0");
+        assert_eq!(&buffer[..size], b"pong");
+        server.close();
+        assert_eq!(client.receive(&mut buffer).ok(), Some(1));
+        assert_eq!(client.receive(&mut buffer).ok(), Some(0));
+    }
+
+    #[test]
+    fn frames_that_fail_to_decode_surface_as_errors() {
+        let (client, server) = MemoryTransport::pair();
+        let server = LayeredTransport::new(Arc::new(server), pipeline());
+
+        client
+            .send(&length_prefixed(b"garbage"))
+            .expect("Failed to send.");
+
+        assert!(server.receive(&mut [0_u8; 16]).is_err());
+    }
+}
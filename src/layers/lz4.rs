@@ -0,0 +1,200 @@
+use super::{invalid_data, Layer};
+
+const MAX_DECOMPRESSED_SIZE: usize = 1024 * 1024;
+const SIZE_PREFIX: usize = 4;
+const MIN_MATCH: usize = 4;
+const LAST_LITERALS: usize = 5;
+const MATCH_LIMIT: usize = 12;
+const MAX_OFFSET: usize = 65535;
+const HASH_BITS: u32 = 12;
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes([
+        data[index],
+        data[index + 1],
+        data[index + 2],
+        data[index + 3],
+    ])
+}
+
+fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn write_length(output: &mut Vec<u8>, mut length: usize) {
+    while length >= 255 {
+        output.push(255);
+        length -= 255;
+    }
+    output.push(length as u8);
+}
+
+fn write_sequence(output: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let literal_length = literals.len();
+    let match_length = matched.map_or(0, |(_, length)| length - MIN_MATCH);
+    output.push(((literal_length.min(15) as u8) << 4) | match_length.min(15) as u8);
+    if literal_length >= 15 {
+        write_length(output, literal_length - 15);
+    }
+    output.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        output.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_length >= 15 {
+            write_length(output, match_length - 15);
+        }
+    }
+}
+
+pub fn lz4_compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(SIZE_PREFIX + data.len() + data.len() / 255 + 16);
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut index = 0;
+    while index + MATCH_LIMIT <= data.len() {
+        let sequence = read_u32(data, index);
+        let slot = hash(sequence);
+        let candidate = std::mem::replace(&mut table[slot], index);
+        if candidate == usize::MAX
+            || index - candidate > MAX_OFFSET
+            || read_u32(data, candidate) != sequence
+        {
+            index += 1;
+            continue;
+        }
+        let mut length = MIN_MATCH;
+        while index + length < data.len() - LAST_LITERALS
+            && data[candidate + length] == data[index + length]
+        {
+            length += 1;
+        }
+        write_sequence(
+            &mut output,
+            &data[anchor..index],
+            Some((index - candidate, length)),
+        );
+        index += length;
+        anchor = index;
+    }
+    write_sequence(&mut output, &data[anchor..], None);
+    output
+}
+
+fn read_length(data: &[u8], index: &mut usize) -> std::io::Result<usize> {
+    let mut length = 0;
+    loop {
+        let byte = *data
+            .get(*index)
+            .ok_or_else(|| invalid_data("the lz4 block is truncated"))?;
+        *index += 1;
+        length += byte as usize;
+        if byte != 255 {
+            return Ok(length);
+        }
+    }
+}
+
+pub fn lz4_decompress(data: &[u8], max_size: usize) -> std::io::Result<Vec<u8>> {
+    let prefix = data
+        .get(..SIZE_PREFIX)
+        .ok_or_else(|| invalid_data("the lz4 block is truncated"))?;
+    let size = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+    if size > max_size {
+        return Err(invalid_data("the decompressed message is too large"));
+    }
+    let mut output = Vec::with_capacity(size);
+    let mut index = SIZE_PREFIX;
+    loop {
+        let token = *data
+            .get(index)
+            .ok_or_else(|| invalid_data("the lz4 block is truncated"))?;
+        index += 1;
+        let mut literal_length = (token >> 4) as usize;
+        if literal_length == 15 {
+            literal_length += read_length(data, &mut index)?;
+        }
+        let literals = data
+            .get(index..index.saturating_add(literal_length))
+            .filter(|literals| output.len() + literals.len() <= size)
+            .ok_or_else(|| invalid_data("the lz4 literals are out of bounds"))?;
+        output.extend_from_slice(literals);
+        index += literal_length;
+        if index == data.len() {
+            break;
+        }
+        let offset = data
+            .get(index..index + 2)
+            .map(|offset| u16::from_le_bytes([offset[0], offset[1]]) as usize)
+            .filter(|offset| *offset > 0 && *offset <= output.len())
+            .ok_or_else(|| invalid_data("the lz4 match offset is invalid"))?;
+        index += 2;
+        let mut match_length = (token & 0x0f) as usize + MIN_MATCH;
+        if token & 0x0f == 15 {
+            match_length += read_length(data, &mut index)?;
+        }
+        if output.len() + match_length > size {
+            return Err(invalid_data("the lz4 match is out of bounds"));
+        }
+        let start = output.len() - offset;
+        for position in start..start + match_length {
+            let byte = output[position];
+            output.push(byte);
+        }
+    }
+    if output.len() != size {
+        return Err(invalid_data("the lz4 block size does not match"));
+    }
+    Ok(output)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lz4Layer;
+
+impl Layer for Lz4Layer {
+    fn name(&self) -> &str {
+        "lz4"
+    }
+
+    fn encode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        Ok(lz4_compress(&data))
+    }
+
+    fn decode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        lz4_decompress(&data, MAX_DECOMPRESSED_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_round_trip_including_overlapping_matches() {
+        let repetitive = "{\"room\":\"lobby\",\"x\":1}".repeat(100).into_bytes();
+        let runs = vec![b'a'; 1000];
+        let mixed = (0..2000_u32)
+            .map(|value| (value.wrapping_mul(7919) % 251) as u8)
+            .collect::<Vec<_>>();
+
+        for data in [vec![], b"short".to_vec(), repetitive.clone(), runs, mixed] {
+            let compressed = lz4_compress(&data);
+            assert_eq!(
+                lz4_decompress(&compressed, MAX_DECOMPRESSED_SIZE).expect("Failed to decompress."),
+                data
+            );
+        }
+        assert!(lz4_compress(&repetitive).len() < repetitive.len() / 10);
+    }
+
+    #[test]
+    fn malformed_blocks_are_rejected() {
+        let compressed = lz4_compress(&b"hello hello hello hello".repeat(4));
+
+        assert!(
+            lz4_decompress(&compressed[..compressed.len() - 3], MAX_DECOMPRESSED_SIZE).is_err()
+        );
+        assert!(lz4_decompress(&compressed, 16).is_err());
+        assert!(lz4_decompress(&[4, 0, 0, 0, 0x00, 0x05, 0x00], MAX_DECOMPRESSED_SIZE).is_err());
+        assert!(Lz4Layer.decode(b"xy".to_vec()).is_err());
+    }
+}
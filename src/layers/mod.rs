@@ -0,0 +1,12 @@
+mod aes;
+mod compression;
+mod layered;
+mod lz4;
+mod pipeline;
+mod zlib;
+pub use aes::*;
+pub use compression::*;
+pub use layered::*;
+pub use lz4::*;
+pub use pipeline::*;
+pub use zlib::*;
@@ -0,0 +1,128 @@
+use super::{AesGcmLayer, Lz4Layer, ZlibLayer};
+use crate::config::env_or;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+pub trait Layer: Send + Sync {
+    fn name(&self) -> &str;
+    fn encode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>>;
+    fn decode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>>;
+}
+
+pub fn invalid_data<E>(error: E) -> Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let mut pipeline = Pipeline::new();
+        for name in env_or("LAYERS", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match layer_by_name(name) {
+                Ok(layer) => pipeline = pipeline.layer(layer),
+                Err(e) => eprintln!("レイヤー{}を追加できませんでした：{}\n", name, e),
+            }
+        }
+        if pipeline.layers.is_empty() {
+            None
+        } else {
+            Some(pipeline)
+        }
+    }
+
+    pub fn layer(mut self, layer: Arc<dyn Layer>) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.layers.iter().map(|layer| layer.name()).collect()
+    }
+
+    pub fn encode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        self.layers
+            .iter()
+            .try_fold(data, |data, layer| layer.encode(data))
+    }
+
+    pub fn decode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        self.layers
+            .iter()
+            .rev()
+            .try_fold(data, |data, layer| layer.decode(data))
+    }
+}
+
+pub fn layer_by_name(name: &str) -> std::io::Result<Arc<dyn Layer>> {
+    match name {
+        "zlib" => Ok(Arc::new(ZlibLayer::default())),
+        "lz4" => Ok(Arc::new(Lz4Layer)),
+        "aes" => Ok(Arc::new(AesGcmLayer::from_env()?)),
+        _ => Err(Error::new(ErrorKind::NotFound, "unknown layer")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn layers_encode_in_order_and_decode_in_reverse() {
+        let pipeline = Pipeline::new()
+            .layer(Arc::new(ZlibLayer::default()))
+            .layer(Arc::new(AesGcmLayer::new(&KEY)));
+        let message = b"hello hello hello hello\0".to_vec();
+
+        let encoded = pipeline.encode(message.clone()).expect("Failed to encode.");
+        let compressed = ZlibLayer::default()
+            .encode(message.clone())
+            .expect("Failed to compress.");
+
+        assert_eq!(pipeline.names(), vec!["zlib", "aes"]);
+        assert_ne!(encoded, message);
+        assert_eq!(
+            AesGcmLayer::new(&KEY)
+                .decode(encoded.clone())
+                .expect("Failed to decrypt."),
+            compressed
+        );
+        assert_eq!(
+            pipeline.decode(encoded).expect("Failed to decode."),
+            message
+        );
+    }
+
+    #[test]
+    fn empty_pipelines_pass_data_through() {
+        assert_eq!(
+            Pipeline::new()
+                .encode(b"raw".to_vec())
+                .expect("Failed to encode."),
+            b"raw"
+        );
+        assert!(layer_by_name("rot13").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use super::{invalid_data, Layer};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+const MAX_DECOMPRESSED_SIZE: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZlibLayer {
+    pub level: Compression,
+}
+
+impl Layer for ZlibLayer {
+    fn name(&self) -> &str {
+        "zlib"
+    }
+
+    fn encode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), self.level);
+        encoder.write_all(&data)?;
+        encoder.finish()
+    }
+
+    fn decode(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        let mut decoded = vec![];
+        ZlibDecoder::new(data.as_slice())
+            .take(MAX_DECOMPRESSED_SIZE + 1)
+            .read_to_end(&mut decoded)?;
+        if decoded.len() as u64 > MAX_DECOMPRESSED_SIZE {
+            return Err(invalid_data("the decompressed message is too large"));
+        }
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repetitive_messages_shrink_and_round_trip() {
+        let layer = ZlibLayer::default();
+        let message = "gg ".repeat(200).into_bytes();
+
+        let compressed = layer.encode(message.clone()).expect("Failed to compress.");
+
+        assert!(compressed.len() < message.len() / 4);
+        assert_eq!(
+            layer.decode(compressed).expect("Failed to decompress."),
+            message
+        );
+        assert!(layer.decode(b"not zlib".to_vec()).is_err());
+    }
+}
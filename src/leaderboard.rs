@@ -0,0 +1,142 @@
+use crate::bus::{MatchEnded, MessageBus};
+use crate::events::escape_json;
+use crate::identity::AccountId;
+use crate::storage::{AccountStore, LeaderboardEntry, MatchParticipant, StorageResult};
+use std::sync::Arc;
+
+pub const DEFAULT_TOP: i64 = 10;
+pub const MAX_TOP: i64 = 100;
+pub const DEFAULT_RADIUS: i64 = 2;
+pub const MAX_RADIUS: i64 = 10;
+
+#[derive(Clone, Debug)]
+pub struct MatchStanding {
+    pub account_id: AccountId,
+    pub score: i64,
+}
+
+pub enum LeaderboardCommand {
+    Top(i64),
+    Rank(i64),
+}
+
+impl LeaderboardCommand {
+    pub fn parse(input: &str) -> Option<LeaderboardCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        let command = parts.next()?;
+        let argument = parts.next().and_then(|arg| arg.parse::<i64>().ok());
+        match command {
+            ":top" => Some(LeaderboardCommand::Top(
+                argument.unwrap_or(DEFAULT_TOP).clamp(1, MAX_TOP),
+            )),
+            ":rank" => Some(LeaderboardCommand::Rank(
+                argument.unwrap_or(DEFAULT_RADIUS).clamp(0, MAX_RADIUS),
+            )),
+            _ => None,
+        }
+    }
+}
+
+pub fn format_entry(entry: &LeaderboardEntry) -> String {
+    format!(
+        "{}. {} {}pts ({}/{})",
+        entry.rank, entry.name, entry.total_score, entry.wins, entry.matches_played
+    )
+}
+
+pub fn entries_to_json(entries: &[LeaderboardEntry]) -> String {
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{{\"rank\":{},\"account_id\":{},\"name\":\"{}\",\"matches_played\":{},\"wins\":{},\"total_score\":{}}}",
+                entry.rank,
+                entry.account_id,
+                escape_json(&entry.name),
+                entry.matches_played,
+                entry.wins,
+                entry.total_score
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", entries.join(","))
+}
+
+#[derive(Clone)]
+pub struct Leaderboard {
+    accounts: Arc<dyn AccountStore>,
+}
+
+impl Leaderboard {
+    pub fn new(accounts: Arc<dyn AccountStore>) -> Self {
+        Leaderboard { accounts }
+    }
+
+    pub fn report_match(&self, room: &str, standings: &[MatchStanding]) -> StorageResult<i64> {
+        let mut sorted = standings.to_vec();
+        sorted.sort_by_key(|standing| std::cmp::Reverse(standing.score));
+        let mut participants = Vec::with_capacity(sorted.len());
+        for (index, standing) in sorted.iter().enumerate() {
+            let placement = match participants.last() {
+                Some(MatchParticipant {
+                    score, placement, ..
+                }) if *score == standing.score => *placement,
+                _ => index as u32 + 1,
+            };
+            participants.push(MatchParticipant {
+                account_id: standing.account_id.0,
+                score: standing.score,
+                placement,
+            });
+        }
+        self.accounts.record_match(room, &participants)
+    }
+
+    pub fn top(&self, limit: i64) -> StorageResult<Vec<LeaderboardEntry>> {
+        self.accounts.leaderboard_page(0, limit.clamp(1, MAX_TOP))
+    }
+
+    pub fn around(
+        &self,
+        account_id: AccountId,
+        radius: i64,
+    ) -> StorageResult<Vec<LeaderboardEntry>> {
+        let radius = radius.clamp(0, MAX_RADIUS);
+        match self.accounts.leaderboard_rank(account_id.0)? {
+            Some(rank) => {
+                let offset = (rank - 1 - radius).max(0);
+                self.accounts.leaderboard_page(offset, radius * 2 + 1)
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn around_name(
+        &self,
+        name: &str,
+        radius: i64,
+    ) -> StorageResult<Option<Vec<LeaderboardEntry>>> {
+        match self.accounts.find_account(name)? {
+            Some(account) => self.around(AccountId(account.id), radius).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn subscribe(&self, bus: &MessageBus) {
+        let leaderboard = self.clone();
+        bus.subscribe(move |event: &MatchEnded| {
+            if let Err(e) = leaderboard.report_match(&event.room, &event.standings) {
+                eprintln!("試合結果の保存に失敗しました：{}", e);
+            }
+        });
+    }
+
+    pub fn find_account_id(&self, name: &str) -> StorageResult<Option<AccountId>> {
+        Ok(self
+            .accounts
+            .find_account(name)?
+            .map(|account| AccountId(account.id)))
+    }
+}
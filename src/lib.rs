@@ -0,0 +1,54 @@
+#![allow(clippy::missing_safety_doc)]
+
+pub mod admin;
+pub mod announcements;
+pub mod anticheat;
+pub mod aoi;
+pub mod attachment;
+#[cfg(feature = "winsock")]
+pub mod bindings;
+pub mod bots;
+pub mod bridge;
+pub mod bus;
+pub mod chat_log;
+pub mod clients;
+pub mod clock;
+pub mod cluster;
+pub mod codec;
+pub mod config;
+pub mod console;
+pub mod context;
+pub mod events;
+pub mod frame;
+#[cfg(feature = "std-net")]
+pub mod gateway;
+pub mod geoip;
+pub mod guilds;
+pub mod identity;
+pub mod ip_stats;
+pub mod latency;
+pub mod layers;
+pub mod leaderboard;
+pub mod metrics;
+pub mod notify;
+pub mod p2p;
+pub mod physics;
+pub mod ping;
+pub mod playback;
+pub mod plugins;
+pub mod pow;
+pub mod presence;
+pub mod query;
+pub mod recorder;
+pub mod rooms;
+pub mod rudp;
+pub mod scoreboard;
+pub mod server;
+pub mod session;
+pub mod snapshot;
+pub mod storage;
+pub mod throttle;
+pub mod trade;
+pub mod transfer;
+pub mod transport;
+pub mod zone;
@@ -1,8 +1,26 @@
 mod assignments;
-mod bindings;
+
+use online_game_programming::config;
 
 fn main() {
-    unsafe {
-        let _ = assignments::unit_05();
+    match config::env_or("ASSIGNMENT", "unit_05").as_str() {
+        "codec_chat" => {
+            let _ = assignments::codec_chat();
+        }
+        "physics_demo" => {
+            let _ = assignments::physics_demo();
+        }
+        "trade_sync" => {
+            let _ = assignments::trade_sync();
+        }
+        "unit_05_select" => unsafe {
+            let _ = assignments::unit_05_select();
+        },
+        "urgent_data" => unsafe {
+            let _ = assignments::urgent_data();
+        },
+        _ => unsafe {
+            let _ = assignments::unit_05();
+        },
     }
 }
@@ -0,0 +1,137 @@
+use crate::bus::{ChatPosted, ClientJoined, ClientLeft, LoggedIn, MatchEnded, MessageBus};
+use crate::events::escape_json;
+use crate::identity::AccountId;
+use crate::rudp::corrupted_packets;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct MetricsState {
+    connected: HashMap<u32, String>,
+    logged_in: HashMap<u32, (AccountId, String)>,
+    joins: u64,
+    chat_by_room: BTreeMap<String, u64>,
+    guest_messages: u64,
+    matches: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct Metrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    fn update<E: 'static>(&self, bus: &MessageBus, update: fn(&mut MetricsState, &E)) {
+        let state = self.state.clone();
+        bus.subscribe(move |event: &E| {
+            update(&mut state.lock().expect("Failed to lock metrics."), event)
+        });
+    }
+
+    pub fn subscribe(&self, bus: &MessageBus) {
+        self.update(bus, |state, event: &ClientJoined| {
+            state
+                .connected
+                .insert(event.client_id, event.address.clone());
+            state.joins += 1;
+        });
+        self.update(bus, |state, event: &ClientLeft| {
+            state.connected.remove(&event.client_id);
+            state.logged_in.remove(&event.client_id);
+        });
+        self.update(bus, |state, event: &LoggedIn| {
+            state
+                .logged_in
+                .insert(event.client_id, (event.account_id, event.name.clone()));
+        });
+        self.update(bus, |state, event: &ChatPosted| {
+            *state.chat_by_room.entry(event.room.clone()).or_insert(0) += 1;
+            if event.account_id.is_none() {
+                state.guest_messages += 1;
+            }
+        });
+        self.update(bus, |state, _: &MatchEnded| state.matches += 1);
+    }
+
+    pub fn to_json(&self) -> String {
+        let state = self.state.lock().expect("Failed to lock metrics.");
+        let mut names = state
+            .logged_in
+            .values()
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+        names.sort();
+        format!(
+            "{{\"connected_clients\":{},\"unique_addresses\":{},\"total_joins\":{},\"logged_in\":[{}],\"unique_accounts\":{},\"chat_messages\":{{{}}},\"guest_messages\":{},\"matches_ended\":{},\"corrupted_packets\":{}}}",
+            state.connected.len(),
+            state.connected.values().collect::<HashSet<_>>().len(),
+            state.joins,
+            names
+                .iter()
+                .map(|name| format!("\"{}\"", escape_json(name)))
+                .collect::<Vec<_>>()
+                .join(","),
+            state
+                .logged_in
+                .values()
+                .map(|(account_id, _)| account_id)
+                .collect::<HashSet<_>>()
+                .len(),
+            state
+                .chat_by_room
+                .iter()
+                .map(|(room, count)| format!("\"{}\":{}", escape_json(room), count))
+                .collect::<Vec<_>>()
+                .join(","),
+            state.guest_messages,
+            state.matches,
+            corrupted_packets()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_follow_bus_events() {
+        let bus = MessageBus::new();
+        let metrics = Metrics::new();
+        metrics.subscribe(&bus);
+
+        for client_id in 0..3 {
+            bus.publish(&ClientJoined {
+                client_id,
+                address: format!("10.0.0.{}", client_id.min(1)),
+            });
+        }
+        bus.publish(&LoggedIn {
+            client_id: 1,
+            account_id: AccountId(7),
+            name: "alice".to_string(),
+        });
+        bus.publish(&ChatPosted {
+            client_id: 1,
+            account_id: Some(AccountId(7)),
+            room: "lobby".to_string(),
+            message: "hi".to_string(),
+        });
+        bus.publish(&ChatPosted {
+            client_id: 0,
+            account_id: None,
+            room: "red".to_string(),
+            message: "hi".to_string(),
+        });
+        bus.publish(&ClientLeft { client_id: 0 });
+
+        assert_eq!(
+            metrics.to_json(),
+            "{\"connected_clients\":2,\"unique_addresses\":1,\"total_joins\":3,\"logged_in\":[\"alice\"],\"unique_accounts\":1,\"chat_messages\":{\"lobby\":1,\"red\":1},\"guest_messages\":1,\"matches_ended\":0,\"corrupted_packets\":0}"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::bridge::{deliver, WebhookConfig};
+use crate::events::escape_json;
+use crate::identity::AccountId;
+use std::io::{Error, ErrorKind};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+
+const QUEUE_SIZE: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfflineMessage {
+    pub whisper_id: u64,
+    pub account_id: AccountId,
+    pub recipient: String,
+    pub sender: String,
+    pub text: String,
+}
+
+impl OfflineMessage {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"event\":\"whisper\",\"whisper_id\":{},\"account_id\":{},\"recipient\":\"{}\",\"sender\":\"{}\",\"text\":\"{}\"}}",
+            self.whisper_id,
+            self.account_id,
+            escape_json(&self.recipient),
+            escape_json(&self.sender),
+            escape_json(&self.text)
+        )
+    }
+}
+
+pub trait Notifier: Send + Sync {
+    fn notify(&self, message: &OfflineMessage) -> std::io::Result<()>;
+}
+
+#[derive(Clone, Debug)]
+pub struct PushConfig {
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+impl PushConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("PUSH_NOTIFY_URL").ok().map(|url| PushConfig {
+            url,
+            secret: std::env::var("PUSH_NOTIFY_SECRET").ok(),
+        })
+    }
+}
+
+pub struct HttpNotifier {
+    sender: SyncSender<String>,
+}
+
+impl HttpNotifier {
+    pub fn spawn(config: PushConfig) -> Self {
+        let webhook = WebhookConfig {
+            secret: config.secret,
+            ..WebhookConfig::new(vec![config.url])
+        };
+        let (sender, receiver) = sync_channel::<String>(QUEUE_SIZE);
+        std::thread::spawn(move || {
+            for body in receiver.iter() {
+                if let Err(e) = deliver(&webhook, &webhook.urls[0], "whisper", &body) {
+                    eprintln!("プッシュ通知の送信に失敗しました：{}\n", e);
+                }
+            }
+        });
+        HttpNotifier { sender }
+    }
+}
+
+impl Notifier for HttpNotifier {
+    fn notify(&self, message: &OfflineMessage) -> std::io::Result<()> {
+        self.sender
+            .try_send(message.to_json())
+            .map_err(|e| match e {
+                TrySendError::Full(_) => {
+                    Error::new(ErrorKind::WouldBlock, "Push notification queue is full.")
+                }
+                TrySendError::Disconnected(_) => Error::new(
+                    ErrorKind::BrokenPipe,
+                    "Push notification worker has stopped.",
+                ),
+            })
+    }
+}
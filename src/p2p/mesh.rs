@@ -0,0 +1,569 @@
+use super::{elect_host, HostHandover};
+use crate::transport::{Transport, UdpEndpoint, UdpTransport};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+pub const MAX_MESH_PEERS: usize = 8;
+const MAX_SESSION_LENGTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeshCommand {
+    Join { session: String, port: u16 },
+    State(String),
+    Leave,
+}
+
+impl MeshCommand {
+    pub fn parse(input: &str) -> Option<MeshCommand> {
+        let input = input.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        if let Some(payload) = input.strip_prefix(":mesh state ") {
+            let payload = payload.trim();
+            return if payload.is_empty() {
+                None
+            } else {
+                Some(MeshCommand::State(payload.to_string()))
+            };
+        }
+        let mut parts = input.split_whitespace();
+        match (parts.next()?, parts.next()?, parts.next(), parts.next()) {
+            (":mesh", "join", Some(session), Some(port)) => Some(MeshCommand::Join {
+                session: session.to_string(),
+                port: port.parse().ok()?,
+            }),
+            (":mesh", "leave", None, None) => Some(MeshCommand::Leave),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MeshMember {
+    pub client_id: u32,
+    pub name: String,
+    pub address: SocketAddr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeshSignal {
+    Members {
+        session: String,
+        peers: Vec<(String, SocketAddr)>,
+    },
+    Joined {
+        session: String,
+        name: String,
+        address: SocketAddr,
+    },
+    Left {
+        session: String,
+        name: String,
+    },
+    Host {
+        session: String,
+        term: u32,
+        name: String,
+        address: SocketAddr,
+    },
+    State {
+        session: String,
+        payload: String,
+    },
+}
+
+impl MeshSignal {
+    pub fn encode(&self) -> String {
+        match self {
+            MeshSignal::Members { session, peers } => peers
+                .iter()
+                .fold(format!("MESH {}", session), |line, (name, address)| {
+                    format!("{} {}@{}", line, name, address)
+                }),
+            MeshSignal::Joined {
+                session,
+                name,
+                address,
+            } => format!("MESH+ {} {}@{}", session, name, address),
+            MeshSignal::Left { session, name } => format!("MESH- {} {}", session, name),
+            MeshSignal::Host {
+                session,
+                term,
+                name,
+                address,
+            } => format!("MESHHOST {} {} {}@{}", session, term, name, address),
+            MeshSignal::State { session, payload } => {
+                format!("MESHSTATE {} {}", session, payload)
+            }
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<MeshSignal> {
+        let input = input.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        if let Some(state) = input.strip_prefix("MESHSTATE ") {
+            let (session, payload) = state.split_once(' ')?;
+            return Some(MeshSignal::State {
+                session: session.to_string(),
+                payload: payload.to_string(),
+            });
+        }
+        let mut parts = input.split_whitespace();
+        let kind = parts.next()?;
+        let session = parts.next()?.to_string();
+        let peer = |part: &str| {
+            let (name, address) = part.rsplit_once('@')?;
+            Some((name.to_string(), address.parse().ok()?))
+        };
+        match kind {
+            "MESH" => Some(MeshSignal::Members {
+                session,
+                peers: parts.map(peer).collect::<Option<Vec<_>>>()?,
+            }),
+            "MESH+" => {
+                let (name, address) = peer(parts.next()?)?;
+                Some(MeshSignal::Joined {
+                    session,
+                    name,
+                    address,
+                })
+            }
+            "MESH-" => Some(MeshSignal::Left {
+                session,
+                name: parts.next()?.to_string(),
+            }),
+            "MESHHOST" => {
+                let term = parts.next()?.parse().ok()?;
+                let (name, address) = peer(parts.next()?)?;
+                Some(MeshSignal::Host {
+                    session,
+                    term,
+                    name,
+                    address,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MeshError {
+    InvalidSession,
+    SessionFull,
+    NotInSession,
+    NotHost,
+}
+
+impl Display for MeshError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::InvalidSession => write!(
+                f,
+                "Mesh sessions must be 1-{} alphanumeric characters.",
+                MAX_SESSION_LENGTH
+            ),
+            MeshError::SessionFull => write!(f, "Mesh session is full."),
+            MeshError::NotInSession => write!(f, "You are not in a mesh session."),
+            MeshError::NotHost => write!(f, "Only the host can hand over session state."),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MeshDeparture {
+    pub session: String,
+    pub member: MeshMember,
+    pub remaining: Vec<MeshMember>,
+    pub handover: Option<HostHandover>,
+}
+
+#[derive(Default)]
+struct MeshSession {
+    members: Vec<MeshMember>,
+    host: u32,
+    term: u32,
+    state: Option<String>,
+}
+
+impl MeshSession {
+    fn host(&self) -> Option<&MeshMember> {
+        self.members
+            .iter()
+            .find(|member| member.client_id == self.host)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MeshRegistry {
+    sessions: Arc<Mutex<HashMap<String, MeshSession>>>,
+}
+
+impl MeshRegistry {
+    pub fn new() -> Self {
+        MeshRegistry::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, MeshSession>> {
+        self.sessions.lock().expect("Failed to lock mesh sessions.")
+    }
+
+    pub fn join(&self, session: &str, member: MeshMember) -> Result<Vec<MeshMember>, MeshError> {
+        if session.is_empty()
+            || session.len() > MAX_SESSION_LENGTH
+            || !session
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(MeshError::InvalidSession);
+        }
+        let mut sessions = self.lock();
+        let mesh = sessions.entry(session.to_string()).or_default();
+        if mesh.members.len() >= MAX_MESH_PEERS {
+            return Err(MeshError::SessionFull);
+        }
+        let others = mesh.members.clone();
+        if others.is_empty() {
+            mesh.host = member.client_id;
+            mesh.term = 1;
+        }
+        mesh.members.push(member);
+        Ok(others)
+    }
+
+    pub fn host(&self, session: &str) -> Option<(MeshMember, u32)> {
+        let sessions = self.lock();
+        let mesh = sessions.get(session)?;
+        mesh.host().map(|host| (host.clone(), mesh.term))
+    }
+
+    pub fn store_state(&self, client_id: u32, state: String) -> Result<String, MeshError> {
+        let mut sessions = self.lock();
+        let (session, mesh) = sessions
+            .iter_mut()
+            .find(|(_, mesh)| mesh.members.iter().any(|m| m.client_id == client_id))
+            .ok_or(MeshError::NotInSession)?;
+        if mesh.host != client_id {
+            return Err(MeshError::NotHost);
+        }
+        mesh.state = Some(state);
+        Ok(session.clone())
+    }
+
+    pub fn leave(&self, client_id: u32) -> Option<MeshDeparture> {
+        let mut sessions = self.lock();
+        let (session, mesh) = sessions
+            .iter_mut()
+            .find(|(_, mesh)| mesh.members.iter().any(|m| m.client_id == client_id))?;
+        let session = session.clone();
+        let index = mesh.members.iter().position(|m| m.client_id == client_id)?;
+        let member = mesh.members.remove(index);
+        let remaining = mesh.members.clone();
+        let mut handover = None;
+        if mesh.host == client_id {
+            if let Some(host) = elect_host(&remaining) {
+                mesh.host = host.client_id;
+                mesh.term += 1;
+                handover = Some(HostHandover {
+                    session: session.clone(),
+                    host: host.clone(),
+                    term: mesh.term,
+                    state: mesh.state.clone(),
+                });
+            }
+        }
+        if remaining.is_empty() {
+            sessions.remove(&session);
+        }
+        Some(MeshDeparture {
+            session,
+            member,
+            remaining,
+            handover,
+        })
+    }
+}
+
+pub struct MeshPeer {
+    endpoint: UdpEndpoint,
+    links: Mutex<HashMap<String, Arc<UdpTransport>>>,
+    host: Mutex<Option<(String, u32)>>,
+    handover: Mutex<Option<String>>,
+}
+
+impl MeshPeer {
+    pub fn new(endpoint: UdpEndpoint) -> Self {
+        MeshPeer {
+            endpoint,
+            links: Mutex::new(HashMap::new()),
+            host: Mutex::new(None),
+            handover: Mutex::new(None),
+        }
+    }
+
+    pub fn local_port(&self) -> std::io::Result<u16> {
+        self.endpoint.local_addr().map(|address| address.port())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<UdpTransport>>> {
+        self.links.lock().expect("Failed to lock mesh links.")
+    }
+
+    fn connect(&self, name: &str, address: SocketAddr) {
+        let link = Arc::new(self.endpoint.connect(address));
+        if let Some(previous) = self.lock().insert(name.to_string(), link) {
+            if previous.peer_addr() != address {
+                previous.close();
+            }
+        }
+    }
+
+    pub fn apply(&self, signal: &MeshSignal) {
+        match signal {
+            MeshSignal::Members { peers, .. } => {
+                for (name, address) in peers.iter() {
+                    self.connect(name, *address);
+                }
+            }
+            MeshSignal::Joined { name, address, .. } => self.connect(name, *address),
+            MeshSignal::Left { name, .. } => {
+                if let Some(link) = self.lock().remove(name) {
+                    link.close();
+                }
+            }
+            MeshSignal::Host { name, term, .. } => {
+                let mut host = self.host.lock().expect("Failed to lock mesh host.");
+                if host.as_ref().is_none_or(|(_, current)| current <= term) {
+                    *host = Some((name.clone(), *term));
+                }
+            }
+            MeshSignal::State { payload, .. } => {
+                *self.handover.lock().expect("Failed to lock mesh handover.") =
+                    Some(payload.clone());
+            }
+        }
+    }
+
+    pub fn host(&self) -> Option<String> {
+        self.host
+            .lock()
+            .expect("Failed to lock mesh host.")
+            .as_ref()
+            .map(|(name, _)| name.clone())
+    }
+
+    pub fn take_handover(&self) -> Option<String> {
+        self.handover
+            .lock()
+            .expect("Failed to lock mesh handover.")
+            .take()
+    }
+
+    pub fn link(&self, name: &str) -> Option<Arc<UdpTransport>> {
+        self.lock().get(name).cloned()
+    }
+
+    pub fn peers(&self) -> Vec<String> {
+        let mut peers = self.lock().keys().cloned().collect::<Vec<_>>();
+        peers.sort();
+        peers
+    }
+
+    pub fn broadcast(&self, text: &str) -> usize {
+        self.lock()
+            .values()
+            .filter(|link| link.send_text(text).is_ok())
+            .count()
+    }
+
+    pub fn broadcast_unreliable(&self, text: &str) -> usize {
+        let frame = format!("{}\0", text);
+        self.lock()
+            .values()
+            .filter(|link| link.send_unreliable(frame.as_bytes()).is_ok())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn member(client_id: u32, name: &str, port: u16) -> MeshMember {
+        MeshMember {
+            client_id,
+            name: name.to_string(),
+            address: SocketAddr::from(([127, 0, 0, 1], port)),
+        }
+    }
+
+    #[test]
+    fn registry_hands_joiners_the_existing_members() {
+        let registry = MeshRegistry::new();
+        assert_eq!(registry.join("lobby", member(0, "alice", 4000)), Ok(vec![]));
+        assert_eq!(
+            registry.join("lobby", member(1, "bob", 4001)),
+            Ok(vec![member(0, "alice", 4000)])
+        );
+        assert_eq!(
+            registry.join("bad room", member(2, "carol", 4002)),
+            Err(MeshError::InvalidSession)
+        );
+
+        let departure = registry.leave(0).expect("Alice was not in the mesh.");
+        assert_eq!(departure.session, "lobby");
+        assert_eq!(departure.member, member(0, "alice", 4000));
+        assert_eq!(departure.remaining, vec![member(1, "bob", 4001)]);
+        assert_eq!(registry.leave(0), None);
+        registry.leave(1);
+        assert_eq!(registry.join("lobby", member(2, "carol", 4002)), Ok(vec![]));
+    }
+
+    #[test]
+    fn signals_round_trip_through_text() {
+        let signals = vec![
+            MeshSignal::Members {
+                session: "lobby".to_string(),
+                peers: vec![
+                    ("alice".to_string(), member(0, "", 4000).address),
+                    ("bob".to_string(), member(0, "", 4001).address),
+                ],
+            },
+            MeshSignal::Members {
+                session: "lobby".to_string(),
+                peers: vec![],
+            },
+            MeshSignal::Joined {
+                session: "lobby".to_string(),
+                name: "carol".to_string(),
+                address: member(0, "", 4002).address,
+            },
+            MeshSignal::Left {
+                session: "lobby".to_string(),
+                name: "alice".to_string(),
+            },
+            MeshSignal::Host {
+                session: "lobby".to_string(),
+                term: 2,
+                name: "bob".to_string(),
+                address: member(0, "", 4001).address,
+            },
+            MeshSignal::State {
+                session: "lobby".to_string(),
+                payload: "{\"turn\": 3}".to_string(),
+            },
+        ];
+        for signal in signals {
+            assert_eq!(MeshSignal::parse(&signal.encode()), Some(signal));
+        }
+        assert_eq!(
+            MeshCommand::parse(":mesh join lobby 4000"),
+            Some(MeshCommand::Join {
+                session: "lobby".to_string(),
+                port: 4000
+            })
+        );
+        assert_eq!(MeshCommand::parse(":mesh join lobby"), None);
+        assert_eq!(MeshCommand::parse(":mesh leave"), Some(MeshCommand::Leave));
+        assert_eq!(
+            MeshCommand::parse(":mesh state turn 3"),
+            Some(MeshCommand::State("turn 3".to_string()))
+        );
+        assert_eq!(MeshCommand::parse(":mesh state "), None);
+    }
+
+    fn receive_frames(link: &UdpTransport, count: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = vec![];
+        let mut buffer = [0_u8; 256];
+        while received.iter().filter(|b| **b == 0).count() < count {
+            assert!(Instant::now() < deadline, "The mesh did not deliver.");
+            let size = link.receive(&mut buffer).expect("Failed to receive.");
+            received.extend_from_slice(&buffer[..size]);
+        }
+        received
+            .split(|b| *b == 0)
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| String::from_utf8_lossy(frame).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn peers_form_a_full_mesh_from_signals() {
+        let names = ["alice", "bob", "carol"];
+        let peers = names
+            .iter()
+            .map(|_| MeshPeer::new(UdpEndpoint::bind(("127.0.0.1", 0)).expect("Failed to bind.")))
+            .collect::<Vec<_>>();
+        let addresses = peers
+            .iter()
+            .map(|peer| {
+                SocketAddr::from((
+                    [127, 0, 0, 1],
+                    peer.local_port().expect("Failed to read port."),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        for (index, peer) in peers.iter().enumerate() {
+            peer.apply(&MeshSignal::Members {
+                session: "lobby".to_string(),
+                peers: (0..index)
+                    .map(|other| (names[other].to_string(), addresses[other]))
+                    .collect(),
+            });
+            for earlier in peers.iter().take(index) {
+                earlier.apply(&MeshSignal::Joined {
+                    session: "lobby".to_string(),
+                    name: names[index].to_string(),
+                    address: addresses[index],
+                });
+            }
+        }
+
+        for (index, peer) in peers.iter().enumerate() {
+            assert_eq!(peer.peers().len(), 2);
+            assert_eq!(peer.broadcast(&format!("hello from {}", names[index])), 2);
+        }
+        assert_eq!(peers[0].broadcast_unreliable("tick"), 2);
+
+        let bob_from_alice = peers[1].link("alice").expect("Bob has no link to Alice.");
+        assert_eq!(
+            receive_frames(&bob_from_alice, 2),
+            vec!["hello from alice", "tick"]
+        );
+        let alice_from_carol = peers[0].link("carol").expect("Alice has no link to Carol.");
+        assert_eq!(
+            receive_frames(&alice_from_carol, 1),
+            vec!["hello from carol"]
+        );
+
+        peers[0].apply(&MeshSignal::Left {
+            session: "lobby".to_string(),
+            name: "carol".to_string(),
+        });
+        assert_eq!(peers[0].peers(), vec!["bob"]);
+
+        peers[1].apply(&MeshSignal::Host {
+            session: "lobby".to_string(),
+            term: 2,
+            name: "bob".to_string(),
+            address: addresses[1],
+        });
+        peers[1].apply(&MeshSignal::Host {
+            session: "lobby".to_string(),
+            term: 1,
+            name: "alice".to_string(),
+            address: addresses[0],
+        });
+        assert_eq!(peers[1].host(), Some("bob".to_string()));
+        peers[1].apply(&MeshSignal::State {
+            session: "lobby".to_string(),
+            payload: "turn=3".to_string(),
+        });
+        assert_eq!(peers[1].take_handover(), Some("turn=3".to_string()));
+        assert_eq!(peers[1].take_handover(), None);
+    }
+}
@@ -0,0 +1,103 @@
+use super::{MeshMember, MeshSignal};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostHandover {
+    pub session: String,
+    pub host: MeshMember,
+    pub term: u32,
+    pub state: Option<String>,
+}
+
+impl HostHandover {
+    pub fn state_signal(&self) -> Option<MeshSignal> {
+        self.state.as_ref().map(|payload| MeshSignal::State {
+            session: self.session.clone(),
+            payload: payload.clone(),
+        })
+    }
+
+    pub fn host_signal(&self) -> MeshSignal {
+        MeshSignal::Host {
+            session: self.session.clone(),
+            term: self.term,
+            name: self.host.name.clone(),
+            address: self.host.address,
+        }
+    }
+}
+
+pub fn elect_host(candidates: &[MeshMember]) -> Option<&MeshMember> {
+    candidates.first()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::{MeshDeparture, MeshError, MeshRegistry};
+    use std::net::SocketAddr;
+
+    fn member(client_id: u32, name: &str, port: u16) -> MeshMember {
+        MeshMember {
+            client_id,
+            name: name.to_string(),
+            address: SocketAddr::from(([127, 0, 0, 1], port)),
+        }
+    }
+
+    #[test]
+    fn host_departures_elect_the_longest_standing_peer_and_hand_over_state() {
+        let registry = MeshRegistry::new();
+        for (client_id, name) in ["alice", "bob", "carol"].iter().enumerate() {
+            registry
+                .join(
+                    "lobby",
+                    member(client_id as u32, name, 4000 + client_id as u16),
+                )
+                .expect("Failed to join the mesh.");
+        }
+        assert_eq!(registry.host("lobby"), Some((member(0, "alice", 4000), 1)));
+        assert_eq!(
+            registry.store_state(1, "turn=3".to_string()),
+            Err(MeshError::NotHost)
+        );
+        assert_eq!(
+            registry.store_state(9, "turn=3".to_string()),
+            Err(MeshError::NotInSession)
+        );
+        registry
+            .store_state(0, "turn=3".to_string())
+            .expect("Failed to store state.");
+
+        let departure = registry.leave(2).expect("Carol was not in the mesh.");
+        assert_eq!(departure.handover, None);
+
+        let MeshDeparture {
+            remaining,
+            handover,
+            ..
+        } = registry.leave(0).expect("Alice was not in the mesh.");
+        let handover = handover.expect("No host was elected.");
+        assert_eq!(remaining, vec![member(1, "bob", 4001)]);
+        assert_eq!(
+            handover,
+            HostHandover {
+                session: "lobby".to_string(),
+                host: member(1, "bob", 4001),
+                term: 2,
+                state: Some("turn=3".to_string()),
+            }
+        );
+        assert_eq!(
+            handover.host_signal().encode(),
+            "MESHHOST lobby 2 bob@127.0.0.1:4001"
+        );
+        assert_eq!(
+            handover.state_signal().map(|signal| signal.encode()),
+            Some("MESHSTATE lobby turn=3".to_string())
+        );
+        assert_eq!(registry.host("lobby"), Some((member(1, "bob", 4001), 2)));
+        registry
+            .store_state(1, "turn=4".to_string())
+            .expect("The new host could not store state.");
+    }
+}
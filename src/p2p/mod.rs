@@ -0,0 +1,6 @@
+mod mesh;
+mod migration;
+mod relay;
+pub use mesh::*;
+pub use migration::*;
+pub use relay::*;
@@ -0,0 +1,532 @@
+use crate::config::{env_millis, env_or};
+use crate::events::escape_json;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_RELAY_QUOTA: u64 = 16 * 1024 * 1024;
+pub const DEFAULT_RELAY_MAX_PEERS: usize = 8;
+pub const DEFAULT_RELAY_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+const MAX_SESSION_LENGTH: usize = 64;
+const MAX_DATAGRAM: usize = 65536;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const KIND_JOIN: u8 = 0;
+const KIND_DATA: u8 = 1;
+const KIND_LEAVE: u8 = 2;
+const KIND_JOINED: u8 = 3;
+const KIND_QUOTA_EXCEEDED: u8 = 4;
+const KIND_ERROR: u8 = 5;
+
+#[derive(Clone, Debug)]
+pub struct RelayConfig {
+    pub bind: String,
+    pub quota_bytes: u64,
+    pub max_peers: usize,
+    pub idle_timeout: Duration,
+}
+
+impl RelayConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("RELAY_BIND").ok().map(|bind| RelayConfig {
+            bind,
+            quota_bytes: env_or("RELAY_QUOTA_BYTES", "")
+                .parse()
+                .unwrap_or(DEFAULT_RELAY_QUOTA),
+            max_peers: env_or("RELAY_MAX_PEERS", "")
+                .parse()
+                .unwrap_or(DEFAULT_RELAY_MAX_PEERS),
+            idle_timeout: env_millis("RELAY_IDLE_TIMEOUT_MS").unwrap_or(DEFAULT_RELAY_IDLE_TIMEOUT),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelayPacket {
+    Join(String),
+    Data(Vec<u8>),
+    Leave,
+    Joined(u16),
+    QuotaExceeded,
+    Error(String),
+}
+
+impl RelayPacket {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            RelayPacket::Join(session) => [&[KIND_JOIN], session.as_bytes()].concat(),
+            RelayPacket::Data(payload) => [&[KIND_DATA], payload.as_slice()].concat(),
+            RelayPacket::Leave => vec![KIND_LEAVE],
+            RelayPacket::Joined(peers) => [&[KIND_JOINED][..], &peers.to_be_bytes()].concat(),
+            RelayPacket::QuotaExceeded => vec![KIND_QUOTA_EXCEEDED],
+            RelayPacket::Error(message) => [&[KIND_ERROR], message.as_bytes()].concat(),
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Option<RelayPacket> {
+        let (kind, body) = data.split_first()?;
+        match *kind {
+            KIND_JOIN => Some(RelayPacket::Join(String::from_utf8(body.to_vec()).ok()?)),
+            KIND_DATA => Some(RelayPacket::Data(body.to_vec())),
+            KIND_LEAVE => Some(RelayPacket::Leave),
+            KIND_JOINED => {
+                let peers = body.get(..2)?;
+                Some(RelayPacket::Joined(u16::from_be_bytes([
+                    peers[0], peers[1],
+                ])))
+            }
+            KIND_QUOTA_EXCEEDED => Some(RelayPacket::QuotaExceeded),
+            KIND_ERROR => Some(RelayPacket::Error(
+                String::from_utf8_lossy(body).to_string(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelayStats {
+    pub session: String,
+    pub peers: usize,
+    pub bytes_relayed: u64,
+    pub packets_relayed: u64,
+    pub packets_dropped: u64,
+}
+
+pub fn relay_stats_to_json(stats: &[RelayStats]) -> String {
+    let stats = stats
+        .iter()
+        .map(|stats| {
+            format!(
+                "{{\"session\":\"{}\",\"peers\":{},\"bytes_relayed\":{},\"packets_relayed\":{},\"packets_dropped\":{}}}",
+                escape_json(&stats.session),
+                stats.peers,
+                stats.bytes_relayed,
+                stats.packets_relayed,
+                stats.packets_dropped
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", stats.join(","))
+}
+
+struct RelaySession {
+    peers: Vec<SocketAddr>,
+    bytes_relayed: u64,
+    packets_relayed: u64,
+    packets_dropped: u64,
+    quota_notified: bool,
+    last_active: Instant,
+}
+
+pub struct RelayTable {
+    quota_bytes: u64,
+    max_peers: usize,
+    idle_timeout: Duration,
+    sessions: HashMap<String, RelaySession>,
+    peers: HashMap<SocketAddr, String>,
+}
+
+impl RelayTable {
+    pub fn new(config: &RelayConfig) -> Self {
+        RelayTable {
+            quota_bytes: config.quota_bytes,
+            max_peers: config.max_peers,
+            idle_timeout: config.idle_timeout,
+            sessions: HashMap::new(),
+            peers: HashMap::new(),
+        }
+    }
+
+    pub fn handle(
+        &mut self,
+        from: SocketAddr,
+        packet: RelayPacket,
+        now: Instant,
+    ) -> Vec<(SocketAddr, RelayPacket)> {
+        match packet {
+            RelayPacket::Join(session) => self.join(from, session, now),
+            RelayPacket::Data(payload) => self.forward(from, payload, now),
+            RelayPacket::Leave => {
+                self.leave(from);
+                vec![]
+            }
+            RelayPacket::Joined(_) | RelayPacket::QuotaExceeded | RelayPacket::Error(_) => vec![],
+        }
+    }
+
+    fn join(
+        &mut self,
+        from: SocketAddr,
+        session: String,
+        now: Instant,
+    ) -> Vec<(SocketAddr, RelayPacket)> {
+        if session.is_empty() || session.len() > MAX_SESSION_LENGTH {
+            return vec![(
+                from,
+                RelayPacket::Error(format!(
+                    "Session names must be 1-{} bytes.",
+                    MAX_SESSION_LENGTH
+                )),
+            )];
+        }
+        if self.peers.get(&from) != Some(&session) {
+            self.leave(from);
+        }
+        let max_peers = self.max_peers;
+        let relay_session = self
+            .sessions
+            .entry(session.clone())
+            .or_insert_with(|| RelaySession {
+                peers: vec![],
+                bytes_relayed: 0,
+                packets_relayed: 0,
+                packets_dropped: 0,
+                quota_notified: false,
+                last_active: now,
+            });
+        if !relay_session.peers.contains(&from) {
+            if relay_session.peers.len() >= max_peers {
+                return vec![(from, RelayPacket::Error("Session is full.".to_string()))];
+            }
+            relay_session.peers.push(from);
+        }
+        relay_session.last_active = now;
+        let peers = relay_session.peers.len() as u16;
+        self.peers.insert(from, session);
+        vec![(from, RelayPacket::Joined(peers))]
+    }
+
+    fn forward(
+        &mut self,
+        from: SocketAddr,
+        payload: Vec<u8>,
+        now: Instant,
+    ) -> Vec<(SocketAddr, RelayPacket)> {
+        let quota_bytes = self.quota_bytes;
+        let sessions = &mut self.sessions;
+        let relay_session = match self
+            .peers
+            .get(&from)
+            .and_then(|session| sessions.get_mut(session))
+        {
+            Some(relay_session) => relay_session,
+            None => {
+                return vec![(
+                    from,
+                    RelayPacket::Error("Join a session first.".to_string()),
+                )]
+            }
+        };
+        relay_session.last_active = now;
+        let targets = relay_session
+            .peers
+            .iter()
+            .filter(|peer| **peer != from)
+            .copied()
+            .collect::<Vec<_>>();
+        let bytes = (payload.len() * targets.len()) as u64;
+        if relay_session.bytes_relayed + bytes > quota_bytes {
+            relay_session.packets_dropped += 1;
+            if relay_session.quota_notified {
+                return vec![];
+            }
+            relay_session.quota_notified = true;
+            return vec![(from, RelayPacket::QuotaExceeded)];
+        }
+        relay_session.bytes_relayed += bytes;
+        relay_session.packets_relayed += targets.len() as u64;
+        targets
+            .into_iter()
+            .map(|target| (target, RelayPacket::Data(payload.clone())))
+            .collect()
+    }
+
+    fn leave(&mut self, from: SocketAddr) {
+        let session = match self.peers.remove(&from) {
+            Some(session) => session,
+            None => return,
+        };
+        let empty = match self.sessions.get_mut(&session) {
+            Some(relay_session) => {
+                relay_session.peers.retain(|peer| *peer != from);
+                relay_session.peers.is_empty()
+            }
+            None => false,
+        };
+        if empty {
+            self.sessions.remove(&session);
+        }
+    }
+
+    pub fn expire(&mut self, now: Instant) -> Vec<RelayStats> {
+        let idle_timeout = self.idle_timeout;
+        let expired = self
+            .sessions
+            .iter()
+            .filter(|(_, relay_session)| {
+                now.duration_since(relay_session.last_active) > idle_timeout
+            })
+            .map(|(session, _)| session.clone())
+            .collect::<Vec<_>>();
+        let stats = self
+            .stats()
+            .into_iter()
+            .filter(|stats| expired.contains(&stats.session))
+            .collect();
+        for session in expired.iter() {
+            if let Some(relay_session) = self.sessions.remove(session) {
+                for peer in relay_session.peers.iter() {
+                    self.peers.remove(peer);
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn stats(&self) -> Vec<RelayStats> {
+        let mut stats = self
+            .sessions
+            .iter()
+            .map(|(session, relay_session)| RelayStats {
+                session: session.clone(),
+                peers: relay_session.peers.len(),
+                bytes_relayed: relay_session.bytes_relayed,
+                packets_relayed: relay_session.packets_relayed,
+                packets_dropped: relay_session.packets_dropped,
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| a.session.cmp(&b.session));
+        stats
+    }
+}
+
+#[derive(Clone)]
+pub struct RelayServer {
+    table: Arc<Mutex<RelayTable>>,
+    address: SocketAddr,
+}
+
+impl RelayServer {
+    pub fn spawn(config: RelayConfig) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(config.bind.as_str())?;
+        socket.set_read_timeout(Some(POLL_INTERVAL.min(config.idle_timeout)))?;
+        let server = RelayServer {
+            table: Arc::new(Mutex::new(RelayTable::new(&config))),
+            address: socket.local_addr()?,
+        };
+        let relay = server.clone();
+        std::thread::spawn(move || relay.run(socket));
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn stats(&self) -> Vec<RelayStats> {
+        self.lock().stats()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RelayTable> {
+        self.table.lock().expect("Failed to lock relay table.")
+    }
+
+    fn run(self, socket: UdpSocket) {
+        let mut buffer = vec![0_u8; MAX_DATAGRAM];
+        loop {
+            let outgoing = match socket.recv_from(&mut buffer) {
+                Ok((size, from)) => match RelayPacket::decode(&buffer[..size]) {
+                    Some(packet) => self.lock().handle(from, packet, Instant::now()),
+                    None => continue,
+                },
+                Err(_) => vec![],
+            };
+            for (target, packet) in outgoing {
+                if let Err(e) = socket.send_to(&packet.encode(), target) {
+                    eprintln!("{}へのリレー送信に失敗しました：{}\n", target, e);
+                }
+            }
+            for stats in self.lock().expire(Instant::now()) {
+                println!(
+                    "リレーセッション{}を終了しました（{}バイト、{}パケット、破棄{}）\n",
+                    &stats.session,
+                    stats.bytes_relayed,
+                    stats.packets_relayed,
+                    stats.packets_dropped
+                );
+            }
+        }
+    }
+}
+
+pub struct RelayClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+}
+
+impl RelayClient {
+    pub fn connect(server: SocketAddr, timeout: Duration) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_read_timeout(Some(timeout))?;
+        Ok(RelayClient { socket, server })
+    }
+
+    pub fn join(&self, session: &str) -> std::io::Result<u16> {
+        self.send_packet(&RelayPacket::Join(session.to_string()))?;
+        loop {
+            match self.receive()? {
+                RelayPacket::Joined(peers) => return Ok(peers),
+                RelayPacket::Error(message) => {
+                    return Err(Error::new(ErrorKind::PermissionDenied, message))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn send(&self, payload: &[u8]) -> std::io::Result<()> {
+        self.send_packet(&RelayPacket::Data(payload.to_vec()))
+    }
+
+    pub fn leave(&self) -> std::io::Result<()> {
+        self.send_packet(&RelayPacket::Leave)
+    }
+
+    pub fn receive(&self) -> std::io::Result<RelayPacket> {
+        let mut buffer = vec![0_u8; MAX_DATAGRAM];
+        loop {
+            let (size, from) = self.socket.recv_from(&mut buffer)?;
+            if from != self.server {
+                continue;
+            }
+            if let Some(packet) = RelayPacket::decode(&buffer[..size]) {
+                return Ok(packet);
+            }
+        }
+    }
+
+    fn send_packet(&self, packet: &RelayPacket) -> std::io::Result<()> {
+        self.socket
+            .send_to(&packet.encode(), self.server)
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(quota_bytes: u64, max_peers: usize) -> RelayConfig {
+        RelayConfig {
+            bind: "127.0.0.1:0".to_string(),
+            quota_bytes,
+            max_peers,
+            idle_timeout: Duration::from_secs(30),
+        }
+    }
+
+    fn address(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn sessions_forward_within_their_quota_and_track_stats() {
+        let now = Instant::now();
+        let mut table = RelayTable::new(&config(8, 2));
+        let (alice, bob, carol) = (address(1), address(2), address(3));
+
+        assert_eq!(
+            table.handle(alice, RelayPacket::Data(b"early".to_vec()), now),
+            vec![(
+                alice,
+                RelayPacket::Error("Join a session first.".to_string())
+            )]
+        );
+        assert_eq!(
+            table.handle(alice, RelayPacket::Join("match".to_string()), now),
+            vec![(alice, RelayPacket::Joined(1))]
+        );
+        assert_eq!(
+            table.handle(bob, RelayPacket::Join("match".to_string()), now),
+            vec![(bob, RelayPacket::Joined(2))]
+        );
+        assert_eq!(
+            table.handle(carol, RelayPacket::Join("match".to_string()), now),
+            vec![(carol, RelayPacket::Error("Session is full.".to_string()))]
+        );
+
+        assert_eq!(
+            table.handle(alice, RelayPacket::Data(b"hello".to_vec()), now),
+            vec![(bob, RelayPacket::Data(b"hello".to_vec()))]
+        );
+        assert_eq!(
+            table.handle(bob, RelayPacket::Data(b"again".to_vec()), now),
+            vec![(bob, RelayPacket::QuotaExceeded)]
+        );
+        assert!(table
+            .handle(bob, RelayPacket::Data(b"again".to_vec()), now)
+            .is_empty());
+        assert_eq!(
+            table.stats(),
+            vec![RelayStats {
+                session: "match".to_string(),
+                peers: 2,
+                bytes_relayed: 5,
+                packets_relayed: 1,
+                packets_dropped: 2,
+            }]
+        );
+
+        table.handle(alice, RelayPacket::Leave, now);
+        table.handle(bob, RelayPacket::Leave, now);
+        assert!(table.stats().is_empty());
+    }
+
+    #[test]
+    fn idle_sessions_expire_with_their_final_stats() {
+        let now = Instant::now();
+        let mut table = RelayTable::new(&config(DEFAULT_RELAY_QUOTA, 2));
+        table.handle(address(1), RelayPacket::Join("idle".to_string()), now);
+
+        assert!(table.expire(now + Duration::from_secs(10)).is_empty());
+        let expired = table.expire(now + Duration::from_secs(31));
+
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].session, "idle");
+        assert!(table.stats().is_empty());
+        assert_eq!(
+            table.handle(address(1), RelayPacket::Data(vec![1]), now),
+            vec![(
+                address(1),
+                RelayPacket::Error("Join a session first.".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn peers_relay_datagrams_through_the_server() {
+        let server =
+            RelayServer::spawn(config(DEFAULT_RELAY_QUOTA, 2)).expect("Failed to start the relay.");
+        let timeout = Duration::from_secs(5);
+        let alice = RelayClient::connect(server.local_addr(), timeout).expect("Failed to bind.");
+        let bob = RelayClient::connect(server.local_addr(), timeout).expect("Failed to bind.");
+
+        assert_eq!(alice.join("match").expect("Failed to join."), 1);
+        assert_eq!(bob.join("match").expect("Failed to join."), 2);
+        alice.send(b"ping").expect("Failed to send.");
+        assert_eq!(
+            bob.receive().expect("Failed to receive."),
+            RelayPacket::Data(b"ping".to_vec())
+        );
+        bob.send(b"pong").expect("Failed to send.");
+        assert_eq!(
+            alice.receive().expect("Failed to receive."),
+            RelayPacket::Data(b"pong".to_vec())
+        );
+        assert_eq!(server.stats()[0].bytes_relayed, 8);
+    }
+}
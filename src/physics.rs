@@ -0,0 +1,399 @@
+use crate::config::{env_millis, env_or};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const DEFAULT_PHYSICS_TICK: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Debug)]
+pub struct PhysicsConfig {
+    pub tick_interval: Duration,
+    pub width: f64,
+    pub height: f64,
+    pub gravity: f64,
+    pub restitution: f64,
+    pub radius: f64,
+    pub initial_bodies: usize,
+    pub max_bodies: usize,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            tick_interval: DEFAULT_PHYSICS_TICK,
+            width: 800.0,
+            height: 600.0,
+            gravity: 500.0,
+            restitution: 0.6,
+            radius: 10.0,
+            initial_bodies: 0,
+            max_bodies: 256,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    pub fn from_env() -> Self {
+        let defaults = PhysicsConfig::default();
+        let positive = |name: &str, default: f64| {
+            env_or(name, "")
+                .parse()
+                .ok()
+                .filter(|value: &f64| *value > 0.0)
+                .unwrap_or(default)
+        };
+        PhysicsConfig {
+            tick_interval: env_millis("PHYSICS_TICK_MS")
+                .filter(|interval| !interval.is_zero())
+                .unwrap_or(defaults.tick_interval),
+            width: positive("PHYSICS_WIDTH", defaults.width),
+            height: positive("PHYSICS_HEIGHT", defaults.height),
+            gravity: env_or("PHYSICS_GRAVITY", "")
+                .parse()
+                .unwrap_or(defaults.gravity),
+            restitution: env_or("PHYSICS_RESTITUTION", "")
+                .parse()
+                .ok()
+                .filter(|value: &f64| (0.0..=1.0).contains(value))
+                .unwrap_or(defaults.restitution),
+            radius: positive("PHYSICS_RADIUS", defaults.radius),
+            initial_bodies: env_or("PHYSICS_BODIES", "").parse().unwrap_or(0),
+            max_bodies: env_or("PHYSICS_MAX_BODIES", "")
+                .parse()
+                .unwrap_or(defaults.max_bodies),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PhysicsCommand {
+    Push(f64, f64),
+    Spawn,
+    Resync,
+}
+
+impl PhysicsCommand {
+    pub fn parse(input: &str) -> Option<PhysicsCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        let component = |value: &str| value.parse().ok().filter(|value: &f64| value.is_finite());
+        match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+            (":push", Some(dx), Some(dy), None) => {
+                Some(PhysicsCommand::Push(component(dx)?, component(dy)?))
+            }
+            (":spawn", None, None, None) => Some(PhysicsCommand::Spawn),
+            (":resync", None, None, None) => Some(PhysicsCommand::Resync),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Body {
+    pub id: u32,
+    pub position: (f64, f64),
+    pub velocity: (f64, f64),
+    pub radius: f64,
+}
+
+impl Body {
+    pub fn quantized(&self) -> (i32, i32) {
+        (
+            self.position.0.round() as i32,
+            self.position.1.round() as i32,
+        )
+    }
+}
+
+pub struct World {
+    config: PhysicsConfig,
+    bodies: Vec<Body>,
+    next_id: u32,
+    tick: u64,
+}
+
+impl World {
+    pub fn new(config: PhysicsConfig) -> Self {
+        let mut world = World {
+            config,
+            bodies: vec![],
+            next_id: 0,
+            tick: 0,
+        };
+        for _ in 0..world.config.initial_bodies {
+            world.spawn_next();
+        }
+        world
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    pub fn spawn(&mut self, position: (f64, f64), velocity: (f64, f64)) -> u32 {
+        self.next_id += 1;
+        let radius = self.config.radius;
+        let max_x = (self.config.width - radius).max(radius);
+        let max_y = (self.config.height - radius).max(radius);
+        self.bodies.push(Body {
+            id: self.next_id,
+            position: (
+                position.0.clamp(radius, max_x),
+                position.1.clamp(radius, max_y),
+            ),
+            velocity,
+            radius,
+        });
+        self.next_id
+    }
+
+    pub fn spawn_next(&mut self) -> u32 {
+        let spacing = self.config.radius * 3.0;
+        let columns = ((self.config.width / spacing) as u32).max(1);
+        let x = spacing * (f64::from(self.next_id % columns) + 0.5);
+        let drift = if x < self.config.width / 2.0 {
+            40.0
+        } else {
+            -40.0
+        };
+        self.spawn((x, self.config.radius), (drift, 0.0))
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.bodies.len();
+        self.bodies.retain(|body| body.id != id);
+        self.bodies.len() != before
+    }
+
+    pub fn push(&mut self, id: u32, impulse: (f64, f64)) -> bool {
+        match self.bodies.iter_mut().find(|body| body.id == id) {
+            Some(body) => {
+                body.velocity.0 += impulse.0;
+                body.velocity.1 += impulse.1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn step(&mut self, dt: f64) {
+        self.tick += 1;
+        let settle_speed = self.config.gravity.abs() * dt * 2.0;
+        for body in self.bodies.iter_mut() {
+            body.velocity.1 += self.config.gravity * dt;
+            body.position.0 += body.velocity.0 * dt;
+            body.position.1 += body.velocity.1 * dt;
+        }
+        self.resolve_collisions();
+        for body in self.bodies.iter_mut() {
+            bounce(
+                &mut body.position.0,
+                &mut body.velocity.0,
+                body.radius,
+                self.config.width,
+                self.config.restitution,
+            );
+            let landed = bounce(
+                &mut body.position.1,
+                &mut body.velocity.1,
+                body.radius,
+                self.config.height,
+                self.config.restitution,
+            );
+            if landed && body.velocity.1.abs() < settle_speed {
+                body.velocity.1 = 0.0;
+                body.velocity.0 *= self.config.restitution;
+            }
+        }
+    }
+
+    fn resolve_collisions(&mut self) {
+        let restitution = self.config.restitution;
+        for i in 0..self.bodies.len() {
+            let (head, tail) = self.bodies.split_at_mut(i + 1);
+            let a = &mut head[i];
+            for b in tail.iter_mut() {
+                let dx = b.position.0 - a.position.0;
+                let dy = b.position.1 - a.position.1;
+                let distance = dx.hypot(dy);
+                let overlap = a.radius + b.radius - distance;
+                if overlap <= 0.0 {
+                    continue;
+                }
+                let normal = if distance > f64::EPSILON {
+                    (dx / distance, dy / distance)
+                } else {
+                    (1.0, 0.0)
+                };
+                a.position.0 -= normal.0 * overlap / 2.0;
+                a.position.1 -= normal.1 * overlap / 2.0;
+                b.position.0 += normal.0 * overlap / 2.0;
+                b.position.1 += normal.1 * overlap / 2.0;
+
+                let closing = (b.velocity.0 - a.velocity.0) * normal.0
+                    + (b.velocity.1 - a.velocity.1) * normal.1;
+                if closing >= 0.0 {
+                    continue;
+                }
+                let impulse = -(1.0 + restitution) * closing / 2.0;
+                a.velocity.0 -= normal.0 * impulse;
+                a.velocity.1 -= normal.1 * impulse;
+                b.velocity.0 += normal.0 * impulse;
+                b.velocity.1 += normal.1 * impulse;
+            }
+        }
+    }
+}
+
+fn bounce(
+    position: &mut f64,
+    velocity: &mut f64,
+    radius: f64,
+    limit: f64,
+    restitution: f64,
+) -> bool {
+    if *position - radius < 0.0 {
+        *position = radius;
+        *velocity = velocity.abs() * restitution;
+        false
+    } else if *position + radius > limit {
+        *position = limit - radius;
+        *velocity = -velocity.abs() * restitution;
+        true
+    } else {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct DeltaEncoder {
+    baseline: Option<HashMap<u32, (i32, i32)>>,
+}
+
+impl DeltaEncoder {
+    pub fn new() -> Self {
+        DeltaEncoder::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.baseline = None;
+    }
+
+    pub fn encode(&mut self, tick: u64, bodies: &[Body]) -> Option<String> {
+        let current = bodies
+            .iter()
+            .map(|body| (body.id, body.quantized()))
+            .collect::<HashMap<_, _>>();
+        let baseline = match self.baseline.replace(current) {
+            Some(baseline) => baseline,
+            None => {
+                return Some(
+                    std::iter::once(format!("FULL {}", tick))
+                        .chain(bodies.iter().map(|body| {
+                            let (x, y) = body.quantized();
+                            format!("{}:{},{}", body.id, x, y)
+                        }))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                )
+            }
+        };
+        let changed = bodies.iter().filter_map(|body| {
+            let position = body.quantized();
+            (baseline.get(&body.id) != Some(&position))
+                .then(|| format!("{}:{},{}", body.id, position.0, position.1))
+        });
+        let current = self.baseline.as_ref().expect("Missing delta baseline.");
+        let mut removed = baseline
+            .keys()
+            .filter(|id| !current.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        removed.sort_unstable();
+        let entries = changed
+            .chain(removed.into_iter().map(|id| format!("-{}", id)))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return None;
+        }
+        Some(format!("DELTA {} {}", tick, entries.join(" ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.05;
+
+    fn config() -> PhysicsConfig {
+        PhysicsConfig {
+            width: 100.0,
+            height: 100.0,
+            ..PhysicsConfig::default()
+        }
+    }
+
+    #[test]
+    fn bodies_fall_collide_and_settle_into_quiet_deltas() {
+        assert_eq!(
+            PhysicsCommand::parse(":push 3 -2.5\0"),
+            Some(PhysicsCommand::Push(3.0, -2.5))
+        );
+        assert_eq!(PhysicsCommand::parse(":push 3"), None);
+        assert_eq!(PhysicsCommand::parse(":push inf 0"), None);
+
+        let mut world = World::new(config());
+        let falling = world.spawn((50.0, 10.0), (0.0, 0.0));
+        let left = world.spawn((20.0, 90.0), (30.0, 0.0));
+        let right = world.spawn((40.0, 90.0), (-30.0, 0.0));
+        let mut encoder = DeltaEncoder::new();
+        assert_eq!(
+            encoder.encode(world.tick(), world.bodies()),
+            Some("FULL 0 1:50,10 2:20,90 3:40,90".to_string())
+        );
+
+        world.step(DT);
+        assert_eq!(
+            encoder.encode(world.tick(), world.bodies()),
+            Some("DELTA 1 1:50,11".to_string())
+        );
+
+        for _ in 0..200 {
+            world.step(DT);
+            for body in world.bodies() {
+                assert!(body.position.0 >= body.radius && body.position.0 <= 100.0 - body.radius);
+                assert!(body.position.1 >= body.radius && body.position.1 <= 100.0 - body.radius);
+            }
+        }
+        let bodies = world.bodies();
+        let gap = (bodies[2].position.0 - bodies[1].position.0).abs();
+        assert!(gap >= bodies[1].radius * 2.0 - 0.01);
+        assert_eq!(bodies[0].quantized().1, 90);
+
+        encoder.encode(world.tick(), world.bodies());
+        world.step(DT);
+        assert_eq!(encoder.encode(world.tick(), world.bodies()), None);
+
+        assert!(world.push(falling, (0.0, -200.0)));
+        assert!(world.remove(right));
+        assert!(!world.push(right, (1.0, 0.0)));
+        world.step(DT);
+        let delta = encoder
+            .encode(world.tick(), world.bodies())
+            .expect("Missing delta.");
+        assert!(delta.starts_with(&format!("DELTA {} 1:", world.tick())));
+        assert!(!delta.contains(&format!(" {}:", left)));
+        assert!(delta.ends_with(" -3"));
+
+        encoder.reset();
+        assert!(encoder
+            .encode(world.tick(), world.bodies())
+            .expect("Missing snapshot.")
+            .starts_with("FULL "));
+    }
+}
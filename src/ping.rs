@@ -0,0 +1,152 @@
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerPing {
+    pub server: String,
+    pub address: Option<IpAddr>,
+    pub rtt: Option<Duration>,
+}
+
+fn resolve(server: &str) -> std::io::Result<IpAddr> {
+    server
+        .to_socket_addrs()
+        .or_else(|_| (server, 0).to_socket_addrs())?
+        .map(|address| address.ip())
+        .find(IpAddr::is_ipv4)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No IPv4 address to ping."))
+}
+
+pub fn rank_servers<F>(servers: &[String], mut ping: F) -> Vec<ServerPing>
+where
+    F: FnMut(IpAddr) -> std::io::Result<Duration>,
+{
+    let mut ranked = servers
+        .iter()
+        .map(|server| {
+            let address = resolve(server).ok();
+            ServerPing {
+                server: server.clone(),
+                address,
+                rtt: address.and_then(|address| ping(address).ok()),
+            }
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|entry| (entry.rtt.is_none(), entry.rtt));
+    ranked
+}
+
+#[cfg(feature = "winsock")]
+mod icmp {
+    use std::io::{Error, ErrorKind};
+    use std::net::IpAddr;
+    use std::time::Duration;
+    use winapi::shared::ipexport::{ICMP_ECHO_REPLY, IP_SUCCESS};
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::icmpapi::{IcmpCloseHandle, IcmpCreateFile, IcmpSendEcho};
+    use winapi::um::winnt::HANDLE;
+
+    const PAYLOAD: &[u8] = b"online_game_programming";
+
+    pub struct IcmpPinger {
+        handle: HANDLE,
+    }
+
+    unsafe impl Send for IcmpPinger {}
+
+    impl IcmpPinger {
+        pub fn new() -> std::io::Result<Self> {
+            let handle = unsafe { IcmpCreateFile() };
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(Error::last_os_error());
+            }
+            Ok(IcmpPinger { handle })
+        }
+
+        pub fn ping(&self, address: IpAddr, timeout: Duration) -> std::io::Result<Duration> {
+            let address = match address {
+                IpAddr::V4(address) => address,
+                IpAddr::V6(_) => {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        "ICMP echo is only implemented for IPv4.",
+                    ))
+                }
+            };
+            let mut reply = vec![0_u8; std::mem::size_of::<ICMP_ECHO_REPLY>() + PAYLOAD.len() + 8];
+            let replies = unsafe {
+                IcmpSendEcho(
+                    self.handle,
+                    u32::from_ne_bytes(address.octets()),
+                    PAYLOAD.as_ptr() as *mut _,
+                    PAYLOAD.len() as u16,
+                    std::ptr::null_mut(),
+                    reply.as_mut_ptr() as *mut _,
+                    reply.len() as DWORD,
+                    timeout.as_millis() as DWORD,
+                )
+            };
+            if replies == 0 {
+                return Err(Error::last_os_error());
+            }
+            let reply =
+                unsafe { std::ptr::read_unaligned(reply.as_ptr() as *const ICMP_ECHO_REPLY) };
+            if reply.Status != IP_SUCCESS {
+                return Err(Error::other(format!(
+                    "ICMP echo failed with status {}",
+                    reply.Status
+                )));
+            }
+            Ok(Duration::from_millis(reply.RoundTripTime as u64))
+        }
+    }
+
+    impl Drop for IcmpPinger {
+        fn drop(&mut self) {
+            unsafe {
+                IcmpCloseHandle(self.handle);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "winsock")]
+pub use icmp::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn servers_are_ranked_by_rtt_with_unreachable_ones_last() {
+        let servers = vec![
+            "127.0.0.3:7000".to_string(),
+            "127.0.0.1:7000".to_string(),
+            "not a server".to_string(),
+            "127.0.0.2".to_string(),
+        ];
+        let ranked = rank_servers(&servers, |address| match address.to_string().as_str() {
+            "127.0.0.1" => Ok(Duration::from_millis(40)),
+            "127.0.0.2" => Ok(Duration::from_millis(12)),
+            _ => Err(Error::new(ErrorKind::TimedOut, "timed out")),
+        });
+
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|entry| (entry.server.as_str(), entry.rtt.map(|rtt| rtt.as_millis())))
+                .collect::<Vec<_>>(),
+            vec![
+                ("127.0.0.2", Some(12)),
+                ("127.0.0.1:7000", Some(40)),
+                ("127.0.0.3:7000", None),
+                ("not a server", None),
+            ]
+        );
+        assert_eq!(ranked[3].address, None);
+    }
+}
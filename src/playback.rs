@@ -0,0 +1,163 @@
+use crate::recorder::{PacketKind, RecordedPacket};
+use crate::server::{ClientContext, Flow, ServerHandler};
+use crate::transport::Transport;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaybackTiming {
+    Original,
+    AsFastAsPossible,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlaybackConfig {
+    pub path: PathBuf,
+    pub timing: PlaybackTiming,
+}
+
+impl PlaybackConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("PLAYBACK_PATH")
+            .ok()
+            .map(|path| PlaybackConfig {
+                path: PathBuf::from(path),
+                timing: match std::env::var("PLAYBACK_TIMING").as_deref() {
+                    Ok("fast") => PlaybackTiming::AsFastAsPossible,
+                    _ => PlaybackTiming::Original,
+                },
+            })
+    }
+}
+
+pub struct ReplayTransport {
+    client_id: u32,
+    closed: AtomicBool,
+}
+
+impl ReplayTransport {
+    pub fn new(client_id: u32) -> Self {
+        ReplayTransport {
+            client_id,
+            closed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn receive(&self, _buffer: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(Error::from(ErrorKind::NotConnected));
+        }
+        for frame in data.split(|b| *b == 0).filter(|frame| !frame.is_empty()) {
+            println!(
+                "再生：クライアント{}へ送信：{}\n",
+                self.client_id,
+                String::from_utf8_lossy(frame)
+            );
+        }
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Playback {
+    pub packets: Vec<RecordedPacket>,
+}
+
+impl Playback {
+    pub fn parse(data: &str) -> Playback {
+        Playback {
+            packets: data.lines().filter_map(RecordedPacket::decode).collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Playback> {
+        Ok(Playback::parse(&std::fs::read_to_string(path)?))
+    }
+
+    fn pace(timing: PlaybackTiming, previous: &mut u64, packet: &RecordedPacket) {
+        if timing == PlaybackTiming::Original {
+            let delay = packet.timestamp_ms.saturating_sub(*previous);
+            std::thread::sleep(Duration::from_millis(delay));
+            *previous = packet.timestamp_ms;
+        }
+    }
+
+    fn first_timestamp(&self) -> u64 {
+        self.packets
+            .first()
+            .map(|packet| packet.timestamp_ms)
+            .unwrap_or(0)
+    }
+
+    pub fn replay<F>(
+        &self,
+        handler: &dyn ServerHandler,
+        timing: PlaybackTiming,
+        mut connect: F,
+    ) -> usize
+    where
+        F: FnMut(u32, &str) -> Option<ClientContext>,
+    {
+        let disconnect = |client: ClientContext| {
+            client.transport.close();
+            handler.on_client_disconnected(&client);
+        };
+        let mut sessions = HashMap::new();
+        let mut previous = self.first_timestamp();
+        let mut delivered = 0;
+        for packet in self.packets.iter() {
+            Playback::pace(timing, &mut previous, packet);
+            match packet.kind {
+                PacketKind::Join => {
+                    if let Some(client) = connect(packet.client_id, &packet.payload) {
+                        handler.on_client_connected(&client);
+                        if let Some(replaced) = sessions.insert(packet.client_id, client) {
+                            disconnect(replaced);
+                        }
+                    }
+                }
+                PacketKind::Message => {
+                    let client = match sessions.get(&packet.client_id) {
+                        Some(client) => client,
+                        None => continue,
+                    };
+                    delivered += 1;
+                    let message = packet.payload.trim_end_matches('\0');
+                    if handler.on_message(client, message) == Flow::Disconnect {
+                        if let Some(client) = sessions.remove(&packet.client_id) {
+                            disconnect(client);
+                        }
+                    }
+                }
+                PacketKind::Leave => {
+                    if let Some(client) = sessions.remove(&packet.client_id) {
+                        disconnect(client);
+                    }
+                }
+            }
+        }
+        let mut remaining = sessions.into_iter().collect::<Vec<_>>();
+        remaining.sort_by_key(|(client_id, _)| *client_id);
+        for (_, client) in remaining.into_iter() {
+            disconnect(client);
+        }
+        delivered
+    }
+}
@@ -0,0 +1,87 @@
+use super::{ChatContext, Plugin, PluginAction};
+use crate::config::{env_millis, env_or};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_MAX_MESSAGES: usize = 5;
+const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+pub struct FloodGuard {
+    max_messages: usize,
+    window: Duration,
+    history: Mutex<HashMap<u32, VecDeque<Instant>>>,
+}
+
+impl FloodGuard {
+    pub fn new(max_messages: usize, window: Duration) -> Self {
+        FloodGuard {
+            max_messages,
+            window,
+            history: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        FloodGuard::new(
+            env_or("FLOOD_MAX_MESSAGES", "")
+                .parse()
+                .unwrap_or(DEFAULT_MAX_MESSAGES),
+            env_millis("FLOOD_WINDOW_MS").unwrap_or(DEFAULT_WINDOW),
+        )
+    }
+}
+
+impl Plugin for FloodGuard {
+    fn name(&self) -> &str {
+        "flood"
+    }
+
+    fn on_chat(&self, context: &ChatContext<'_>, _message: &str) -> PluginAction {
+        let now = Instant::now();
+        let mut history = self.history.lock().expect("Failed to lock flood history.");
+        let sent = history.entry(context.client_id).or_default();
+        while sent
+            .front()
+            .is_some_and(|sent_at| now.duration_since(*sent_at) >= self.window)
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= self.max_messages {
+            println!(
+                "クライアント{}（{}）の{}での連投を破棄しました\n",
+                context.client_id,
+                context.nickname.unwrap_or("-"),
+                context.room
+            );
+            return PluginAction::Drop;
+        }
+        sent.push_back(now);
+        PluginAction::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_beyond_the_limit_are_dropped_per_client() {
+        let guard = FloodGuard::new(2, Duration::from_secs(60));
+        let alice = ChatContext {
+            client_id: 0,
+            nickname: Some("alice"),
+            room: "lobby",
+        };
+        let bob = ChatContext {
+            client_id: 1,
+            nickname: None,
+            room: "lobby",
+        };
+
+        assert_eq!(guard.on_chat(&alice, "1"), PluginAction::Continue);
+        assert_eq!(guard.on_chat(&alice, "2"), PluginAction::Continue);
+        assert_eq!(guard.on_chat(&alice, "3"), PluginAction::Drop);
+        assert_eq!(guard.on_chat(&bob, "1"), PluginAction::Continue);
+    }
+}
@@ -0,0 +1,289 @@
+use super::{ChatContext, Plugin, PluginAction, ServerRequest};
+use crate::config::env_or;
+use mlua::{Function, IntoLuaMulti, Lua, Value};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(std::io::Error),
+    Lua(mlua::Error),
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::Io(e) => write!(f, "Failed to read script: {}", e),
+            ScriptError::Lua(e) => write!(f, "Lua error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<std::io::Error> for ScriptError {
+    fn from(e: std::io::Error) -> Self {
+        ScriptError::Io(e)
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(e: mlua::Error) -> Self {
+        ScriptError::Lua(e)
+    }
+}
+
+pub struct LuaPlugin {
+    lua: Mutex<Lua>,
+    requests: Arc<Mutex<Vec<ServerRequest>>>,
+}
+
+impl LuaPlugin {
+    pub fn from_env() -> Result<Self, ScriptError> {
+        LuaPlugin::load(env_or("LUA_SCRIPT", "server.lua"))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+        LuaPlugin::from_source(&source, &path.as_ref().display().to_string())
+    }
+
+    pub fn from_source(source: &str, name: &str) -> Result<Self, ScriptError> {
+        let lua = Lua::new();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let server = lua.create_table()?;
+
+        let queue = requests.clone();
+        server.set(
+            "broadcast",
+            lua.create_function(move |_, text: String| {
+                push_request(&queue, ServerRequest::Broadcast(text));
+                Ok(())
+            })?,
+        )?;
+        let queue = requests.clone();
+        server.set(
+            "kick",
+            lua.create_function(move |_, client_id: u32| {
+                push_request(&queue, ServerRequest::Kick(client_id));
+                Ok(())
+            })?,
+        )?;
+        let queue = requests.clone();
+        server.set(
+            "set_motd",
+            lua.create_function(move |_, motd: String| {
+                push_request(&queue, ServerRequest::SetMotd(motd));
+                Ok(())
+            })?,
+        )?;
+        let queue = requests.clone();
+        server.set(
+            "start_match",
+            lua.create_function(move |_, room: String| {
+                push_request(&queue, ServerRequest::StartMatch(room));
+                Ok(())
+            })?,
+        )?;
+        let queue = requests.clone();
+        server.set(
+            "score",
+            lua.create_function(move |_, (client_id, points): (u32, i64)| {
+                push_request(&queue, ServerRequest::Score { client_id, points });
+                Ok(())
+            })?,
+        )?;
+        let queue = requests.clone();
+        server.set(
+            "end_match",
+            lua.create_function(move |_, room: String| {
+                push_request(&queue, ServerRequest::EndMatch(room));
+                Ok(())
+            })?,
+        )?;
+        lua.globals().set("server", server)?;
+        lua.load(source).set_name(name).exec()?;
+
+        Ok(LuaPlugin {
+            lua: Mutex::new(lua),
+            requests,
+        })
+    }
+
+    fn call<A: for<'lua> IntoLuaMulti<'lua>>(&self, hook: &str, args: A) -> Option<ChatVerdict> {
+        let lua = self.lua.lock().expect("Failed to lock Lua state.");
+        let result = lua
+            .globals()
+            .get::<_, Option<Function>>(hook)
+            .and_then(|function| match function {
+                Some(function) => function.call::<_, Value>(args).map(ChatVerdict::from),
+                None => Ok(ChatVerdict::Continue),
+            });
+        match result {
+            Ok(verdict) => Some(verdict),
+            Err(e) => {
+                eprintln!("Luaの{}でエラーが発生しました：{}\n", hook, e);
+                None
+            }
+        }
+    }
+}
+
+fn push_request(requests: &Mutex<Vec<ServerRequest>>, request: ServerRequest) {
+    requests
+        .lock()
+        .expect("Failed to lock script requests.")
+        .push(request);
+}
+
+enum ChatVerdict {
+    Continue,
+    Rewrite(String),
+    Drop,
+}
+
+impl From<Value<'_>> for ChatVerdict {
+    fn from(value: Value<'_>) -> Self {
+        match value {
+            Value::Boolean(false) => ChatVerdict::Drop,
+            Value::String(text) => match text.to_str() {
+                Ok(text) => ChatVerdict::Rewrite(text.to_string()),
+                Err(_) => ChatVerdict::Continue,
+            },
+            _ => ChatVerdict::Continue,
+        }
+    }
+}
+
+impl Plugin for LuaPlugin {
+    fn name(&self) -> &str {
+        "lua"
+    }
+
+    fn on_chat(&self, context: &ChatContext<'_>, message: &str) -> PluginAction {
+        let args = (
+            context.client_id,
+            context.room.to_string(),
+            message.to_string(),
+            context.nickname.map(str::to_string),
+        );
+        match self.call("on_chat", args) {
+            Some(ChatVerdict::Rewrite(message)) => PluginAction::Rewrite(message),
+            Some(ChatVerdict::Drop) => PluginAction::Drop,
+            Some(ChatVerdict::Continue) | None => PluginAction::Continue,
+        }
+    }
+
+    fn on_join(&self, client_id: u32, address: &str) {
+        let _ = self.call("on_join", (client_id, address.to_string()));
+    }
+
+    fn on_tick(&self) {
+        let _ = self.call("on_tick", ());
+    }
+
+    fn drain_requests(&self) -> Vec<ServerRequest> {
+        std::mem::take(
+            &mut *self
+                .requests
+                .lock()
+                .expect("Failed to lock script requests."),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        local ticks = 0
+
+        function on_join(client_id, address)
+            server.broadcast("client " .. client_id .. " joined from " .. address)
+        end
+
+        function on_chat(client_id, room, message, nickname)
+            if message == "spam" then
+                server.kick(client_id)
+                return false
+            end
+            if message == "goal" then
+                server.start_match(room)
+                server.score(client_id, 3)
+                server.end_match(room)
+            end
+            if room == "shout" then
+                return string.upper(message)
+            end
+        end
+
+        function on_tick()
+            ticks = ticks + 1
+            server.set_motd("tick " .. ticks)
+        end
+    "#;
+
+    fn context(room: &str) -> ChatContext<'_> {
+        ChatContext {
+            client_id: 2,
+            nickname: None,
+            room,
+        }
+    }
+
+    #[test]
+    fn hooks_shape_chat_and_queue_server_requests() {
+        let plugin = LuaPlugin::from_source(SCRIPT, "test.lua").expect("Failed to load script.");
+
+        plugin.on_join(2, "127.0.0.1");
+        assert_eq!(
+            plugin.on_chat(&context("lobby"), "hi"),
+            PluginAction::Continue
+        );
+        assert_eq!(
+            plugin.on_chat(&context("shout"), "hi"),
+            PluginAction::Rewrite("HI".to_string())
+        );
+        assert_eq!(
+            plugin.on_chat(&context("lobby"), "spam"),
+            PluginAction::Drop
+        );
+        assert_eq!(
+            plugin.on_chat(&context("red"), "goal"),
+            PluginAction::Continue
+        );
+        plugin.on_tick();
+        plugin.on_tick();
+
+        assert_eq!(
+            plugin.drain_requests(),
+            vec![
+                ServerRequest::Broadcast("client 2 joined from 127.0.0.1".to_string()),
+                ServerRequest::Kick(2),
+                ServerRequest::StartMatch("red".to_string()),
+                ServerRequest::Score {
+                    client_id: 2,
+                    points: 3
+                },
+                ServerRequest::EndMatch("red".to_string()),
+                ServerRequest::SetMotd("tick 1".to_string()),
+                ServerRequest::SetMotd("tick 2".to_string()),
+            ]
+        );
+        assert!(plugin.drain_requests().is_empty());
+    }
+
+    #[test]
+    fn script_errors_leave_chat_untouched() {
+        let plugin = LuaPlugin::from_source("function on_chat() error('boom') end", "broken.lua")
+            .expect("Failed to load script.");
+
+        assert_eq!(
+            plugin.on_chat(&context("lobby"), "hi"),
+            PluginAction::Continue
+        );
+        assert!(LuaPlugin::from_source("this is not lua", "invalid.lua").is_err());
+    }
+}
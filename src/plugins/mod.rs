@@ -0,0 +1,12 @@
+mod flood;
+#[cfg(feature = "lua")]
+mod lua;
+mod profanity;
+mod registry;
+mod stats;
+pub use flood::*;
+#[cfg(feature = "lua")]
+pub use lua::*;
+pub use profanity::*;
+pub use registry::*;
+pub use stats::*;
@@ -0,0 +1,83 @@
+use super::{ChatContext, Plugin, PluginAction};
+use crate::config::env_or;
+use std::collections::HashSet;
+
+pub struct ProfanityFilter {
+    words: HashSet<String>,
+}
+
+impl ProfanityFilter {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        ProfanityFilter {
+            words: words
+                .into_iter()
+                .map(|word| word.as_ref().trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        ProfanityFilter::new(env_or("PROFANITY_WORDS", "").split(','))
+    }
+
+    fn mask_word(&self, word: &str, masked: &mut String) {
+        if self.words.contains(&word.to_lowercase()) {
+            masked.extend(word.chars().map(|_| '*'));
+        } else {
+            masked.push_str(word);
+        }
+    }
+
+    pub fn mask(&self, message: &str) -> String {
+        let mut masked = String::with_capacity(message.len());
+        let mut word = String::new();
+        for c in message.chars() {
+            if c.is_alphanumeric() {
+                word.push(c);
+                continue;
+            }
+            self.mask_word(&word, &mut masked);
+            word.clear();
+            masked.push(c);
+        }
+        self.mask_word(&word, &mut masked);
+        masked
+    }
+}
+
+impl Plugin for ProfanityFilter {
+    fn name(&self) -> &str {
+        "profanity"
+    }
+
+    fn on_chat(&self, _context: &ChatContext<'_>, message: &str) -> PluginAction {
+        let masked = self.mask(message);
+        if masked == message {
+            PluginAction::Continue
+        } else {
+            PluginAction::Rewrite(masked)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_words_are_masked_case_insensitively() {
+        let filter = ProfanityFilter::new(vec!["darn", " heck "]);
+
+        assert_eq!(
+            filter.mask("Darn it, what the HECK!"),
+            "**** it, what the ****!"
+        );
+        assert_eq!(filter.mask("darnation"), "darnation");
+        assert_eq!(filter.mask(""), "");
+    }
+}
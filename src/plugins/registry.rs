@@ -0,0 +1,257 @@
+#[cfg(feature = "lua")]
+use super::LuaPlugin;
+use super::{FloodGuard, ProfanityFilter, StatsPlugin};
+use std::sync::Arc;
+
+pub struct ChatContext<'a> {
+    pub client_id: u32,
+    pub nickname: Option<&'a str>,
+    pub room: &'a str,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PluginAction {
+    Continue,
+    Rewrite(String),
+    Reply(String),
+    Drop,
+}
+
+#[cfg_attr(not(feature = "lua"), allow(dead_code))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerRequest {
+    Broadcast(String),
+    Kick(u32),
+    SetMotd(String),
+    StartMatch(String),
+    Score { client_id: u32, points: i64 },
+    EndMatch(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PluginOutcome {
+    Deliver(String),
+    Reply(String),
+    Dropped,
+}
+
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn commands(&self) -> &[&str] {
+        &[]
+    }
+
+    fn on_command(
+        &self,
+        _context: &ChatContext<'_>,
+        _command: &str,
+        _args: &[&str],
+    ) -> PluginAction {
+        PluginAction::Continue
+    }
+
+    fn on_chat(&self, _context: &ChatContext<'_>, _message: &str) -> PluginAction {
+        PluginAction::Continue
+    }
+
+    fn on_join(&self, _client_id: u32, _address: &str) {}
+
+    fn on_tick(&self) {}
+
+    fn drain_requests(&self) -> Vec<ServerRequest> {
+        vec![]
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        PluginRegistry::default()
+    }
+
+    pub fn from_names(names: &[String]) -> Self {
+        let mut registry = PluginRegistry::new();
+        for name in names.iter() {
+            match plugin_by_name(name) {
+                Some(plugin) => {
+                    println!("プラグイン{}を登録しました\n", plugin.name());
+                    registry.register(plugin);
+                }
+                None => eprintln!("プラグイン{}は存在しません\n", name),
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, plugin: Arc<dyn Plugin>) -> &mut Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    pub fn dispatch(&self, context: &ChatContext<'_>, message: &str) -> PluginOutcome {
+        let mut parts = message.split_whitespace();
+        if let Some(command) = parts.next().filter(|command| command.starts_with(':')) {
+            let args = parts.collect::<Vec<_>>();
+            for plugin in self
+                .plugins
+                .iter()
+                .filter(|plugin| plugin.commands().contains(&command))
+            {
+                match plugin.on_command(context, command, &args) {
+                    PluginAction::Continue => continue,
+                    PluginAction::Rewrite(message) => return PluginOutcome::Deliver(message),
+                    PluginAction::Reply(reply) => return PluginOutcome::Reply(reply),
+                    PluginAction::Drop => return PluginOutcome::Dropped,
+                }
+            }
+        }
+
+        let mut message = message.to_string();
+        for plugin in self.plugins.iter() {
+            match plugin.on_chat(context, &message) {
+                PluginAction::Continue => {}
+                PluginAction::Rewrite(rewritten) => message = rewritten,
+                PluginAction::Reply(reply) => return PluginOutcome::Reply(reply),
+                PluginAction::Drop => return PluginOutcome::Dropped,
+            }
+        }
+        PluginOutcome::Deliver(message)
+    }
+
+    pub fn on_join(&self, client_id: u32, address: &str) {
+        for plugin in self.plugins.iter() {
+            plugin.on_join(client_id, address);
+        }
+    }
+
+    pub fn on_tick(&self) {
+        for plugin in self.plugins.iter() {
+            plugin.on_tick();
+        }
+    }
+
+    pub fn drain_requests(&self) -> Vec<ServerRequest> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| plugin.drain_requests())
+            .collect()
+    }
+}
+
+pub fn plugin_by_name(name: &str) -> Option<Arc<dyn Plugin>> {
+    match name {
+        "flood" => Some(Arc::new(FloodGuard::from_env())),
+        #[cfg(feature = "lua")]
+        "lua" => match LuaPlugin::from_env() {
+            Ok(plugin) => Some(Arc::new(plugin)),
+            Err(e) => {
+                eprintln!("Luaスクリプトを読み込めませんでした：{}\n", e);
+                None
+            }
+        },
+        "profanity" => Some(Arc::new(ProfanityFilter::from_env())),
+        "stats" => Some(Arc::new(StatsPlugin::new())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Shout;
+
+    impl Plugin for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn commands(&self) -> &[&str] {
+            &[":shout"]
+        }
+
+        fn on_command(
+            &self,
+            _context: &ChatContext<'_>,
+            _command: &str,
+            args: &[&str],
+        ) -> PluginAction {
+            PluginAction::Rewrite(args.join(" ").to_uppercase())
+        }
+
+        fn on_chat(&self, _context: &ChatContext<'_>, message: &str) -> PluginAction {
+            PluginAction::Rewrite(format!("{}!", message))
+        }
+    }
+
+    struct Silence;
+
+    impl Plugin for Silence {
+        fn name(&self) -> &str {
+            "silence"
+        }
+
+        fn on_chat(&self, _context: &ChatContext<'_>, message: &str) -> PluginAction {
+            if message.contains("quiet") {
+                PluginAction::Drop
+            } else {
+                PluginAction::Continue
+            }
+        }
+    }
+
+    fn context() -> ChatContext<'static> {
+        ChatContext {
+            client_id: 0,
+            nickname: None,
+            room: "lobby",
+        }
+    }
+
+    #[test]
+    fn chat_plugins_run_in_registration_order() {
+        let mut registry = PluginRegistry::new();
+        registry
+            .register(Arc::new(Shout))
+            .register(Arc::new(Silence));
+
+        assert_eq!(
+            registry.dispatch(&context(), "hi"),
+            PluginOutcome::Deliver("hi!".to_string())
+        );
+        assert_eq!(
+            registry.dispatch(&context(), "quiet"),
+            PluginOutcome::Dropped
+        );
+    }
+
+    #[test]
+    fn commands_only_reach_plugins_that_registered_them() {
+        let mut registry = PluginRegistry::new();
+        registry
+            .register(Arc::new(Silence))
+            .register(Arc::new(Shout));
+
+        assert_eq!(
+            registry.dispatch(&context(), ":shout be quiet"),
+            PluginOutcome::Deliver("BE QUIET".to_string())
+        );
+        assert_eq!(
+            registry.dispatch(&context(), ":unknown"),
+            PluginOutcome::Deliver(":unknown!".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_plugin_names_are_skipped() {
+        let registry = PluginRegistry::from_names(&["stats".to_string(), "nope".to_string()]);
+
+        assert_eq!(registry.plugins.len(), 1);
+        assert_eq!(registry.plugins[0].name(), "stats");
+    }
+}
@@ -0,0 +1,48 @@
+use super::{ChatContext, Plugin, PluginAction};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Default)]
+pub struct StatsPlugin {
+    messages: Mutex<HashMap<u32, u64>>,
+}
+
+impl StatsPlugin {
+    pub fn new() -> Self {
+        StatsPlugin::default()
+    }
+}
+
+impl Plugin for StatsPlugin {
+    fn name(&self) -> &str {
+        "stats"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &[":stats"]
+    }
+
+    fn on_command(
+        &self,
+        context: &ChatContext<'_>,
+        _command: &str,
+        _args: &[&str],
+    ) -> PluginAction {
+        let messages = self.messages.lock().expect("Failed to lock chat stats.");
+        PluginAction::Reply(format!(
+            "You have sent {} messages. {} messages were sent in total.",
+            messages.get(&context.client_id).copied().unwrap_or(0),
+            messages.values().sum::<u64>()
+        ))
+    }
+
+    fn on_chat(&self, context: &ChatContext<'_>, _message: &str) -> PluginAction {
+        *self
+            .messages
+            .lock()
+            .expect("Failed to lock chat stats.")
+            .entry(context.client_id)
+            .or_insert(0) += 1;
+        PluginAction::Continue
+    }
+}
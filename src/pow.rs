@@ -0,0 +1,107 @@
+use crate::config::env_or;
+use crate::identity::hex;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+pub const MAX_DIFFICULTY: u32 = 32;
+
+const NONCE_SIZE: usize = 16;
+
+pub fn difficulty_from_env() -> Option<u32> {
+    env_or("POW_DIFFICULTY", "")
+        .parse::<u32>()
+        .ok()
+        .filter(|difficulty| *difficulty > 0)
+        .map(|difficulty| difficulty.min(MAX_DIFFICULTY))
+}
+
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn digest(nonce: &str, solution: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(nonce.as_bytes());
+    hasher.update(solution.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    pub nonce: String,
+    pub difficulty: u32,
+}
+
+impl Challenge {
+    pub fn issue(difficulty: u32) -> Self {
+        Challenge {
+            nonce: hex(&rand::thread_rng().gen::<[u8; NONCE_SIZE]>()),
+            difficulty,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("POW {} {}", &self.nonce, self.difficulty)
+    }
+
+    pub fn verify(&self, solution: &str) -> bool {
+        leading_zero_bits(&digest(&self.nonce, solution)) >= self.difficulty
+    }
+
+    pub fn solve(&self) -> u64 {
+        (0..)
+            .find(|counter: &u64| self.verify(&counter.to_string()))
+            .expect("The proof-of-work search space is exhausted.")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PowCommand<'a> {
+    pub solution: &'a str,
+}
+
+impl<'a> PowCommand<'a> {
+    pub fn parse(input: &'a str) -> Option<Self> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":pow", Some(solution), None) => Some(PowCommand { solution }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solutions_must_reach_the_requested_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0, 0, 0x1f, 0]), 19);
+        assert_eq!(leading_zero_bits(&[0x80]), 0);
+        assert_eq!(leading_zero_bits(&[0, 0]), 16);
+
+        let challenge = Challenge::issue(8);
+        assert!(challenge.encode().starts_with("POW "));
+        assert!(challenge.encode().ends_with(" 8"));
+        let solution = challenge.solve().to_string();
+        assert!(challenge.verify(&solution));
+        assert!(leading_zero_bits(&digest(&challenge.nonce, &solution)) >= 8);
+        assert_ne!(Challenge::issue(8).nonce, challenge.nonce);
+
+        assert_eq!(
+            PowCommand::parse(":pow 1234\0"),
+            Some(PowCommand { solution: "1234" })
+        );
+        assert_eq!(PowCommand::parse(":pow"), None);
+        assert_eq!(PowCommand::parse(":pow 1 2"), None);
+    }
+}
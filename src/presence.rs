@@ -0,0 +1,102 @@
+use std::fmt::{Display, Formatter};
+
+pub const MAX_FRIENDS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Presence {
+    #[default]
+    Online,
+    Away,
+    Busy,
+    InGame,
+}
+
+impl Presence {
+    pub fn parse(input: &str) -> Option<Presence> {
+        match input.to_ascii_lowercase().as_str() {
+            "online" => Some(Presence::Online),
+            "away" => Some(Presence::Away),
+            "busy" => Some(Presence::Busy),
+            "in-game" | "ingame" => Some(Presence::InGame),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Presence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Presence::Online => write!(f, "online"),
+            Presence::Away => write!(f, "away"),
+            Presence::Busy => write!(f, "busy"),
+            Presence::InGame => write!(f, "in-game"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PresenceCommand {
+    Status(Presence),
+    Who,
+    Friend(String),
+}
+
+impl PresenceCommand {
+    pub fn parse(input: &str) -> Option<PresenceCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":status", Some(status), None) => Presence::parse(status).map(PresenceCommand::Status),
+            (":who", None, None) => Some(PresenceCommand::Who),
+            (":friend", Some(name), None) => Some(PresenceCommand::Friend(name.to_string())),
+            _ => None,
+        }
+    }
+}
+
+pub fn member_list(members: &[(String, Presence)]) -> String {
+    std::iter::once("MEMBERS".to_string())
+        .chain(
+            members
+                .iter()
+                .map(|(name, presence)| format!("{}:{}", name, presence)),
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn presence_notice(name: &str, presence: Presence) -> String {
+    format!("PRESENCE {} {}", name, presence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_and_statuses_round_trip() {
+        assert_eq!(
+            PresenceCommand::parse(":status In-Game"),
+            Some(PresenceCommand::Status(Presence::InGame))
+        );
+        assert_eq!(PresenceCommand::parse(":status sleeping"), None);
+        assert_eq!(PresenceCommand::parse(":who\0"), Some(PresenceCommand::Who));
+        assert_eq!(
+            PresenceCommand::parse(":friend alice"),
+            Some(PresenceCommand::Friend("alice".to_string()))
+        );
+        assert_eq!(
+            member_list(&[
+                ("alice".to_string(), Presence::Busy),
+                ("Guest1".to_string(), Presence::Online)
+            ]),
+            "MEMBERS alice:busy Guest1:online"
+        );
+        assert_eq!(member_list(&[]), "MEMBERS");
+        assert_eq!(
+            presence_notice("alice", Presence::Away),
+            "PRESENCE alice away"
+        );
+    }
+}
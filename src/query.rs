@@ -0,0 +1,306 @@
+use crate::clients::ClientRegistry;
+use crate::config::env_or;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SERVER_NAME: &str = "online_game_programming";
+pub const DEFAULT_MAP: &str = "lobby";
+
+const HEADER: [u8; 4] = [0xff; 4];
+const A2S_INFO: u8 = b'T';
+const A2S_PLAYER: u8 = b'U';
+const A2S_RULES: u8 = b'V';
+const S2C_CHALLENGE: u8 = b'A';
+const S2A_INFO: u8 = b'I';
+const S2A_PLAYER: u8 = b'D';
+const S2A_RULES: u8 = b'E';
+const INFO_PAYLOAD: &[u8] = b"Source Engine Query\0";
+const NO_CHALLENGE: i32 = -1;
+const PROTOCOL_VERSION: u8 = 17;
+const MAX_PACKET: usize = 1400;
+const MAX_REQUEST: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct QueryConfig {
+    pub bind: String,
+    pub name: String,
+    pub map: String,
+}
+
+impl QueryConfig {
+    pub fn from_env(port: u16) -> Option<Self> {
+        if env_or("QUERY_ENABLED", "false") != "true" {
+            return None;
+        }
+        Some(QueryConfig {
+            bind: env_or("QUERY_BIND", &format!("0.0.0.0:{}", port)),
+            name: env_or("SERVER_NAME", DEFAULT_SERVER_NAME),
+            map: env_or("SERVER_MAP", DEFAULT_MAP),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryRequest {
+    Info(i32),
+    Player(i32),
+    Rules(i32),
+}
+
+impl QueryRequest {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let body = data.strip_prefix(&HEADER[..])?;
+        let (kind, body) = body.split_first()?;
+        let (body, parse): (&[u8], fn(i32) -> QueryRequest) = match *kind {
+            A2S_INFO => (body.strip_prefix(INFO_PAYLOAD)?, QueryRequest::Info),
+            A2S_PLAYER => (body, QueryRequest::Player),
+            A2S_RULES => (body, QueryRequest::Rules),
+            _ => return None,
+        };
+        let challenge = match body.len() {
+            0 if *kind == A2S_INFO => NO_CHALLENGE,
+            4 => i32::from_le_bytes([body[0], body[1], body[2], body[3]]),
+            _ => return None,
+        };
+        Some(parse(challenge))
+    }
+
+    fn challenge(&self) -> i32 {
+        match self {
+            QueryRequest::Info(challenge)
+            | QueryRequest::Player(challenge)
+            | QueryRequest::Rules(challenge) => *challenge,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerStatus {
+    pub name: String,
+    pub map: String,
+    pub max_players: usize,
+    pub players: Vec<(String, Duration)>,
+    pub rules: Vec<(String, String)>,
+}
+
+struct Packet(Vec<u8>);
+
+impl Packet {
+    fn new(kind: u8) -> Self {
+        Packet([&HEADER[..], &[kind]].concat())
+    }
+
+    fn byte(mut self, value: u8) -> Self {
+        self.0.push(value);
+        self
+    }
+
+    fn text(mut self, value: &str) -> Self {
+        self.0
+            .extend(value.bytes().filter(|byte| *byte != 0).chain(Some(0)));
+        self
+    }
+
+    fn bytes(mut self, value: &[u8]) -> Self {
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    fn fits(&self, extra: usize) -> bool {
+        self.0.len() + extra <= MAX_PACKET
+    }
+}
+
+fn encode_info(status: &ServerStatus) -> Vec<u8> {
+    Packet::new(S2A_INFO)
+        .byte(PROTOCOL_VERSION)
+        .text(&status.name)
+        .text(&status.map)
+        .text("online_game_programming")
+        .text("online_game_programming")
+        .bytes(&0_i16.to_le_bytes())
+        .byte(status.players.len().min(u8::MAX as usize) as u8)
+        .byte(status.max_players.min(u8::MAX as usize) as u8)
+        .byte(0)
+        .byte(b'd')
+        .byte(if cfg!(windows) { b'w' } else { b'l' })
+        .byte(0)
+        .byte(0)
+        .text(env!("CARGO_PKG_VERSION"))
+        .0
+}
+
+fn encode_players(status: &ServerStatus) -> Vec<u8> {
+    let mut packet = Packet::new(S2A_PLAYER).byte(0);
+    let mut count = 0_u8;
+    for (index, (name, connected)) in status.players.iter().enumerate() {
+        if count == u8::MAX || !packet.fits(name.len() + 10) {
+            break;
+        }
+        packet = packet
+            .byte(index as u8)
+            .text(name)
+            .bytes(&0_i32.to_le_bytes())
+            .bytes(&connected.as_secs_f32().to_le_bytes());
+        count += 1;
+    }
+    packet.0[HEADER.len() + 1] = count;
+    packet.0
+}
+
+fn encode_rules(status: &ServerStatus) -> Vec<u8> {
+    let mut packet = Packet::new(S2A_RULES).bytes(&[0, 0]);
+    let mut count = 0_i16;
+    for (name, value) in status.rules.iter() {
+        if !packet.fits(name.len() + value.len() + 2) {
+            break;
+        }
+        packet = packet.text(name).text(value);
+        count += 1;
+    }
+    packet.0[HEADER.len() + 1..HEADER.len() + 3].copy_from_slice(&count.to_le_bytes());
+    packet.0
+}
+
+pub struct QueryResponder {
+    secret: [u8; 16],
+}
+
+impl QueryResponder {
+    pub fn new() -> Self {
+        QueryResponder {
+            secret: rand::thread_rng().gen(),
+        }
+    }
+
+    pub fn challenge(&self, from: SocketAddr) -> i32 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.secret);
+        hasher.update(from.to_string().as_bytes());
+        let hash = hasher.finalize();
+        i32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) & i32::MAX
+    }
+
+    pub fn respond(
+        &self,
+        from: SocketAddr,
+        request: QueryRequest,
+        status: impl FnOnce() -> ServerStatus,
+    ) -> Vec<u8> {
+        let challenge = self.challenge(from);
+        if request.challenge() != challenge {
+            return Packet::new(S2C_CHALLENGE).bytes(&challenge.to_le_bytes()).0;
+        }
+        let status = status();
+        match request {
+            QueryRequest::Info(_) => encode_info(&status),
+            QueryRequest::Player(_) => encode_players(&status),
+            QueryRequest::Rules(_) => encode_rules(&status),
+        }
+    }
+}
+
+impl Default for QueryResponder {
+    fn default() -> Self {
+        QueryResponder::new()
+    }
+}
+
+pub struct QueryServer {
+    address: SocketAddr,
+}
+
+impl QueryServer {
+    pub fn spawn(
+        config: QueryConfig,
+        clients: ClientRegistry,
+        max_players: usize,
+        rules: Vec<(String, String)>,
+    ) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(config.bind.as_str())?;
+        let address = socket.local_addr()?;
+        std::thread::spawn(move || {
+            let responder = QueryResponder::new();
+            let mut buffer = [0_u8; MAX_REQUEST];
+            loop {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(_) => continue,
+                };
+                let request = match QueryRequest::parse(&buffer[..size]) {
+                    Some(request) => request,
+                    None => continue,
+                };
+                let response = responder.respond(from, request, || ServerStatus {
+                    name: config.name.clone(),
+                    map: config.map.clone(),
+                    max_players,
+                    players: clients.players(Instant::now()),
+                    rules: rules.clone(),
+                });
+                let _ = socket.send_to(&response, from);
+            }
+        });
+        Ok(QueryServer { address })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> ServerStatus {
+        ServerStatus {
+            name: "Tokyo #1".to_string(),
+            map: "lobby".to_string(),
+            max_players: 8,
+            players: vec![("alice".to_string(), Duration::from_secs(90))],
+            rules: vec![("require_login".to_string(), "false".to_string())],
+        }
+    }
+
+    #[test]
+    fn queries_need_the_address_challenge_before_answering() {
+        let responder = QueryResponder::new();
+        let from: SocketAddr = "192.0.2.1:27005".parse().expect("Invalid address.");
+        let info = [&HEADER[..], &[A2S_INFO], INFO_PAYLOAD].concat();
+        let request = QueryRequest::parse(&info).expect("Failed to parse.");
+        assert_eq!(request, QueryRequest::Info(NO_CHALLENGE));
+
+        let reply = responder.respond(from, request, status);
+        assert_eq!(&reply[..5], &[0xff, 0xff, 0xff, 0xff, S2C_CHALLENGE]);
+        let challenge = i32::from_le_bytes([reply[5], reply[6], reply[7], reply[8]]);
+        assert_eq!(challenge, responder.challenge(from));
+        assert_ne!(
+            challenge,
+            responder.challenge("192.0.2.2:27005".parse().expect("Invalid address."))
+        );
+
+        let info = [&info[..], &challenge.to_le_bytes()].concat();
+        let reply = responder.respond(from, QueryRequest::parse(&info).expect("Parse."), status);
+        assert_eq!(reply[4], S2A_INFO);
+        assert!(reply.windows(9).any(|window| window == b"Tokyo #1\0"));
+
+        let players = [&HEADER[..], &[A2S_PLAYER], &challenge.to_le_bytes()].concat();
+        let reply = responder.respond(from, QueryRequest::parse(&players).expect("Parse."), status);
+        assert_eq!(
+            &reply[4..12],
+            &[S2A_PLAYER, 1, 0, b'a', b'l', b'i', b'c', b'e']
+        );
+        assert_eq!(&reply[reply.len() - 4..], &90_f32.to_le_bytes());
+
+        let rules = [&HEADER[..], &[A2S_RULES], &challenge.to_le_bytes()].concat();
+        let reply = responder.respond(from, QueryRequest::parse(&rules).expect("Parse."), status);
+        assert_eq!(&reply[4..7], &[S2A_RULES, 1, 0]);
+        assert_eq!(&reply[7..], b"require_login\0false\0");
+
+        assert_eq!(QueryRequest::parse(b"\xff\xff\xff\xffU"), None);
+        assert_eq!(QueryRequest::parse(b"chat"), None);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::snapshot::{escape_field, unescape_field};
+use crate::storage::unix_now;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketKind {
+    Join,
+    Message,
+    Leave,
+}
+
+impl PacketKind {
+    fn name(&self) -> &'static str {
+        match self {
+            PacketKind::Join => "join",
+            PacketKind::Message => "message",
+            PacketKind::Leave => "leave",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<PacketKind> {
+        match name {
+            "join" => Some(PacketKind::Join),
+            "message" => Some(PacketKind::Message),
+            "leave" => Some(PacketKind::Leave),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedPacket {
+    pub timestamp_ms: u64,
+    pub client_id: u32,
+    pub kind: PacketKind,
+    pub payload: String,
+}
+
+impl RecordedPacket {
+    pub fn encode(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.timestamp_ms,
+            self.client_id,
+            self.kind.name(),
+            escape_field(&self.payload)
+        )
+    }
+
+    pub fn decode(line: &str) -> Option<RecordedPacket> {
+        let mut fields = line.splitn(4, '\t');
+        Some(RecordedPacket {
+            timestamp_ms: fields.next()?.parse().ok()?,
+            client_id: fields.next()?.parse().ok()?,
+            kind: PacketKind::from_name(fields.next()?)?,
+            payload: unescape_field(fields.next()?),
+        })
+    }
+}
+
+fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn redact(payload: &str) -> String {
+    let mut parts = payload
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .splitn(3, char::is_whitespace);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(command @ ":register"), Some(name), Some(_))
+        | (Some(command @ ":login"), Some(name), Some(_)) => format!("{} {} ****", command, name),
+        (Some(":resume"), Some(_), _) => ":resume ****".to_string(),
+        _ => payload.to_string(),
+    }
+}
+
+#[derive(Clone)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    pub fn create(directory: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        let path = directory.join(format!("session-{}.rec", unix_now()));
+        let file = File::create(&path)?;
+        Ok(Recorder {
+            path,
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, client_id: u32, kind: PacketKind, payload: &str) {
+        let packet = RecordedPacket {
+            timestamp_ms: timestamp_ms(),
+            client_id,
+            kind,
+            payload: redact(payload),
+        };
+        let line = packet.encode() + "\n";
+        let mut file = self.file.lock().expect("Failed to lock recorder.");
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            eprintln!("セッションの記録に失敗しました：{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credentials_and_session_tokens_are_redacted() {
+        assert_eq!(redact(":login alice hunter2"), ":login alice ****");
+        assert_eq!(redact(" :register bob s3cret\0"), ":register bob ****");
+        assert_eq!(redact(":resume 0123456789abcdef"), ":resume ****");
+        assert_eq!(redact(":resume"), ":resume");
+        assert_eq!(redact("hello :login"), "hello :login");
+    }
+}
@@ -0,0 +1,107 @@
+use crate::bus::{ChatPosted, MessageBus};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_ROOM: &str = "lobby";
+pub const DEFAULT_HISTORY_SIZE: usize = 20;
+pub const MAX_ROOM_NAME_LENGTH: usize = 32;
+
+pub enum RoomCommand {
+    Join(String),
+    Ready(bool),
+}
+
+impl RoomCommand {
+    pub fn parse(input: &str) -> Option<RoomCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next()) {
+            (":join", Some(room)) => Some(RoomCommand::Join(room.to_string())),
+            (":join", None) => Some(RoomCommand::Join(DEFAULT_ROOM.to_string())),
+            (":ready", None) => Some(RoomCommand::Ready(true)),
+            (":unready", None) => Some(RoomCommand::Ready(false)),
+            _ => None,
+        }
+    }
+}
+
+pub fn valid_room_name(room: &str) -> bool {
+    !room.is_empty()
+        && room.chars().count() <= MAX_ROOM_NAME_LENGTH
+        && room
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+pub fn ready_check(members: &[(bool, bool)], skip_afk: bool) -> bool {
+    let mut considered = members
+        .iter()
+        .filter(|(_, afk)| !(skip_afk && *afk))
+        .peekable();
+    considered.peek().is_some() && considered.all(|(ready, _)| *ready)
+}
+
+#[derive(Clone)]
+pub struct Rooms {
+    queue_size: usize,
+    queues: Arc<Mutex<HashMap<String, VecDeque<String>>>>,
+}
+
+impl Rooms {
+    pub fn new(queue_size: usize) -> Self {
+        Rooms {
+            queue_size,
+            queues: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, VecDeque<String>>> {
+        self.queues.lock().expect("Failed to lock rooms.")
+    }
+
+    pub fn push(&self, room: &str, line: String) {
+        if self.queue_size == 0 {
+            return;
+        }
+        let mut queues = self.lock();
+        let queue = queues.entry(room.to_string()).or_default();
+        while queue.len() >= self.queue_size {
+            queue.pop_front();
+        }
+        queue.push_back(line);
+    }
+
+    pub fn queued(&self, room: &str) -> Vec<String> {
+        self.lock()
+            .get(room)
+            .map(|queue| queue.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> Vec<(String, Vec<String>)> {
+        self.lock()
+            .iter()
+            .map(|(room, queue)| (room.clone(), queue.iter().cloned().collect()))
+            .collect()
+    }
+
+    pub fn subscribe(&self, bus: &MessageBus) {
+        let rooms = self.clone();
+        bus.subscribe(move |event: &ChatPosted| {
+            rooms.push(
+                &event.room,
+                format!("{}：{}", event.client_id, &event.message),
+            )
+        });
+    }
+
+    pub fn restore(&self, rooms: Vec<(String, Vec<String>)>) {
+        for (room, lines) in rooms {
+            self.lock().entry(room.clone()).or_default();
+            for line in lines {
+                self.push(&room, line);
+            }
+        }
+    }
+}
@@ -0,0 +1,499 @@
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(200);
+
+const KIND_DATA: u8 = 0;
+const KIND_ACK: u8 = 1;
+const KIND_UNRELIABLE: u8 = 2;
+const KIND_CLOSE: u8 = 3;
+const KIND_MIGRATE: u8 = 4;
+const MAC_SIZE: usize = 32;
+const CHECKSUM_SIZE: usize = 4;
+const RECEIVE_WINDOW: u32 = 1024;
+
+static CORRUPTED_PACKETS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Packet {
+    Data {
+        sequence: u32,
+        ack: u32,
+        payload: Vec<u8>,
+    },
+    Ack {
+        ack: u32,
+    },
+    Unreliable {
+        payload: Vec<u8>,
+    },
+    Close,
+    Migrate {
+        session: u64,
+        counter: u64,
+        mac: Vec<u8>,
+    },
+}
+
+fn read_u64(data: &[u8]) -> Option<u64> {
+    let bytes = data.get(..8)?;
+    let mut value = [0_u8; 8];
+    value.copy_from_slice(bytes);
+    Some(u64::from_be_bytes(value))
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+    let bytes = data.get(..4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Packet::Data {
+                sequence,
+                ack,
+                payload,
+            } => {
+                let mut data = Vec::with_capacity(payload.len() + 9);
+                data.push(KIND_DATA);
+                data.extend_from_slice(&sequence.to_be_bytes());
+                data.extend_from_slice(&ack.to_be_bytes());
+                data.extend_from_slice(payload);
+                data
+            }
+            Packet::Ack { ack } => {
+                let mut data = vec![KIND_ACK];
+                data.extend_from_slice(&ack.to_be_bytes());
+                data
+            }
+            Packet::Unreliable { payload } => {
+                let mut data = Vec::with_capacity(payload.len() + 1);
+                data.push(KIND_UNRELIABLE);
+                data.extend_from_slice(payload);
+                data
+            }
+            Packet::Close => vec![KIND_CLOSE],
+            Packet::Migrate {
+                session,
+                counter,
+                mac,
+            } => {
+                let mut data = Vec::with_capacity(mac.len() + 17);
+                data.push(KIND_MIGRATE);
+                data.extend_from_slice(&session.to_be_bytes());
+                data.extend_from_slice(&counter.to_be_bytes());
+                data.extend_from_slice(mac);
+                data
+            }
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Packet> {
+        let (kind, rest) = data.split_first()?;
+        match *kind {
+            KIND_DATA => Some(Packet::Data {
+                sequence: read_u32(rest)?,
+                ack: read_u32(rest.get(4..)?)?,
+                payload: rest.get(8..)?.to_vec(),
+            }),
+            KIND_ACK if rest.len() == 4 => Some(Packet::Ack {
+                ack: read_u32(rest)?,
+            }),
+            KIND_UNRELIABLE => Some(Packet::Unreliable {
+                payload: rest.to_vec(),
+            }),
+            KIND_CLOSE if rest.is_empty() => Some(Packet::Close),
+            KIND_MIGRATE if rest.len() == 16 + MAC_SIZE => Some(Packet::Migrate {
+                session: read_u64(rest)?,
+                counter: read_u64(&rest[8..])?,
+                mac: rest[16..].to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct SessionKey {
+    id: u64,
+    secret: Vec<u8>,
+    counter: u64,
+}
+
+impl SessionKey {
+    pub fn new(token: &str) -> Self {
+        let digest = Sha256::digest(token.as_bytes());
+        SessionKey {
+            id: read_u64(&digest).unwrap_or_default(),
+            secret: token.as_bytes().to_vec(),
+            counter: 0,
+        }
+    }
+
+    fn mac(&self, counter: u64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key length.");
+        mac.update(&self.id.to_be_bytes());
+        mac.update(&counter.to_be_bytes());
+        mac
+    }
+
+    pub fn migration(&mut self) -> Packet {
+        self.counter += 1;
+        Packet::Migrate {
+            session: self.id,
+            counter: self.counter,
+            mac: self.mac(self.counter).finalize().into_bytes().to_vec(),
+        }
+    }
+
+    pub fn accept_migration(&mut self, packet: &Packet) -> bool {
+        match packet {
+            Packet::Migrate {
+                session,
+                counter,
+                mac,
+            } if *session == self.id
+                && *counter > self.counter
+                && self.mac(*counter).verify(mac).is_ok() =>
+            {
+                self.counter = *counter;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn append_checksum(mut data: Vec<u8>) -> Vec<u8> {
+    let checksum = crc32fast::hash(&data);
+    data.extend_from_slice(&checksum.to_be_bytes());
+    data
+}
+
+pub fn verify_checksum(data: &[u8]) -> Option<&[u8]> {
+    let (packet, checksum) = data.split_at(data.len().checked_sub(CHECKSUM_SIZE)?);
+    if read_u32(checksum)? == crc32fast::hash(packet) {
+        Some(packet)
+    } else {
+        None
+    }
+}
+
+pub fn record_corrupted_packet() {
+    CORRUPTED_PACKETS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn corrupted_packets() -> u64 {
+    CORRUPTED_PACKETS.load(Ordering::Relaxed)
+}
+
+fn precedes(a: u32, b: u32) -> bool {
+    a != b && b.wrapping_sub(a) < 1 << 31
+}
+
+struct InFlight {
+    payload: Vec<u8>,
+    sent_at: Instant,
+}
+
+pub struct ReliableChannel {
+    retransmit_timeout: Duration,
+    next_sequence: u32,
+    unacked: BTreeMap<u32, InFlight>,
+    next_expected: u32,
+    out_of_order: BTreeMap<u32, Vec<u8>>,
+}
+
+impl Default for ReliableChannel {
+    fn default() -> Self {
+        ReliableChannel::new(DEFAULT_RETRANSMIT_TIMEOUT)
+    }
+}
+
+impl ReliableChannel {
+    pub fn new(retransmit_timeout: Duration) -> Self {
+        ReliableChannel {
+            retransmit_timeout,
+            next_sequence: 0,
+            unacked: BTreeMap::new(),
+            next_expected: 0,
+            out_of_order: BTreeMap::new(),
+        }
+    }
+
+    pub fn send(&mut self, payload: Vec<u8>, now: Instant) -> Packet {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.unacked.insert(
+            sequence,
+            InFlight {
+                payload: payload.clone(),
+                sent_at: now,
+            },
+        );
+        Packet::Data {
+            sequence,
+            ack: self.next_expected,
+            payload,
+        }
+    }
+
+    fn acknowledge(&mut self, ack: u32) {
+        self.unacked.retain(|sequence, _| !precedes(*sequence, ack));
+    }
+
+    pub fn receive(&mut self, packet: Packet) -> (Vec<Vec<u8>>, Option<Packet>) {
+        match packet {
+            Packet::Data {
+                sequence,
+                ack,
+                payload,
+            } => {
+                self.acknowledge(ack);
+                if sequence.wrapping_sub(self.next_expected) < RECEIVE_WINDOW {
+                    self.out_of_order.entry(sequence).or_insert(payload);
+                }
+                let mut delivered = vec![];
+                while let Some(payload) = self.out_of_order.remove(&self.next_expected) {
+                    delivered.push(payload);
+                    self.next_expected = self.next_expected.wrapping_add(1);
+                }
+                (
+                    delivered,
+                    Some(Packet::Ack {
+                        ack: self.next_expected,
+                    }),
+                )
+            }
+            Packet::Ack { ack } => {
+                self.acknowledge(ack);
+                (vec![], None)
+            }
+            Packet::Unreliable { payload } => (vec![payload], None),
+            Packet::Close | Packet::Migrate { .. } => (vec![], None),
+        }
+    }
+
+    pub fn retransmissions(&mut self, now: Instant) -> Vec<Packet> {
+        let ack = self.next_expected;
+        let timeout = self.retransmit_timeout;
+        self.unacked
+            .iter_mut()
+            .filter(|(_, in_flight)| now.duration_since(in_flight.sent_at) >= timeout)
+            .map(|(sequence, in_flight)| {
+                in_flight.sent_at = now;
+                Packet::Data {
+                    sequence: *sequence,
+                    ack,
+                    payload: in_flight.payload.clone(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.unacked.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+    const LOSSY_TRANSMISSIONS: usize = 400;
+    const MAX_TICKS: u32 = 20_000;
+
+    #[derive(Clone, Copy, Debug)]
+    enum Fate {
+        Drop,
+        Duplicate(u32),
+        Deliver(u32),
+    }
+
+    fn fate() -> impl Strategy<Value = Fate> {
+        prop_oneof![
+            Just(Fate::Drop),
+            (0_u32..6).prop_map(Fate::Duplicate),
+            (0_u32..6).prop_map(Fate::Deliver),
+        ]
+    }
+
+    struct Network {
+        schedule: Vec<Fate>,
+        transmissions: usize,
+        in_flight: Vec<(u32, bool, Packet)>,
+    }
+
+    impl Network {
+        fn transmit(&mut self, tick: u32, to_receiver: bool, packet: Packet) {
+            let fate = if self.transmissions < LOSSY_TRANSMISSIONS {
+                self.schedule[self.transmissions % self.schedule.len()]
+            } else {
+                Fate::Deliver(1)
+            };
+            self.transmissions += 1;
+            match fate {
+                Fate::Drop => {}
+                Fate::Duplicate(delay) => {
+                    self.in_flight
+                        .push((tick + delay, to_receiver, packet.clone()));
+                    self.in_flight.push((tick + delay + 1, to_receiver, packet));
+                }
+                Fate::Deliver(delay) => self.in_flight.push((tick + delay, to_receiver, packet)),
+            }
+        }
+
+        fn due(&mut self, tick: u32) -> Vec<(bool, Packet)> {
+            let (due, pending) = self
+                .in_flight
+                .drain(..)
+                .partition::<Vec<_>, _>(|(at, _, _)| *at <= tick);
+            self.in_flight = pending;
+            due.into_iter()
+                .map(|(_, to_receiver, packet)| (to_receiver, packet))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn migrations_need_the_session_secret_and_a_fresh_counter() {
+        let mut client = SessionKey::new("ab".repeat(32).as_str());
+        let mut server = SessionKey::new("ab".repeat(32).as_str());
+        let mut stranger = SessionKey::new("cd".repeat(32).as_str());
+
+        let first = client.migration();
+        assert!(!server.accept_migration(&stranger.migration()));
+        assert!(server.accept_migration(&first));
+        assert!(!server.accept_migration(&first));
+        assert!(server.accept_migration(&client.migration()));
+
+        let mut forged = client.migration();
+        if let Packet::Migrate { mac, .. } = &mut forged {
+            mac[0] ^= 1;
+        }
+        assert!(!server.accept_migration(&forged));
+    }
+
+    #[test]
+    fn sequences_wrap_around_and_far_future_packets_are_dropped() {
+        let now = Instant::now();
+        let mut sender = ReliableChannel::default();
+        let mut receiver = ReliableChannel::default();
+        sender.next_sequence = u32::MAX - 1;
+        receiver.next_expected = u32::MAX - 1;
+
+        let packets = (0_u8..4)
+            .map(|message| sender.send(vec![message], now))
+            .collect::<Vec<_>>();
+        let (delivered, _) = receiver.receive(packets[1].clone());
+        assert!(delivered.is_empty());
+        let (delivered, _) = receiver.receive(packets[3].clone());
+        assert!(delivered.is_empty());
+        let (delivered, _) = receiver.receive(packets[0].clone());
+        assert_eq!(delivered, vec![vec![0], vec![1]]);
+        let (delivered, ack) = receiver.receive(packets[2].clone());
+        assert_eq!(delivered, vec![vec![2], vec![3]]);
+        assert_eq!(ack, Some(Packet::Ack { ack: 2 }));
+
+        sender.receive(Packet::Ack { ack: 1 });
+        assert_eq!(sender.in_flight(), 1);
+        sender.receive(Packet::Ack { ack: 2 });
+        assert_eq!(sender.in_flight(), 0);
+
+        for sequence in [RECEIVE_WINDOW + 2, u32::MAX / 2, u32::MAX - 1] {
+            let (delivered, ack) = receiver.receive(Packet::Data {
+                sequence,
+                ack: 0,
+                payload: vec![],
+            });
+            assert!(delivered.is_empty());
+            assert_eq!(ack, Some(Packet::Ack { ack: 2 }));
+        }
+        assert!(receiver.out_of_order.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn packets_round_trip_through_the_wire_format(
+            sequence in any::<u32>(),
+            ack in any::<u32>(),
+            payload in vec(any::<u8>(), 0..64),
+        ) {
+            for packet in [
+                Packet::Data { sequence, ack, payload: payload.clone() },
+                Packet::Ack { ack },
+                Packet::Unreliable { payload: payload.clone() },
+                Packet::Close,
+                Packet::Migrate { session: sequence as u64, counter: ack as u64, mac: vec![7; MAC_SIZE] },
+            ] {
+                prop_assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+            }
+        }
+
+        #[test]
+        fn checksums_reject_any_single_bit_flip(
+            payload in vec(any::<u8>(), 0..64),
+            flip in any::<prop::sample::Index>(),
+            bit in 0_u8..8,
+        ) {
+            let packet = Packet::Unreliable { payload }.encode();
+            let mut sealed = append_checksum(packet.clone());
+            prop_assert_eq!(verify_checksum(&sealed), Some(packet.as_slice()));
+            let index = flip.index(sealed.len());
+            sealed[index] ^= 1 << bit;
+            prop_assert_eq!(verify_checksum(&sealed), None);
+        }
+
+        #[test]
+        fn reliable_messages_arrive_exactly_once_and_in_order(
+            messages in vec(vec(any::<u8>(), 0..16), 1..40),
+            schedule in vec(fate(), 1..64),
+        ) {
+            let start = Instant::now();
+            let mut sender = ReliableChannel::new(TICK * 5);
+            let mut receiver = ReliableChannel::new(TICK * 5);
+            let mut network = Network {
+                schedule,
+                transmissions: 0,
+                in_flight: vec![],
+            };
+            let mut pending = messages.iter().cloned();
+            let mut delivered = vec![];
+
+            for tick in 0..MAX_TICKS {
+                let now = start + TICK * tick;
+                if let Some(message) = pending.next() {
+                    let packet = sender.send(message, now);
+                    network.transmit(tick, true, packet);
+                }
+                for (to_receiver, packet) in network.due(tick) {
+                    if to_receiver {
+                        let (mut received, ack) = receiver.receive(packet);
+                        delivered.append(&mut received);
+                        if let Some(ack) = ack {
+                            network.transmit(tick, false, ack);
+                        }
+                    } else {
+                        sender.receive(packet);
+                    }
+                }
+                for packet in sender.retransmissions(now) {
+                    network.transmit(tick, true, packet);
+                }
+                if delivered.len() == messages.len() && sender.in_flight() == 0 {
+                    break;
+                }
+            }
+
+            prop_assert_eq!(&delivered, &messages);
+            prop_assert_eq!(sender.in_flight(), 0);
+        }
+    }
+}
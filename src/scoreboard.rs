@@ -0,0 +1,284 @@
+use crate::identity::AccountId;
+use crate::leaderboard::MatchStanding;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    Joined {
+        player: String,
+        account_id: Option<AccountId>,
+    },
+    Scored {
+        player: String,
+        account_id: Option<AccountId>,
+        points: i64,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScoreboardCommand {
+    Show,
+    Spectate(Option<String>),
+}
+
+impl ScoreboardCommand {
+    pub fn parse(input: &str) -> Option<ScoreboardCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":scoreboard", None, None) => Some(ScoreboardCommand::Show),
+            (":spectate", room, None) => {
+                Some(ScoreboardCommand::Spectate(room.map(str::to_string)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoreEntry {
+    pub player: String,
+    pub account_id: Option<AccountId>,
+    pub score: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scoreboard {
+    room: String,
+    entries: Vec<ScoreEntry>,
+    dirty: bool,
+    last_broadcast: Option<Instant>,
+}
+
+impl Scoreboard {
+    pub fn new(room: &str) -> Self {
+        Scoreboard {
+            room: room.to_string(),
+            entries: vec![],
+            dirty: true,
+            last_broadcast: None,
+        }
+    }
+
+    pub fn room(&self) -> &str {
+        &self.room
+    }
+
+    pub fn apply(&mut self, event: GameEvent) {
+        let (player, account_id, points) = match event {
+            GameEvent::Joined { player, account_id } => (player, account_id, 0),
+            GameEvent::Scored {
+                player,
+                account_id,
+                points,
+            } => (player, account_id, points),
+        };
+        match self.entries.iter_mut().find(|entry| entry.player == player) {
+            Some(entry) => {
+                entry.score += points;
+                entry.account_id = entry.account_id.or(account_id);
+                self.dirty |= points != 0;
+            }
+            None => {
+                self.entries.push(ScoreEntry {
+                    player,
+                    account_id,
+                    score: points,
+                });
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn standings(&self) -> Vec<(u32, ScoreEntry)> {
+        let mut sorted = self.entries.clone();
+        sorted.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.player.cmp(&b.player)));
+        let mut standings: Vec<(u32, ScoreEntry)> = Vec::with_capacity(sorted.len());
+        for (index, entry) in sorted.into_iter().enumerate() {
+            let placement = match standings.last() {
+                Some((placement, previous)) if previous.score == entry.score => *placement,
+                _ => index as u32 + 1,
+            };
+            standings.push((placement, entry));
+        }
+        standings
+    }
+
+    pub fn encode(&self) -> String {
+        std::iter::once(format!("SCORE {}", &self.room))
+            .chain(
+                self.standings()
+                    .iter()
+                    .map(|(_, entry)| format!("{}:{}", &entry.player, entry.score)),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn encode_final(&self) -> String {
+        std::iter::once(format!("FINAL {}", &self.room))
+            .chain(self.standings().iter().map(|(placement, entry)| {
+                format!("{}.{}:{}", placement, &entry.player, entry.score)
+            }))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn match_standings(&self) -> Vec<MatchStanding> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                entry.account_id.map(|account_id| MatchStanding {
+                    account_id,
+                    score: entry.score,
+                })
+            })
+            .collect()
+    }
+
+    fn take_due(&mut self, now: Instant, interval: Option<Duration>) -> bool {
+        let periodic = match (interval, self.last_broadcast) {
+            (Some(interval), Some(last)) => now.saturating_duration_since(last) >= interval,
+            _ => false,
+        };
+        if !(self.dirty || periodic) {
+            return false;
+        }
+        self.dirty = false;
+        self.last_broadcast = Some(now);
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct Scoreboards {
+    boards: Arc<Mutex<HashMap<String, Scoreboard>>>,
+    interval: Option<Duration>,
+}
+
+impl Scoreboards {
+    pub fn new(interval: Option<Duration>) -> Self {
+        Scoreboards {
+            boards: Arc::new(Mutex::new(HashMap::new())),
+            interval,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Scoreboard>> {
+        self.boards.lock().expect("Failed to lock scoreboards.")
+    }
+
+    pub fn start(&self, room: &str, players: Vec<(String, Option<AccountId>)>) {
+        let mut scoreboard = Scoreboard::new(room);
+        for (player, account_id) in players.into_iter() {
+            scoreboard.apply(GameEvent::Joined { player, account_id });
+        }
+        self.lock().insert(room.to_string(), scoreboard);
+    }
+
+    pub fn record(&self, room: &str, event: GameEvent) -> bool {
+        match self.lock().get_mut(room) {
+            Some(scoreboard) => {
+                scoreboard.apply(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn current(&self, room: &str) -> Option<String> {
+        self.lock().get(room).map(Scoreboard::encode)
+    }
+
+    pub fn finish(&self, room: &str) -> Option<Scoreboard> {
+        self.lock().remove(room)
+    }
+
+    pub fn due(&self, now: Instant) -> Vec<(String, String)> {
+        self.lock()
+            .values_mut()
+            .filter_map(|scoreboard| {
+                scoreboard
+                    .take_due(now, self.interval)
+                    .then(|| (scoreboard.room.clone(), scoreboard.encode()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_changes_are_coalesced_until_the_next_broadcast() {
+        assert_eq!(
+            ScoreboardCommand::parse(":spectate red\0"),
+            Some(ScoreboardCommand::Spectate(Some("red".to_string())))
+        );
+        assert_eq!(
+            ScoreboardCommand::parse(":spectate"),
+            Some(ScoreboardCommand::Spectate(None))
+        );
+
+        let scoreboards = Scoreboards::new(Some(Duration::from_secs(5)));
+        let start = Instant::now();
+        assert!(!scoreboards.record(
+            "red",
+            GameEvent::Scored {
+                player: "alice".to_string(),
+                account_id: None,
+                points: 1,
+            }
+        ));
+        scoreboards.start(
+            "red",
+            vec![
+                ("alice".to_string(), Some(AccountId(1))),
+                ("Guest2".to_string(), None),
+            ],
+        );
+        assert_eq!(
+            scoreboards.due(start),
+            vec![("red".to_string(), "SCORE red Guest2:0 alice:0".to_string())]
+        );
+        assert!(scoreboards.due(start + Duration::from_secs(1)).is_empty());
+
+        for (player, points) in [("alice", 3), ("Guest2", 5), ("alice", 2), ("bob", 5)].iter() {
+            assert!(scoreboards.record(
+                "red",
+                GameEvent::Scored {
+                    player: player.to_string(),
+                    account_id: None,
+                    points: *points,
+                }
+            ));
+        }
+        assert_eq!(
+            scoreboards.due(start + Duration::from_secs(2)),
+            vec![(
+                "red".to_string(),
+                "SCORE red Guest2:5 alice:5 bob:5".to_string()
+            )]
+        );
+        assert_eq!(scoreboards.due(start + Duration::from_secs(7)).len(), 1);
+
+        let scoreboard = scoreboards.finish("red").expect("Missing scoreboard.");
+        assert_eq!(
+            scoreboard.encode_final(),
+            "FINAL red 1.Guest2:5 1.alice:5 1.bob:5"
+        );
+        assert_eq!(
+            scoreboard
+                .match_standings()
+                .iter()
+                .map(|standing| (standing.account_id, standing.score))
+                .collect::<Vec<_>>(),
+            vec![(AccountId(1), 5)]
+        );
+        assert_eq!(scoreboards.current("red"), None);
+    }
+}
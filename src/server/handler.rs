@@ -0,0 +1,450 @@
+use crate::frame::TextFrameDecoder;
+use crate::transport::SendPriority;
+use crate::transport::{Transport, HALF_CLOSE_TIMEOUT};
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const BUFFER_SIZE: usize = 2048;
+pub const DEFAULT_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Disconnect,
+}
+
+#[derive(Clone)]
+pub struct ClientContext {
+    pub id: u32,
+    pub address: String,
+    pub transport: Arc<dyn Transport>,
+}
+
+impl ClientContext {
+    pub fn send_text(&self, text: &str) {
+        let _ = self.transport.send_text(text);
+    }
+}
+
+pub trait ServerHandler: Send + Sync {
+    fn on_client_connected(&self, _client: &ClientContext) {}
+
+    fn on_message(&self, client: &ClientContext, message: &str) -> Flow;
+
+    fn on_client_disconnected(&self, _client: &ClientContext) {}
+
+    fn on_tick(&self) {}
+}
+
+pub struct ClientSession {
+    pub client: ClientContext,
+    decoder: TextFrameDecoder,
+}
+
+impl ClientSession {
+    pub fn new(client: ClientContext, max_frame_size: usize) -> Self {
+        ClientSession {
+            client,
+            decoder: TextFrameDecoder::with_max_frame_size(max_frame_size),
+        }
+    }
+
+    pub fn deliver(&mut self, handler: &dyn ServerHandler, data: &[u8]) -> Flow {
+        self.decoder.push(data);
+        loop {
+            let message = match self.decoder.next_frame() {
+                Ok(Some(message)) => message,
+                Ok(None) => return Flow::Continue,
+                Err(e) => {
+                    eprintln!(
+                        "クライアント{}から不正なフレームを受信しました：{}\n",
+                        self.client.id, e
+                    );
+                    let _ = self.client.transport.send_text_prioritized(
+                        &format!("ERR Protocol error: {}.", e),
+                        SendPriority::Control,
+                    );
+                    return Flow::Disconnect;
+                }
+            };
+            if handler.on_message(&self.client, &message) == Flow::Disconnect {
+                return Flow::Disconnect;
+            }
+        }
+    }
+}
+
+pub fn half_close(transport: &dyn Transport, buffer: &mut [u8]) {
+    if !transport.shutdown_send() {
+        return;
+    }
+    let deadline = Instant::now() + HALF_CLOSE_TIMEOUT;
+    while Instant::now() < deadline {
+        match transport.receive(buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
+
+pub(crate) fn catch_panic(f: impl FnOnce()) -> Result<(), String> {
+    std::panic::catch_unwind(AssertUnwindSafe(f))
+        .map_err(|panic| panic_message(&*panic).to_string())
+}
+
+fn guarded(client: &ClientContext, stage: &str, f: impl FnOnce()) -> bool {
+    match catch_panic(f) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!(
+                "クライアント{}（{}）の{}中にパニックが発生しました：{}\n",
+                client.id, &client.address, stage, e
+            );
+            false
+        }
+    }
+}
+
+fn run_session(handler: &dyn ServerHandler, client: &ClientContext, max_frame_size: usize) {
+    handler.on_client_connected(client);
+
+    let mut recv_buffer = [0_u8; BUFFER_SIZE];
+    let mut session = ClientSession::new(client.clone(), max_frame_size);
+    loop {
+        let recv_size = match session.client.transport.receive(&mut recv_buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(recv_size) => recv_size,
+        };
+        if session.deliver(handler, &recv_buffer[..recv_size]) == Flow::Disconnect {
+            half_close(&*session.client.transport, &mut recv_buffer);
+            break;
+        }
+    }
+}
+
+pub fn serve_client(
+    handler: Arc<dyn ServerHandler>,
+    client: ClientContext,
+    max_frame_size: usize,
+) -> JoinHandle<()> {
+    supervise_client(handler, client, max_frame_size, |_| {})
+}
+
+pub fn supervise_client<F>(
+    handler: Arc<dyn ServerHandler>,
+    client: ClientContext,
+    max_frame_size: usize,
+    recover: F,
+) -> JoinHandle<()>
+where
+    F: FnOnce(&ClientContext) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let served = guarded(&client, "セッション処理", || {
+            run_session(&*handler, &client, max_frame_size)
+        });
+        client.transport.close();
+        let released = guarded(&client, "切断処理", || {
+            handler.on_client_disconnected(&client)
+        });
+        if !(served && released) {
+            recover(&client);
+        }
+    })
+}
+
+pub fn spawn_ticker(handler: Arc<dyn ServerHandler>, interval: Duration) -> JoinHandle<()> {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        if let Err(e) = catch_panic(|| handler.on_tick()) {
+            eprintln!("ティック処理中にパニックが発生しました：{}\n", e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::DEFAULT_MAX_FRAME_SIZE;
+    use crate::transport::MockTransport;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl RecordingHandler {
+        fn record(&self, call: String) {
+            self.calls.lock().expect("Failed to lock calls.").push(call);
+        }
+
+        fn ticks(&self) -> usize {
+            self.calls
+                .lock()
+                .expect("Failed to lock calls.")
+                .iter()
+                .filter(|call| *call == "tick")
+                .count()
+        }
+    }
+
+    impl ServerHandler for RecordingHandler {
+        fn on_client_connected(&self, client: &ClientContext) {
+            self.record(format!("connected {}", client.id));
+            client.send_text("Hello");
+        }
+
+        fn on_message(&self, client: &ClientContext, message: &str) -> Flow {
+            self.record(format!("message {} {}", client.id, message));
+            if message == "quit" {
+                return Flow::Disconnect;
+            }
+            if message == "crash" {
+                panic!("handler bug");
+            }
+            client.send_text(message);
+            Flow::Continue
+        }
+
+        fn on_client_disconnected(&self, client: &ClientContext) {
+            self.record(format!("disconnected {}", client.id));
+        }
+
+        fn on_tick(&self) {
+            self.record("tick".to_string());
+            if self.ticks() == 1 {
+                panic!("tick bug");
+            }
+        }
+    }
+
+    #[test]
+    fn handler_sees_connect_messages_and_disconnect_in_order() {
+        let handler = Arc::new(RecordingHandler::default());
+        let transport = Arc::new(MockTransport::new());
+        transport
+            .script_read(b"ping\0pi")
+            .script_read(b"ng\0quit\0")
+            .script_text("never read");
+
+        serve_client(
+            handler.clone(),
+            ClientContext {
+                id: 3,
+                address: "127.0.0.1".to_string(),
+                transport: transport.clone(),
+            },
+            DEFAULT_MAX_FRAME_SIZE,
+        )
+        .join()
+        .expect("Client thread panicked.");
+
+        assert_eq!(
+            *handler.calls.lock().expect("Failed to lock calls."),
+            vec![
+                "connected 3",
+                "message 3 ping",
+                "message 3 ping",
+                "message 3 quit",
+                "disconnected 3"
+            ]
+        );
+        assert_eq!(transport.sent_text(), vec!["Hello", "ping", "ping"]);
+        assert!(transport.is_send_shut_down());
+        assert!(transport.is_closed());
+    }
+
+    #[test]
+    fn japanese_split_across_reads_arrives_intact() {
+        let handler = Arc::new(RecordingHandler::default());
+        let transport = Arc::new(MockTransport::new());
+        let bytes = "日本語\0quit\0".as_bytes();
+        transport
+            .script_read(&bytes[..2])
+            .script_read(&bytes[2..7])
+            .script_read(&bytes[7..]);
+
+        serve_client(
+            handler.clone(),
+            ClientContext {
+                id: 1,
+                address: "127.0.0.1".to_string(),
+                transport: transport.clone(),
+            },
+            DEFAULT_MAX_FRAME_SIZE,
+        )
+        .join()
+        .expect("Client thread panicked.");
+
+        assert_eq!(transport.sent_text(), vec!["Hello", "日本語"]);
+    }
+
+    #[test]
+    fn oversized_frames_end_the_session_with_a_protocol_error() {
+        let handler = Arc::new(RecordingHandler::default());
+        let transport = Arc::new(MockTransport::new());
+        transport
+            .script_read(b"ping\0")
+            .script_read(b"0123456789")
+            .script_text("never read");
+
+        serve_client(
+            handler.clone(),
+            ClientContext {
+                id: 2,
+                address: "127.0.0.1".to_string(),
+                transport: transport.clone(),
+            },
+            8,
+        )
+        .join()
+        .expect("Client thread panicked.");
+
+        assert_eq!(
+            transport.sent_text(),
+            vec![
+                "Hello",
+                "ping",
+                "ERR Protocol error: a frame of 10 bytes exceeds the 8-byte limit."
+            ]
+        );
+        assert!(transport.is_closed());
+        assert_eq!(
+            handler
+                .calls
+                .lock()
+                .expect("Failed to lock calls.")
+                .last()
+                .map(String::as_str),
+            Some("disconnected 2")
+        );
+    }
+
+    #[test]
+    fn closed_connections_still_notify_the_handler() {
+        let handler = Arc::new(RecordingHandler::default());
+        let transport = Arc::new(MockTransport::new());
+
+        serve_client(
+            handler.clone(),
+            ClientContext {
+                id: 0,
+                address: "127.0.0.1".to_string(),
+                transport,
+            },
+            DEFAULT_MAX_FRAME_SIZE,
+        )
+        .join()
+        .expect("Client thread panicked.");
+
+        assert_eq!(
+            *handler.calls.lock().expect("Failed to lock calls."),
+            vec!["connected 0", "disconnected 0"]
+        );
+    }
+
+    #[test]
+    fn panicking_sessions_are_closed_released_and_recovered() {
+        let handler = Arc::new(RecordingHandler::default());
+        let transport = Arc::new(MockTransport::new());
+        transport
+            .script_read(b"ping\0crash\0")
+            .script_text("never read");
+        let recovered = Arc::new(Mutex::new(vec![]));
+
+        supervise_client(
+            handler.clone(),
+            ClientContext {
+                id: 4,
+                address: "127.0.0.1".to_string(),
+                transport: transport.clone(),
+            },
+            DEFAULT_MAX_FRAME_SIZE,
+            {
+                let recovered = recovered.clone();
+                move |client| {
+                    recovered
+                        .lock()
+                        .expect("Failed to lock recovered clients.")
+                        .push(client.id)
+                }
+            },
+        )
+        .join()
+        .expect("A panicking session escaped its supervisor.");
+
+        assert_eq!(
+            *handler.calls.lock().expect("Failed to lock calls."),
+            vec![
+                "connected 4",
+                "message 4 ping",
+                "message 4 crash",
+                "disconnected 4"
+            ]
+        );
+        assert_eq!(transport.sent_text(), vec!["Hello", "ping"]);
+        assert!(transport.is_closed());
+        assert_eq!(
+            *recovered.lock().expect("Failed to lock recovered clients."),
+            vec![4]
+        );
+    }
+
+    #[test]
+    fn the_ticker_survives_a_panicking_tick() {
+        let handler = Arc::new(RecordingHandler::default());
+        spawn_ticker(handler.clone(), Duration::from_millis(1));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while handler.ticks() < 3 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(handler.ticks() >= 3);
+    }
+
+    #[test]
+    fn sessions_can_be_fed_from_a_single_threaded_loop() {
+        let handler = RecordingHandler::default();
+        let transport = Arc::new(MockTransport::new());
+        let mut first = ClientSession::new(
+            ClientContext {
+                id: 1,
+                address: "127.0.0.1".to_string(),
+                transport: transport.clone(),
+            },
+            DEFAULT_MAX_FRAME_SIZE,
+        );
+        let mut second = ClientSession::new(
+            ClientContext {
+                id: 2,
+                address: "127.0.0.1".to_string(),
+                transport: transport.clone(),
+            },
+            DEFAULT_MAX_FRAME_SIZE,
+        );
+
+        assert_eq!(first.deliver(&handler, b"he"), Flow::Continue);
+        assert_eq!(second.deliver(&handler, b"hi\0"), Flow::Continue);
+        assert_eq!(first.deliver(&handler, b"llo\0qu"), Flow::Continue);
+        assert_eq!(first.deliver(&handler, b"it\0ignored\0"), Flow::Disconnect);
+
+        assert_eq!(
+            *handler.calls.lock().expect("Failed to lock calls."),
+            vec!["message 2 hi", "message 1 hello", "message 1 quit"]
+        );
+        assert_eq!(transport.sent_text(), vec!["hi", "hello"]);
+    }
+}
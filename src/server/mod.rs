@@ -0,0 +1,8 @@
+mod handler;
+mod net_server;
+#[cfg(feature = "winsock")]
+mod winsock;
+pub use handler::*;
+pub use net_server::*;
+#[cfg(feature = "winsock")]
+pub use winsock::*;
@@ -0,0 +1,483 @@
+use super::catch_panic;
+#[cfg(feature = "winsock")]
+use super::startup_wsa;
+use crate::codec::{Codec, JsonCodec, Message};
+use crate::config::DEFAULT_PORT;
+use crate::frame::{length_prefixed, LengthPrefixedDecoder, DEFAULT_MAX_FRAME_SIZE};
+use crate::layers::{CompressedTransport, CompressionConfig, LayeredTransport, Pipeline};
+use crate::transport::{PriorityTransport, Transport, TransportKind};
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::thread::JoinHandle;
+
+pub const DEFAULT_MAX_CLIENTS: usize = 10;
+const BUFFER_SIZE: usize = 2048;
+
+type MessageHandler = Arc<dyn Fn(&ClientHandle, Message) + Send + Sync>;
+type DisconnectHandler = Arc<dyn Fn(&ClientHandle) + Send + Sync>;
+type Slots = Arc<RwLock<Vec<Option<Arc<dyn Transport>>>>>;
+
+fn send_message(
+    transport: &Arc<dyn Transport>,
+    codec: &Arc<dyn Codec>,
+    message: &Message,
+) -> std::io::Result<usize> {
+    let payload = codec
+        .encode(message)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    transport.send_prioritized(&length_prefixed(&payload), message.priority())
+}
+
+#[derive(Clone)]
+pub struct ClientHandle {
+    id: usize,
+    address: String,
+    transport: Arc<dyn Transport>,
+    codec: Arc<dyn Codec>,
+    slots: Slots,
+}
+
+impl ClientHandle {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn send(&self, message: &Message) -> std::io::Result<usize> {
+        send_message(&self.transport, &self.codec, message)
+    }
+
+    pub fn send_to(&self, id: usize, message: &Message) -> std::io::Result<usize> {
+        let transport = self
+            .slots
+            .read()
+            .expect("Failed to lock client slots.")
+            .get(id)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| Error::new(ErrorKind::NotConnected, "no client in that slot"))?;
+        send_message(&transport, &self.codec, message)
+    }
+
+    fn connected(&self) -> Vec<(usize, Arc<dyn Transport>)> {
+        self.slots
+            .read()
+            .expect("Failed to lock client slots.")
+            .iter()
+            .enumerate()
+            .filter_map(|(id, slot)| slot.clone().map(|transport| (id, transport)))
+            .collect()
+    }
+
+    pub fn broadcast(&self, message: &Message) {
+        for (_, transport) in self.connected() {
+            let _ = send_message(&transport, &self.codec, message);
+        }
+    }
+
+    pub fn close(&self) {
+        if !self.transport.shutdown_send() {
+            self.transport.shutdown();
+        }
+    }
+}
+
+pub struct NetServerBuilder {
+    port: u16,
+    transport: TransportKind,
+    layers: Option<Pipeline>,
+    compression: Option<CompressionConfig>,
+    codec: Arc<dyn Codec>,
+    max_clients: usize,
+    max_frame_size: usize,
+    on_message: Option<MessageHandler>,
+    on_disconnect: Option<DisconnectHandler>,
+}
+
+impl NetServerBuilder {
+    pub fn bind(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn transport(mut self, transport: TransportKind) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn layers(mut self, layers: Pipeline) -> Self {
+        self.layers = Some(layers);
+        self
+    }
+
+    pub fn compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn codec(mut self, codec: Arc<dyn Codec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn on_message<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ClientHandle, Message) + Send + Sync + 'static,
+    {
+        self.on_message = Some(Arc::new(handler));
+        self
+    }
+
+    pub fn on_disconnect<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ClientHandle) + Send + Sync + 'static,
+    {
+        self.on_disconnect = Some(Arc::new(handler));
+        self
+    }
+
+    pub fn build(self) -> NetServer {
+        let mut slots = vec![];
+        slots.resize_with(self.max_clients, || None);
+        NetServer {
+            port: self.port,
+            transport: self.transport,
+            layers: self.layers,
+            compression: self.compression,
+            codec: self.codec,
+            max_frame_size: self.max_frame_size,
+            on_message: self.on_message,
+            on_disconnect: self.on_disconnect,
+            slots: Arc::new(RwLock::new(slots)),
+        }
+    }
+}
+
+pub struct NetServer {
+    port: u16,
+    transport: TransportKind,
+    layers: Option<Pipeline>,
+    compression: Option<CompressionConfig>,
+    codec: Arc<dyn Codec>,
+    max_frame_size: usize,
+    on_message: Option<MessageHandler>,
+    on_disconnect: Option<DisconnectHandler>,
+    slots: Slots,
+}
+
+impl NetServer {
+    pub fn builder() -> NetServerBuilder {
+        NetServerBuilder {
+            port: DEFAULT_PORT,
+            transport: TransportKind::Tcp,
+            layers: None,
+            compression: None,
+            codec: Arc::new(JsonCodec),
+            max_clients: DEFAULT_MAX_CLIENTS,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            on_message: None,
+            on_disconnect: None,
+        }
+    }
+
+    pub fn run(&self) -> bool {
+        #[cfg(feature = "winsock")]
+        if !unsafe { startup_wsa() } {
+            return false;
+        }
+        let listener = match self.transport.bind(self.port) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!(
+                    "{}のリスナーを起動できませんでした：{}\n",
+                    self.transport.name(),
+                    e
+                );
+                return false;
+            }
+        };
+        println!(
+            "サーバーが起動しました。（{}、コーデック：{}）\n",
+            self.transport.name(),
+            self.codec.name()
+        );
+
+        loop {
+            let (mut transport, address) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    eprintln!("クライアントと接続失敗。エラー：{}\n", e);
+                    continue;
+                }
+            };
+            if let Some(layers) = self.layers.clone() {
+                transport = Arc::new(LayeredTransport::new(transport, layers));
+            }
+            if let Some(compression) = self.compression.clone() {
+                match CompressedTransport::negotiate(transport.clone(), compression) {
+                    Ok(compressed) => transport = Arc::new(compressed),
+                    Err(e) => {
+                        eprintln!("圧縮方式の交渉に失敗しました：{}\n", e);
+                        transport.close();
+                        continue;
+                    }
+                }
+            }
+            transport = Arc::new(PriorityTransport::new(transport));
+            println!(
+                "クライアントが接続してきました！：IPAddress({})\n",
+                &address
+            );
+            let _ = self.serve(transport, address);
+        }
+    }
+
+    fn claim_slot(&self, transport: &Arc<dyn Transport>) -> Option<usize> {
+        let mut slots = self.slots.write().expect("Failed to lock client slots.");
+        let id = slots.iter().position(|slot| slot.is_none())?;
+        slots[id] = Some(transport.clone());
+        Some(id)
+    }
+
+    fn serve(&self, transport: Arc<dyn Transport>, address: String) -> Option<JoinHandle<()>> {
+        let id = match self.claim_slot(&transport) {
+            Some(id) => id,
+            None => {
+                println!(
+                    "接続数が上限に達したため、{}からの接続を拒否しました\n",
+                    &address
+                );
+                transport.close();
+                return None;
+            }
+        };
+        let client = ClientHandle {
+            id,
+            address,
+            transport,
+            codec: self.codec.clone(),
+            slots: self.slots.clone(),
+        };
+        let on_message = self.on_message.clone();
+        let on_disconnect = self.on_disconnect.clone();
+        let max_frame_size = self.max_frame_size;
+        Some(std::thread::spawn(move || {
+            let served = catch_panic(|| {
+                let mut recv_buffer = [0_u8; BUFFER_SIZE];
+                let mut decoder = LengthPrefixedDecoder::with_max_frame_size(max_frame_size);
+                'outer_loop: loop {
+                    let recv_size = match client.transport.receive(&mut recv_buffer) {
+                        Ok(0) | Err(_) => break 'outer_loop,
+                        Ok(recv_size) => recv_size,
+                    };
+                    decoder.push(&recv_buffer[..recv_size]);
+                    loop {
+                        let frame = match decoder.next_frame() {
+                            Ok(Some(frame)) => frame,
+                            Ok(None) => break,
+                            Err(e) => {
+                                eprintln!(
+                                    "クライアント{}から不正なフレームを受信しました：{}\n",
+                                    client.id, e
+                                );
+                                break 'outer_loop;
+                            }
+                        };
+                        match client.codec.decode(&frame) {
+                            Ok(message) => {
+                                if let Some(on_message) = on_message.as_ref() {
+                                    on_message(&client, message);
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!(
+                                    "クライアント{}から不正なメッセージを受信しました：{}\n",
+                                    client.id, e
+                                );
+                                break 'outer_loop;
+                            }
+                        }
+                    }
+                }
+            });
+            let released = catch_panic(|| {
+                if let Some(on_disconnect) = on_disconnect.as_ref() {
+                    on_disconnect(&client);
+                }
+            });
+            let report = |stage: &str, result: Result<(), String>| {
+                if let Err(e) = result {
+                    eprintln!(
+                        "クライアント{}の{}中にパニックが発生しました：{}\n",
+                        client.id, stage, e
+                    );
+                }
+            };
+            report("セッション処理", served);
+            report("切断処理", released);
+            client.slots.write().unwrap_or_else(PoisonError::into_inner)[client.id] = None;
+            client.transport.close();
+            println!("クライアント{}が切断しました\n", client.id);
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ChatBody;
+    use crate::frame::LengthPrefixedDecoder;
+    use crate::transport::MockTransport;
+
+    fn script_message(transport: &MockTransport, message: &Message) {
+        let payload = JsonCodec
+            .encode(message)
+            .expect("Failed to encode message.");
+        transport.script_read(&length_prefixed(&payload));
+    }
+
+    fn received(transport: &MockTransport) -> Vec<Message> {
+        let mut decoder = LengthPrefixedDecoder::new();
+        decoder.push(&transport.writes().concat());
+        std::iter::from_fn(|| decoder.next_frame().expect("Received an oversized frame."))
+            .map(|frame| JsonCodec.decode(&frame).expect("Failed to decode message."))
+            .collect()
+    }
+
+    fn chat(text: &str) -> Message {
+        Message::Chat {
+            room: "lobby".to_string(),
+            sender: None,
+            body: ChatBody::Plain(text.to_string()),
+        }
+    }
+
+    #[test]
+    fn handler_broadcasts_decoded_messages_and_frees_the_slot() {
+        let server = NetServer::builder()
+            .max_clients(2)
+            .on_message(|client, message| client.broadcast(&message))
+            .build();
+        let listener = Arc::new(MockTransport::new());
+        let speaker = Arc::new(MockTransport::new());
+        server.claim_slot(&(listener.clone() as Arc<dyn Transport>));
+        script_message(&speaker, &chat("hello"));
+
+        server
+            .serve(speaker.clone(), "127.0.0.1".to_string())
+            .expect("Server rejected the client.")
+            .join()
+            .expect("Client thread panicked.");
+
+        assert_eq!(received(&speaker), vec![chat("hello")]);
+        assert_eq!(received(&listener), vec![chat("hello")]);
+        assert!(speaker.is_closed());
+        assert!(server.slots.read().expect("Failed to lock client slots.")[1].is_none());
+    }
+
+    #[test]
+    fn messages_reach_a_single_slot_and_disconnects_are_reported() {
+        let disconnected = Arc::new(RwLock::new(vec![]));
+        let server = {
+            let disconnected = disconnected.clone();
+            NetServer::builder()
+                .max_clients(3)
+                .on_message(|client, message| {
+                    client.send_to(0, &message).expect("Failed to send.");
+                    assert!(client.send_to(2, &message).is_err());
+                })
+                .on_disconnect(move |client| {
+                    disconnected
+                        .write()
+                        .expect("Failed to lock disconnected clients.")
+                        .push(client.id())
+                })
+                .build()
+        };
+        let target = Arc::new(MockTransport::new());
+        let speaker = Arc::new(MockTransport::new());
+        server.claim_slot(&(target.clone() as Arc<dyn Transport>));
+        script_message(&speaker, &chat("psst"));
+
+        server
+            .serve(speaker.clone(), "127.0.0.1".to_string())
+            .expect("Server rejected the client.")
+            .join()
+            .expect("Client thread panicked.");
+
+        assert_eq!(received(&target), vec![chat("psst")]);
+        assert!(received(&speaker).is_empty());
+        assert_eq!(
+            *disconnected
+                .read()
+                .expect("Failed to lock disconnected clients."),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn clients_beyond_max_clients_are_rejected() {
+        let server = NetServer::builder().max_clients(1).build();
+        let first = Arc::new(MockTransport::new());
+        let second = Arc::new(MockTransport::new());
+        server.claim_slot(&(first as Arc<dyn Transport>));
+
+        assert!(server
+            .serve(second.clone(), "127.0.0.1".to_string())
+            .is_none());
+        assert!(second.is_closed());
+    }
+
+    #[test]
+    fn panicking_handlers_still_close_the_client_and_free_its_slot() {
+        let server = NetServer::builder()
+            .max_clients(1)
+            .on_message(|_, _| panic!("message handler bug"))
+            .on_disconnect(|_| panic!("disconnect handler bug"))
+            .build();
+        let crasher = Arc::new(MockTransport::new());
+        script_message(&crasher, &chat("boom"));
+
+        server
+            .serve(crasher.clone(), "127.0.0.1".to_string())
+            .expect("Server rejected the client.")
+            .join()
+            .expect("A panicking handler escaped the client thread.");
+
+        assert!(crasher.is_closed());
+        assert_eq!(
+            server.claim_slot(&(Arc::new(MockTransport::new()) as Arc<dyn Transport>)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn undecodable_frames_disconnect_the_client() {
+        let server = NetServer::builder().build();
+        let transport = Arc::new(MockTransport::new());
+        transport.script_read(&length_prefixed(b"not json"));
+        script_message(&transport, &chat("never handled"));
+
+        server
+            .serve(transport.clone(), "127.0.0.1".to_string())
+            .expect("Server rejected the client.")
+            .join()
+            .expect("Client thread panicked.");
+
+        assert!(transport.writes().is_empty());
+        assert!(transport.is_closed());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::bindings::Windows::Win32::NetworkManagement::IpHelper::AF_INET;
+use crate::bindings::Windows::Win32::Networking::WinSock::{
+    accept, bind, htons, socket, WSACleanup, WSAData, WSAGetLastError, WSAStartup, IN_ADDR,
+    IN_ADDR_0, SOCKADDR, SOCKADDR_IN, SOCKET, SOCKET_ERROR, SOCK_STREAM,
+};
+use crate::bindings::Windows::Win32::System::SystemServices::CHAR;
+use std::net::{Ipv4Addr, SocketAddr};
+use winapi::shared::minwindef::MAKEWORD;
+use winapi::shared::ws2def::INADDR_ANY;
+use winapi::um::winsock2::INVALID_SOCKET;
+
+const CLIENT_ADDR_SIZE: usize = std::mem::size_of::<SOCKADDR_IN>();
+
+pub fn empty_address() -> SOCKADDR_IN {
+    SOCKADDR_IN {
+        sin_family: 0,
+        sin_port: 0,
+        sin_addr: IN_ADDR {
+            S_un: IN_ADDR_0 { S_addr: 0 },
+        },
+        sin_zero: [CHAR(0); 8],
+    }
+}
+
+pub fn client_ip(addr: &SOCKADDR_IN) -> String {
+    unsafe {
+        format!(
+            "{}.{}.{}.{}",
+            addr.sin_addr.S_un.S_un_b.s_b1,
+            addr.sin_addr.S_un.S_un_b.s_b2,
+            addr.sin_addr.S_un.S_un_b.s_b3,
+            addr.sin_addr.S_un.S_un_b.s_b4,
+        )
+    }
+}
+
+pub fn socket_addr(addr: &SOCKADDR_IN) -> SocketAddr {
+    let octets = unsafe { addr.sin_addr.S_un.S_un_b };
+    SocketAddr::from((
+        Ipv4Addr::new(octets.s_b1, octets.s_b2, octets.s_b3, octets.s_b4),
+        u16::from_be(addr.sin_port),
+    ))
+}
+
+pub unsafe fn check_socket_error(result: i32, msg: &str) -> bool {
+    if result == SOCKET_ERROR {
+        eprintln!("{}", msg);
+        eprintln!("Error: {}", WSAGetLastError().0);
+        WSACleanup();
+        false
+    } else {
+        true
+    }
+}
+
+pub unsafe fn startup_wsa() -> bool {
+    let version = MAKEWORD(2, 2);
+    let mut wsa_data = WSAData::default();
+    let result = WSAStartup(version, &mut wsa_data as *mut _);
+    if result != 0 {
+        eprintln!(
+            "WSAStartup failed to initialize with error: {}\n",
+            WSAGetLastError().0
+        );
+        false
+    } else {
+        true
+    }
+}
+
+pub unsafe fn create_and_bind_socket(port: u16) -> Option<SOCKET> {
+    let addr = SOCKADDR_IN {
+        sin_family: AF_INET.0 as u16,
+        sin_port: htons(port),
+        sin_addr: IN_ADDR {
+            S_un: IN_ADDR_0 { S_addr: INADDR_ANY },
+        },
+        sin_zero: [CHAR(0); 8],
+    };
+    let socket = socket(AF_INET.0 as i32, SOCK_STREAM as i32, 0);
+    if socket.0 == INVALID_SOCKET {
+        eprintln!("ソケットの生成に失敗しました：{}\n", WSAGetLastError().0);
+        WSACleanup();
+        None
+    } else {
+        let result = bind(
+            &socket,
+            &addr as *const _ as *const SOCKADDR,
+            std::mem::size_of::<SOCKADDR_IN>() as i32,
+        );
+        if !check_socket_error(result, "Socket binding failed.") {
+            None
+        } else {
+            Some(socket)
+        }
+    }
+}
+
+pub unsafe fn accept_client(server_socket: &SOCKET) -> Option<(SOCKET, SOCKADDR_IN)> {
+    let mut client_addr = empty_address();
+    let mut client_addr_size = CLIENT_ADDR_SIZE;
+    let accepted_socket = accept(
+        server_socket,
+        &mut client_addr as *mut _ as *mut SOCKADDR,
+        &mut client_addr_size as *mut _ as *mut i32,
+    );
+    if accepted_socket.0 == INVALID_SOCKET {
+        None
+    } else {
+        Some((accepted_socket, client_addr))
+    }
+}
@@ -0,0 +1,2454 @@
+use crate::anticheat::{AbilityCommand, Verdict};
+use crate::clock::TimeCommand;
+use crate::cluster::{ReadCommand, ReceiptStatus, Whisper, WhisperCommand, WhisperReceipt};
+use crate::console;
+use crate::context::ServerContext;
+use crate::events::ServerEvent;
+use crate::guilds::{
+    guild_message, guild_notice, roster, GuildCommand, GuildError, GuildResult, Guilds,
+};
+use crate::identity::{AccountId, AuthCommand};
+use crate::latency::{LatencyTracker, PingCommand};
+use crate::leaderboard::{format_entry, LeaderboardCommand};
+use crate::notify::OfflineMessage;
+use crate::p2p::{MeshCommand, MeshMember, MeshSignal};
+use crate::plugins::{ChatContext, PluginOutcome, PluginRegistry, ServerRequest};
+use crate::pow::{Challenge, PowCommand};
+use crate::presence::{member_list, presence_notice, Presence, PresenceCommand, MAX_FRIENDS};
+use crate::recorder::{redact, PacketKind};
+use crate::rooms::{ready_check, valid_room_name, RoomCommand, DEFAULT_ROOM, MAX_ROOM_NAME_LENGTH};
+use crate::scoreboard::{GameEvent, ScoreboardCommand};
+use crate::server::{ClientContext, Flow, ServerHandler};
+use crate::storage::GuildRecord;
+use crate::transfer::{handoff_instruction, PlayerState, TransferCommand};
+use crate::transport::{EncodingCommand, SendPriority, TextEncoding, Transport};
+use crate::zone::ZoneCommand;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Instant, SystemTime};
+
+const RECV_PREFIX: &str = "受信データ：";
+
+pub struct ChatHandler {
+    context: ServerContext,
+    server_msg: RwLock<String>,
+    plugins: PluginRegistry,
+    next_whisper_id: AtomicU64,
+}
+
+impl ChatHandler {
+    pub fn new(context: ServerContext, plugins: PluginRegistry) -> Self {
+        ChatHandler {
+            context,
+            server_msg: RwLock::new("Hello".to_string()),
+            plugins,
+            next_whisper_id: AtomicU64::new(1),
+        }
+    }
+
+    fn apply_requests(&self) {
+        for request in self.plugins.drain_requests() {
+            match request {
+                ServerRequest::Broadcast(text) => {
+                    for (_, _, transport, _) in self.context.clients.connected() {
+                        send_text(&transport, &format!("[Server] {}", text));
+                    }
+                }
+                ServerRequest::Kick(client_id) => {
+                    for (_, _, transport, _) in self
+                        .context
+                        .clients
+                        .connected()
+                        .into_iter()
+                        .filter(|(id, _, _, _)| *id == client_id)
+                    {
+                        console::system(format_args!(
+                            "プラグインがクライアント{}を切断します\n",
+                            client_id
+                        ));
+                        send_prioritized(&transport, "Kicked by server.", SendPriority::Control);
+                        transport.shutdown();
+                    }
+                }
+                ServerRequest::SetMotd(motd) => {
+                    console::system(format_args!("MOTDを変更しました：{}\n", &motd));
+                    *self
+                        .server_msg
+                        .write()
+                        .expect("Failed to lock server message.") = motd;
+                }
+                ServerRequest::StartMatch(room) => start_match(&self.context, &room),
+                ServerRequest::Score { client_id, points } => self.award(client_id, points),
+                ServerRequest::EndMatch(room) => {
+                    if !finish_match(&self.context, &room) {
+                        console::error(format_args!("ルーム{}では試合が行われていません\n", &room));
+                    }
+                }
+            }
+        }
+    }
+
+    fn notify_mesh(&self, members: &[MeshMember], signal: &MeshSignal) {
+        let signal = signal.encode();
+        for member in members.iter() {
+            let transport = self
+                .context
+                .clients
+                .get(member.client_id)
+                .and_then(|other_client| {
+                    other_client
+                        .read()
+                        .expect("Failed to lock socket client.")
+                        .transport
+                        .clone()
+                });
+            if let Some(transport) = transport {
+                send_text(&transport, &signal);
+            }
+        }
+    }
+
+    fn enforce(
+        &self,
+        client: &ClientContext,
+        name: &str,
+        verdict: Verdict,
+        rejection: &str,
+    ) -> Option<Flow> {
+        let violation = match verdict {
+            Verdict::Allowed => return None,
+            Verdict::Flagged(violation) => violation,
+            Verdict::Kick(violation) => {
+                console::system(format_args!(
+                    "{}を不正行為により切断します：{}\n",
+                    name, &violation
+                ));
+                send_prioritized(
+                    &client.transport,
+                    "Disconnected: cheating detected.",
+                    SendPriority::Control,
+                );
+                return Some(Flow::Disconnect);
+            }
+        };
+        console::system(format_args!(
+            "{}の不正行為を検知しました：{}\n",
+            name, &violation
+        ));
+        client.send_text(rejection);
+        Some(Flow::Continue)
+    }
+
+    fn notify_room(&self, room: &str, text: &str) {
+        for member in self.context.clients.in_room(room).iter() {
+            let transport = member
+                .read()
+                .expect("Failed to lock socket client.")
+                .transport
+                .clone();
+            if let Some(transport) = transport {
+                send_text(&transport, text);
+            }
+        }
+    }
+
+    fn broadcast_announcements(&self, now: SystemTime) {
+        for text in self.context.announcements.due(now) {
+            console::system(format_args!("告知を配信しました：{}\n", &text));
+            for (_, _, transport, _) in self.context.clients.connected() {
+                send_text(&transport, &format!("[Server] {}", text));
+            }
+        }
+    }
+
+    fn fire_scheduled_events(&self) {
+        for event in self.context.clock.advance() {
+            console::system(format_args!(
+                "予定イベント{}の時刻になりました：{}\n",
+                event.id, &event.label
+            ));
+            for (_, _, transport, _) in self.context.clients.connected() {
+                send_text(&transport, &event.encode_start());
+            }
+        }
+    }
+
+    fn award(&self, client_id: u32, points: i64) {
+        let (player, account_id, room) = match self.context.clients.get(client_id) {
+            Some(socket_client) => {
+                let client_lock = socket_client.read().expect("Failed to lock socket client.");
+                (
+                    client_lock.display_name(),
+                    client_lock.account_id,
+                    client_lock.room.clone(),
+                )
+            }
+            None => return,
+        };
+        let event = GameEvent::Scored {
+            player,
+            account_id,
+            points,
+        };
+        if !self.context.scoreboards.record(&room, event) {
+            console::error(format_args!("ルーム{}では試合が行われていません\n", &room));
+        }
+    }
+
+    fn broadcast_scoreboards(&self, now: Instant) {
+        for (room, line) in self.context.scoreboards.due(now) {
+            for transport in self.context.clients.audience(&room).iter() {
+                send_prioritized(transport, &line, SendPriority::GameState);
+            }
+        }
+    }
+
+    fn check_ready(&self, room: &str) {
+        let members = self.context.clients.in_room(room);
+        let states = members
+            .iter()
+            .map(|member| {
+                let member_lock = member.read().expect("Failed to lock socket client.");
+                (member_lock.ready, member_lock.afk)
+            })
+            .collect::<Vec<_>>();
+        if !ready_check(&states, self.context.skip_afk_in_ready_checks) {
+            return;
+        }
+        console::system(format_args!("ルーム{}の全員が準備完了しました\n", room));
+        for member in members.iter() {
+            member.write().expect("Failed to lock socket client.").ready = false;
+        }
+        self.notify_room(room, "[Server] Everyone is ready.");
+    }
+
+    fn update_presence(&self, client_id: u32, from: &[Presence], presence: Presence) {
+        let name = match self.context.clients.get(client_id) {
+            Some(socket_client) => {
+                let mut client_lock = socket_client
+                    .write()
+                    .expect("Failed to lock socket client.");
+                if client_lock.presence == presence
+                    || !(from.is_empty() || from.contains(&client_lock.presence))
+                {
+                    return;
+                }
+                client_lock.presence = presence;
+                client_lock.display_name()
+            }
+            None => return,
+        };
+        console::system(format_args!(
+            "{}のステータスが{}に変わりました\n",
+            &name, presence
+        ));
+        let notice = presence_notice(&name, presence);
+        for transport in self.context.clients.watchers(&name).iter() {
+            send_text(transport, &notice);
+        }
+    }
+
+    fn join_mesh(&self, client: &ClientContext, name: String, session: String, port: u16) -> bool {
+        let address = match client.address.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => {
+                client.send_text("ERR Your address cannot be used for a mesh session.");
+                return false;
+            }
+        };
+        self.leave_mesh(client.id);
+        let member = MeshMember {
+            client_id: client.id,
+            name: name.clone(),
+            address,
+        };
+        match self.context.mesh.join(&session, member) {
+            Ok(others) => {
+                console::system(format_args!(
+                    "クライアント{}がメッシュ{}に参加しました（{}）\n",
+                    client.id, &session, address
+                ));
+                client.send_text(
+                    &MeshSignal::Members {
+                        session: session.clone(),
+                        peers: others
+                            .iter()
+                            .map(|other| (other.name.clone(), other.address))
+                            .collect(),
+                    }
+                    .encode(),
+                );
+                if let Some((host, term)) = self.context.mesh.host(&session) {
+                    client.send_text(
+                        &MeshSignal::Host {
+                            session: session.clone(),
+                            term,
+                            name: host.name,
+                            address: host.address,
+                        }
+                        .encode(),
+                    );
+                }
+                self.notify_mesh(
+                    &others,
+                    &MeshSignal::Joined {
+                        session,
+                        name,
+                        address,
+                    },
+                );
+                true
+            }
+            Err(e) => {
+                client.send_text(&format!("ERR {}", e));
+                false
+            }
+        }
+    }
+
+    fn leave_mesh(&self, client_id: u32) {
+        if let Some(departure) = self.context.mesh.leave(client_id) {
+            console::system(format_args!(
+                "クライアント{}がメッシュ{}から離脱しました\n",
+                client_id, &departure.session
+            ));
+            self.notify_mesh(
+                &departure.remaining,
+                &MeshSignal::Left {
+                    session: departure.session,
+                    name: departure.member.name,
+                },
+            );
+            if let Some(handover) = departure.handover {
+                console::system(format_args!(
+                    "メッシュ{}のホストがクライアント{}に移行しました（第{}期）\n",
+                    &handover.session, handover.host.client_id, handover.term
+                ));
+                if let Some(state) = handover.state_signal() {
+                    self.notify_mesh(std::slice::from_ref(&handover.host), &state);
+                }
+                self.notify_mesh(&departure.remaining, &handover.host_signal());
+            }
+        }
+    }
+
+    fn whisper(&self, client: &ClientContext, whisper: Whisper) {
+        let receipt = whisper.confirmation();
+        if let Some(transport) = self.context.clients.find_by_nickname(&whisper.target) {
+            console::system(format_args!(
+                "{} -> {}（ささやき）：{}\n",
+                &whisper.sender, &whisper.target, &whisper.text
+            ));
+            send_text(&transport, &whisper.format());
+            client.send_text(&receipt);
+            client.send_text(&whisper.receipt(ReceiptStatus::Delivered).encode());
+            return;
+        }
+        if let Some(transfer) = self.context.transfer.as_ref() {
+            match transfer.forward_mail(&whisper.target, &whisper.format()) {
+                Ok(true) => {
+                    client.send_text(&receipt);
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => console::error(format_args!(
+                    "ハンドオフ中のささやきの転送に失敗しました：{}\n",
+                    e
+                )),
+            }
+        }
+        let pending = whisper.clone();
+        let routed = match self.context.cluster.as_ref() {
+            Some(cluster) => cluster.whisper(whisper),
+            None => Ok(false),
+        };
+        match routed {
+            Ok(true) => client.send_text(&receipt),
+            Ok(false) if self.notify_offline(&pending) => {
+                client.send_text(&receipt);
+                client.send_text(&pending.receipt(ReceiptStatus::Notified).encode());
+            }
+            Ok(false) => client.send_text(&format!("ERR {} is not online.", &pending.target)),
+            Err(e) => {
+                console::error(format_args!("ささやきの転送に失敗しました：{}\n", e));
+                client.send_text("ERR Failed to deliver the whisper.");
+            }
+        }
+    }
+
+    fn notify_offline(&self, whisper: &Whisper) -> bool {
+        let (notifier, accounts) = match (
+            self.context.notifier.as_ref(),
+            self.context.accounts.as_ref(),
+        ) {
+            (Some(notifier), Some(accounts)) => (notifier, accounts),
+            _ => return false,
+        };
+        let account = match accounts.find_account(&whisper.target) {
+            Ok(Some(account)) => account,
+            Ok(None) => return false,
+            Err(e) => {
+                console::error(format_args!("アカウントの検索に失敗しました：{}\n", e));
+                return false;
+            }
+        };
+        let message = OfflineMessage {
+            whisper_id: whisper.id,
+            account_id: AccountId(account.id),
+            recipient: account.name,
+            sender: whisper.sender.clone(),
+            text: whisper.text.clone(),
+        };
+        match notifier.notify(&message) {
+            Ok(()) => {
+                console::system(format_args!(
+                    "オフラインの{}へプッシュ通知を依頼しました\n",
+                    &message.recipient
+                ));
+                true
+            }
+            Err(e) => {
+                console::error(format_args!("プッシュ通知の依頼に失敗しました：{}\n", e));
+                false
+            }
+        }
+    }
+
+    fn acknowledge(&self, client: &ClientContext, receipt: WhisperReceipt) {
+        if let Some(transport) = self.context.clients.find_by_nickname(&receipt.sender) {
+            send_text(&transport, &receipt.encode());
+            return;
+        }
+        let sender = receipt.sender.clone();
+        let routed = match self.context.cluster.as_ref() {
+            Some(cluster) => cluster.receipt(receipt),
+            None => Ok(false),
+        };
+        match routed {
+            Ok(true) => {}
+            Ok(false) => client.send_text(&format!("ERR {} is not online.", sender)),
+            Err(e) => {
+                console::error(format_args!("既読通知の転送に失敗しました：{}\n", e));
+                client.send_text("ERR Failed to deliver the read receipt.");
+            }
+        }
+    }
+
+    fn notify_guild(&self, guilds: &Guilds, guild: &GuildRecord, text: &str) -> GuildResult<()> {
+        for member in guilds.members(guild.id)? {
+            if let Some((transport, _)) = self.context.clients.find_by_account(member.account_id) {
+                send_text(&transport, text);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_guild(
+        &self,
+        client: &ClientContext,
+        account_id: Option<AccountId>,
+        name: &str,
+        command: GuildCommand,
+    ) {
+        let (guilds, account_id) = match (self.context.guilds.as_ref(), account_id) {
+            (Some(guilds), Some(account_id)) => (guilds, account_id),
+            (None, _) => {
+                client.send_text("ERR Guilds are not available.");
+                return;
+            }
+            (_, None) => {
+                client.send_text("ERR Please :login to use guilds.");
+                return;
+            }
+        };
+        let result = match command {
+            GuildCommand::Create(guild) => guilds.create(account_id, &guild).map(|guild| {
+                console::system(format_args!(
+                    "{}がギルド{}を設立しました\n",
+                    name, &guild.name
+                ));
+                client.send_text(&format!("OK guild {}", &guild.name));
+            }),
+            GuildCommand::Invite(target) => guilds.invite(account_id, &target).map(|guild| {
+                client.send_text(&format!("OK invited {}", &target));
+                if let Some(transport) = self.context.clients.find_by_nickname(&target) {
+                    send_text(
+                        &transport,
+                        &format!("GUILD_INVITE {} {}", &guild.name, name),
+                    );
+                }
+            }),
+            GuildCommand::Accept(guild) => guilds.accept(account_id, &guild).and_then(|guild| {
+                client.send_text(&format!("OK guild {}", &guild.name));
+                let notice = guild_notice(&guild.name, &format!("{} joined the guild.", name));
+                self.notify_guild(guilds, &guild, &notice)
+            }),
+            GuildCommand::Kick(target) => {
+                guilds
+                    .kick(account_id, &target)
+                    .and_then(|(guild, member)| {
+                        client.send_text(&format!("OK kicked {}", &member.name));
+                        if let Some((transport, _)) =
+                            self.context.clients.find_by_account(member.account_id)
+                        {
+                            send_text(&transport, &format!("GUILD_KICKED {}", &guild.name));
+                        }
+                        let notice = guild_notice(
+                            &guild.name,
+                            &format!("{} was removed by {}.", &member.name, name),
+                        );
+                        self.notify_guild(guilds, &guild, &notice)
+                    })
+            }
+            GuildCommand::Role(target, role) => guilds
+                .set_role(account_id, &target, role)
+                .and_then(|(guild, member)| {
+                    client.send_text(&format!("OK {} {}", &member.name, member.role));
+                    let notice =
+                        guild_notice(&guild.name, &format!("{} is now {}.", &member.name, role));
+                    self.notify_guild(guilds, &guild, &notice)
+                }),
+            GuildCommand::Leave => guilds.leave(account_id).and_then(|(guild, disbanded)| {
+                client.send_text(&format!("OK left {}", &guild.name));
+                if disbanded {
+                    console::system(format_args!("ギルド{}が解散しました\n", &guild.name));
+                    return Ok(());
+                }
+                let notice = guild_notice(&guild.name, &format!("{} left the guild.", name));
+                self.notify_guild(guilds, &guild, &notice)
+            }),
+            GuildCommand::Members => guilds.membership(account_id).and_then(|membership| {
+                let membership = membership.ok_or(GuildError::NotInGuild)?;
+                let members = guilds
+                    .members(membership.guild.id)?
+                    .into_iter()
+                    .map(|member| {
+                        let presence = self
+                            .context
+                            .clients
+                            .find_by_account(member.account_id)
+                            .map(|(_, presence)| presence);
+                        (member, presence)
+                    })
+                    .collect::<Vec<_>>();
+                client.send_text(&roster(&membership.guild.name, &members));
+                Ok(())
+            }),
+            GuildCommand::Chat(text) => guilds.membership(account_id).and_then(|membership| {
+                let guild = membership.ok_or(GuildError::NotInGuild)?.guild;
+                console::system(format_args!(
+                    "{} -> ギルド{}：{}\n",
+                    name, &guild.name, &text
+                ));
+                self.notify_guild(guilds, &guild, &guild_message(&guild.name, name, &text))
+            }),
+        };
+        match result {
+            Ok(()) => {}
+            Err(GuildError::Storage(e)) => {
+                console::error(format_args!("ギルドの更新に失敗しました：{}\n", e));
+                client.send_text("ERR Failed to update the guild.");
+            }
+            Err(e) => client.send_text(&format!("ERR {}", e)),
+        }
+    }
+
+    fn welcome(&self, client: &ClientContext) {
+        let server_msg = self
+            .server_msg
+            .read()
+            .expect("Failed to lock server message.")
+            .clone();
+        client.send_text(&server_msg);
+        let mut backlog = self
+            .context
+            .chat_log
+            .as_ref()
+            .map(|chat_log| chat_log.replay(DEFAULT_ROOM))
+            .unwrap_or_default();
+        if backlog.is_empty() {
+            backlog = self.context.rooms.queued(DEFAULT_ROOM);
+        }
+        for line in backlog.iter() {
+            client.send_text(line);
+        }
+    }
+
+    fn check_challenge(&self, client: &ClientContext, message: &str) -> Option<Flow> {
+        let socket_client = self.context.clients.get(client.id)?;
+        let mut client_lock = socket_client
+            .write()
+            .expect("Failed to lock socket client.");
+        let challenge = client_lock.challenge.as_ref()?;
+        let solution = match PowCommand::parse(message) {
+            Some(command) => command.solution,
+            None => {
+                client.send_text("ERR Solve the proof-of-work challenge first.");
+                return Some(Flow::Continue);
+            }
+        };
+        if !challenge.verify(solution) {
+            self.context.ip_stats.record_failure(&client.address);
+            console::system(format_args!(
+                "クライアント{}のプルーフオブワークが不正なため切断します\n",
+                client.id
+            ));
+            client.send_text("ERR Invalid proof-of-work.");
+            return Some(Flow::Disconnect);
+        }
+        client_lock.challenge = None;
+        drop(client_lock);
+        client.send_text("OK POW");
+        self.welcome(client);
+        Some(Flow::Continue)
+    }
+}
+
+impl ServerHandler for ChatHandler {
+    fn on_client_connected(&self, client: &ClientContext) {
+        self.context.clients.touch(client.id);
+        self.plugins.on_join(client.id, &client.address);
+        self.apply_requests();
+        if let Some(anticheat) = self.context.anticheat.as_ref() {
+            anticheat.track(client.id, Instant::now());
+        }
+        let bot = self
+            .context
+            .clients
+            .get(client.id)
+            .is_some_and(|socket_client| {
+                socket_client
+                    .read()
+                    .expect("Failed to lock socket client.")
+                    .bot
+            });
+        if let Some(difficulty) = self.context.pow_difficulty.filter(|_| !bot) {
+            let challenge = Challenge::issue(difficulty);
+            client.send_text(&challenge.encode());
+            if let Some(socket_client) = self.context.clients.get(client.id) {
+                socket_client
+                    .write()
+                    .expect("Failed to lock socket client.")
+                    .challenge = Some(challenge);
+            }
+            return;
+        }
+        self.welcome(client);
+    }
+
+    fn on_message(&self, client: &ClientContext, message: &str) -> Flow {
+        let socket_client = match self.context.clients.get(client.id) {
+            Some(socket_client) => socket_client,
+            None => return Flow::Disconnect,
+        };
+        if let Some(PingCommand::Pong(nonce)) = PingCommand::parse(message) {
+            self.context
+                .clients
+                .complete_ping(client.id, nonce, Instant::now());
+            return Flow::Continue;
+        }
+        self.context
+            .ip_stats
+            .record_bytes(&client.address, message.len());
+        if let Some(flow) = self.check_challenge(client, message) {
+            return flow;
+        }
+        if self.context.clients.touch(client.id) {
+            let (name, room) = {
+                let client_lock = socket_client.read().expect("Failed to lock socket client.");
+                (client_lock.display_name(), client_lock.room.clone())
+            };
+            console::system(format_args!(
+                "クライアント{}が離席から戻りました\n",
+                client.id
+            ));
+            self.notify_room(&room, &format!("[Server] {} is back.", name));
+            self.update_presence(client.id, &[Presence::Away], Presence::Online);
+        }
+        let client_lock = socket_client.read().expect("Failed to lock socket client.");
+        let mut incoming_message = message.to_string();
+        console::chat(
+            client_lock.id,
+            &client_lock.room,
+            format_args!("{}{}", RECV_PREFIX, redact(&incoming_message)),
+        );
+        if let Some(recorder) = self.context.recorder.as_ref() {
+            recorder.record(client_lock.id, PacketKind::Message, &incoming_message);
+        }
+        if incoming_message.starts_with(":end") {
+            console::system(format_args!("終了コマンドを受信しました\n"));
+            client.send_text("Bye!");
+            return Flow::Disconnect;
+        }
+        if let Some(command) = AuthCommand::parse(&incoming_message) {
+            let result = self.context.identity.handle(command);
+            match &result {
+                Ok(session) => {
+                    client.send_text(&format!("OK {} {}", &session.name, &session.token))
+                }
+                Err(e) => {
+                    self.context.ip_stats.record_failure(&client.address);
+                    client.send_text(&format!("ERR {}", e))
+                }
+            }
+            drop(client_lock);
+            if let Ok(session) = result {
+                let mut client_lock = socket_client
+                    .write()
+                    .expect("Failed to lock socket client.");
+                console::system(format_args!(
+                    "クライアント{}が{}としてログインしました\n",
+                    client_lock.id, &session.name
+                ));
+                client.transport.bind_session(&session.token);
+                client_lock.account_id = Some(session.account_id);
+                client_lock.nickname = Some(session.name.clone());
+                let _ = self.context.events.send(ServerEvent::LoggedIn {
+                    client_id: client_lock.id,
+                    account_id: session.account_id,
+                    name: session.name,
+                });
+            }
+            return Flow::Continue;
+        }
+        if PingCommand::parse(&incoming_message) == Some(PingCommand::Ping) {
+            match client_lock.latency.summary() {
+                Some(summary) => client.send_text(&summary.to_string()),
+                None => client.send_text("RTT not measured yet."),
+            }
+            return Flow::Continue;
+        }
+        if let Some(command) = TimeCommand::parse(&incoming_message) {
+            match command {
+                TimeCommand::Time => {
+                    client.send_text(&self.context.clock.now(SystemTime::now()).encode())
+                }
+                TimeCommand::Events => {
+                    for event in self.context.clock.upcoming() {
+                        client.send_text(&event.encode());
+                    }
+                    client.send_text("OK events");
+                }
+            }
+            return Flow::Continue;
+        }
+        if let Some(command) = LeaderboardCommand::parse(&incoming_message) {
+            let entries = match (self.context.leaderboard.as_ref(), command) {
+                (None, _) => Err("ERR Leaderboard is not available.".to_string()),
+                (Some(leaderboard), LeaderboardCommand::Top(limit)) => {
+                    leaderboard.top(limit).map_err(|e| format!("ERR {}", e))
+                }
+                (Some(leaderboard), LeaderboardCommand::Rank(radius)) => {
+                    match client_lock.account_id {
+                        Some(account_id) => leaderboard
+                            .around(account_id, radius)
+                            .map_err(|e| format!("ERR {}", e)),
+                        None => Err("ERR Please :login to see your rank.".to_string()),
+                    }
+                }
+            };
+            match entries {
+                Ok(entries) if entries.is_empty() => client.send_text("No results yet."),
+                Ok(entries) => {
+                    for entry in entries.iter() {
+                        client.send_text(&format_entry(entry));
+                    }
+                }
+                Err(reply) => client.send_text(&reply),
+            }
+            return Flow::Continue;
+        }
+        if let Some(EncodingCommand(name)) = EncodingCommand::parse(&incoming_message) {
+            match TextEncoding::from_name(&name) {
+                Some(encoding) if client.transport.set_encoding(encoding) => {
+                    console::system(format_args!(
+                        "クライアント{}の文字コードを{}に切り替えました\n",
+                        client.id,
+                        encoding.name()
+                    ));
+                    client.send_text(&format!("OK {}", encoding.name()));
+                }
+                Some(_) => client.send_text("ERR This connection cannot change its encoding."),
+                None => client.send_text(&format!("ERR Unsupported encoding {}.", name)),
+            }
+            return Flow::Continue;
+        }
+        if let Some(command) = PresenceCommand::parse(&incoming_message) {
+            let room = client_lock.room.clone();
+            drop(client_lock);
+            match command {
+                PresenceCommand::Status(presence) => {
+                    self.update_presence(client.id, &[], presence);
+                    client.send_text(&format!("OK {}", presence));
+                }
+                PresenceCommand::Who => {
+                    let members = self
+                        .context
+                        .clients
+                        .in_room(&room)
+                        .iter()
+                        .map(|member| {
+                            let member_lock = member.read().expect("Failed to lock socket client.");
+                            (member_lock.display_name(), member_lock.presence)
+                        })
+                        .collect::<Vec<_>>();
+                    client.send_text(&member_list(&members));
+                }
+                PresenceCommand::Friend(name) => {
+                    {
+                        let mut client_lock = socket_client
+                            .write()
+                            .expect("Failed to lock socket client.");
+                        if !client_lock.friends.contains(&name) {
+                            if client_lock.friends.len() >= MAX_FRIENDS {
+                                client.send_text(&format!(
+                                    "ERR You can have at most {} friends.",
+                                    MAX_FRIENDS
+                                ));
+                                return Flow::Continue;
+                            }
+                            client_lock.friends.push(name.clone());
+                        }
+                    }
+                    client.send_text(&format!("OK friend {}", &name));
+                    if let Some(presence) = self.context.clients.presence_of(&name) {
+                        client.send_text(&presence_notice(&name, presence));
+                    }
+                }
+            }
+            return Flow::Continue;
+        }
+        if let Some(command) = ScoreboardCommand::parse(&incoming_message) {
+            let room = client_lock
+                .spectating
+                .clone()
+                .unwrap_or_else(|| client_lock.room.clone());
+            drop(client_lock);
+            match command {
+                ScoreboardCommand::Show => match self.context.scoreboards.current(&room) {
+                    Some(line) => client.send_text(&line),
+                    None => client.send_text(&format!("ERR No match is running in {}.", room)),
+                },
+                ScoreboardCommand::Spectate(Some(room)) if !valid_room_name(&room) => client
+                    .send_text(&format!(
+                        "ERR Room names must be 1-{} alphanumeric characters.",
+                        MAX_ROOM_NAME_LENGTH
+                    )),
+                ScoreboardCommand::Spectate(target) => {
+                    socket_client
+                        .write()
+                        .expect("Failed to lock socket client.")
+                        .spectating = target.clone();
+                    match target {
+                        Some(room) => {
+                            client.send_text(&format!("OK spectate {}", &room));
+                            if let Some(line) = self.context.scoreboards.current(&room) {
+                                client.send_text(&line);
+                            }
+                        }
+                        None => client.send_text("OK spectate off"),
+                    }
+                }
+            }
+            return Flow::Continue;
+        }
+        if let Some(RoomCommand::Ready(ready)) = RoomCommand::parse(&incoming_message) {
+            let room = client_lock.room.clone();
+            drop(client_lock);
+            socket_client
+                .write()
+                .expect("Failed to lock socket client.")
+                .ready = ready;
+            client.send_text(if ready { "OK ready" } else { "OK unready" });
+            self.check_ready(&room);
+            return Flow::Continue;
+        }
+        if let Some(RoomCommand::Join(room)) = RoomCommand::parse(&incoming_message) {
+            if !valid_room_name(&room) {
+                client.send_text(&format!(
+                    "ERR Room names must be 1-{} alphanumeric characters.",
+                    MAX_ROOM_NAME_LENGTH
+                ));
+                return Flow::Continue;
+            }
+            drop(client_lock);
+            let client_lock = {
+                let mut client_lock = socket_client
+                    .write()
+                    .expect("Failed to lock socket client.");
+                console::system(format_args!(
+                    "クライアント{}が{}から{}に移動しました\n",
+                    client_lock.id, &client_lock.room, &room
+                ));
+                client_lock.room = room;
+                client_lock.ready = false;
+                client_lock.clone()
+            };
+            client.send_text(&format!("OK {}", &client_lock.room));
+            for line in self.context.rooms.queued(&client_lock.room).iter() {
+                client.send_text(line);
+            }
+            return Flow::Continue;
+        }
+        if let Some(TransferCommand::Claim(token)) = TransferCommand::parse(&incoming_message) {
+            let ticket = match self.context.transfer.as_ref() {
+                Some(transfer) => transfer.claim(&token),
+                None => {
+                    client.send_text("ERR Transfers are not enabled.");
+                    return Flow::Continue;
+                }
+            };
+            let ticket = match ticket {
+                Some(ticket) => ticket,
+                None => {
+                    client.send_text("ERR Unknown or expired handoff token.");
+                    return Flow::Continue;
+                }
+            };
+            drop(client_lock);
+            let mut client_lock = socket_client
+                .write()
+                .expect("Failed to lock socket client.");
+            console::system(format_args!(
+                "クライアント{}がサーバー{}から{}として移動してきました\n",
+                client_lock.id, &ticket.origin, &ticket.player.name
+            ));
+            client_lock.account_id = ticket.player.account_id.map(AccountId);
+            client_lock.nickname = ticket.player.nickname;
+            client_lock.room = ticket.player.room;
+            client_lock.position = ticket.player.position;
+            client.send_text(&format!(
+                "ZONE {} {} {}",
+                self.context
+                    .transfer
+                    .as_ref()
+                    .map(|transfer| transfer.server_id())
+                    .unwrap_or_default(),
+                client_lock.position.0,
+                client_lock.position.1
+            ));
+            for line in ticket.pending.iter() {
+                client.send_text(line);
+            }
+            return Flow::Continue;
+        }
+        if self.context.identity.require_login
+            && client_lock.account_id.is_none()
+            && !client_lock.bot
+        {
+            client.send_text("ERR Please :register or :login before chatting.");
+            return Flow::Continue;
+        }
+        if let Some(command) = GuildCommand::parse(&incoming_message) {
+            let (account_id, name) = (client_lock.account_id, client_lock.display_name());
+            drop(client_lock);
+            self.handle_guild(client, account_id, &name, command);
+            return Flow::Continue;
+        }
+        if let Some(command) = WhisperCommand::parse(&incoming_message) {
+            let sender = client_lock.display_name();
+            drop(client_lock);
+            let whisper = Whisper {
+                id: self.next_whisper_id.fetch_add(1, Ordering::SeqCst),
+                origin: self
+                    .context
+                    .cluster
+                    .as_ref()
+                    .map(|cluster| cluster.node_id().to_string())
+                    .unwrap_or_default(),
+                sender,
+                target: command.target,
+                text: command.text,
+            };
+            self.whisper(client, whisper);
+            return Flow::Continue;
+        }
+        if let Some(command) = ReadCommand::parse(&incoming_message) {
+            let reader = client_lock.display_name();
+            drop(client_lock);
+            let receipt = WhisperReceipt {
+                id: command.id,
+                status: ReceiptStatus::Read,
+                sender: command.sender,
+                reader,
+            };
+            self.acknowledge(client, receipt);
+            return Flow::Continue;
+        }
+        if let Some(ZoneCommand::Move(x, y)) = ZoneCommand::parse(&incoming_message) {
+            if let Some(anticheat) = self.context.anticheat.as_ref() {
+                let (name, position) = (client_lock.display_name(), client_lock.position);
+                let verdict =
+                    anticheat.check_move(client.id, &name, position, (x, y), Instant::now());
+                let rejection = format!("POS {} {}", position.0, position.1);
+                if let Some(flow) = self.enforce(client, &name, verdict, &rejection) {
+                    return flow;
+                }
+            }
+            let zone = match self.context.zone.as_ref() {
+                Some(zone) if !zone.contains(x, y) => zone,
+                _ => {
+                    drop(client_lock);
+                    socket_client
+                        .write()
+                        .expect("Failed to lock socket client.")
+                        .position = (x, y);
+                    client.send_text(&format!("POS {} {}", x, y));
+                    return Flow::Continue;
+                }
+            };
+            let route = match zone.locate(x, y) {
+                Some(route) => route,
+                None => {
+                    client.send_text("ERR That position is outside the world.");
+                    return Flow::Continue;
+                }
+            };
+            let player = PlayerState {
+                position: (x, y),
+                ..PlayerState::from_client(&client_lock)
+            };
+            return match zone.hand_off(&route, player) {
+                Ok(token) => {
+                    console::system(format_args!(
+                        "クライアント{}をゾーン{}へ引き渡しました\n",
+                        client_lock.id, &route.id
+                    ));
+                    client.send_text(&handoff_instruction(
+                        &route.id,
+                        &route.client_address,
+                        &token,
+                    ));
+                    Flow::Disconnect
+                }
+                Err(e) => {
+                    console::error(format_args!(
+                        "ゾーン{}への引き渡しに失敗しました：{}\n",
+                        &route.id, e
+                    ));
+                    client.send_text(&format!("ERR Failed to hand off to zone {}.", &route.id));
+                    Flow::Continue
+                }
+            };
+        }
+        if let Some(AbilityCommand(ability)) = AbilityCommand::parse(&incoming_message) {
+            let (name, room) = (client_lock.display_name(), client_lock.room.clone());
+            drop(client_lock);
+            if let Some(anticheat) = self.context.anticheat.as_ref() {
+                let verdict = anticheat.check_ability(client.id, &name, &ability, Instant::now());
+                let rejection = format!("ERR {} is not ready yet.", &ability);
+                if let Some(flow) = self.enforce(client, &name, verdict, &rejection) {
+                    return flow;
+                }
+            }
+            self.notify_room(&room, &format!("ABILITY {} {}", &name, &ability));
+            return Flow::Continue;
+        }
+        if let Some(command) = MeshCommand::parse(&incoming_message) {
+            let name = client_lock.display_name();
+            drop(client_lock);
+            match command {
+                MeshCommand::Join { session, port } => {
+                    if self.join_mesh(client, name, session, port) {
+                        self.update_presence(
+                            client.id,
+                            &[Presence::Online, Presence::Away],
+                            Presence::InGame,
+                        );
+                    }
+                }
+                MeshCommand::State(state) => {
+                    if let Err(e) = self.context.mesh.store_state(client.id, state) {
+                        client.send_text(&format!("ERR {}", e));
+                    }
+                }
+                MeshCommand::Leave => {
+                    self.leave_mesh(client.id);
+                    self.update_presence(client.id, &[Presence::InGame], Presence::Online);
+                }
+            }
+            return Flow::Continue;
+        }
+        let (nickname, room) = (client_lock.nickname.clone(), client_lock.room.clone());
+        drop(client_lock);
+        let context = ChatContext {
+            client_id: client.id,
+            nickname: nickname.as_deref(),
+            room: &room,
+        };
+        let outcome = self.plugins.dispatch(&context, &incoming_message);
+        self.apply_requests();
+        incoming_message = match outcome {
+            PluginOutcome::Deliver(message) => message,
+            PluginOutcome::Reply(reply) => {
+                client.send_text(&reply);
+                return Flow::Continue;
+            }
+            PluginOutcome::Dropped => return Flow::Continue,
+        };
+        let client_lock = socket_client.read().expect("Failed to lock socket client.");
+        let _ = self.context.events.send(ServerEvent::Chat {
+            client_id: client_lock.id,
+            account_id: client_lock.account_id,
+            room: client_lock.room.clone(),
+            message: incoming_message.clone(),
+        });
+        if let Some(nickname) = client_lock.nickname.as_ref() {
+            incoming_message = format!("{}：{}", nickname, &incoming_message);
+        }
+
+        console::chat(
+            client_lock.id,
+            &client_lock.room,
+            format_args!(
+                "{} -> {}：{}\n",
+                client_lock.id, client_lock.id, &incoming_message
+            ),
+        );
+        client.send_text(&incoming_message);
+
+        for other_client in self.context.clients.others(client_lock.id).iter() {
+            if let Ok(other_client_lock) = other_client.try_read() {
+                let other_transport = match other_client_lock.transport.as_ref() {
+                    Some(other_transport) if other_client_lock.room == client_lock.room => {
+                        other_transport
+                    }
+                    _ => continue,
+                };
+                console::chat(
+                    client_lock.id,
+                    &client_lock.room,
+                    format_args!(
+                        "{} -> {}：{}\n",
+                        client_lock.id, other_client_lock.id, &incoming_message
+                    ),
+                );
+                let _ = other_transport.send_text(&incoming_message);
+            }
+        }
+        Flow::Continue
+    }
+
+    fn on_tick(&self) {
+        self.plugins.on_tick();
+        self.apply_requests();
+        self.broadcast_announcements(SystemTime::now());
+        self.fire_scheduled_events();
+        self.broadcast_scoreboards(Instant::now());
+        if let Err(e) = self.context.ip_stats.flush(Instant::now()) {
+            console::error(format_args!("IP統計の保存に失敗しました：{}\n", e));
+        }
+        if let Some(interval) = self.context.heartbeat_interval {
+            for (transport, nonce) in self
+                .context
+                .clients
+                .start_heartbeats(interval, Instant::now())
+            {
+                send_prioritized(
+                    &transport,
+                    &format!("PING {}", nonce),
+                    SendPriority::Control,
+                );
+            }
+        }
+        if let Some(timeout) = self.context.idle_timeout {
+            for (client_id, transport) in self.context.clients.idle(timeout) {
+                console::system(format_args!(
+                    "クライアント{}が{}秒間応答しないため切断します\n",
+                    client_id,
+                    timeout.as_secs()
+                ));
+                send_prioritized(
+                    &transport,
+                    "Disconnected: idle timeout.",
+                    SendPriority::Control,
+                );
+                transport.shutdown();
+            }
+        }
+        if let Some(timeout) = self.context.pre_auth_timeout {
+            for (client_id, transport) in self.context.clients.unauthenticated(timeout) {
+                console::system(format_args!(
+                    "クライアント{}が{}秒以内にログインしなかったため切断します\n",
+                    client_id,
+                    timeout.as_secs()
+                ));
+                send_prioritized(
+                    &transport,
+                    "Disconnected: login timeout.",
+                    SendPriority::Control,
+                );
+                transport.shutdown();
+            }
+        }
+        if let Some(threshold) = self.context.afk_timeout {
+            let mut rooms = vec![];
+            for (client_id, name, room) in self.context.clients.mark_afk(threshold) {
+                console::system(format_args!("{}が離席状態になりました\n", &name));
+                self.notify_room(&room, &format!("[Server] {} is now AFK.", name));
+                self.update_presence(client_id, &[Presence::Online], Presence::Away);
+                if !rooms.contains(&room) {
+                    rooms.push(room);
+                }
+            }
+            if self.context.skip_afk_in_ready_checks {
+                for room in rooms.iter() {
+                    self.check_ready(room);
+                }
+            }
+        }
+        if let Some(interest) = self.context.interest.as_ref() {
+            let (entities, transports): (Vec<_>, Vec<_>) =
+                self.context.clients.entities().into_iter().unzip();
+            for update in interest.update(&entities) {
+                let transport = match entities
+                    .iter()
+                    .position(|entity| entity.id == update.observer)
+                {
+                    Some(index) => &transports[index],
+                    None => continue,
+                };
+                for event in update.events.iter() {
+                    send_prioritized(transport, &event.encode(), SendPriority::GameState);
+                }
+                if let Some(snapshot) = update.snapshot_line() {
+                    send_prioritized(transport, &snapshot, SendPriority::GameState);
+                }
+            }
+        }
+    }
+
+    fn on_client_disconnected(&self, client: &ClientContext) {
+        let socket_client = match self.context.clients.get(client.id) {
+            Some(socket_client) => socket_client,
+            None => return,
+        };
+        let mut client_lock = socket_client
+            .write()
+            .expect("Failed to lock socket client.");
+        console::system(format_args!(
+            "クライアント{}（{}）が切断しました\n",
+            client.id, &client.address
+        ));
+        self.leave_mesh(client.id);
+        if let Some(interest) = self.context.interest.as_ref() {
+            interest.forget(client.id);
+        }
+        if let Some(anticheat) = self.context.anticheat.as_ref() {
+            anticheat.forget(client.id);
+        }
+        client_lock.transport = None;
+        client_lock.peer = None;
+        client_lock.location = None;
+        client_lock.account_id = None;
+        client_lock.nickname = None;
+        client_lock.room = DEFAULT_ROOM.to_string();
+        client_lock.position = (0, 0);
+        client_lock.connected_at = None;
+        client_lock.last_active = None;
+        client_lock.afk = false;
+        client_lock.ready = false;
+        client_lock.presence = Presence::Online;
+        client_lock.friends.clear();
+        client_lock.spectating = None;
+        client_lock.bot = false;
+        client_lock.latency = LatencyTracker::default();
+        client_lock.challenge = None;
+        if let Some(recorder) = self.context.recorder.as_ref() {
+            recorder.record(client_lock.id, PacketKind::Leave, "");
+        }
+        let _ = self.context.events.send(ServerEvent::ClientLeft {
+            client_id: client_lock.id,
+        });
+    }
+}
+
+pub fn start_match(context: &ServerContext, room: &str) {
+    let players = context
+        .clients
+        .in_room(room)
+        .iter()
+        .map(|member| {
+            let member_lock = member.read().expect("Failed to lock socket client.");
+            (member_lock.display_name(), member_lock.account_id)
+        })
+        .collect::<Vec<_>>();
+    console::system(format_args!(
+        "ルーム{}で{}人の試合が始まりました\n",
+        room,
+        players.len()
+    ));
+    context.scoreboards.start(room, players);
+    for transport in context.clients.audience(room).iter() {
+        send_text(transport, &format!("MATCH_START {}", room));
+    }
+}
+
+pub fn finish_match(context: &ServerContext, room: &str) -> bool {
+    let scoreboard = match context.scoreboards.finish(room) {
+        Some(scoreboard) => scoreboard,
+        None => return false,
+    };
+    let standings = scoreboard.encode_final();
+    console::system(format_args!("試合結果：{}\n", &standings));
+    for transport in context.clients.audience(room).iter() {
+        send_text(transport, &standings);
+    }
+    let standings = scoreboard.match_standings();
+    if !standings.is_empty() {
+        let _ = context.events.send(ServerEvent::MatchEnded {
+            room: room.to_string(),
+            standings,
+        });
+    }
+    true
+}
+
+pub fn send_text(transport: &Arc<dyn Transport>, text: &str) {
+    let _ = transport.send_text(text);
+}
+
+pub fn send_prioritized(transport: &Arc<dyn Transport>, text: &str, priority: SendPriority) {
+    let _ = transport.send_text_prioritized(text, priority);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::announcements::Schedule;
+    use crate::anticheat::{AntiCheat, AntiCheatConfig};
+    use crate::aoi::InterestManager;
+    use crate::bots::{connect_bot, ReadyBot};
+    use crate::bus::MessageBus;
+    use crate::clients::SharedClient;
+    use crate::events::EventListener;
+    use crate::identity::Identity;
+    use crate::leaderboard::Leaderboard;
+    use crate::notify::Notifier;
+    use crate::playback::{Playback, PlaybackTiming};
+    use crate::plugins::{ProfanityFilter, StatsPlugin};
+    use crate::recorder::RecordedPacket;
+    use crate::session::ClientPool;
+    use crate::storage::{
+        AccountRecord, AccountStore, LeaderboardEntry, MatchParticipant, PlayerStats, StorageResult,
+    };
+    use crate::transport::{MemoryTransport, MockTransport};
+    use crate::zone::{ZoneBounds, ZoneConfig, ZoneNode, ZoneRoute, DEFAULT_HANDOFF_TTL};
+    use std::io::ErrorKind;
+    use std::sync::mpsc::channel;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    fn connect_mock(pool: &ClientPool, index: u32) -> (SharedClient, Arc<MockTransport>) {
+        let client = pool.clients.get(index).expect("Client is missing.");
+        let transport = Arc::new(MockTransport::new());
+        client
+            .write()
+            .expect("Failed to lock socket client.")
+            .transport = Some(transport.clone());
+        (client, transport)
+    }
+
+    fn run_session(pool: &mut ClientPool, client: SharedClient) -> Vec<ServerEvent> {
+        run_session_with_plugins(pool, client, PluginRegistry::new())
+    }
+
+    fn run_session_with_plugins(
+        pool: &mut ClientPool,
+        client: SharedClient,
+        plugins: PluginRegistry,
+    ) -> Vec<ServerEvent> {
+        let (events, received) = channel();
+        let handler = Arc::new(ChatHandler::new(
+            ServerContext::new(pool.clients.clone(), events),
+            plugins,
+        ));
+        pool.start_messaging(handler, client);
+        for thread in pool.socket_client_threads.drain(..) {
+            thread.join().expect("Client thread panicked.");
+        }
+        received.try_iter().collect()
+    }
+
+    #[test]
+    fn chat_is_echoed_and_broadcast_to_clients_in_the_same_room() {
+        let mut pool = ClientPool::new(3);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        let (carol, carol_transport) = connect_mock(&pool, 2);
+        carol.write().expect("Failed to lock socket client.").room = "red".to_string();
+        alice_transport.script_text("hello");
+
+        let events = run_session(&mut pool, alice.clone());
+
+        assert_eq!(alice_transport.sent_text(), vec!["Hello", "hello"]);
+        assert_eq!(bob_transport.sent_text(), vec!["hello"]);
+        assert!(carol_transport.sent_text().is_empty());
+        assert!(alice_transport.is_closed());
+        assert!(alice
+            .read()
+            .expect("Failed to lock socket client.")
+            .transport
+            .is_none());
+        assert!(matches!(
+            events.as_slice(),
+            [ServerEvent::Chat { message, .. }, ServerEvent::ClientLeft { client_id: 0 }]
+                if message == "hello"
+        ));
+    }
+
+    #[test]
+    fn frames_are_reassembled_across_and_within_reads() {
+        let mut pool = ClientPool::new(1);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        alice_transport
+            .script_read(b"one\0tw")
+            .script_read(b"o\0")
+            .script_read(b"unterminated");
+
+        run_session(&mut pool, alice);
+
+        assert_eq!(alice_transport.sent_text(), vec!["Hello", "one", "two"]);
+    }
+
+    #[test]
+    fn end_command_stops_reading_and_says_goodbye() {
+        let mut pool = ClientPool::new(2);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        alice_transport.script_text(":end").script_text("ignored");
+
+        run_session(&mut pool, alice);
+
+        assert_eq!(alice_transport.sent_text(), vec!["Hello", "Bye!"]);
+        assert!(bob_transport.sent_text().is_empty());
+    }
+
+    #[test]
+    fn receive_errors_and_failed_sends_end_or_skip_cleanly() {
+        let mut pool = ClientPool::new(2);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        bob_transport.fail_sends(Some(ErrorKind::ConnectionReset));
+        alice_transport
+            .script_text("hello")
+            .script_error(ErrorKind::ConnectionAborted)
+            .script_text("never read");
+
+        let events = run_session(&mut pool, alice);
+
+        assert_eq!(alice_transport.sent_text(), vec!["Hello", "hello"]);
+        assert!(bob_transport.writes().is_empty());
+        assert!(matches!(
+            events.last(),
+            Some(ServerEvent::ClientLeft { client_id: 0 })
+        ));
+    }
+
+    #[test]
+    fn sessions_run_unchanged_over_the_in_memory_transport() {
+        let mut pool = ClientPool::new(1);
+        let (client, server) = MemoryTransport::pair();
+        let alice = pool.clients.get(0).expect("Client is missing.");
+        alice
+            .write()
+            .expect("Failed to lock socket client.")
+            .transport = Some(Arc::new(server));
+        client.send_text("hi").expect("Failed to send.");
+        client.send_text(":end").expect("Failed to send.");
+
+        run_session(&mut pool, alice);
+
+        let mut received = vec![];
+        let mut buffer = [0_u8; 64];
+        loop {
+            match client.receive(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => received.extend_from_slice(&buffer[..size]),
+            }
+        }
+        assert_eq!(received, b"Hello\0hi\0Bye!\0");
+    }
+
+    #[test]
+    fn plugins_rewrite_chat_and_answer_their_commands() {
+        let mut pool = ClientPool::new(2);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        alice_transport.script_text("darn it").script_text(":stats");
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(Arc::new(ProfanityFilter::new(vec!["darn"])))
+            .register(Arc::new(StatsPlugin::new()));
+
+        let events = run_session_with_plugins(&mut pool, alice, plugins);
+
+        assert_eq!(
+            alice_transport.sent_text(),
+            vec![
+                "Hello",
+                "**** it",
+                "You have sent 1 messages. 1 messages were sent in total."
+            ]
+        );
+        assert_eq!(bob_transport.sent_text(), vec!["**** it"]);
+        assert!(matches!(
+            events.first(),
+            Some(ServerEvent::Chat { message, .. }) if message == "**** it"
+        ));
+    }
+
+    #[test]
+    fn whispers_reach_only_their_target() {
+        let mut pool = ClientPool::new(3);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (bob, bob_transport) = connect_mock(&pool, 1);
+        let (_, carol_transport) = connect_mock(&pool, 2);
+        alice
+            .write()
+            .expect("Failed to lock socket client.")
+            .nickname = Some("alice".to_string());
+        bob.write().expect("Failed to lock socket client.").nickname = Some("bob".to_string());
+        alice_transport
+            .script_text(":w bob psst")
+            .script_text(":whisper dave hi");
+
+        let events = run_session(&mut pool, alice);
+
+        assert_eq!(
+            alice_transport.sent_text(),
+            vec![
+                "Hello",
+                "[Whisper -> bob #1] psst",
+                "DELIVERED 1 bob",
+                "ERR dave is not online."
+            ]
+        );
+        assert_eq!(bob_transport.sent_text(), vec!["[Whisper #1] alice：psst"]);
+        assert!(carol_transport.sent_text().is_empty());
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, ServerEvent::Chat { .. })));
+    }
+
+    #[test]
+    fn mesh_joins_exchange_peer_addresses_through_the_server() {
+        let mut pool = ClientPool::new(2);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        alice
+            .write()
+            .expect("Failed to lock socket client.")
+            .address = "127.0.0.1".to_string();
+        alice_transport
+            .script_text(":mesh join lobby 4000")
+            .script_text(":mesh leave");
+        let (events, _) = channel();
+        let context = ServerContext::new(pool.clients.clone(), events);
+        context
+            .mesh
+            .join(
+                "lobby",
+                MeshMember {
+                    client_id: 1,
+                    name: "bob".to_string(),
+                    address: "10.0.0.2:5000".parse().expect("Invalid address."),
+                },
+            )
+            .expect("Failed to join the mesh.");
+
+        pool.start_messaging(
+            Arc::new(ChatHandler::new(context.clone(), PluginRegistry::new())),
+            alice,
+        );
+        for thread in pool.socket_client_threads.drain(..) {
+            thread.join().expect("Client thread panicked.");
+        }
+
+        assert_eq!(
+            alice_transport.sent_text(),
+            vec![
+                "Hello",
+                "MESH lobby bob@10.0.0.2:5000",
+                "MESHHOST lobby 1 bob@10.0.0.2:5000"
+            ]
+        );
+        assert_eq!(
+            bob_transport.sent_text(),
+            vec!["MESH+ lobby Guest0@127.0.0.1:4000", "MESH- lobby Guest0"]
+        );
+        assert!(context.mesh.leave(0).is_none());
+    }
+
+    #[test]
+    fn host_disconnects_hand_the_mesh_over_to_the_next_peer() {
+        let mut pool = ClientPool::new(2);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        alice_transport
+            .script_text(":mesh state turn=7")
+            .script_text(":mesh state turn=8");
+        let (events, _) = channel();
+        let context = ServerContext::new(pool.clients.clone(), events);
+        for (client_id, name, address) in
+            [(0, "alice", "10.0.0.1:4000"), (1, "bob", "10.0.0.2:5000")]
+        {
+            context
+                .mesh
+                .join(
+                    "lobby",
+                    MeshMember {
+                        client_id,
+                        name: name.to_string(),
+                        address: address.parse().expect("Invalid address."),
+                    },
+                )
+                .expect("Failed to join the mesh.");
+        }
+
+        pool.start_messaging(
+            Arc::new(ChatHandler::new(context.clone(), PluginRegistry::new())),
+            alice,
+        );
+        for thread in pool.socket_client_threads.drain(..) {
+            thread.join().expect("Client thread panicked.");
+        }
+
+        assert_eq!(alice_transport.sent_text(), vec!["Hello"]);
+        assert_eq!(
+            bob_transport.sent_text(),
+            vec![
+                "MESH- lobby alice",
+                "MESHSTATE lobby turn=8",
+                "MESHHOST lobby 2 bob@10.0.0.2:5000"
+            ]
+        );
+        let (host, term) = context.mesh.host("lobby").expect("The mesh ended.");
+        assert_eq!((host.name.as_str(), term), ("bob", 2));
+    }
+
+    fn start_zone(zone_id: &str, routes: Vec<ZoneRoute>) -> ZoneNode {
+        ZoneNode::start(ZoneConfig {
+            zone_id: zone_id.to_string(),
+            bind: "127.0.0.1:0".to_string(),
+            routes,
+            handoff_ttl: DEFAULT_HANDOFF_TTL,
+        })
+        .expect("Failed to start the zone.")
+    }
+
+    #[test]
+    fn crossing_a_zone_boundary_hands_the_player_to_the_next_zone() {
+        let route = |id: &str, min_x: i32, handoff_address: String| ZoneRoute {
+            id: id.to_string(),
+            bounds: ZoneBounds {
+                min_x,
+                min_y: 0,
+                max_x: min_x + 99,
+                max_y: 99,
+            },
+            client_address: format!("{}.example:7000", id),
+            handoff_address,
+        };
+        let east = start_zone("east", vec![route("east", 100, String::new())]);
+        let west = start_zone(
+            "west",
+            vec![
+                route("west", 0, String::new()),
+                route("east", 100, east.local_addr().to_string()),
+            ],
+        );
+
+        let mut west_pool = ClientPool::new(1);
+        let (alice, alice_transport) = connect_mock(&west_pool, 0);
+        alice
+            .write()
+            .expect("Failed to lock socket client.")
+            .nickname = Some("alice".to_string());
+        alice_transport
+            .script_text(":join market")
+            .script_text(":move 10 10")
+            .script_text(":move 500 10")
+            .script_text(":move 150 20")
+            .script_text("still here?");
+        let (events, _) = channel();
+        let mut west_context = ServerContext::new(west_pool.clients.clone(), events);
+        west_context.zone = Some(west);
+        west_pool.start_messaging(
+            Arc::new(ChatHandler::new(west_context, PluginRegistry::new())),
+            alice,
+        );
+        for thread in west_pool.socket_client_threads.drain(..) {
+            thread.join().expect("Client thread panicked.");
+        }
+        let sent = alice_transport.sent_text();
+        assert_eq!(
+            sent[..4],
+            [
+                "Hello",
+                "OK market",
+                "POS 10 10",
+                "ERR That position is outside the world."
+            ]
+        );
+        assert_eq!(sent.len(), 5);
+        let token = sent[4]
+            .strip_prefix("HANDOFF east east.example:7000 ")
+            .expect("The player was not handed off.");
+
+        let mut east_pool = ClientPool::new(1);
+        let (arriving, arriving_transport) = connect_mock(&east_pool, 0);
+        arriving_transport
+            .script_text(&format!(":handoff {}", token))
+            .script_text("hi east");
+        let (events, _) = channel();
+        let mut east_context = ServerContext::new(east_pool.clients.clone(), events);
+        east_context.transfer = Some(east.transfer());
+        east_context.zone = Some(east);
+        east_pool.start_messaging(
+            Arc::new(ChatHandler::new(east_context, PluginRegistry::new())),
+            arriving,
+        );
+        for thread in east_pool.socket_client_threads.drain(..) {
+            thread.join().expect("Client thread panicked.");
+        }
+        assert_eq!(
+            arriving_transport.sent_text(),
+            vec!["Hello", "ZONE east 150 20", "alice：hi east"]
+        );
+    }
+
+    #[test]
+    fn ticks_only_send_snapshots_of_nearby_players() {
+        let pool = ClientPool::new(3);
+        let transports = [(0, 0), (3, 4), (40, 40)]
+            .iter()
+            .enumerate()
+            .map(|(index, position)| {
+                let (client, transport) = connect_mock(&pool, index as u32);
+                client
+                    .write()
+                    .expect("Failed to lock socket client.")
+                    .position = *position;
+                transport
+            })
+            .collect::<Vec<_>>();
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.interest = Some(InterestManager::new(10));
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+
+        handler.on_tick();
+        pool.clients
+            .get(1)
+            .expect("Client 1 is missing.")
+            .write()
+            .expect("Failed to lock socket client.")
+            .position = (30, 40);
+        handler.on_tick();
+
+        assert_eq!(
+            transports[0].sent_text(),
+            vec!["ENTER 1 Guest1 3 4", "SNAP 1:3,4", "LEAVE 1"]
+        );
+        assert_eq!(
+            transports[1].sent_text(),
+            vec![
+                "ENTER 0 Guest0 0 0",
+                "SNAP 0:0,0",
+                "ENTER 2 Guest2 40 40",
+                "LEAVE 0",
+                "SNAP 2:40,40"
+            ]
+        );
+        assert_eq!(
+            transports[2].sent_text(),
+            vec!["ENTER 1 Guest1 30 40", "SNAP 1:30,40"]
+        );
+    }
+
+    #[test]
+    fn ticks_disconnect_clients_idle_past_the_timeout() {
+        let pool = ClientPool::new(2);
+        let (idle, idle_transport) = connect_mock(&pool, 0);
+        let (_, active_transport) = connect_mock(&pool, 1);
+        idle.write()
+            .expect("Failed to lock socket client.")
+            .last_active = Some(Instant::now() - Duration::from_secs(5));
+        pool.clients.touch(1);
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.idle_timeout = Some(Duration::from_secs(1));
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+
+        handler.on_tick();
+
+        assert_eq!(
+            idle_transport.sent_text(),
+            vec!["Disconnected: idle timeout."]
+        );
+        assert!(idle_transport.is_shut_down());
+        assert!(active_transport.sent_text().is_empty());
+        assert!(!active_transport.is_shut_down());
+    }
+
+    #[test]
+    fn ticks_disconnect_guests_that_do_not_log_in_before_the_timeout() {
+        let pool = ClientPool::new(3);
+        let (guest, guest_transport) = connect_mock(&pool, 0);
+        let (member, member_transport) = connect_mock(&pool, 1);
+        let (_, fresh_transport) = connect_mock(&pool, 2);
+        for (client, account_id) in [(&guest, None), (&member, Some(AccountId(7)))] {
+            let mut client_lock = client.write().expect("Failed to lock socket client.");
+            client_lock.connected_at = Some(Instant::now() - Duration::from_secs(5));
+            client_lock.account_id = account_id;
+        }
+        pool.clients
+            .get(2)
+            .expect("Client is missing.")
+            .write()
+            .expect("Failed to lock socket client.")
+            .connected_at = Some(Instant::now());
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.pre_auth_timeout = Some(Duration::from_secs(1));
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+
+        handler.on_tick();
+
+        assert_eq!(
+            guest_transport.sent_text(),
+            vec!["Disconnected: login timeout."]
+        );
+        assert!(guest_transport.is_shut_down());
+        assert!(!member_transport.is_shut_down());
+        assert!(!fresh_transport.is_shut_down());
+    }
+
+    #[test]
+    fn clients_must_solve_the_proof_of_work_before_chatting() {
+        let pool = ClientPool::new(2);
+        let (solver, solver_transport) = connect_mock(&pool, 0);
+        let (_, cheater_transport) = connect_mock(&pool, 1);
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.pow_difficulty = Some(4);
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+        let solver_context = ClientContext {
+            id: 0,
+            address: "127.0.0.1".to_string(),
+            transport: solver_transport.clone(),
+        };
+        let cheater_context = ClientContext {
+            id: 1,
+            address: "127.0.0.1".to_string(),
+            transport: cheater_transport.clone(),
+        };
+
+        handler.on_client_connected(&solver_context);
+        handler.on_client_connected(&cheater_context);
+        let challenge_of = |id: u32| {
+            pool.clients
+                .get(id)
+                .expect("Client is missing.")
+                .read()
+                .expect("Failed to lock socket client.")
+                .challenge
+                .clone()
+                .expect("Missing proof-of-work challenge.")
+        };
+        let (challenge, cheater_challenge) = (challenge_of(0), challenge_of(1));
+        let invalid = (0..)
+            .find(|counter: &u64| !cheater_challenge.verify(&counter.to_string()))
+            .expect("Every solution is valid.");
+
+        assert_eq!(handler.on_message(&solver_context, "hi"), Flow::Continue);
+        assert_eq!(
+            handler.on_message(&solver_context, &format!(":pow {}", challenge.solve())),
+            Flow::Continue
+        );
+        assert_eq!(
+            handler.on_message(&cheater_context, &format!(":pow {}", invalid)),
+            Flow::Disconnect
+        );
+        assert_eq!(
+            solver_transport.sent_text(),
+            vec![
+                challenge.encode().as_str(),
+                "ERR Solve the proof-of-work challenge first.",
+                "OK POW",
+                "Hello"
+            ]
+        );
+        assert_eq!(
+            cheater_transport.sent_text(),
+            vec![
+                cheater_challenge.encode().as_str(),
+                "ERR Invalid proof-of-work."
+            ]
+        );
+        assert!(solver
+            .read()
+            .expect("Failed to lock socket client.")
+            .challenge
+            .is_none());
+    }
+
+    #[test]
+    fn afk_players_are_announced_and_skipped_in_ready_checks() {
+        let pool = ClientPool::new(2);
+        let (away, away_transport) = connect_mock(&pool, 0);
+        let (_, ready_transport) = connect_mock(&pool, 1);
+        away.write()
+            .expect("Failed to lock socket client.")
+            .last_active = Some(Instant::now() - Duration::from_secs(5));
+        pool.clients.touch(1);
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.afk_timeout = Some(Duration::from_secs(1));
+        context.skip_afk_in_ready_checks = true;
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+        let away_context = ClientContext {
+            id: 0,
+            address: "127.0.0.1".to_string(),
+            transport: away_transport.clone(),
+        };
+        let ready_context = ClientContext {
+            id: 1,
+            address: "127.0.0.1".to_string(),
+            transport: ready_transport.clone(),
+        };
+
+        handler.on_message(&ready_context, ":ready");
+        handler.on_tick();
+        handler.on_tick();
+        handler.on_message(&away_context, ":ready");
+
+        assert_eq!(
+            away_transport.sent_text(),
+            vec![
+                "[Server] Guest0 is now AFK.",
+                "[Server] Everyone is ready.",
+                "[Server] Guest0 is back.",
+                "OK ready"
+            ]
+        );
+        assert_eq!(
+            ready_transport.sent_text(),
+            vec![
+                "OK ready",
+                "[Server] Guest0 is now AFK.",
+                "[Server] Everyone is ready.",
+                "[Server] Guest0 is back."
+            ]
+        );
+    }
+
+    #[test]
+    fn presence_changes_reach_friends_and_member_lists() {
+        let pool = ClientPool::new(3);
+        let (_, alice_transport) = connect_mock(&pool, 0);
+        let (bob, bob_transport) = connect_mock(&pool, 1);
+        let (carol, carol_transport) = connect_mock(&pool, 2);
+        carol.write().expect("Failed to lock socket client.").room = "red".to_string();
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.afk_timeout = Some(Duration::from_secs(1));
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+        let contexts = [&alice_transport, &bob_transport, &carol_transport]
+            .iter()
+            .enumerate()
+            .map(|(id, transport)| ClientContext {
+                id: id as u32,
+                address: "127.0.0.1".to_string(),
+                transport: (*transport).clone(),
+            })
+            .collect::<Vec<_>>();
+
+        handler.on_message(&contexts[0], ":friend Guest1");
+        handler.on_message(&contexts[2], ":friend Guest1");
+        handler.on_message(&contexts[1], ":status busy");
+        handler.on_message(&contexts[0], ":who");
+        bob.write()
+            .expect("Failed to lock socket client.")
+            .last_active = Some(Instant::now() - Duration::from_secs(5));
+        handler.on_tick();
+        handler.on_message(&contexts[1], ":status online");
+        bob.write()
+            .expect("Failed to lock socket client.")
+            .last_active = Some(Instant::now() - Duration::from_secs(5));
+        handler.on_tick();
+
+        assert_eq!(
+            alice_transport.sent_text(),
+            vec![
+                "OK friend Guest1",
+                "PRESENCE Guest1 online",
+                "PRESENCE Guest1 busy",
+                "MEMBERS Guest0:online Guest1:busy",
+                "[Server] Guest1 is now AFK.",
+                "[Server] Guest1 is back.",
+                "PRESENCE Guest1 online",
+                "[Server] Guest1 is now AFK.",
+                "PRESENCE Guest1 away"
+            ]
+        );
+        assert_eq!(
+            carol_transport.sent_text(),
+            vec![
+                "OK friend Guest1",
+                "PRESENCE Guest1 online",
+                "PRESENCE Guest1 busy",
+                "PRESENCE Guest1 online",
+                "PRESENCE Guest1 away"
+            ]
+        );
+    }
+
+    #[test]
+    fn read_receipts_are_relayed_back_to_the_sender() {
+        let pool = ClientPool::new(2);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (_, bob_transport) = connect_mock(&pool, 1);
+        alice
+            .write()
+            .expect("Failed to lock socket client.")
+            .nickname = Some("alice".to_string());
+        let (events, _) = channel();
+        let handler = ChatHandler::new(
+            ServerContext::new(pool.clients.clone(), events),
+            PluginRegistry::new(),
+        );
+        let bob_context = ClientContext {
+            id: 1,
+            address: "127.0.0.1".to_string(),
+            transport: bob_transport.clone(),
+        };
+
+        handler.on_message(&bob_context, ":read alice 4");
+        handler.on_message(&bob_context, ":read dave 5");
+
+        assert_eq!(alice_transport.sent_text(), vec!["READ 4 Guest1"]);
+        assert_eq!(bob_transport.sent_text(), vec!["ERR dave is not online."]);
+    }
+
+    struct KnownAccounts {
+        names: Vec<&'static str>,
+        matches: Mutex<Vec<String>>,
+    }
+
+    impl KnownAccounts {
+        fn new(names: Vec<&'static str>) -> Self {
+            KnownAccounts {
+                names,
+                matches: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl AccountStore for KnownAccounts {
+        fn create_account(&self, _: &str, _: &str) -> StorageResult<i64> {
+            Ok(0)
+        }
+
+        fn find_account(&self, name: &str) -> StorageResult<Option<AccountRecord>> {
+            Ok(self
+                .names
+                .iter()
+                .position(|known| *known == name)
+                .map(|index| AccountRecord {
+                    id: index as i64 + 1,
+                    name: name.to_string(),
+                    password_hash: String::new(),
+                    created_at: 0,
+                }))
+        }
+
+        fn record_stats(&self, _: i64, _: bool, _: i64) -> StorageResult<()> {
+            Ok(())
+        }
+
+        fn stats(&self, _: i64) -> StorageResult<PlayerStats> {
+            Ok(PlayerStats::default())
+        }
+
+        fn record_match(
+            &self,
+            room: &str,
+            participants: &[MatchParticipant],
+        ) -> StorageResult<i64> {
+            let mut matches = self.matches.lock().expect("Failed to lock matches.");
+            matches.push(format!(
+                "{} {}",
+                room,
+                participants
+                    .iter()
+                    .map(|participant| format!(
+                        "{}:{}#{}",
+                        participant.account_id, participant.score, participant.placement
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+            Ok(matches.len() as i64)
+        }
+
+        fn leaderboard_page(&self, _: i64, _: i64) -> StorageResult<Vec<LeaderboardEntry>> {
+            Ok(vec![])
+        }
+
+        fn leaderboard_rank(&self, _: i64) -> StorageResult<Option<i64>> {
+            Ok(None)
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier(Mutex<Vec<OfflineMessage>>);
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&self, message: &OfflineMessage) -> std::io::Result<()> {
+            self.0
+                .lock()
+                .expect("Failed to lock notifications.")
+                .push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn whispers_to_offline_accounts_trigger_a_push_notification() {
+        let pool = ClientPool::new(1);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        alice
+            .write()
+            .expect("Failed to lock socket client.")
+            .nickname = Some("alice".to_string());
+        let (events, _) = channel();
+        let notifier = Arc::new(RecordingNotifier::default());
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.accounts = Some(Arc::new(KnownAccounts::new(vec!["carol", "bob"])));
+        context.notifier = Some(notifier.clone());
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+        let alice_context = ClientContext {
+            id: 0,
+            address: "127.0.0.1".to_string(),
+            transport: alice_transport.clone(),
+        };
+
+        handler.on_message(&alice_context, ":w bob see you tonight");
+        handler.on_message(&alice_context, ":w dave hi");
+
+        assert_eq!(
+            alice_transport.sent_text(),
+            vec![
+                "[Whisper -> bob #1] see you tonight",
+                "NOTIFIED 1 bob",
+                "ERR dave is not online."
+            ]
+        );
+        assert_eq!(
+            *notifier.0.lock().expect("Failed to lock notifications."),
+            vec![OfflineMessage {
+                whisper_id: 1,
+                account_id: AccountId(2),
+                recipient: "bob".to_string(),
+                sender: "alice".to_string(),
+                text: "see you tonight".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn due_announcements_are_sent_to_every_connected_client() {
+        let pool = ClientPool::new(2);
+        let (_, alice_transport) = connect_mock(&pool, 0);
+        let (bob, bob_transport) = connect_mock(&pool, 1);
+        bob.write().expect("Failed to lock socket client.").room = "red".to_string();
+        let (events, _) = channel();
+        let context = ServerContext::new(pool.clients.clone(), events);
+        let now = SystemTime::now();
+        context.announcements.add(
+            Schedule::Every(Duration::from_secs(60)),
+            "Event starts soon.".to_string(),
+            now,
+        );
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+
+        handler.broadcast_announcements(now);
+        handler.broadcast_announcements(now + Duration::from_secs(60));
+
+        assert_eq!(
+            alice_transport.sent_text(),
+            vec!["[Server] Event starts soon."]
+        );
+        assert_eq!(
+            bob_transport.sent_text(),
+            vec!["[Server] Event starts soon."]
+        );
+    }
+
+    #[test]
+    fn scoreboards_reach_players_and_spectators_until_the_match_ends() {
+        let pool = ClientPool::new(3);
+        let (alice, alice_transport) = connect_mock(&pool, 0);
+        let (bob, bob_transport) = connect_mock(&pool, 1);
+        let (_, spectator_transport) = connect_mock(&pool, 2);
+        {
+            let mut alice_lock = alice.write().expect("Failed to lock socket client.");
+            alice_lock.room = "red".to_string();
+            alice_lock.account_id = Some(AccountId(7));
+        }
+        bob.write().expect("Failed to lock socket client.").room = "red".to_string();
+        let (events, received) = channel();
+        let context = ServerContext::new(pool.clients.clone(), events);
+        let handler = ChatHandler::new(context.clone(), PluginRegistry::new());
+        let spectator_context = ClientContext {
+            id: 2,
+            address: "127.0.0.1".to_string(),
+            transport: spectator_transport.clone(),
+        };
+
+        handler.on_message(&spectator_context, ":spectate red");
+        start_match(&context, "red");
+        handler.on_tick();
+        for (player, points) in [("Guest1", 2), ("Guest0", 1), ("Guest1", 2)].iter() {
+            context.scoreboards.record(
+                "red",
+                GameEvent::Scored {
+                    player: player.to_string(),
+                    account_id: None,
+                    points: *points,
+                },
+            );
+        }
+        handler.on_tick();
+        handler.on_tick();
+        assert!(finish_match(&context, "red"));
+        assert!(!finish_match(&context, "red"));
+        handler.on_message(&spectator_context, ":scoreboard");
+
+        let broadcast = vec![
+            "MATCH_START red",
+            "SCORE red Guest0:0 Guest1:0",
+            "SCORE red Guest1:4 Guest0:1",
+            "FINAL red 1.Guest1:4 2.Guest0:1",
+        ];
+        assert_eq!(alice_transport.sent_text(), broadcast);
+        assert_eq!(bob_transport.sent_text(), broadcast);
+        let mut expected = vec!["OK spectate red"];
+        expected.extend(broadcast);
+        expected.push("ERR No match is running in red.");
+        assert_eq!(spectator_transport.sent_text(), expected);
+        let ended = received.try_iter().last();
+        match ended.clone() {
+            Some(ServerEvent::MatchEnded { room, standings }) => {
+                assert_eq!(room, "red");
+                assert_eq!(
+                    standings
+                        .iter()
+                        .map(|standing| (standing.account_id, standing.score))
+                        .collect::<Vec<_>>(),
+                    vec![(AccountId(7), 1)]
+                );
+            }
+            _ => panic!("Expected the match result to be published."),
+        }
+
+        let accounts = Arc::new(KnownAccounts::new(vec![]));
+        let mut bus = MessageBus::new();
+        Leaderboard::new(accounts.clone()).subscribe(&bus);
+        bus.on_event(&ended.expect("Expected the match result to be published."));
+        assert_eq!(
+            *accounts.matches.lock().expect("Failed to lock matches."),
+            vec!["red 7:1#1".to_string()]
+        );
+    }
+
+    #[test]
+    fn bots_fill_a_room_and_ready_up_for_every_match() {
+        let mut pool = ClientPool::new(1);
+        let (human, human_transport) = connect_mock(&pool, 0);
+        human.write().expect("Failed to lock socket client.").room = "red".to_string();
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.identity = Identity::new(None, true);
+        context.pow_difficulty = Some(8);
+        context.pre_auth_timeout = Some(Duration::from_secs(0));
+        let handler = Arc::new(ChatHandler::new(context.clone(), PluginRegistry::new()));
+        let human_context = ClientContext {
+            id: 0,
+            address: "127.0.0.1".to_string(),
+            transport: human_transport.clone(),
+        };
+        let bot = connect_bot(&pool.clients, Box::new(ReadyBot::new("red")));
+        let bot_ready = || {
+            let bot_lock = bot.read().expect("Failed to lock socket client.");
+            bot_lock.room == "red" && bot_lock.ready
+        };
+        let wait_until = |condition: &dyn Fn() -> bool| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !condition() {
+                assert!(
+                    Instant::now() < deadline,
+                    "The bot did not respond in time."
+                );
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+
+        pool.start_messaging(handler.clone(), bot.clone());
+        wait_until(&bot_ready);
+        handler.on_message(&human_context, ":ready");
+        start_match(&context, "red");
+        assert!(finish_match(&context, "red"));
+        wait_until(&bot_ready);
+        handler.on_tick();
+
+        assert_eq!(
+            human_transport.sent_text(),
+            vec![
+                "OK ready",
+                "[Server] Everyone is ready.",
+                "MATCH_START red",
+                "FINAL red 1.Bot1:0 1.Guest0:0",
+                "Bot1：gg"
+            ]
+        );
+        let transport = {
+            let bot_lock = bot.read().expect("Failed to lock socket client.");
+            assert_eq!(bot_lock.display_name(), "Bot1");
+            assert!(bot_lock.challenge.is_none());
+            bot_lock
+                .transport
+                .clone()
+                .expect("The bot was disconnected.")
+        };
+        transport.close();
+        for thread in pool.socket_client_threads.drain(..) {
+            thread.join().expect("Client thread panicked.");
+        }
+        assert!(!bot.read().expect("Failed to lock socket client.").bot);
+    }
+
+    #[test]
+    fn teleporting_players_are_snapped_back_and_then_kicked() {
+        let pool = ClientPool::new(2);
+        let (_, cheater_transport) = connect_mock(&pool, 0);
+        let (_, witness_transport) = connect_mock(&pool, 1);
+        let (events, _) = channel();
+        let mut context = ServerContext::new(pool.clients.clone(), events);
+        context.anticheat = Some(
+            AntiCheat::new(AntiCheatConfig {
+                tick_interval: Duration::from_secs(60),
+                max_distance_per_tick: Some(10.0),
+                max_inputs_per_second: None,
+                cooldowns: [("dash".to_string(), Duration::from_secs(60))]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                kick_after: Some(3),
+                evidence: None,
+            })
+            .expect("Failed to create anti-cheat."),
+        );
+        let handler = ChatHandler::new(context, PluginRegistry::new());
+        let cheater_context = ClientContext {
+            id: 0,
+            address: "127.0.0.1".to_string(),
+            transport: cheater_transport.clone(),
+        };
+
+        assert_eq!(
+            handler.on_message(&cheater_context, ":move 6 8"),
+            Flow::Continue
+        );
+        assert_eq!(
+            handler.on_message(&cheater_context, ":move 900 8"),
+            Flow::Continue
+        );
+        assert_eq!(
+            handler.on_message(&cheater_context, ":ability dash"),
+            Flow::Continue
+        );
+        assert_eq!(
+            handler.on_message(&cheater_context, ":ability dash"),
+            Flow::Continue
+        );
+        assert_eq!(
+            handler.on_message(&cheater_context, ":move 6 900"),
+            Flow::Disconnect
+        );
+
+        assert_eq!(
+            cheater_transport.sent_text(),
+            vec![
+                "POS 6 8",
+                "POS 6 8",
+                "ABILITY Guest0 dash",
+                "ERR dash is not ready yet.",
+                "Disconnected: cheating detected."
+            ]
+        );
+        assert_eq!(witness_transport.sent_text(), vec!["ABILITY Guest0 dash"]);
+    }
+
+    #[test]
+    fn recorded_sessions_replay_through_the_handler() {
+        let recording = [
+            (0, 7, PacketKind::Join, "10.0.0.7"),
+            (5, 9, PacketKind::Join, "10.0.0.9"),
+            (10, 7, PacketKind::Message, "hello"),
+            (15, 9, PacketKind::Message, ":end"),
+            (20, 9, PacketKind::Message, "never delivered"),
+            (25, 7, PacketKind::Message, "still here\0"),
+            (30, 7, PacketKind::Leave, ""),
+        ]
+        .iter()
+        .map(|(timestamp_ms, client_id, kind, payload)| {
+            RecordedPacket {
+                timestamp_ms: *timestamp_ms,
+                client_id: *client_id,
+                kind: *kind,
+                payload: payload.to_string(),
+            }
+            .encode()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+        let pool = ClientPool::new(2);
+        let (events, _received) = channel();
+        let handler = ChatHandler::new(
+            ServerContext::new(pool.clients.clone(), events),
+            PluginRegistry::new(),
+        );
+        let mut transports = vec![];
+
+        let delivered = Playback::parse(&recording).replay(
+            &handler,
+            PlaybackTiming::AsFastAsPossible,
+            |_, address| {
+                let client = pool.clients.find_empty();
+                let mut client_lock = client.write().expect("Failed to lock socket client.");
+                let transport = Arc::new(MockTransport::new());
+                client_lock.address = address.to_string();
+                client_lock.transport = Some(transport.clone());
+                transports.push(transport.clone());
+                Some(ClientContext {
+                    id: client_lock.id,
+                    address: address.to_string(),
+                    transport,
+                })
+            },
+        );
+
+        assert_eq!(delivered, 3);
+        assert_eq!(
+            transports[0].sent_text(),
+            vec!["Hello", "hello", "still here"]
+        );
+        assert_eq!(transports[1].sent_text(), vec!["Hello", "hello", "Bye!"]);
+        assert!(transports.iter().all(|transport| transport.is_closed()));
+        assert_eq!(pool.clients.connected().len(), 0);
+    }
+}
@@ -0,0 +1,6 @@
+mod chat;
+mod pool;
+mod relay;
+pub use chat::*;
+pub use pool::*;
+pub use relay::*;
@@ -0,0 +1,92 @@
+use crate::clients::{ClientRegistry, SharedClient};
+use crate::frame::DEFAULT_MAX_FRAME_SIZE;
+use crate::server::{supervise_client, ClientContext, ServerHandler};
+use std::sync::Arc;
+
+pub struct ClientPool {
+    pub clients: ClientRegistry,
+    pub socket_client_threads: Vec<std::thread::JoinHandle<()>>,
+    pub max_frame_size: usize,
+}
+
+impl ClientPool {
+    pub fn new(pool_size: usize) -> Self {
+        ClientPool {
+            clients: ClientRegistry::new(pool_size),
+            socket_client_threads: Vec::with_capacity(pool_size),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    pub fn start_messaging(
+        &mut self,
+        handler: Arc<dyn ServerHandler>,
+        socket_client: SharedClient,
+    ) {
+        let client = {
+            let client_lock = socket_client.read().expect("Failed to lock socket client.");
+            match client_lock.transport.clone() {
+                Some(transport) => ClientContext {
+                    id: client_lock.id,
+                    address: client_lock.address.clone(),
+                    transport,
+                },
+                None => return,
+            }
+        };
+        let clients = self.clients.clone();
+        self.socket_client_threads
+            .retain(|thread| !thread.is_finished());
+        self.socket_client_threads.push(supervise_client(
+            handler,
+            client,
+            self.max_frame_size,
+            move |client| {
+                if clients.reclaim(client.id, &client.transport) {
+                    eprintln!("クライアント{}のスロットを回収しました\n", client.id);
+                }
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Flow;
+    use crate::transport::MockTransport;
+
+    struct Silent;
+
+    impl ServerHandler for Silent {
+        fn on_message(&self, _client: &ClientContext, _message: &str) -> Flow {
+            Flow::Continue
+        }
+    }
+
+    #[test]
+    fn finished_client_threads_are_pruned() {
+        let mut pool = ClientPool::new(1);
+        let handler: Arc<dyn ServerHandler> = Arc::new(Silent);
+        for _ in 0..8 {
+            let client = pool.clients.find_empty();
+            client
+                .write()
+                .expect("Failed to lock socket client.")
+                .transport = Some(Arc::new(MockTransport::new()));
+            pool.start_messaging(handler.clone(), client.clone());
+            while !pool
+                .socket_client_threads
+                .iter()
+                .all(|thread| thread.is_finished())
+            {
+                std::thread::yield_now();
+            }
+            client
+                .write()
+                .expect("Failed to lock socket client.")
+                .transport = None;
+        }
+        assert_eq!(pool.socket_client_threads.len(), 1);
+    }
+}
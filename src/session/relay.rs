@@ -0,0 +1,72 @@
+use super::send_text;
+use crate::bridge::RelayedMessage;
+use crate::clients::ClientRegistry;
+use crate::cluster::{ClusterNode, DirectMessage, ReceiptStatus};
+use crate::console;
+use std::sync::mpsc::Receiver;
+
+pub fn spawn_relay_delivery(messages: Receiver<RelayedMessage>, clients: ClientRegistry) {
+    std::thread::spawn(move || {
+        for relayed in messages.iter() {
+            for (client_id, _, transport, room) in clients.connected() {
+                if room != relayed.room {
+                    continue;
+                }
+                console::chat(
+                    relayed.client_id,
+                    &relayed.room,
+                    format_args!(
+                        "{}/{} -> {}：{}\n",
+                        &relayed.origin, relayed.client_id, client_id, &relayed.message
+                    ),
+                );
+                send_text(&transport, &relayed.message);
+            }
+        }
+    });
+}
+
+pub fn spawn_whisper_delivery(
+    messages: Receiver<DirectMessage>,
+    clients: ClientRegistry,
+    cluster: ClusterNode,
+) {
+    std::thread::spawn(move || {
+        for message in messages.iter() {
+            match message {
+                DirectMessage::Whisper(whisper) => {
+                    match clients.find_by_nickname(&whisper.target) {
+                        Some(transport) => {
+                            console::system(format_args!(
+                                "{}/{} -> {}（ささやき）：{}\n",
+                                &whisper.origin, &whisper.sender, &whisper.target, &whisper.text
+                            ));
+                            send_text(&transport, &whisper.format());
+                            if let Err(e) =
+                                cluster.receipt(whisper.receipt(ReceiptStatus::Delivered))
+                            {
+                                console::error(format_args!(
+                                    "配信通知の送信に失敗しました：{}\n",
+                                    e
+                                ));
+                            }
+                        }
+                        None => console::error(format_args!(
+                            "ささやきの宛先{}が見つかりません\n",
+                            &whisper.target
+                        )),
+                    }
+                }
+                DirectMessage::Receipt(receipt) => {
+                    match clients.find_by_nickname(&receipt.sender) {
+                        Some(transport) => send_text(&transport, &receipt.encode()),
+                        None => console::error(format_args!(
+                            "通知の宛先{}が見つかりません\n",
+                            &receipt.sender
+                        )),
+                    }
+                }
+            }
+        }
+    });
+}
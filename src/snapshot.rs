@@ -0,0 +1,223 @@
+use crate::identity::{AccountId, Identity, Session};
+use crate::rooms::Rooms;
+#[cfg(feature = "sqlite")]
+use crate::storage::Storage;
+use crate::storage::{unix_now, BanRecord};
+use std::path::PathBuf;
+
+const SNAPSHOT_HEADER: &str = "ogp-snapshot 1";
+
+#[derive(Clone, Debug)]
+pub struct SnapshotConfig {
+    pub path: PathBuf,
+}
+
+impl SnapshotConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("SNAPSHOT_PATH")
+            .ok()
+            .map(|path| SnapshotConfig {
+                path: PathBuf::from(path),
+            })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ServerSnapshot {
+    pub saved_at: i64,
+    pub rooms: Vec<(String, Vec<String>)>,
+    pub sessions: Vec<Session>,
+    pub bans: Vec<(String, BanRecord)>,
+}
+
+pub fn escape_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\0', "\\0")
+}
+
+pub fn unescape_field(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+impl ServerSnapshot {
+    pub fn encode(&self) -> String {
+        let mut lines = vec![
+            SNAPSHOT_HEADER.to_string(),
+            format!("saved_at\t{}", self.saved_at),
+        ];
+        for (room, messages) in self.rooms.iter() {
+            lines.push(format!("room\t{}", escape_field(room)));
+            for message in messages.iter() {
+                lines.push(format!(
+                    "message\t{}\t{}",
+                    escape_field(room),
+                    escape_field(message)
+                ));
+            }
+        }
+        for session in self.sessions.iter() {
+            lines.push(format!(
+                "session\t{}\t{}\t{}\t{}",
+                escape_field(&session.token),
+                session.account_id,
+                escape_field(&session.name),
+                session.expires_at
+            ));
+        }
+        for (target, ban) in self.bans.iter() {
+            lines.push(format!(
+                "ban\t{}\t{}\t{}",
+                escape_field(target),
+                ban.expires_at
+                    .map(|expires_at| expires_at.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                escape_field(&ban.reason)
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn decode(data: &str) -> Option<ServerSnapshot> {
+        let mut lines = data.lines();
+        if lines.next()? != SNAPSHOT_HEADER {
+            return None;
+        }
+        let mut snapshot = ServerSnapshot::default();
+        for line in lines {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            match fields.as_slice() {
+                ["saved_at", saved_at] => snapshot.saved_at = saved_at.parse().ok()?,
+                ["room", room] => snapshot.rooms.push((unescape_field(room), vec![])),
+                ["message", room, message] => {
+                    let room = unescape_field(room);
+                    let index = match snapshot.rooms.iter().position(|(name, _)| *name == room) {
+                        Some(index) => index,
+                        None => {
+                            snapshot.rooms.push((room, vec![]));
+                            snapshot.rooms.len() - 1
+                        }
+                    };
+                    snapshot.rooms[index].1.push(unescape_field(message));
+                }
+                ["session", token, account_id, name, expires_at] => {
+                    snapshot.sessions.push(Session {
+                        token: unescape_field(token),
+                        account_id: AccountId(account_id.parse().ok()?),
+                        name: unescape_field(name),
+                        expires_at: expires_at.parse().ok()?,
+                    })
+                }
+                ["ban", target, expires_at, reason] => snapshot.bans.push((
+                    unescape_field(target),
+                    BanRecord {
+                        reason: unescape_field(reason),
+                        expires_at: expires_at.parse().ok(),
+                    },
+                )),
+                [""] => {}
+                _ => return None,
+            }
+        }
+        Some(snapshot)
+    }
+}
+
+#[derive(Clone)]
+pub struct Snapshotter {
+    config: SnapshotConfig,
+    rooms: Rooms,
+    identity: Identity,
+    #[cfg(feature = "sqlite")]
+    storage: Option<Storage>,
+}
+
+impl Snapshotter {
+    pub fn new(
+        config: SnapshotConfig,
+        rooms: Rooms,
+        identity: Identity,
+        #[cfg(feature = "sqlite")] storage: Option<Storage>,
+    ) -> Self {
+        Snapshotter {
+            config,
+            rooms,
+            identity,
+            #[cfg(feature = "sqlite")]
+            storage,
+        }
+    }
+
+    pub fn capture(&self) -> ServerSnapshot {
+        #[cfg(feature = "sqlite")]
+        let bans = match self.storage.as_ref().map(|storage| storage.list_bans()) {
+            Some(Ok(bans)) => bans,
+            Some(Err(e)) => {
+                eprintln!("BAN一覧の取得に失敗しました：{}", e);
+                vec![]
+            }
+            None => vec![],
+        };
+        #[cfg(not(feature = "sqlite"))]
+        let bans = vec![];
+        ServerSnapshot {
+            saved_at: unix_now(),
+            rooms: self.rooms.snapshot(),
+            sessions: self.identity.sessions(),
+            bans,
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<ServerSnapshot> {
+        let snapshot = self.capture();
+        let temporary = self.config.path.with_extension("tmp");
+        std::fs::write(&temporary, snapshot.encode())?;
+        std::fs::rename(&temporary, &self.config.path)?;
+        Ok(snapshot)
+    }
+
+    pub fn restore(&self) -> std::io::Result<Option<ServerSnapshot>> {
+        let data = match std::fs::read_to_string(&self.config.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let snapshot = ServerSnapshot::decode(&data).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Malformed server snapshot.",
+            )
+        })?;
+
+        self.rooms.restore(snapshot.rooms.clone());
+        self.identity.restore_sessions(snapshot.sessions.clone());
+        #[cfg(feature = "sqlite")]
+        if let Some(storage) = self.storage.as_ref() {
+            for (target, ban) in snapshot.bans.iter() {
+                if let Err(e) = storage.add_ban(target, &ban.reason, ban.expires_at) {
+                    eprintln!("BANの復元に失敗しました：{}", e);
+                }
+            }
+        }
+        Ok(Some(snapshot))
+    }
+}
@@ -0,0 +1,58 @@
+use super::error::StorageResult;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[derive(Clone, Debug)]
+pub struct BanRecord {
+    pub reason: String,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountRecord {
+    pub id: i64,
+    pub name: String,
+    pub password_hash: String,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PlayerStats {
+    pub account_id: i64,
+    pub matches_played: i64,
+    pub wins: i64,
+    pub total_score: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchParticipant {
+    pub account_id: i64,
+    pub score: i64,
+    pub placement: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub rank: i64,
+    pub account_id: i64,
+    pub name: String,
+    pub matches_played: i64,
+    pub wins: i64,
+    pub total_score: i64,
+}
+
+pub trait AccountStore: Send + Sync {
+    fn create_account(&self, name: &str, password_hash: &str) -> StorageResult<i64>;
+    fn find_account(&self, name: &str) -> StorageResult<Option<AccountRecord>>;
+    fn record_stats(&self, account_id: i64, won: bool, score: i64) -> StorageResult<()>;
+    fn stats(&self, account_id: i64) -> StorageResult<PlayerStats>;
+    fn record_match(&self, room: &str, participants: &[MatchParticipant]) -> StorageResult<i64>;
+    fn leaderboard_page(&self, offset: i64, limit: i64) -> StorageResult<Vec<LeaderboardEntry>>;
+    fn leaderboard_rank(&self, account_id: i64) -> StorageResult<Option<i64>>;
+}
@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum StorageError {
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    #[cfg(feature = "postgres")]
+    Postgres(tokio_postgres::Error),
+    #[cfg(feature = "postgres")]
+    Pool(String),
+}
+
+impl Display for StorageError {
+    #[cfg_attr(
+        not(any(feature = "sqlite", feature = "postgres")),
+        allow(unused_variables)
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            #[cfg(feature = "sqlite")]
+            StorageError::Sqlite(ref e) => write!(f, "SQLite error: {}", e),
+            #[cfg(feature = "postgres")]
+            StorageError::Postgres(ref e) => write!(f, "PostgreSQL error: {}", e),
+            #[cfg(feature = "postgres")]
+            StorageError::Pool(ref e) => write!(f, "Connection pool error: {}", e),
+        }
+    }
+}
+
+impl StorageError {
+    pub fn is_unique_violation(&self) -> bool {
+        match *self {
+            #[cfg(feature = "sqlite")]
+            StorageError::Sqlite(rusqlite::Error::SqliteFailure(ref e, _)) => {
+                e.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+            }
+            #[cfg(feature = "postgres")]
+            StorageError::Postgres(ref e) => {
+                e.code() == Some(&tokio_postgres::error::SqlState::UNIQUE_VIOLATION)
+            }
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Sqlite(e)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<tokio_postgres::Error> for StorageError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        StorageError::Postgres(e)
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
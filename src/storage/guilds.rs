@@ -0,0 +1,32 @@
+use super::error::StorageResult;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuildRecord {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuildMemberRecord {
+    pub account_id: i64,
+    pub name: String,
+    pub role: String,
+}
+
+pub trait GuildStore: Send + Sync {
+    fn create_guild(&self, name: &str, founder: i64, role: &str) -> StorageResult<i64>;
+    fn find_guild(&self, name: &str) -> StorageResult<Option<GuildRecord>>;
+    fn guild_membership(&self, account_id: i64) -> StorageResult<Option<(GuildRecord, String)>>;
+    fn guild_members(&self, guild_id: i64) -> StorageResult<Vec<GuildMemberRecord>>;
+    fn add_guild_member(&self, guild_id: i64, account_id: i64, role: &str) -> StorageResult<()>;
+    fn set_guild_role(&self, guild_id: i64, account_id: i64, role: &str) -> StorageResult<bool>;
+    fn remove_guild_member(&self, guild_id: i64, account_id: i64) -> StorageResult<bool>;
+    fn delete_guild(&self, guild_id: i64) -> StorageResult<()>;
+    fn add_guild_invite(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+        invited_by: i64,
+    ) -> StorageResult<()>;
+    fn take_guild_invite(&self, guild_id: i64, account_id: i64) -> StorageResult<bool>;
+}
@@ -0,0 +1,92 @@
+use rusqlite::{Connection, Result};
+
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE accounts (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    name          TEXT    NOT NULL UNIQUE,
+    password_hash TEXT    NOT NULL,
+    created_at    INTEGER NOT NULL
+);
+
+CREATE TABLE bans (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    target     TEXT    NOT NULL UNIQUE,
+    reason     TEXT    NOT NULL DEFAULT '',
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER
+);
+
+CREATE TABLE chat_messages (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    room       TEXT    NOT NULL,
+    client_id  INTEGER NOT NULL,
+    account_id INTEGER REFERENCES accounts(id),
+    message    TEXT    NOT NULL,
+    sent_at    INTEGER NOT NULL
+);
+CREATE INDEX chat_messages_room ON chat_messages(room, id);
+
+CREATE TABLE match_results (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    room        TEXT    NOT NULL,
+    finished_at INTEGER NOT NULL
+);
+
+CREATE TABLE match_participants (
+    match_id   INTEGER NOT NULL REFERENCES match_results(id),
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    score      INTEGER NOT NULL,
+    placement  INTEGER NOT NULL,
+    PRIMARY KEY (match_id, account_id)
+);
+"#,
+    r#"
+CREATE TABLE player_stats (
+    account_id     INTEGER PRIMARY KEY REFERENCES accounts(id),
+    matches_played INTEGER NOT NULL DEFAULT 0,
+    wins           INTEGER NOT NULL DEFAULT 0,
+    total_score    INTEGER NOT NULL DEFAULT 0
+);
+"#,
+    r#"
+CREATE TABLE guilds (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    name       TEXT    NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE guild_members (
+    account_id INTEGER PRIMARY KEY REFERENCES accounts(id),
+    guild_id   INTEGER NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    role       TEXT    NOT NULL,
+    joined_at  INTEGER NOT NULL
+);
+CREATE INDEX guild_members_guild ON guild_members(guild_id);
+
+CREATE TABLE guild_invites (
+    guild_id   INTEGER NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    invited_by INTEGER NOT NULL REFERENCES accounts(id),
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, account_id)
+);
+"#,
+];
+
+pub fn schema_version(connection: &Connection) -> Result<usize> {
+    connection.query_row("PRAGMA user_version", [], |row| {
+        row.get::<_, i64>(0).map(|v| v as usize)
+    })
+}
+
+pub fn migrate(connection: &mut Connection) -> Result<usize> {
+    let current = schema_version(connection)?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", &((index + 1) as i64))?;
+        transaction.commit()?;
+    }
+    Ok(MIGRATIONS.len())
+}
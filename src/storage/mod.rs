@@ -0,0 +1,41 @@
+mod accounts;
+mod error;
+mod guilds;
+#[cfg(feature = "sqlite")]
+mod migrations;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+pub use accounts::*;
+pub use error::*;
+pub use guilds::*;
+#[cfg(feature = "postgres")]
+pub use postgres::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite::*;
+
+#[cfg(feature = "sqlite")]
+use std::sync::Arc;
+
+#[cfg(feature = "sqlite")]
+pub fn open_account_store(storage: &Storage) -> StorageResult<Arc<dyn AccountStore>> {
+    open_stores(storage).map(|(accounts, _)| accounts)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn open_stores(
+    storage: &Storage,
+) -> StorageResult<(Arc<dyn AccountStore>, Arc<dyn GuildStore>)> {
+    match std::env::var("DATABASE_URL") {
+        #[cfg(feature = "postgres")]
+        Ok(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+            let pool_size = std::env::var("DATABASE_POOL_SIZE")
+                .ok()
+                .and_then(|size| size.parse().ok());
+            let store = Arc::new(PostgresStore::connect(&url, pool_size)?);
+            Ok((store.clone(), store))
+        }
+        _ => Ok((Arc::new(storage.clone()), Arc::new(storage.clone()))),
+    }
+}
@@ -0,0 +1,569 @@
+use super::accounts::unix_now;
+use super::accounts::{
+    AccountRecord, AccountStore, LeaderboardEntry, MatchParticipant, PlayerStats,
+};
+use super::error::{StorageError, StorageResult};
+use super::guilds::{GuildMemberRecord, GuildRecord, GuildStore};
+use deadpool_postgres::{Manager, Pool};
+use tokio::runtime::Runtime;
+use tokio_postgres::NoTls;
+
+const DEFAULT_POOL_SIZE: usize = 16;
+
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE accounts (
+    id            BIGSERIAL PRIMARY KEY,
+    name          TEXT   NOT NULL UNIQUE,
+    password_hash TEXT   NOT NULL,
+    created_at    BIGINT NOT NULL
+);
+
+CREATE TABLE player_stats (
+    account_id     BIGINT PRIMARY KEY REFERENCES accounts(id),
+    matches_played BIGINT NOT NULL DEFAULT 0,
+    wins           BIGINT NOT NULL DEFAULT 0,
+    total_score    BIGINT NOT NULL DEFAULT 0
+);
+"#,
+    r#"
+CREATE TABLE match_results (
+    id          BIGSERIAL PRIMARY KEY,
+    room        TEXT   NOT NULL,
+    finished_at BIGINT NOT NULL
+);
+
+CREATE TABLE match_participants (
+    match_id   BIGINT NOT NULL REFERENCES match_results(id),
+    account_id BIGINT NOT NULL REFERENCES accounts(id),
+    score      BIGINT NOT NULL,
+    placement  BIGINT NOT NULL,
+    PRIMARY KEY (match_id, account_id)
+);
+"#,
+    r#"
+CREATE TABLE guilds (
+    id         BIGSERIAL PRIMARY KEY,
+    name       TEXT   NOT NULL UNIQUE,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE guild_members (
+    account_id BIGINT PRIMARY KEY REFERENCES accounts(id),
+    guild_id   BIGINT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    role       TEXT   NOT NULL,
+    joined_at  BIGINT NOT NULL
+);
+CREATE INDEX guild_members_guild ON guild_members(guild_id);
+
+CREATE TABLE guild_invites (
+    guild_id   BIGINT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    account_id BIGINT NOT NULL REFERENCES accounts(id),
+    invited_by BIGINT NOT NULL REFERENCES accounts(id),
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, account_id)
+);
+"#,
+];
+
+const UPDATE_STATS: &str =
+    "INSERT INTO player_stats (account_id, matches_played, wins, total_score)
+     VALUES ($1, 1, $2, $3)
+     ON CONFLICT (account_id) DO UPDATE SET
+         matches_played = player_stats.matches_played + 1,
+         wins = player_stats.wins + EXCLUDED.wins,
+         total_score = player_stats.total_score + EXCLUDED.total_score";
+
+pub struct PostgresStore {
+    pool: Pool,
+    runtime: Runtime,
+}
+
+impl PostgresStore {
+    pub fn connect(url: &str, pool_size: Option<usize>) -> StorageResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| StorageError::Pool(e.to_string()))?;
+        let config = url.parse::<tokio_postgres::Config>()?;
+        let pool = Pool::new(
+            Manager::new(config, NoTls),
+            pool_size.unwrap_or(DEFAULT_POOL_SIZE),
+        );
+        let store = PostgresStore { pool, runtime };
+        store.runtime.block_on(store.migrate())?;
+        Ok(store)
+    }
+
+    async fn client(&self) -> StorageResult<deadpool_postgres::Client> {
+        self.pool
+            .get()
+            .await
+            .map_err(|e| StorageError::Pool(e.to_string()))
+    }
+
+    async fn migrate(&self) -> StorageResult<()> {
+        let mut client = self.client().await?;
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS schema_migrations (version BIGINT PRIMARY KEY)",
+            )
+            .await?;
+        let current: i64 = client
+            .query_one(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+                &[],
+            )
+            .await?
+            .get(0);
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+            let transaction = client.transaction().await?;
+            transaction.batch_execute(migration).await?;
+            transaction
+                .execute(
+                    "INSERT INTO schema_migrations (version) VALUES ($1)",
+                    &[&((index + 1) as i64)],
+                )
+                .await?;
+            transaction.commit().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn create_account_async(
+        &self,
+        name: &str,
+        password_hash: &str,
+    ) -> StorageResult<i64> {
+        let client = self.client().await?;
+        let row = client
+            .query_one(
+                "INSERT INTO accounts (name, password_hash, created_at) VALUES ($1, $2, $3)
+                 RETURNING id",
+                &[&name, &password_hash, &unix_now()],
+            )
+            .await?;
+        Ok(row.get(0))
+    }
+
+    pub async fn find_account_async(&self, name: &str) -> StorageResult<Option<AccountRecord>> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                "SELECT id, name, password_hash, created_at FROM accounts WHERE name = $1",
+                &[&name],
+            )
+            .await?;
+        Ok(row.map(|row| AccountRecord {
+            id: row.get(0),
+            name: row.get(1),
+            password_hash: row.get(2),
+            created_at: row.get(3),
+        }))
+    }
+
+    pub async fn record_stats_async(
+        &self,
+        account_id: i64,
+        won: bool,
+        score: i64,
+    ) -> StorageResult<()> {
+        let client = self.client().await?;
+        client
+            .execute(UPDATE_STATS, &[&account_id, &(won as i64), &score])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn record_match_async(
+        &self,
+        room: &str,
+        participants: &[MatchParticipant],
+    ) -> StorageResult<i64> {
+        let mut client = self.client().await?;
+        let transaction = client.transaction().await?;
+        let match_id: i64 = transaction
+            .query_one(
+                "INSERT INTO match_results (room, finished_at) VALUES ($1, $2) RETURNING id",
+                &[&room, &unix_now()],
+            )
+            .await?
+            .get(0);
+        for participant in participants {
+            transaction
+                .execute(
+                    "INSERT INTO match_participants (match_id, account_id, score, placement)
+                     VALUES ($1, $2, $3, $4)",
+                    &[
+                        &match_id,
+                        &participant.account_id,
+                        &participant.score,
+                        &(participant.placement as i64),
+                    ],
+                )
+                .await?;
+            transaction
+                .execute(
+                    UPDATE_STATS,
+                    &[
+                        &participant.account_id,
+                        &((participant.placement == 1) as i64),
+                        &participant.score,
+                    ],
+                )
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(match_id)
+    }
+
+    pub async fn leaderboard_page_async(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<LeaderboardEntry>> {
+        let client = self.client().await?;
+        let rows = client
+            .query(
+                "SELECT a.id, a.name, s.matches_played, s.wins, s.total_score
+                 FROM player_stats s JOIN accounts a ON a.id = s.account_id
+                 ORDER BY s.total_score DESC, s.wins DESC, a.id ASC
+                 LIMIT $1 OFFSET $2",
+                &[&limit, &offset],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| LeaderboardEntry {
+                rank: offset + index as i64 + 1,
+                account_id: row.get(0),
+                name: row.get(1),
+                matches_played: row.get(2),
+                wins: row.get(3),
+                total_score: row.get(4),
+            })
+            .collect())
+    }
+
+    pub async fn leaderboard_rank_async(&self, account_id: i64) -> StorageResult<Option<i64>> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                "SELECT COUNT(other.account_id) + 1 FROM player_stats me
+                 LEFT JOIN player_stats other
+                   ON other.total_score > me.total_score
+                   OR (other.total_score = me.total_score AND other.wins > me.wins)
+                   OR (other.total_score = me.total_score AND other.wins = me.wins
+                       AND other.account_id < me.account_id)
+                 WHERE me.account_id = $1
+                 GROUP BY me.account_id",
+                &[&account_id],
+            )
+            .await?;
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    pub async fn stats_async(&self, account_id: i64) -> StorageResult<PlayerStats> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                "SELECT matches_played, wins, total_score FROM player_stats WHERE account_id = $1",
+                &[&account_id],
+            )
+            .await?;
+        Ok(row
+            .map(|row| PlayerStats {
+                account_id,
+                matches_played: row.get(0),
+                wins: row.get(1),
+                total_score: row.get(2),
+            })
+            .unwrap_or(PlayerStats {
+                account_id,
+                ..PlayerStats::default()
+            }))
+    }
+
+    pub async fn create_guild_async(
+        &self,
+        name: &str,
+        founder: i64,
+        role: &str,
+    ) -> StorageResult<i64> {
+        let mut client = self.client().await?;
+        let transaction = client.transaction().await?;
+        let guild_id: i64 = transaction
+            .query_one(
+                "INSERT INTO guilds (name, created_at) VALUES ($1, $2) RETURNING id",
+                &[&name, &unix_now()],
+            )
+            .await?
+            .get(0);
+        transaction
+            .execute(
+                "INSERT INTO guild_members (account_id, guild_id, role, joined_at)
+                 VALUES ($1, $2, $3, $4)",
+                &[&founder, &guild_id, &role, &unix_now()],
+            )
+            .await?;
+        transaction
+            .execute(
+                "DELETE FROM guild_invites WHERE account_id = $1",
+                &[&founder],
+            )
+            .await?;
+        transaction.commit().await?;
+        Ok(guild_id)
+    }
+
+    pub async fn find_guild_async(&self, name: &str) -> StorageResult<Option<GuildRecord>> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                "SELECT id, name FROM guilds WHERE lower(name) = lower($1)",
+                &[&name],
+            )
+            .await?;
+        Ok(row.map(|row| GuildRecord {
+            id: row.get(0),
+            name: row.get(1),
+        }))
+    }
+
+    pub async fn guild_membership_async(
+        &self,
+        account_id: i64,
+    ) -> StorageResult<Option<(GuildRecord, String)>> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                "SELECT g.id, g.name, m.role FROM guild_members m
+                 JOIN guilds g ON g.id = m.guild_id
+                 WHERE m.account_id = $1",
+                &[&account_id],
+            )
+            .await?;
+        Ok(row.map(|row| {
+            (
+                GuildRecord {
+                    id: row.get(0),
+                    name: row.get(1),
+                },
+                row.get(2),
+            )
+        }))
+    }
+
+    pub async fn guild_members_async(
+        &self,
+        guild_id: i64,
+    ) -> StorageResult<Vec<GuildMemberRecord>> {
+        let client = self.client().await?;
+        let rows = client
+            .query(
+                "SELECT a.id, a.name, m.role FROM guild_members m
+                 JOIN accounts a ON a.id = m.account_id
+                 WHERE m.guild_id = $1
+                 ORDER BY m.joined_at ASC, a.id ASC",
+                &[&guild_id],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| GuildMemberRecord {
+                account_id: row.get(0),
+                name: row.get(1),
+                role: row.get(2),
+            })
+            .collect())
+    }
+
+    pub async fn add_guild_member_async(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+        role: &str,
+    ) -> StorageResult<()> {
+        let mut client = self.client().await?;
+        let transaction = client.transaction().await?;
+        transaction
+            .execute(
+                "INSERT INTO guild_members (account_id, guild_id, role, joined_at)
+                 VALUES ($1, $2, $3, $4)",
+                &[&account_id, &guild_id, &role, &unix_now()],
+            )
+            .await?;
+        transaction
+            .execute(
+                "DELETE FROM guild_invites WHERE account_id = $1",
+                &[&account_id],
+            )
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn set_guild_role_async(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+        role: &str,
+    ) -> StorageResult<bool> {
+        let client = self.client().await?;
+        let updated = client
+            .execute(
+                "UPDATE guild_members SET role = $3 WHERE guild_id = $1 AND account_id = $2",
+                &[&guild_id, &account_id, &role],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    pub async fn remove_guild_member_async(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+    ) -> StorageResult<bool> {
+        let client = self.client().await?;
+        let removed = client
+            .execute(
+                "DELETE FROM guild_members WHERE guild_id = $1 AND account_id = $2",
+                &[&guild_id, &account_id],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+
+    pub async fn delete_guild_async(&self, guild_id: i64) -> StorageResult<()> {
+        let client = self.client().await?;
+        client
+            .execute("DELETE FROM guilds WHERE id = $1", &[&guild_id])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn add_guild_invite_async(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+        invited_by: i64,
+    ) -> StorageResult<()> {
+        let client = self.client().await?;
+        client
+            .execute(
+                "INSERT INTO guild_invites (guild_id, account_id, invited_by, created_at)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (guild_id, account_id) DO UPDATE SET
+                     invited_by = EXCLUDED.invited_by,
+                     created_at = EXCLUDED.created_at",
+                &[&guild_id, &account_id, &invited_by, &unix_now()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn take_guild_invite_async(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+    ) -> StorageResult<bool> {
+        let client = self.client().await?;
+        let removed = client
+            .execute(
+                "DELETE FROM guild_invites WHERE guild_id = $1 AND account_id = $2",
+                &[&guild_id, &account_id],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+}
+
+impl AccountStore for PostgresStore {
+    fn create_account(&self, name: &str, password_hash: &str) -> StorageResult<i64> {
+        self.runtime
+            .block_on(self.create_account_async(name, password_hash))
+    }
+
+    fn find_account(&self, name: &str) -> StorageResult<Option<AccountRecord>> {
+        self.runtime.block_on(self.find_account_async(name))
+    }
+
+    fn record_stats(&self, account_id: i64, won: bool, score: i64) -> StorageResult<()> {
+        self.runtime
+            .block_on(self.record_stats_async(account_id, won, score))
+    }
+
+    fn stats(&self, account_id: i64) -> StorageResult<PlayerStats> {
+        self.runtime.block_on(self.stats_async(account_id))
+    }
+
+    fn record_match(&self, room: &str, participants: &[MatchParticipant]) -> StorageResult<i64> {
+        self.runtime
+            .block_on(self.record_match_async(room, participants))
+    }
+
+    fn leaderboard_page(&self, offset: i64, limit: i64) -> StorageResult<Vec<LeaderboardEntry>> {
+        self.runtime
+            .block_on(self.leaderboard_page_async(offset, limit))
+    }
+
+    fn leaderboard_rank(&self, account_id: i64) -> StorageResult<Option<i64>> {
+        self.runtime
+            .block_on(self.leaderboard_rank_async(account_id))
+    }
+}
+
+impl GuildStore for PostgresStore {
+    fn create_guild(&self, name: &str, founder: i64, role: &str) -> StorageResult<i64> {
+        self.runtime
+            .block_on(self.create_guild_async(name, founder, role))
+    }
+
+    fn find_guild(&self, name: &str) -> StorageResult<Option<GuildRecord>> {
+        self.runtime.block_on(self.find_guild_async(name))
+    }
+
+    fn guild_membership(&self, account_id: i64) -> StorageResult<Option<(GuildRecord, String)>> {
+        self.runtime
+            .block_on(self.guild_membership_async(account_id))
+    }
+
+    fn guild_members(&self, guild_id: i64) -> StorageResult<Vec<GuildMemberRecord>> {
+        self.runtime.block_on(self.guild_members_async(guild_id))
+    }
+
+    fn add_guild_member(&self, guild_id: i64, account_id: i64, role: &str) -> StorageResult<()> {
+        self.runtime
+            .block_on(self.add_guild_member_async(guild_id, account_id, role))
+    }
+
+    fn set_guild_role(&self, guild_id: i64, account_id: i64, role: &str) -> StorageResult<bool> {
+        self.runtime
+            .block_on(self.set_guild_role_async(guild_id, account_id, role))
+    }
+
+    fn remove_guild_member(&self, guild_id: i64, account_id: i64) -> StorageResult<bool> {
+        self.runtime
+            .block_on(self.remove_guild_member_async(guild_id, account_id))
+    }
+
+    fn delete_guild(&self, guild_id: i64) -> StorageResult<()> {
+        self.runtime.block_on(self.delete_guild_async(guild_id))
+    }
+
+    fn add_guild_invite(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+        invited_by: i64,
+    ) -> StorageResult<()> {
+        self.runtime
+            .block_on(self.add_guild_invite_async(guild_id, account_id, invited_by))
+    }
+
+    fn take_guild_invite(&self, guild_id: i64, account_id: i64) -> StorageResult<bool> {
+        self.runtime
+            .block_on(self.take_guild_invite_async(guild_id, account_id))
+    }
+}
@@ -0,0 +1,412 @@
+use super::accounts::{
+    unix_now, AccountRecord, AccountStore, BanRecord, LeaderboardEntry, MatchParticipant,
+    PlayerStats,
+};
+use super::error::StorageResult;
+use super::guilds::{GuildMemberRecord, GuildRecord, GuildStore};
+use super::migrations::migrate;
+use crate::config::env_or;
+use crate::events::{EventListener, ServerEvent};
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_DATABASE_PATH: &str = "online_game_programming.db";
+
+#[derive(Clone)]
+pub struct Storage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Storage {
+    pub fn open(path: &str) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_from_env() -> Result<Self> {
+        Self::open(&env_or("DATABASE_PATH", DEFAULT_DATABASE_PATH))
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self> {
+        connection.pragma_update(None, "foreign_keys", &true)?;
+        migrate(&mut connection)?;
+        Ok(Storage {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().expect("Failed to lock storage.")
+    }
+
+    pub fn add_ban(&self, target: &str, reason: &str, expires_at: Option<i64>) -> Result<()> {
+        self.lock().execute(
+            "INSERT INTO bans (target, reason, created_at, expires_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(target) DO UPDATE SET reason = ?2, created_at = ?3, expires_at = ?4",
+            params![target, reason, unix_now(), expires_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_ban(&self, target: &str) -> Result<bool> {
+        let removed = self
+            .lock()
+            .execute("DELETE FROM bans WHERE target = ?1", params![target])?;
+        Ok(removed > 0)
+    }
+
+    pub fn find_ban(&self, target: &str) -> Result<Option<BanRecord>> {
+        self.lock()
+            .query_row(
+                "SELECT reason, expires_at FROM bans
+                 WHERE target = ?1 AND (expires_at IS NULL OR expires_at > ?2)",
+                params![target, unix_now()],
+                |row| {
+                    Ok(BanRecord {
+                        reason: row.get(0)?,
+                        expires_at: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    pub fn list_bans(&self) -> Result<Vec<(String, BanRecord)>> {
+        let connection = self.lock();
+        let mut statement = connection.prepare(
+            "SELECT target, reason, expires_at FROM bans
+             WHERE expires_at IS NULL OR expires_at > ?1",
+        )?;
+        let bans = statement
+            .query_map(params![unix_now()], |row| {
+                Ok((
+                    row.get(0)?,
+                    BanRecord {
+                        reason: row.get(1)?,
+                        expires_at: row.get(2)?,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(bans)
+    }
+
+    pub fn append_chat(
+        &self,
+        room: &str,
+        client_id: u32,
+        account_id: Option<i64>,
+        message: &str,
+    ) -> Result<()> {
+        self.lock().execute(
+            "INSERT INTO chat_messages (room, client_id, account_id, message, sent_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![room, client_id, account_id, message, unix_now()],
+        )?;
+        Ok(())
+    }
+}
+
+impl AccountStore for Storage {
+    fn create_account(&self, name: &str, password_hash: &str) -> StorageResult<i64> {
+        let connection = self.lock();
+        connection.execute(
+            "INSERT INTO accounts (name, password_hash, created_at) VALUES (?1, ?2, ?3)",
+            params![name, password_hash, unix_now()],
+        )?;
+        Ok(connection.last_insert_rowid())
+    }
+
+    fn find_account(&self, name: &str) -> StorageResult<Option<AccountRecord>> {
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT id, name, password_hash, created_at FROM accounts WHERE name = ?1",
+                params![name],
+                |row| {
+                    Ok(AccountRecord {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        password_hash: row.get(2)?,
+                        created_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn record_stats(&self, account_id: i64, won: bool, score: i64) -> StorageResult<()> {
+        update_stats(&self.lock(), account_id, won, score)?;
+        Ok(())
+    }
+
+    fn stats(&self, account_id: i64) -> StorageResult<PlayerStats> {
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT matches_played, wins, total_score FROM player_stats WHERE account_id = ?1",
+                params![account_id],
+                |row| {
+                    Ok(PlayerStats {
+                        account_id,
+                        matches_played: row.get(0)?,
+                        wins: row.get(1)?,
+                        total_score: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?
+            .unwrap_or(PlayerStats {
+                account_id,
+                ..PlayerStats::default()
+            }))
+    }
+
+    fn record_match(&self, room: &str, participants: &[MatchParticipant]) -> StorageResult<i64> {
+        let mut connection = self.lock();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO match_results (room, finished_at) VALUES (?1, ?2)",
+            params![room, unix_now()],
+        )?;
+        let match_id = transaction.last_insert_rowid();
+        for participant in participants {
+            transaction.execute(
+                "INSERT INTO match_participants (match_id, account_id, score, placement)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    match_id,
+                    participant.account_id,
+                    participant.score,
+                    participant.placement
+                ],
+            )?;
+            update_stats(
+                &transaction,
+                participant.account_id,
+                participant.placement == 1,
+                participant.score,
+            )?;
+        }
+        transaction.commit()?;
+        Ok(match_id)
+    }
+
+    fn leaderboard_page(&self, offset: i64, limit: i64) -> StorageResult<Vec<LeaderboardEntry>> {
+        let connection = self.lock();
+        let mut statement = connection.prepare(
+            "SELECT a.id, a.name, s.matches_played, s.wins, s.total_score
+             FROM player_stats s JOIN accounts a ON a.id = s.account_id
+             ORDER BY s.total_score DESC, s.wins DESC, a.id ASC
+             LIMIT ?1 OFFSET ?2",
+        )?;
+        let entries = statement
+            .query_map(params![limit, offset], |row| {
+                Ok(LeaderboardEntry {
+                    rank: 0,
+                    account_id: row.get(0)?,
+                    name: row.get(1)?,
+                    matches_played: row.get(2)?,
+                    wins: row.get(3)?,
+                    total_score: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| LeaderboardEntry {
+                rank: offset + index as i64 + 1,
+                ..entry
+            })
+            .collect())
+    }
+
+    fn leaderboard_rank(&self, account_id: i64) -> StorageResult<Option<i64>> {
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT COUNT(other.account_id) + 1 FROM player_stats me
+                 LEFT JOIN player_stats other
+                   ON other.total_score > me.total_score
+                   OR (other.total_score = me.total_score AND other.wins > me.wins)
+                   OR (other.total_score = me.total_score AND other.wins = me.wins
+                       AND other.account_id < me.account_id)
+                 WHERE me.account_id = ?1
+                 GROUP BY me.account_id",
+                params![account_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+}
+
+fn update_stats(connection: &Connection, account_id: i64, won: bool, score: i64) -> Result<()> {
+    connection.execute(
+        "INSERT INTO player_stats (account_id, matches_played, wins, total_score)
+         VALUES (?1, 1, ?2, ?3)
+         ON CONFLICT(account_id) DO UPDATE SET
+             matches_played = matches_played + 1,
+             wins = wins + ?2,
+             total_score = total_score + ?3",
+        params![account_id, won as i64, score],
+    )?;
+    Ok(())
+}
+
+impl GuildStore for Storage {
+    fn create_guild(&self, name: &str, founder: i64, role: &str) -> StorageResult<i64> {
+        let mut connection = self.lock();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO guilds (name, created_at) VALUES (?1, ?2)",
+            params![name, unix_now()],
+        )?;
+        let guild_id = transaction.last_insert_rowid();
+        transaction.execute(
+            "INSERT INTO guild_members (account_id, guild_id, role, joined_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![founder, guild_id, role, unix_now()],
+        )?;
+        transaction.execute(
+            "DELETE FROM guild_invites WHERE account_id = ?1",
+            params![founder],
+        )?;
+        transaction.commit()?;
+        Ok(guild_id)
+    }
+
+    fn find_guild(&self, name: &str) -> StorageResult<Option<GuildRecord>> {
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT id, name FROM guilds WHERE name = ?1 COLLATE NOCASE",
+                params![name],
+                |row| {
+                    Ok(GuildRecord {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn guild_membership(&self, account_id: i64) -> StorageResult<Option<(GuildRecord, String)>> {
+        Ok(self
+            .lock()
+            .query_row(
+                "SELECT g.id, g.name, m.role FROM guild_members m
+                 JOIN guilds g ON g.id = m.guild_id
+                 WHERE m.account_id = ?1",
+                params![account_id],
+                |row| {
+                    Ok((
+                        GuildRecord {
+                            id: row.get(0)?,
+                            name: row.get(1)?,
+                        },
+                        row.get(2)?,
+                    ))
+                },
+            )
+            .optional()?)
+    }
+
+    fn guild_members(&self, guild_id: i64) -> StorageResult<Vec<GuildMemberRecord>> {
+        let connection = self.lock();
+        let mut statement = connection.prepare(
+            "SELECT a.id, a.name, m.role FROM guild_members m
+             JOIN accounts a ON a.id = m.account_id
+             WHERE m.guild_id = ?1
+             ORDER BY m.joined_at ASC, a.id ASC",
+        )?;
+        let members = statement
+            .query_map(params![guild_id], |row| {
+                Ok(GuildMemberRecord {
+                    account_id: row.get(0)?,
+                    name: row.get(1)?,
+                    role: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(members)
+    }
+
+    fn add_guild_member(&self, guild_id: i64, account_id: i64, role: &str) -> StorageResult<()> {
+        let mut connection = self.lock();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO guild_members (account_id, guild_id, role, joined_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![account_id, guild_id, role, unix_now()],
+        )?;
+        transaction.execute(
+            "DELETE FROM guild_invites WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn set_guild_role(&self, guild_id: i64, account_id: i64, role: &str) -> StorageResult<bool> {
+        let updated = self.lock().execute(
+            "UPDATE guild_members SET role = ?3 WHERE guild_id = ?1 AND account_id = ?2",
+            params![guild_id, account_id, role],
+        )?;
+        Ok(updated > 0)
+    }
+
+    fn remove_guild_member(&self, guild_id: i64, account_id: i64) -> StorageResult<bool> {
+        let removed = self.lock().execute(
+            "DELETE FROM guild_members WHERE guild_id = ?1 AND account_id = ?2",
+            params![guild_id, account_id],
+        )?;
+        Ok(removed > 0)
+    }
+
+    fn delete_guild(&self, guild_id: i64) -> StorageResult<()> {
+        self.lock()
+            .execute("DELETE FROM guilds WHERE id = ?1", params![guild_id])?;
+        Ok(())
+    }
+
+    fn add_guild_invite(
+        &self,
+        guild_id: i64,
+        account_id: i64,
+        invited_by: i64,
+    ) -> StorageResult<()> {
+        self.lock().execute(
+            "INSERT INTO guild_invites (guild_id, account_id, invited_by, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(guild_id, account_id) DO UPDATE SET invited_by = ?3, created_at = ?4",
+            params![guild_id, account_id, invited_by, unix_now()],
+        )?;
+        Ok(())
+    }
+
+    fn take_guild_invite(&self, guild_id: i64, account_id: i64) -> StorageResult<bool> {
+        let removed = self.lock().execute(
+            "DELETE FROM guild_invites WHERE guild_id = ?1 AND account_id = ?2",
+            params![guild_id, account_id],
+        )?;
+        Ok(removed > 0)
+    }
+}
+
+impl EventListener for Storage {
+    fn on_event(&mut self, event: &ServerEvent) {
+        if let ServerEvent::Chat {
+            client_id,
+            account_id,
+            room,
+            message,
+        } = event
+        {
+            let account_id = account_id.map(|id| id.0);
+            if let Err(e) = self.append_chat(room, *client_id, account_id, message) {
+                eprintln!("チャット履歴の保存に失敗しました：{}", e);
+            }
+        }
+    }
+}
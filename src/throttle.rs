@@ -0,0 +1,128 @@
+use crate::config::{env_millis, env_or};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Default)]
+pub struct ThrottleConfig {
+    pub max_per_address: Option<usize>,
+    pub accept_rate: Option<f64>,
+    pub accept_burst: f64,
+    pub pre_auth_timeout: Option<Duration>,
+}
+
+impl ThrottleConfig {
+    pub fn from_env() -> Self {
+        let accept_rate = env_or("ACCEPT_RATE", "")
+            .parse::<f64>()
+            .ok()
+            .filter(|rate| *rate > 0.0);
+        ThrottleConfig {
+            max_per_address: env_or("MAX_CONNECTIONS_PER_IP", "")
+                .parse()
+                .ok()
+                .filter(|max| *max > 0),
+            accept_rate,
+            accept_burst: env_or("ACCEPT_BURST", "")
+                .parse::<f64>()
+                .ok()
+                .filter(|burst| *burst >= 1.0)
+                .unwrap_or_else(|| accept_rate.unwrap_or(1.0).max(1.0)),
+            pre_auth_timeout: env_millis("PRE_AUTH_TIMEOUT_MS")
+                .filter(|timeout| !timeout.is_zero()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    RateLimited,
+    TooManyConnections(usize),
+}
+
+#[derive(Debug)]
+pub struct AcceptThrottle {
+    config: ThrottleConfig,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl AcceptThrottle {
+    pub fn new(config: ThrottleConfig, now: Instant) -> Self {
+        AcceptThrottle {
+            tokens: config.accept_burst,
+            config,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(rate) = self.config.accept_rate {
+            let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(self.config.accept_burst);
+        }
+        self.refilled_at = now;
+    }
+
+    pub fn admit(
+        &mut self,
+        connections_from_address: usize,
+        now: Instant,
+    ) -> Result<(), Rejection> {
+        if let Some(max) = self.config.max_per_address {
+            if connections_from_address >= max {
+                return Err(Rejection::TooManyConnections(max));
+            }
+        }
+        if self.config.accept_rate.is_some() {
+            self.refill(now);
+            if self.tokens < 1.0 {
+                return Err(Rejection::RateLimited);
+            }
+            self.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_are_limited_per_address_and_by_a_refilling_bucket() {
+        let start = Instant::now();
+        let mut throttle = AcceptThrottle::new(
+            ThrottleConfig {
+                max_per_address: Some(2),
+                accept_rate: Some(2.0),
+                accept_burst: 2.0,
+                pre_auth_timeout: None,
+            },
+            start,
+        );
+
+        assert_eq!(
+            throttle.admit(2, start),
+            Err(Rejection::TooManyConnections(2))
+        );
+        assert_eq!(throttle.admit(0, start), Ok(()));
+        assert_eq!(throttle.admit(1, start), Ok(()));
+        assert_eq!(throttle.admit(0, start), Err(Rejection::RateLimited));
+        assert_eq!(
+            throttle.admit(0, start + Duration::from_millis(250)),
+            Err(Rejection::RateLimited)
+        );
+        assert_eq!(
+            throttle.admit(0, start + Duration::from_millis(500)),
+            Ok(())
+        );
+        assert_eq!(throttle.admit(0, start + Duration::from_secs(60)), Ok(()));
+        assert_eq!(throttle.admit(0, start + Duration::from_secs(60)), Ok(()));
+        assert_eq!(
+            throttle.admit(0, start + Duration::from_secs(60)),
+            Err(Rejection::RateLimited)
+        );
+
+        let mut unlimited = AcceptThrottle::new(ThrottleConfig::default(), start);
+        assert!((0..100).all(|count| unlimited.admit(count, start).is_ok()));
+    }
+}
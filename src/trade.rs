@@ -0,0 +1,336 @@
+use crate::config::env_or;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+pub const DEFAULT_STARTING_ITEMS: &str = "gold:100,potion:3";
+
+pub type Items = BTreeMap<String, u32>;
+
+pub fn parse_items(input: &str) -> Option<Items> {
+    let mut items = Items::new();
+    if input == "-" {
+        return Some(items);
+    }
+    for entry in input.split(',') {
+        let (item, count) = entry.split_once(':')?;
+        let count: u32 = count.parse().ok()?;
+        if item.is_empty() || !item.chars().all(char::is_alphanumeric) || count == 0 {
+            return None;
+        }
+        let total = items.entry(item.to_string()).or_insert(0);
+        *total = total.checked_add(count)?;
+    }
+    Some(items)
+}
+
+pub fn encode_items(items: &Items) -> String {
+    if items.is_empty() {
+        return "-".to_string();
+    }
+    items
+        .iter()
+        .map(|(item, count)| format!("{}:{}", item, count))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn starting_items_from_env() -> Items {
+    parse_items(&env_or("TRADE_STARTING_ITEMS", DEFAULT_STARTING_ITEMS))
+        .or_else(|| parse_items(DEFAULT_STARTING_ITEMS))
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TradeError {
+    EmptyTrade,
+    SelfTrade,
+    UnknownPlayer(usize),
+    UnknownTrade(u32),
+    NotYourTrade(u32),
+    MissingItems { player: usize, item: String },
+    TooManyItems { player: usize, item: String },
+}
+
+impl Display for TradeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TradeError::EmptyTrade => write!(f, "A trade must move at least one item."),
+            TradeError::SelfTrade => write!(f, "You cannot trade with yourself."),
+            TradeError::UnknownPlayer(player) => write!(f, "Player {} is not online.", player),
+            TradeError::UnknownTrade(id) => write!(f, "No pending trade #{}.", id),
+            TradeError::NotYourTrade(id) => write!(f, "Trade #{} was not offered to you.", id),
+            TradeError::MissingItems { player, item } => {
+                write!(f, "Player {} does not have enough {}.", player, item)
+            }
+            TradeError::TooManyItems { player, item } => {
+                write!(f, "Player {} cannot hold any more {}.", player, item)
+            }
+        }
+    }
+}
+
+pub type TradeResult<T> = Result<T, TradeError>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trade {
+    pub id: u32,
+    pub from: usize,
+    pub to: usize,
+    pub give: Items,
+    pub want: Items,
+}
+
+impl Trade {
+    pub fn counterparty(&self, player: usize) -> usize {
+        if self.from == player {
+            self.to
+        } else {
+            self.from
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TradeBook {
+    inventories: HashMap<usize, Items>,
+    pending: HashMap<u32, Trade>,
+    next_id: u32,
+}
+
+impl TradeBook {
+    pub fn new() -> Self {
+        TradeBook::default()
+    }
+
+    pub fn join(&mut self, player: usize, items: Items) {
+        self.inventories.insert(player, items);
+    }
+
+    pub fn leave(&mut self, player: usize) -> Vec<Trade> {
+        self.inventories.remove(&player);
+        let cancelled = self
+            .pending
+            .values()
+            .filter(|trade| trade.from == player || trade.to == player)
+            .map(|trade| trade.id)
+            .collect::<Vec<_>>();
+        cancelled
+            .into_iter()
+            .filter_map(|id| self.pending.remove(&id))
+            .collect()
+    }
+
+    pub fn inventory(&self, player: usize) -> Option<&Items> {
+        self.inventories.get(&player)
+    }
+
+    fn check_holds(&self, player: usize, items: &Items) -> TradeResult<()> {
+        let inventory = self
+            .inventories
+            .get(&player)
+            .ok_or(TradeError::UnknownPlayer(player))?;
+        match items
+            .iter()
+            .find(|(item, count)| inventory.get(*item).copied().unwrap_or(0) < **count)
+        {
+            Some((item, _)) => Err(TradeError::MissingItems {
+                player,
+                item: item.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn propose(
+        &mut self,
+        from: usize,
+        to: usize,
+        give: Items,
+        want: Items,
+    ) -> TradeResult<Trade> {
+        if from == to {
+            return Err(TradeError::SelfTrade);
+        }
+        if give.is_empty() && want.is_empty() {
+            return Err(TradeError::EmptyTrade);
+        }
+        if !self.inventories.contains_key(&to) {
+            return Err(TradeError::UnknownPlayer(to));
+        }
+        self.check_holds(from, &give)?;
+        self.next_id += 1;
+        let trade = Trade {
+            id: self.next_id,
+            from,
+            to,
+            give,
+            want,
+        };
+        self.pending.insert(trade.id, trade.clone());
+        Ok(trade)
+    }
+
+    pub fn accept(&mut self, player: usize, id: u32) -> TradeResult<Trade> {
+        let trade = match self.pending.get(&id) {
+            Some(trade) if trade.to == player => trade.clone(),
+            Some(_) => return Err(TradeError::NotYourTrade(id)),
+            None => return Err(TradeError::UnknownTrade(id)),
+        };
+        self.check_holds(trade.from, &trade.give)?;
+        self.check_holds(trade.to, &trade.want)?;
+        let from = self.transfer(trade.from, &trade.give, &trade.want)?;
+        let to = self.transfer(trade.to, &trade.want, &trade.give)?;
+        self.pending.remove(&id);
+        self.inventories.insert(trade.from, from);
+        self.inventories.insert(trade.to, to);
+        Ok(trade)
+    }
+
+    pub fn decline(&mut self, player: usize, id: u32) -> TradeResult<Trade> {
+        match self.pending.get(&id) {
+            Some(trade) if trade.from == player || trade.to == player => {}
+            Some(_) => return Err(TradeError::NotYourTrade(id)),
+            None => return Err(TradeError::UnknownTrade(id)),
+        }
+        Ok(self.pending.remove(&id).expect("Pending trade vanished."))
+    }
+
+    fn transfer(&self, player: usize, outgoing: &Items, incoming: &Items) -> TradeResult<Items> {
+        let mut inventory = self
+            .inventories
+            .get(&player)
+            .cloned()
+            .ok_or(TradeError::UnknownPlayer(player))?;
+        for (item, count) in outgoing.iter() {
+            let remaining = inventory
+                .get(item)
+                .copied()
+                .unwrap_or(0)
+                .checked_sub(*count)
+                .ok_or_else(|| TradeError::MissingItems {
+                    player,
+                    item: item.clone(),
+                })?;
+            if remaining == 0 {
+                inventory.remove(item);
+            } else {
+                inventory.insert(item.clone(), remaining);
+            }
+        }
+        for (item, count) in incoming.iter() {
+            let held = inventory.entry(item.clone()).or_insert(0);
+            *held = held
+                .checked_add(*count)
+                .ok_or_else(|| TradeError::TooManyItems {
+                    player,
+                    item: item.clone(),
+                })?;
+        }
+        Ok(inventory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(input: &str) -> Items {
+        parse_items(input).expect("Invalid item list.")
+    }
+
+    #[test]
+    fn accepted_trades_move_both_sides_or_nothing() {
+        assert_eq!(parse_items("gold:0"), None);
+        assert_eq!(parse_items("gold"), None);
+        assert_eq!(
+            encode_items(&items("potion:1,gold:5,gold:5")),
+            "gold:10,potion:1"
+        );
+
+        let mut book = TradeBook::new();
+        book.join(0, items("gold:100,potion:3"));
+        book.join(1, items("sword:1"));
+        assert_eq!(
+            book.propose(0, 1, items("gold:500"), items("sword:1")),
+            Err(TradeError::MissingItems {
+                player: 0,
+                item: "gold".to_string()
+            })
+        );
+        assert_eq!(
+            book.propose(0, 2, items("gold:5"), Items::new()),
+            Err(TradeError::UnknownPlayer(2))
+        );
+
+        let trade = book
+            .propose(0, 1, items("gold:60,potion:3"), items("sword:1"))
+            .expect("Failed to propose.");
+        assert_eq!(
+            book.accept(0, trade.id),
+            Err(TradeError::NotYourTrade(trade.id))
+        );
+        book.accept(1, trade.id).expect("Failed to accept.");
+        assert_eq!(book.inventory(0), Some(&items("gold:40,sword:1")));
+        assert_eq!(book.inventory(1), Some(&items("gold:60,potion:3")));
+        assert_eq!(
+            book.accept(1, trade.id),
+            Err(TradeError::UnknownTrade(trade.id))
+        );
+
+        let stale = book
+            .propose(1, 0, items("gold:60"), items("sword:1"))
+            .expect("Failed to propose.");
+        let other = book
+            .propose(1, 0, items("gold:10"), Items::new())
+            .expect("Failed to propose.");
+        book.accept(0, other.id).expect("Failed to accept.");
+        assert_eq!(
+            book.accept(0, stale.id),
+            Err(TradeError::MissingItems {
+                player: 1,
+                item: "gold".to_string()
+            })
+        );
+        assert_eq!(book.inventory(0), Some(&items("gold:50,sword:1")));
+        assert_eq!(book.inventory(1), Some(&items("gold:50,potion:3")));
+        assert_eq!(book.decline(0, stale.id), Ok(stale));
+
+        let pending = book
+            .propose(0, 1, items("sword:1"), Items::new())
+            .expect("Failed to propose.");
+        assert_eq!(pending.counterparty(1), 0);
+        assert_eq!(book.leave(1), vec![pending]);
+        assert_eq!(book.inventory(1), None);
+    }
+
+    #[test]
+    fn item_counts_never_overflow() {
+        let max = u32::MAX.to_string();
+        assert_eq!(parse_items(&format!("gold:{},gold:1", max)), None);
+
+        let mut book = TradeBook::new();
+        book.join(0, items("gold:1"));
+        book.join(1, items(&format!("gold:{},sword:1", max)));
+        let trade = book
+            .propose(0, 1, items("gold:1"), items("sword:1"))
+            .expect("Failed to propose.");
+        assert_eq!(
+            book.accept(1, trade.id),
+            Err(TradeError::TooManyItems {
+                player: 1,
+                item: "gold".to_string()
+            })
+        );
+        assert_eq!(book.inventory(0), Some(&items("gold:1")));
+        assert_eq!(
+            book.inventory(1),
+            Some(&items(&format!("gold:{},sword:1", max)))
+        );
+
+        let refund = book
+            .propose(1, 0, items("gold:1"), items("gold:1"))
+            .expect("Failed to propose.");
+        book.accept(0, refund.id).expect("Failed to accept.");
+        assert_eq!(book.inventory(0), Some(&items("gold:1")));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::bridge::default_instance_id;
+use crate::config::{env_millis, env_or};
+use std::time::Duration;
+
+pub const DEFAULT_TICKET_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferPeer {
+    pub id: String,
+    pub client_address: String,
+    pub transfer_address: String,
+}
+
+impl TransferPeer {
+    pub fn parse(input: &str) -> Option<TransferPeer> {
+        let (id, addresses) = input.trim().split_once('=')?;
+        let (client_address, transfer_address) = addresses.split_once('|')?;
+        let peer = TransferPeer {
+            id: id.trim().to_string(),
+            client_address: client_address.trim().to_string(),
+            transfer_address: transfer_address.trim().to_string(),
+        };
+        if peer.id.is_empty() || peer.client_address.is_empty() || peer.transfer_address.is_empty()
+        {
+            return None;
+        }
+        Some(peer)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TransferConfig {
+    pub server_id: String,
+    pub bind: String,
+    pub peers: Vec<TransferPeer>,
+    pub ticket_ttl: Duration,
+}
+
+impl TransferConfig {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("TRANSFER_BIND")
+            .ok()
+            .map(|bind| TransferConfig {
+                server_id: std::env::var("INSTANCE_ID").unwrap_or_else(|_| default_instance_id()),
+                bind,
+                peers: env_or("TRANSFER_PEERS", "")
+                    .split(';')
+                    .filter(|entry| !entry.trim().is_empty())
+                    .filter_map(|entry| {
+                        let peer = TransferPeer::parse(entry);
+                        if peer.is_none() {
+                            eprintln!("転送先{}を解析できませんでした\n", entry);
+                        }
+                        peer
+                    })
+                    .collect(),
+                ticket_ttl: env_millis("TRANSFER_TICKET_TTL_MS").unwrap_or(DEFAULT_TICKET_TTL),
+            })
+    }
+}
@@ -0,0 +1,6 @@
+mod config;
+mod service;
+mod ticket;
+pub use config::*;
+pub use service::*;
+pub use ticket::*;
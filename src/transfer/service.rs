@@ -0,0 +1,309 @@
+use super::{
+    handoff_instruction, PlayerState, TransferConfig, TransferMessage, TransferPeer, TransferTicket,
+};
+use crate::clients::SharedClient;
+use crate::identity::hex;
+use rand::Rng;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TOKEN_SIZE: usize = 16;
+const HANDOFF_IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Arrival {
+    ticket: TransferTicket,
+    expires_at: Instant,
+}
+
+struct Departure {
+    token: String,
+    handoff_address: String,
+    expires_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct TransferService {
+    config: Arc<TransferConfig>,
+    local_addr: SocketAddr,
+    arrivals: Arc<Mutex<HashMap<String, Arrival>>>,
+    departures: Arc<Mutex<HashMap<String, Departure>>>,
+}
+
+impl TransferService {
+    pub fn start(config: TransferConfig) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(config.bind.as_str())?;
+        let service = TransferService {
+            local_addr: listener.local_addr()?,
+            config: Arc::new(config),
+            arrivals: Arc::new(Mutex::new(HashMap::new())),
+            departures: Arc::new(Mutex::new(HashMap::new())),
+        };
+        let accept_service = service.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        eprintln!("ハンドオフ接続の受け付けに失敗しました：{}\n", e);
+                        continue;
+                    }
+                };
+                let service = accept_service.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = service.serve(stream) {
+                        eprintln!("ハンドオフ要求の処理に失敗しました：{}\n", e);
+                    }
+                });
+            }
+        });
+        Ok(service)
+    }
+
+    pub fn server_id(&self) -> &str {
+        &self.config.server_id
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn peer(&self, id: &str) -> Option<&TransferPeer> {
+        self.config.peers.iter().find(|peer| peer.id == id)
+    }
+
+    pub fn hand_off(&self, transfer_address: &str, player: PlayerState) -> std::io::Result<String> {
+        let token = hex(&rand::thread_rng().gen::<[u8; TOKEN_SIZE]>());
+        let name = player.name.clone();
+        let ticket = TransferTicket {
+            token: token.clone(),
+            origin: self.config.server_id.clone(),
+            player,
+            pending: vec![],
+        };
+        request(transfer_address, &TransferMessage::Transfer(ticket))?;
+        self.departures
+            .lock()
+            .expect("Failed to lock transfer departures.")
+            .insert(
+                name,
+                Departure {
+                    token: token.clone(),
+                    handoff_address: transfer_address.to_string(),
+                    expires_at: Instant::now() + self.config.ticket_ttl,
+                },
+            );
+        Ok(token)
+    }
+
+    pub fn transfer_client(
+        &self,
+        peer: &TransferPeer,
+        client: &SharedClient,
+    ) -> std::io::Result<String> {
+        let (player, transport) = {
+            let client_lock = client.read().expect("Failed to lock socket client.");
+            (
+                PlayerState::from_client(&client_lock),
+                client_lock.transport.clone(),
+            )
+        };
+        let transport = transport
+            .ok_or_else(|| Error::new(ErrorKind::NotConnected, "Client is not connected."))?;
+        let token = self.hand_off(&peer.transfer_address, player)?;
+        transport.send_text(&handoff_instruction(&peer.id, &peer.client_address, &token))?;
+        transport.shutdown();
+        Ok(token)
+    }
+
+    pub fn forward_mail(&self, name: &str, text: &str) -> std::io::Result<bool> {
+        let (token, handoff_address) = {
+            let mut departures = self
+                .departures
+                .lock()
+                .expect("Failed to lock transfer departures.");
+            let now = Instant::now();
+            departures.retain(|_, departure| departure.expires_at > now);
+            match departures.get(name) {
+                Some(departure) => (departure.token.clone(), departure.handoff_address.clone()),
+                None => return Ok(false),
+            }
+        };
+        let mail = TransferMessage::Mail {
+            token,
+            text: text.to_string(),
+        };
+        match request(&handoff_address, &mail) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                self.departures
+                    .lock()
+                    .expect("Failed to lock transfer departures.")
+                    .remove(name);
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn claim(&self, token: &str) -> Option<TransferTicket> {
+        let mut arrivals = self
+            .arrivals
+            .lock()
+            .expect("Failed to lock transfer arrivals.");
+        let now = Instant::now();
+        arrivals.retain(|_, arrival| arrival.expires_at > now);
+        arrivals.remove(token).map(|arrival| arrival.ticket)
+    }
+
+    fn serve(&self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(HANDOFF_IO_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        let reply = match serde_json::from_str::<TransferMessage>(&line)? {
+            TransferMessage::Transfer(ticket) => {
+                println!(
+                    "サーバー{}から{}を受け入れます\n",
+                    &ticket.origin, &ticket.player.name
+                );
+                self.arrivals
+                    .lock()
+                    .expect("Failed to lock transfer arrivals.")
+                    .insert(
+                        ticket.token.clone(),
+                        Arrival {
+                            ticket,
+                            expires_at: Instant::now() + self.config.ticket_ttl,
+                        },
+                    );
+                TransferMessage::Accepted
+            }
+            TransferMessage::Mail { token, text } => {
+                match self
+                    .arrivals
+                    .lock()
+                    .expect("Failed to lock transfer arrivals.")
+                    .get_mut(&token)
+                {
+                    Some(arrival) => {
+                        arrival.ticket.pending.push(text);
+                        TransferMessage::Accepted
+                    }
+                    None => TransferMessage::Rejected("Unknown handoff token.".to_string()),
+                }
+            }
+            _ => TransferMessage::Rejected("Unexpected transfer message.".to_string()),
+        };
+        let mut reply = serde_json::to_vec(&reply)?;
+        reply.push(b'\n');
+        writer.write_all(&reply)
+    }
+}
+
+fn request(address: &str, message: &TransferMessage) -> std::io::Result<()> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(HANDOFF_IO_TIMEOUT))?;
+    let mut payload = serde_json::to_vec(message)?;
+    payload.push(b'\n');
+    (&stream).write_all(&payload)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    match serde_json::from_str::<TransferMessage>(&line)? {
+        TransferMessage::Accepted => Ok(()),
+        TransferMessage::Rejected(reason) => Err(Error::new(ErrorKind::NotFound, reason)),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            "Unexpected transfer reply.",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::Client;
+    use crate::transfer::DEFAULT_TICKET_TTL;
+    use crate::transport::MockTransport;
+    use std::sync::RwLock;
+
+    fn start(server_id: &str, peers: Vec<TransferPeer>) -> TransferService {
+        TransferService::start(TransferConfig {
+            server_id: server_id.to_string(),
+            bind: "127.0.0.1:0".to_string(),
+            peers,
+            ticket_ttl: DEFAULT_TICKET_TTL,
+        })
+        .expect("Failed to start the transfer service.")
+    }
+
+    fn peer_of(service: &TransferService) -> TransferPeer {
+        TransferPeer {
+            id: service.server_id().to_string(),
+            client_address: format!("{}.example:7000", service.server_id()),
+            transfer_address: service.local_addr().to_string(),
+        }
+    }
+
+    #[test]
+    fn players_move_between_servers_with_their_state_and_pending_mail() {
+        let east = start("east", vec![]);
+        let east_peer = peer_of(&east);
+        let west = start("west", vec![east_peer.clone()]);
+        assert_eq!(west.peer("east"), Some(&east_peer));
+        assert_eq!(west.peer("north"), None);
+
+        let player = PlayerState {
+            name: "alice".to_string(),
+            nickname: Some("alice".to_string()),
+            account_id: Some(7),
+            room: "market".to_string(),
+            position: (150, 10),
+        };
+        let token = west
+            .hand_off(&east_peer.transfer_address, player.clone())
+            .expect("Failed to hand off.");
+        assert!(west
+            .forward_mail("alice", "[Whisper] bob：see you east")
+            .expect("Failed to forward mail."));
+        assert!(!west
+            .forward_mail("carol", "hello")
+            .expect("Failed to forward mail."));
+
+        let ticket = east.claim(&token).expect("The ticket was not transferred.");
+        assert_eq!(ticket.origin, "west");
+        assert_eq!(ticket.player, player);
+        assert_eq!(ticket.pending, vec!["[Whisper] bob：see you east"]);
+        assert!(east.claim(&token).is_none());
+        assert!(!west
+            .forward_mail("alice", "too late")
+            .expect("Failed to forward mail."));
+    }
+
+    #[test]
+    fn transferred_clients_are_told_where_to_reconnect_and_disconnected() {
+        let east = start("east", vec![]);
+        let east_peer = peer_of(&east);
+        let west = start("west", vec![east_peer.clone()]);
+        let transport = Arc::new(MockTransport::new());
+        let client: SharedClient = Arc::new(RwLock::new(Client {
+            id: 2,
+            transport: Some(transport.clone()),
+            nickname: Some("bob".to_string()),
+            ..Client::default()
+        }));
+
+        let token = west
+            .transfer_client(&east_peer, &client)
+            .expect("Failed to transfer the client.");
+        assert_eq!(
+            transport.sent_text(),
+            vec![format!("HANDOFF east east.example:7000 {}", token)]
+        );
+        assert!(transport.is_shut_down());
+        let ticket = east.claim(&token).expect("The ticket was not transferred.");
+        assert_eq!(ticket.player.nickname, Some("bob".to_string()));
+    }
+}
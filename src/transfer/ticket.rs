@@ -0,0 +1,98 @@
+use crate::clients::Client;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransferCommand {
+    Claim(String),
+}
+
+impl TransferCommand {
+    pub fn parse(input: &str) -> Option<TransferCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":handoff", Some(token), None) => Some(TransferCommand::Claim(token.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerState {
+    pub name: String,
+    pub nickname: Option<String>,
+    pub account_id: Option<i64>,
+    pub room: String,
+    pub position: (i32, i32),
+}
+
+impl PlayerState {
+    pub fn from_client(client: &Client) -> Self {
+        PlayerState {
+            name: client.display_name(),
+            nickname: client.nickname.clone(),
+            account_id: client.account_id.map(|account_id| account_id.0),
+            room: client.room.clone(),
+            position: client.position,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferTicket {
+    pub token: String,
+    pub origin: String,
+    pub player: PlayerState,
+    pub pending: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferMessage {
+    Transfer(TransferTicket),
+    Mail { token: String, text: String },
+    Accepted,
+    Rejected(String),
+}
+
+pub fn handoff_instruction(server_id: &str, client_address: &str, token: &str) -> String {
+    format!("HANDOFF {} {} {}", server_id, client_address, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::AccountId;
+
+    #[test]
+    fn claims_parse_and_player_state_is_exported_from_clients() {
+        assert_eq!(
+            TransferCommand::parse(":handoff abc123\0"),
+            Some(TransferCommand::Claim("abc123".to_string()))
+        );
+        assert_eq!(TransferCommand::parse(":handoff"), None);
+
+        let client = Client {
+            id: 3,
+            account_id: Some(AccountId(7)),
+            nickname: Some("alice".to_string()),
+            room: "market".to_string(),
+            position: (4, 5),
+            ..Client::default()
+        };
+        assert_eq!(
+            PlayerState::from_client(&client),
+            PlayerState {
+                name: "alice".to_string(),
+                nickname: Some("alice".to_string()),
+                account_id: Some(7),
+                room: "market".to_string(),
+                position: (4, 5),
+            }
+        );
+        assert_eq!(
+            handoff_instruction("east", "east.example:7000", "abc"),
+            "HANDOFF east east.example:7000 abc"
+        );
+    }
+}
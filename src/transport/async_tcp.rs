@@ -0,0 +1,28 @@
+use super::{Listener, StdTcpTransport, Transport};
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+
+pub struct TokioListener {
+    runtime: Runtime,
+    listener: tokio::net::TcpListener,
+}
+
+impl TokioListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        let runtime = Builder::new_multi_thread().enable_io().build()?;
+        let listener = runtime.block_on(tokio::net::TcpListener::bind(("0.0.0.0", port)))?;
+        Ok(TokioListener { runtime, listener })
+    }
+}
+
+impl Listener for TokioListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (stream, address) = self.runtime.block_on(self.listener.accept())?;
+        let stream = stream.into_std()?;
+        stream.set_nonblocking(false)?;
+        Ok((
+            Arc::new(StdTcpTransport::new(stream)),
+            address.ip().to_string(),
+        ))
+    }
+}
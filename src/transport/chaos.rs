@@ -0,0 +1,137 @@
+use super::Transport;
+use crate::config::{env_millis, env_probability};
+use rand::Rng;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default)]
+pub struct ChaosConfig {
+    pub drop: f64,
+    pub send_error: f64,
+    pub accept_delay: Duration,
+}
+
+impl ChaosConfig {
+    pub fn from_env() -> Option<Self> {
+        let config = ChaosConfig {
+            drop: env_probability("CHAOS_DROP"),
+            send_error: env_probability("CHAOS_SEND_ERROR"),
+            accept_delay: env_millis("CHAOS_ACCEPT_DELAY_MS").unwrap_or_default(),
+        };
+        if config.drop > 0.0 || config.send_error > 0.0 || config.accept_delay > Duration::ZERO {
+            Some(config)
+        } else {
+            None
+        }
+    }
+
+    pub fn sample_accept_delay(&self) -> Duration {
+        if self.accept_delay == Duration::ZERO {
+            return Duration::ZERO;
+        }
+        self.accept_delay
+            .mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+pub struct ChaosTransport {
+    inner: Arc<dyn Transport>,
+    config: ChaosConfig,
+}
+
+impl ChaosTransport {
+    pub fn new(inner: Arc<dyn Transport>, config: ChaosConfig) -> Self {
+        ChaosTransport { inner, config }
+    }
+}
+
+impl Transport for ChaosTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        if self.config.drop > 0.0 && rand::thread_rng().gen_bool(self.config.drop) {
+            self.inner.shutdown();
+            return Err(Error::new(
+                ErrorKind::ConnectionReset,
+                "chaos dropped the connection",
+            ));
+        }
+        self.inner.receive(buffer)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        if self.config.send_error > 0.0 && rand::thread_rng().gen_bool(self.config.send_error) {
+            return Err(Error::new(
+                ErrorKind::BrokenPipe,
+                "chaos injected a send error",
+            ));
+        }
+        self.inner.send(data)
+    }
+
+    fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.inner.shutdown_send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    fn chaos(mock: &Arc<MockTransport>, drop: f64, send_error: f64) -> ChaosTransport {
+        ChaosTransport::new(
+            mock.clone(),
+            ChaosConfig {
+                drop,
+                send_error,
+                accept_delay: Duration::ZERO,
+            },
+        )
+    }
+
+    #[test]
+    fn dropped_connections_fail_the_read_and_shut_down_the_socket() {
+        let mock = Arc::new(MockTransport::new());
+        mock.script_text("hello");
+        let transport = chaos(&mock, 1.0, 0.0);
+
+        let error = transport
+            .receive(&mut [0_u8; 16])
+            .expect_err("Chaos should drop the connection.");
+
+        assert_eq!(error.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(mock.receive(&mut [0_u8; 16]).ok(), Some(0));
+    }
+
+    #[test]
+    fn injected_send_errors_never_reach_the_wire() {
+        let mock = Arc::new(MockTransport::new());
+        let transport = chaos(&mock, 0.0, 1.0);
+
+        assert!(transport.send_text("hello").is_err());
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn zero_probabilities_pass_everything_through() {
+        let mock = Arc::new(MockTransport::new());
+        mock.script_text("hello");
+        let transport = chaos(&mock, 0.0, 0.0);
+
+        assert_eq!(transport.receive(&mut [0_u8; 16]).ok(), Some(6));
+        assert!(transport.send_text("world").is_ok());
+        assert_eq!(mock.sent_text(), vec!["world"]);
+    }
+}
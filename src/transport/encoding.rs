@@ -0,0 +1,287 @@
+use super::Transport;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+
+const RAW_BUFFER_SIZE: usize = 2048;
+#[cfg(feature = "winsock")]
+const CODE_PAGE_SHIFT_JIS: u32 = 932;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    #[cfg(feature = "winsock")]
+    ShiftJis,
+}
+
+impl TextEncoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(TextEncoding::Utf8),
+            #[cfg(feature = "winsock")]
+            "sjis" | "shift_jis" | "shift-jis" | "cp932" => Some(TextEncoding::ShiftJis),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf-8",
+            #[cfg(feature = "winsock")]
+            TextEncoding::ShiftJis => "shift_jis",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct EncodingCommand(pub String);
+
+impl EncodingCommand {
+    pub fn parse(input: &str) -> Option<EncodingCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next()) {
+            (":encoding", Some(name), None) => Some(EncodingCommand(name.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "winsock"), allow(dead_code))]
+fn complete_prefix(bytes: &[u8]) -> usize {
+    let mut index = 0;
+    while index < bytes.len() {
+        let lead = matches!(bytes[index], 0x81..=0x9f | 0xe0..=0xfc);
+        if lead && index + 1 == bytes.len() {
+            return index;
+        }
+        index += if lead { 2 } else { 1 };
+    }
+    bytes.len()
+}
+
+#[cfg(feature = "winsock")]
+fn shift_jis_to_utf8(bytes: &[u8]) -> Vec<u8> {
+    use winapi::um::stringapiset::MultiByteToWideChar;
+    if bytes.is_empty() {
+        return vec![];
+    }
+    unsafe {
+        let length = MultiByteToWideChar(
+            CODE_PAGE_SHIFT_JIS,
+            0,
+            bytes.as_ptr() as *const i8,
+            bytes.len() as i32,
+            std::ptr::null_mut(),
+            0,
+        );
+        let mut wide = vec![0_u16; length.max(0) as usize];
+        let written = MultiByteToWideChar(
+            CODE_PAGE_SHIFT_JIS,
+            0,
+            bytes.as_ptr() as *const i8,
+            bytes.len() as i32,
+            wide.as_mut_ptr(),
+            wide.len() as i32,
+        );
+        String::from_utf16_lossy(&wide[..written.max(0) as usize]).into_bytes()
+    }
+}
+
+#[cfg(feature = "winsock")]
+fn utf8_to_shift_jis(text: &str) -> Vec<u8> {
+    use winapi::um::stringapiset::WideCharToMultiByte;
+    let wide = text.encode_utf16().collect::<Vec<_>>();
+    if wide.is_empty() {
+        return vec![];
+    }
+    unsafe {
+        let length = WideCharToMultiByte(
+            CODE_PAGE_SHIFT_JIS,
+            0,
+            wide.as_ptr(),
+            wide.len() as i32,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null(),
+            std::ptr::null_mut(),
+        );
+        let mut bytes = vec![0_u8; length.max(0) as usize];
+        let written = WideCharToMultiByte(
+            CODE_PAGE_SHIFT_JIS,
+            0,
+            wide.as_ptr(),
+            wide.len() as i32,
+            bytes.as_mut_ptr() as *mut i8,
+            bytes.len() as i32,
+            std::ptr::null(),
+            std::ptr::null_mut(),
+        );
+        bytes.truncate(written.max(0) as usize);
+        bytes
+    }
+}
+
+pub struct EncodingTransport {
+    inner: Arc<dyn Transport>,
+    encoding: RwLock<TextEncoding>,
+    pending: Mutex<Vec<u8>>,
+    decoded: Mutex<VecDeque<u8>>,
+}
+
+impl EncodingTransport {
+    pub fn new(inner: Arc<dyn Transport>, encoding: TextEncoding) -> Self {
+        EncodingTransport {
+            inner,
+            encoding: RwLock::new(encoding),
+            pending: Mutex::new(vec![]),
+            decoded: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn encoding(&self) -> TextEncoding {
+        *self.encoding.read().expect("Failed to lock text encoding.")
+    }
+
+    fn drain_decoded(&self, buffer: &mut [u8]) -> usize {
+        let mut decoded = self.decoded.lock().expect("Failed to lock decoded text.");
+        let size = buffer.len().min(decoded.len());
+        for (slot, byte) in buffer.iter_mut().zip(decoded.drain(..size)) {
+            *slot = byte;
+        }
+        size
+    }
+
+    fn decode(&self, encoding: TextEncoding, raw: &[u8]) {
+        let mut pending = self.pending.lock().expect("Failed to lock pending bytes.");
+        pending.extend_from_slice(raw);
+        let text = match encoding {
+            TextEncoding::Utf8 => pending.drain(..).collect::<Vec<_>>(),
+            #[cfg(feature = "winsock")]
+            TextEncoding::ShiftJis => {
+                let complete = complete_prefix(&pending);
+                shift_jis_to_utf8(&pending.drain(..complete).collect::<Vec<_>>())
+            }
+        };
+        self.decoded
+            .lock()
+            .expect("Failed to lock decoded text.")
+            .extend(text);
+    }
+}
+
+impl Transport for EncodingTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let size = self.drain_decoded(buffer);
+            if size > 0 {
+                return Ok(size);
+            }
+            let encoding = self.encoding();
+            if encoding == TextEncoding::Utf8
+                && self
+                    .pending
+                    .lock()
+                    .expect("Failed to lock pending bytes.")
+                    .is_empty()
+            {
+                return self.inner.receive(buffer);
+            }
+            let mut raw = [0_u8; RAW_BUFFER_SIZE];
+            let size = self.inner.receive(&mut raw)?;
+            if size == 0 {
+                return Ok(0);
+            }
+            self.decode(encoding, &raw[..size]);
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        match self.encoding() {
+            TextEncoding::Utf8 => self.inner.send(data),
+            #[cfg(feature = "winsock")]
+            TextEncoding::ShiftJis => {
+                let encoded = utf8_to_shift_jis(&String::from_utf8_lossy(data));
+                self.inner.send(&encoded).map(|_| data.len())
+            }
+        }
+    }
+
+    fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn set_encoding(&self, encoding: TextEncoding) -> bool {
+        *self
+            .encoding
+            .write()
+            .expect("Failed to lock text encoding.") = encoding;
+        true
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.inner.shutdown_send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn double_byte_lead_bytes_wait_for_their_trail_byte() {
+        assert_eq!(complete_prefix(b"abc"), 3);
+        assert_eq!(complete_prefix(&[b'a', 0x82]), 1);
+        assert_eq!(complete_prefix(&[0x82, 0xa0, 0x93]), 2);
+        assert_eq!(complete_prefix(&[0x82, b'a', b'b']), 3);
+        assert_eq!(TextEncoding::from_name("UTF8"), Some(TextEncoding::Utf8));
+        assert_eq!(TextEncoding::from_name("latin-1"), None);
+        assert_eq!(
+            EncodingCommand::parse(":encoding sjis\0"),
+            Some(EncodingCommand("sjis".to_string()))
+        );
+    }
+
+    #[test]
+    fn utf8_connections_pass_bytes_through() {
+        let inner = Arc::new(MockTransport::new());
+        inner.script_read("日本語\0".as_bytes());
+        let transport = EncodingTransport::new(inner.clone(), TextEncoding::Utf8);
+
+        let mut buffer = [0_u8; 64];
+        let size = transport.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], "日本語\0".as_bytes());
+        transport.send_text("こんにちは").expect("Failed to send.");
+        assert_eq!(inner.sent_text(), vec!["こんにちは"]);
+    }
+
+    #[cfg(feature = "winsock")]
+    #[test]
+    fn shift_jis_is_transcoded_at_the_boundary() {
+        let inner = Arc::new(MockTransport::new());
+        inner
+            .script_read(&[0x82, 0xa0, 0x93])
+            .script_read(&[0xfa, 0x00]);
+        let transport = EncodingTransport::new(inner.clone(), TextEncoding::ShiftJis);
+
+        let mut buffer = [0_u8; 64];
+        let mut received = vec![];
+        while !received.ends_with(&[0]) {
+            let size = transport.receive(&mut buffer).expect("Failed to receive.");
+            received.extend_from_slice(&buffer[..size]);
+        }
+        assert_eq!(received, "あ日\0".as_bytes());
+        transport.send_text("あ").expect("Failed to send.");
+        assert_eq!(inner.writes().concat(), vec![0x82, 0xa0, 0x00]);
+    }
+}
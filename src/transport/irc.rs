@@ -0,0 +1,335 @@
+use super::{Listener, Transport};
+use crate::rooms::DEFAULT_ROOM;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+const SERVER_NAME: &str = "ogp";
+const MAX_LINE_LENGTH: u64 = 4096;
+
+#[derive(Debug)]
+struct IrcSession {
+    nick: String,
+    password: Option<String>,
+    registered: bool,
+    room: String,
+}
+
+impl IrcSession {
+    fn new() -> Self {
+        IrcSession {
+            nick: "*".to_string(),
+            password: None,
+            registered: false,
+            room: DEFAULT_ROOM.to_string(),
+        }
+    }
+
+    fn login(&self) -> Option<String> {
+        let password = self.password.as_ref()?;
+        Some(format!(":login {} {}", &self.nick, password))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Translation {
+    commands: Vec<String>,
+    replies: Vec<String>,
+    quit: bool,
+}
+
+fn parse_line(line: &str) -> Option<(String, Vec<&str>)> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let line = match line.strip_prefix(':') {
+        Some(prefixed) => prefixed.split_once(' ')?.1,
+        None => line,
+    };
+    let (line, trailing) = match line.split_once(" :") {
+        Some((line, trailing)) => (line, Some(trailing)),
+        None => (line, None),
+    };
+    let mut parts = line.split_whitespace();
+    let command = parts.next()?.to_ascii_uppercase();
+    Some((command, parts.chain(trailing).collect()))
+}
+
+fn translate_incoming(session: &mut IrcSession, line: &str) -> Translation {
+    let mut translation = Translation::default();
+    let (command, params) = match parse_line(line) {
+        Some(parsed) => parsed,
+        None => return translation,
+    };
+    let prefix = format!(":{}!{}@{}", &session.nick, &session.nick, SERVER_NAME);
+    match (command.as_str(), params.as_slice()) {
+        ("PASS", [password, ..]) => session.password = Some(password.to_string()),
+        ("NICK", [nick, ..]) => {
+            session.nick = nick.to_string();
+            if session.registered {
+                translation.commands.extend(session.login());
+            }
+        }
+        ("USER", _) if !session.registered => {
+            session.registered = true;
+            translation.commands.extend(session.login());
+            translation.replies.push(format!(
+                ":{} 001 {} :Welcome to online_game_programming, {}",
+                SERVER_NAME, &session.nick, &session.nick
+            ));
+            translation.replies.push(format!(
+                ":{} 422 {} :MOTD File is missing",
+                SERVER_NAME, &session.nick
+            ));
+        }
+        ("PING", [token, ..]) => translation
+            .replies
+            .push(format!(":{} PONG {} :{}", SERVER_NAME, SERVER_NAME, token)),
+        ("PONG", params) => {
+            if let Some(nonce) = params.last() {
+                translation.commands.push(format!(":pong {}", nonce));
+            }
+        }
+        ("JOIN", [channels, ..]) => {
+            let channel = channels.split(',').next().unwrap_or_default();
+            let room = channel.trim_start_matches('#');
+            session.room = room.to_string();
+            translation.commands.push(format!(":join {}", room));
+            translation
+                .replies
+                .push(format!("{} JOIN #{}", &prefix, room));
+        }
+        ("PART", [channels, ..]) => {
+            let channel = channels.split(',').next().unwrap_or_default();
+            session.room = DEFAULT_ROOM.to_string();
+            translation.commands.push(format!(":join {}", DEFAULT_ROOM));
+            translation
+                .replies
+                .push(format!("{} PART {}", &prefix, channel));
+        }
+        ("PRIVMSG", [target, text]) if target.starts_with('#') => {
+            translation.commands.push(text.to_string())
+        }
+        ("PRIVMSG", [target, text]) => translation.commands.push(format!(":w {} {}", target, text)),
+        ("QUIT", _) => translation.quit = true,
+        _ => {}
+    }
+    translation
+}
+
+fn translate_outgoing(session: &IrcSession, text: &str) -> String {
+    if let Some(nonce) = text.strip_prefix("PING ") {
+        return format!("PING :{}", nonce);
+    }
+    if let Some(whisper) = text.strip_prefix("[Whisper #") {
+        if let Some((_, rest)) = whisper.split_once("] ") {
+            if let Some((sender, message)) = rest.split_once('：') {
+                return format!(
+                    ":{}!{}@{} PRIVMSG {} :{}",
+                    sender, sender, SERVER_NAME, &session.nick, message
+                );
+            }
+        }
+    }
+    match text.split_once('：') {
+        Some((sender, message)) if !sender.is_empty() && !sender.contains(char::is_whitespace) => {
+            format!(
+                ":{}!{}@{} PRIVMSG #{} :{}",
+                sender, sender, SERVER_NAME, &session.room, message
+            )
+        }
+        _ => format!(":{} NOTICE {} :{}", SERVER_NAME, &session.nick, text),
+    }
+}
+
+struct Reader {
+    stream: BufReader<TcpStream>,
+    pending: Vec<u8>,
+}
+
+pub struct IrcTransport {
+    reader: Mutex<Reader>,
+    writer: Mutex<TcpStream>,
+    session: Mutex<IrcSession>,
+}
+
+impl IrcTransport {
+    pub fn new(stream: TcpStream) -> std::io::Result<Self> {
+        let writer = stream.try_clone()?;
+        Ok(IrcTransport {
+            reader: Mutex::new(Reader {
+                stream: BufReader::new(stream),
+                pending: vec![],
+            }),
+            writer: Mutex::new(writer),
+            session: Mutex::new(IrcSession::new()),
+        })
+    }
+
+    fn lock_session(&self) -> std::sync::MutexGuard<'_, IrcSession> {
+        self.session.lock().expect("Failed to lock IRC session.")
+    }
+
+    fn write_lines(&self, lines: &[String]) -> std::io::Result<()> {
+        let data = lines
+            .iter()
+            .map(|line| format!("{}\r\n", line.replace(['\r', '\n'], " ")))
+            .collect::<String>();
+        self.writer
+            .lock()
+            .expect("Failed to lock IRC writer.")
+            .write_all(data.as_bytes())
+    }
+}
+
+impl Transport for IrcTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let mut reader = self.reader.lock().expect("Failed to lock IRC reader.");
+        while reader.pending.is_empty() {
+            let mut line = vec![];
+            match (&mut reader.stream)
+                .take(MAX_LINE_LENGTH)
+                .read_until(b'\n', &mut line)
+            {
+                Ok(0) => return Ok(0),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(0),
+                Err(e) => return Err(e),
+            }
+            let line = String::from_utf8_lossy(&line);
+            let translation = translate_incoming(&mut self.lock_session(), &line);
+            self.write_lines(&translation.replies)?;
+            if translation.quit {
+                return Ok(0);
+            }
+            for command in translation.commands.iter() {
+                reader.pending.extend_from_slice(command.as_bytes());
+                reader.pending.push(0);
+            }
+        }
+        let size = reader.pending.len().min(buffer.len());
+        buffer[..size].copy_from_slice(&reader.pending[..size]);
+        reader.pending.drain(..size);
+        Ok(size)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let lines = {
+            let session = self.lock_session();
+            data.split(|b| *b == 0)
+                .filter(|message| !message.is_empty())
+                .map(|message| translate_outgoing(&session, &String::from_utf8_lossy(message)))
+                .collect::<Vec<_>>()
+        };
+        self.write_lines(&lines)?;
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .expect("Failed to lock IRC writer.")
+            .shutdown(Shutdown::Both);
+    }
+
+    fn close(&self) {
+        self.shutdown();
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.writer
+            .lock()
+            .expect("Failed to lock IRC writer.")
+            .peer_addr()
+            .ok()
+    }
+}
+
+pub struct IrcListener {
+    listener: TcpListener,
+}
+
+impl IrcListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        Ok(IrcListener {
+            listener: TcpListener::bind(("0.0.0.0", port))?,
+        })
+    }
+}
+
+impl Listener for IrcListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (stream, address) = self.listener.accept()?;
+        Ok((
+            Arc::new(IrcTransport::new(stream)?),
+            address.ip().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn irc_commands_map_onto_chat_rooms_and_whispers() {
+        let listener = IrcListener::bind(0).expect("Failed to bind.");
+        let port = listener
+            .listener
+            .local_addr()
+            .expect("Failed to read address.")
+            .port();
+        let mut client = TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect.");
+        let (server, _) = listener.accept().expect("Failed to accept.");
+        client
+            .write_all(
+                b"PASS hunter2\r\nNICK alice\r\nUSER alice 0 * :Alice\r\nJOIN #red\r\n\
+                  PRIVMSG #red :hello room\r\nPRIVMSG bob :psst\r\nPING :42\r\nPONG ogp :7\r\n",
+            )
+            .expect("Failed to write.");
+
+        let mut frames = vec![];
+        while frames.len() < 5 {
+            let mut buffer = [0_u8; 256];
+            let size = server.receive(&mut buffer).expect("Failed to receive.");
+            frames.extend(
+                buffer[..size]
+                    .split(|b| *b == 0)
+                    .filter(|frame| !frame.is_empty())
+                    .map(|frame| String::from_utf8_lossy(frame).to_string()),
+            );
+        }
+        assert_eq!(
+            frames,
+            vec![
+                ":login alice hunter2",
+                ":join red",
+                "hello room",
+                ":w bob psst",
+                ":pong 7"
+            ]
+        );
+
+        server.send_text("bob：hi alice").expect("Failed to send.");
+        server
+            .send_text("[Whisper #3] bob：secret")
+            .expect("Failed to send.");
+        server.send_text("PING 9").expect("Failed to send.");
+        server.send_text("OK ready").expect("Failed to send.");
+        server.close();
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).expect("Failed to read.");
+        assert_eq!(
+            reply.lines().collect::<Vec<_>>(),
+            vec![
+                ":ogp 001 alice :Welcome to online_game_programming, alice",
+                ":ogp 422 alice :MOTD File is missing",
+                ":alice!alice@ogp JOIN #red",
+                ":ogp PONG ogp :42",
+                ":bob!bob@ogp PRIVMSG #red :hi alice",
+                ":bob!bob@ogp PRIVMSG alice :secret",
+                "PING :9",
+                ":ogp NOTICE alice :OK ready",
+            ]
+        );
+    }
+}
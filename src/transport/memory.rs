@@ -0,0 +1,173 @@
+use super::{Listener, Transport};
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<(VecDeque<u8>, bool)>,
+    changed: Condvar,
+}
+
+impl Pipe {
+    fn write(&self, data: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.state.lock().expect("Failed to lock memory pipe.");
+        if state.1 {
+            return Err(Error::new(
+                ErrorKind::BrokenPipe,
+                "the memory pipe is closed",
+            ));
+        }
+        state.0.extend(data);
+        self.changed.notify_all();
+        Ok(data.len())
+    }
+
+    fn read(&self, buffer: &mut [u8]) -> usize {
+        let mut state = self.state.lock().expect("Failed to lock memory pipe.");
+        while state.0.is_empty() && !state.1 {
+            state = self
+                .changed
+                .wait(state)
+                .expect("Failed to lock memory pipe.");
+        }
+        let size = state.0.len().min(buffer.len());
+        for (slot, byte) in buffer.iter_mut().zip(state.0.drain(..size)) {
+            *slot = byte;
+        }
+        size
+    }
+
+    fn close(&self) {
+        self.state.lock().expect("Failed to lock memory pipe.").1 = true;
+        self.changed.notify_all();
+    }
+}
+
+pub struct MemoryTransport {
+    incoming: Arc<Pipe>,
+    outgoing: Arc<Pipe>,
+}
+
+impl MemoryTransport {
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let forward = Arc::new(Pipe::default());
+        let backward = Arc::new(Pipe::default());
+        (
+            MemoryTransport {
+                incoming: backward.clone(),
+                outgoing: forward.clone(),
+            },
+            MemoryTransport {
+                incoming: forward,
+                outgoing: backward,
+            },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.incoming.read(buffer))
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        self.outgoing.write(data)
+    }
+
+    fn shutdown(&self) {
+        self.incoming.close();
+        self.outgoing.close();
+    }
+
+    fn close(&self) {
+        self.shutdown();
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryConnector {
+    next_id: Arc<AtomicUsize>,
+    pending: Sender<(Arc<dyn Transport>, String)>,
+}
+
+impl MemoryConnector {
+    pub fn connect(&self) -> std::io::Result<MemoryTransport> {
+        let (client, server) = MemoryTransport::pair();
+        let address = format!("memory:{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        self.pending
+            .send((Arc::new(server), address))
+            .map_err(|_| Error::new(ErrorKind::ConnectionRefused, "the memory listener is gone"))?;
+        Ok(client)
+    }
+}
+
+pub struct MemoryListener {
+    pending: Mutex<Receiver<(Arc<dyn Transport>, String)>>,
+}
+
+impl MemoryListener {
+    pub fn new() -> (MemoryListener, MemoryConnector) {
+        let (pending, incoming) = channel();
+        (
+            MemoryListener {
+                pending: Mutex::new(incoming),
+            },
+            MemoryConnector {
+                next_id: Arc::new(AtomicUsize::new(0)),
+                pending,
+            },
+        )
+    }
+}
+
+impl Listener for MemoryListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        self.pending
+            .lock()
+            .expect("Failed to lock memory listener.")
+            .recv()
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "every memory connector is gone"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connected_pairs_exchange_bytes_in_both_directions() {
+        let (listener, connector) = MemoryListener::new();
+        let client = connector.connect().expect("Failed to connect.");
+        let (server, address) = listener.accept().expect("Failed to accept.");
+        let mut buffer = [0_u8; 16];
+
+        client.send_text("hello").expect("Failed to send.");
+        let size = server.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"hello\0");
+
+        server.send(b"hi").expect("Failed to send.");
+        let size = client.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"hi");
+        assert_eq!(address, "memory:0");
+    }
+
+    #[test]
+    fn closing_one_end_wakes_the_blocked_reader() {
+        let (client, server) = MemoryTransport::pair();
+        let reader = std::thread::spawn(move || server.receive(&mut [0_u8; 16]));
+
+        client.close();
+
+        assert_eq!(
+            reader
+                .join()
+                .expect("Reader panicked.")
+                .expect("Failed to receive."),
+            0
+        );
+        assert!(client.send(b"late").is_err());
+    }
+}
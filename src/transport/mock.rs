@@ -0,0 +1,119 @@
+use super::Transport;
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+#[derive(Default)]
+pub struct MockTransport {
+    reads: Mutex<VecDeque<Result<Vec<u8>, ErrorKind>>>,
+    writes: Mutex<Vec<Vec<u8>>>,
+    send_error: Mutex<Option<ErrorKind>>,
+    shut_down: AtomicBool,
+    send_shut_down: AtomicBool,
+    closed: AtomicBool,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport::default()
+    }
+
+    pub fn script_read(&self, data: &[u8]) -> &Self {
+        self.reads
+            .lock()
+            .expect("Failed to lock mock reads.")
+            .push_back(Ok(data.to_vec()));
+        self
+    }
+
+    pub fn script_text(&self, text: &str) -> &Self {
+        self.script_read(format!("{}\0", text).as_bytes())
+    }
+
+    pub fn script_error(&self, kind: ErrorKind) -> &Self {
+        self.reads
+            .lock()
+            .expect("Failed to lock mock reads.")
+            .push_back(Err(kind));
+        self
+    }
+
+    pub fn fail_sends(&self, kind: Option<ErrorKind>) {
+        *self.send_error.lock().expect("Failed to lock mock errors.") = kind;
+    }
+
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.writes
+            .lock()
+            .expect("Failed to lock mock writes.")
+            .clone()
+    }
+
+    pub fn sent_text(&self) -> Vec<String> {
+        self.writes()
+            .concat()
+            .split(|b| *b == 0)
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| String::from_utf8_lossy(frame).to_string())
+            .collect()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::SeqCst)
+    }
+
+    pub fn is_send_shut_down(&self) -> bool {
+        self.send_shut_down.load(Ordering::SeqCst)
+    }
+}
+
+impl Transport for MockTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        if self.shut_down.load(Ordering::SeqCst) {
+            return Ok(0);
+        }
+        let next = self
+            .reads
+            .lock()
+            .expect("Failed to lock mock reads.")
+            .pop_front();
+        match next {
+            Some(Ok(data)) => {
+                let size = data.len().min(buffer.len());
+                buffer[..size].copy_from_slice(&data[..size]);
+                Ok(size)
+            }
+            Some(Err(kind)) => Err(kind.into()),
+            None => Ok(0),
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        if let Some(kind) = *self.send_error.lock().expect("Failed to lock mock errors.") {
+            return Err(kind.into());
+        }
+        self.writes
+            .lock()
+            .expect("Failed to lock mock writes.")
+            .push(data.to_vec());
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.send_shut_down.store(true, Ordering::SeqCst);
+        true
+    }
+}
@@ -0,0 +1,219 @@
+#[cfg(feature = "tokio")]
+mod async_tcp;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod encoding;
+#[cfg(feature = "std-net")]
+mod irc;
+#[cfg(test)]
+mod memory;
+#[cfg(test)]
+mod mock;
+mod netem;
+#[cfg(feature = "winsock")]
+mod pipe;
+mod plain;
+mod priority;
+mod shm;
+#[cfg(feature = "winsock")]
+mod socket;
+#[cfg(feature = "std-net")]
+mod tcp;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "std-net")]
+mod udp;
+#[cfg(all(unix, feature = "std-net"))]
+mod unix;
+#[cfg(feature = "websocket")]
+mod websocket;
+#[cfg(feature = "tokio")]
+pub use async_tcp::*;
+#[cfg(feature = "chaos")]
+pub use chaos::*;
+pub use encoding::*;
+#[cfg(feature = "std-net")]
+pub use irc::*;
+#[cfg(test)]
+pub use memory::*;
+#[cfg(test)]
+pub use mock::*;
+pub use netem::*;
+#[cfg(feature = "winsock")]
+pub use pipe::*;
+pub use plain::*;
+pub use priority::*;
+pub use shm::*;
+#[cfg(feature = "winsock")]
+pub use socket::*;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "std-net")]
+pub use tcp::*;
+#[cfg(feature = "tls")]
+pub use tls::*;
+#[cfg(feature = "std-net")]
+pub use udp::*;
+#[cfg(all(unix, feature = "std-net"))]
+pub use unix::*;
+#[cfg(feature = "websocket")]
+pub use websocket::*;
+
+pub const HALF_CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub trait Transport: Send + Sync {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize>;
+    fn send(&self, data: &[u8]) -> std::io::Result<usize>;
+    fn shutdown(&self);
+    fn close(&self);
+
+    fn send_text(&self, text: &str) -> std::io::Result<usize> {
+        self.send(format!("{}\0", text).as_bytes())
+    }
+
+    fn send_prioritized(&self, data: &[u8], _priority: SendPriority) -> std::io::Result<usize> {
+        self.send(data)
+    }
+
+    fn send_text_prioritized(&self, text: &str, priority: SendPriority) -> std::io::Result<usize> {
+        self.send_prioritized(format!("{}\0", text).as_bytes(), priority)
+    }
+
+    fn set_encoding(&self, _encoding: TextEncoding) -> bool {
+        false
+    }
+
+    fn bind_session(&self, _token: &str) -> bool {
+        false
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    fn shutdown_send(&self) -> bool {
+        false
+    }
+}
+
+pub trait Listener: Send + Sync {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)>;
+}
+
+pub fn unix_path(address: &str) -> Option<&str> {
+    address
+        .strip_prefix("unix:")
+        .filter(|path| !path.is_empty())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransportKind {
+    #[default]
+    Tcp,
+    #[cfg(feature = "std-net")]
+    Udp,
+    #[cfg(feature = "std-net")]
+    Irc,
+    #[cfg(feature = "websocket")]
+    WebSocket,
+    #[cfg(feature = "tls")]
+    Tls,
+    #[cfg(feature = "tokio")]
+    Tokio,
+    #[cfg(feature = "winsock")]
+    NamedPipe,
+    #[cfg(feature = "winsock")]
+    SharedMemory,
+}
+
+impl TransportKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tcp" => Some(TransportKind::Tcp),
+            #[cfg(feature = "std-net")]
+            "udp" => Some(TransportKind::Udp),
+            #[cfg(feature = "std-net")]
+            "irc" => Some(TransportKind::Irc),
+            #[cfg(feature = "websocket")]
+            "websocket" | "ws" => Some(TransportKind::WebSocket),
+            #[cfg(feature = "tls")]
+            "tls" => Some(TransportKind::Tls),
+            #[cfg(feature = "tokio")]
+            "tokio" => Some(TransportKind::Tokio),
+            #[cfg(feature = "winsock")]
+            "pipe" | "namedpipe" => Some(TransportKind::NamedPipe),
+            #[cfg(feature = "winsock")]
+            "shm" => Some(TransportKind::SharedMemory),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransportKind::Tcp => "tcp",
+            #[cfg(feature = "std-net")]
+            TransportKind::Udp => "udp",
+            #[cfg(feature = "std-net")]
+            TransportKind::Irc => "irc",
+            #[cfg(feature = "websocket")]
+            TransportKind::WebSocket => "websocket",
+            #[cfg(feature = "tls")]
+            TransportKind::Tls => "tls",
+            #[cfg(feature = "tokio")]
+            TransportKind::Tokio => "tokio",
+            #[cfg(feature = "winsock")]
+            TransportKind::NamedPipe => "namedpipe",
+            #[cfg(feature = "winsock")]
+            TransportKind::SharedMemory => "shm",
+        }
+    }
+
+    pub fn bind(&self, port: u16) -> std::io::Result<Box<dyn Listener>> {
+        Ok(match self {
+            #[cfg(feature = "winsock")]
+            TransportKind::Tcp => Box::new(SocketListener::bind(port)?),
+            #[cfg(all(not(feature = "winsock"), feature = "std-net"))]
+            TransportKind::Tcp => Box::new(StdTcpListener::bind(port)?),
+            #[cfg(not(any(feature = "winsock", feature = "std-net")))]
+            TransportKind::Tcp => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("TCP on port {} needs the winsock or std-net feature", port),
+            ))?,
+            #[cfg(feature = "std-net")]
+            TransportKind::Udp => Box::new(UdpListener::bind(port)?),
+            #[cfg(feature = "std-net")]
+            TransportKind::Irc => Box::new(IrcListener::bind(port)?),
+            #[cfg(feature = "websocket")]
+            TransportKind::WebSocket => Box::new(WebSocketListener::bind(port)?),
+            #[cfg(feature = "tls")]
+            TransportKind::Tls => Box::new(TlsListener::bind(port)?),
+            #[cfg(feature = "tokio")]
+            TransportKind::Tokio => Box::new(TokioListener::bind(port)?),
+            #[cfg(feature = "winsock")]
+            TransportKind::NamedPipe => Box::new(NamedPipeListener::bind(port)?),
+            #[cfg(feature = "winsock")]
+            TransportKind::SharedMemory => Box::new(ShmListener::bind(port)?),
+        })
+    }
+
+    pub fn bind_address(
+        &self,
+        address: Option<&str>,
+        port: u16,
+    ) -> std::io::Result<Box<dyn Listener>> {
+        match address.and_then(unix_path) {
+            #[cfg(all(unix, feature = "std-net"))]
+            Some(path) => Ok(Box::new(UnixSocketListener::bind(path)?)),
+            #[cfg(not(all(unix, feature = "std-net")))]
+            Some(path) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!(
+                    "Unix domain socket {} needs a non-Windows target with the std-net feature",
+                    path
+                ),
+            )),
+            None => self.bind(port),
+        }
+    }
+}
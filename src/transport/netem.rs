@@ -0,0 +1,254 @@
+use super::Transport;
+use crate::config::{env_millis, env_probability};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const REORDER_DELAY: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Debug, Default)]
+pub struct NetemConfig {
+    pub latency: Duration,
+    pub jitter: Duration,
+    pub loss: f64,
+    pub duplicate: f64,
+    pub reorder: f64,
+    pub bandwidth: Option<u64>,
+    pub queue_limit: Option<usize>,
+}
+
+impl NetemConfig {
+    pub fn from_env() -> Option<Self> {
+        let config = NetemConfig {
+            latency: env_millis("NETEM_LATENCY_MS").unwrap_or_default(),
+            jitter: env_millis("NETEM_JITTER_MS").unwrap_or_default(),
+            loss: env_probability("NETEM_LOSS"),
+            duplicate: env_probability("NETEM_DUPLICATE"),
+            reorder: env_probability("NETEM_REORDER"),
+            bandwidth: std::env::var("NETEM_RATE_KBPS")
+                .ok()
+                .and_then(|rate| rate.parse::<u64>().ok())
+                .filter(|rate| *rate > 0)
+                .map(|rate| rate * 1000 / 8),
+            queue_limit: std::env::var("NETEM_QUEUE_BYTES")
+                .ok()
+                .and_then(|limit| limit.parse().ok()),
+        };
+        if config.is_enabled() {
+            Some(config)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.latency > Duration::ZERO
+            || self.jitter > Duration::ZERO
+            || self.loss > 0.0
+            || self.duplicate > 0.0
+            || self.reorder > 0.0
+            || self.bandwidth.is_some()
+    }
+
+    fn sample_delay(&self) -> Duration {
+        let mut rng = rand::thread_rng();
+        let jitter = self.jitter.as_secs_f64();
+        let offset = if jitter > 0.0 {
+            rng.gen_range(-jitter..=jitter)
+        } else {
+            0.0
+        };
+        let mut delay = (self.latency.as_secs_f64() + offset).max(0.0);
+        if self.reorder > 0.0 && rng.gen_bool(self.reorder) {
+            delay += (self.latency + self.jitter + REORDER_DELAY).as_secs_f64();
+        }
+        Duration::from_secs_f64(delay)
+    }
+}
+
+enum Action {
+    Send(Vec<u8>),
+    ShutdownSend,
+    Shutdown,
+    Close,
+}
+
+struct Scheduled {
+    due: Instant,
+    sequence: u64,
+    action: Action,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        self.due == other.due && self.sequence == other.sequence
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.due, other.sequence).cmp(&(self.due, self.sequence))
+    }
+}
+
+pub struct NetemTransport {
+    inner: Arc<dyn Transport>,
+    config: NetemConfig,
+    sequence: AtomicU64,
+    scheduler: Sender<Scheduled>,
+    link_free_at: Mutex<Instant>,
+}
+
+impl NetemTransport {
+    pub fn new(inner: Arc<dyn Transport>, config: NetemConfig) -> Self {
+        let (scheduler, scheduled) = channel();
+        let delivery = inner.clone();
+        std::thread::spawn(move || deliver(delivery, scheduled));
+        NetemTransport {
+            inner,
+            config,
+            sequence: AtomicU64::new(0),
+            scheduler,
+            link_free_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn schedule(&self, delay: Duration, action: Action) {
+        let _ = self.scheduler.send(Scheduled {
+            due: Instant::now() + delay,
+            sequence: self.sequence.fetch_add(1, AtomicOrdering::SeqCst),
+            action,
+        });
+    }
+
+    fn serialize(&self, size: usize) -> Option<Duration> {
+        let rate = match self.config.bandwidth {
+            Some(rate) => rate as f64,
+            None => return Some(Duration::ZERO),
+        };
+        let now = Instant::now();
+        let mut link_free_at = self
+            .link_free_at
+            .lock()
+            .expect("Failed to lock netem link.");
+        let start = (*link_free_at).max(now);
+        let queued_bytes = (start - now).as_secs_f64() * rate;
+        if let Some(limit) = self.config.queue_limit {
+            if queued_bytes + size as f64 > limit as f64 {
+                return None;
+            }
+        }
+        let finish = start + Duration::from_secs_f64(size as f64 / rate);
+        *link_free_at = finish;
+        Some(finish - now)
+    }
+
+    fn drain_delay(&self) -> Duration {
+        let backlog = self
+            .link_free_at
+            .lock()
+            .expect("Failed to lock netem link.")
+            .saturating_duration_since(Instant::now());
+        backlog + (self.config.latency + self.config.jitter) * 2 + REORDER_DELAY
+    }
+}
+
+fn deliver(inner: Arc<dyn Transport>, scheduled: Receiver<Scheduled>) {
+    let mut queue = BinaryHeap::new();
+    let mut disconnected = false;
+    loop {
+        let now = Instant::now();
+        while queue.peek().map(|next: &Scheduled| next.due <= now) == Some(true) {
+            match queue.pop().map(|next| next.action) {
+                Some(Action::Send(data)) => {
+                    let _ = inner.send(&data);
+                }
+                Some(Action::ShutdownSend) => {
+                    inner.shutdown_send();
+                }
+                Some(Action::Shutdown) => inner.shutdown(),
+                Some(Action::Close) => {
+                    inner.close();
+                    return;
+                }
+                None => {}
+            }
+        }
+
+        let wait = queue
+            .peek()
+            .map(|next| next.due.saturating_duration_since(Instant::now()));
+        if disconnected {
+            match wait {
+                Some(wait) => std::thread::sleep(wait),
+                None => return,
+            }
+            continue;
+        }
+        let received = match wait {
+            Some(wait) => scheduled.recv_timeout(wait),
+            None => scheduled.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(next) => queue.push(next),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => disconnected = true,
+        }
+    }
+}
+
+impl Transport for NetemTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.receive(buffer)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let mut rng = rand::thread_rng();
+        if self.config.loss > 0.0 && rng.gen_bool(self.config.loss) {
+            return Ok(data.len());
+        }
+        let copies = if self.config.duplicate > 0.0 && rng.gen_bool(self.config.duplicate) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            if let Some(queueing) = self.serialize(data.len()) {
+                self.schedule(
+                    queueing + self.config.sample_delay(),
+                    Action::Send(data.to_vec()),
+                );
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.schedule(self.drain_delay(), Action::Shutdown);
+    }
+
+    fn close(&self) {
+        self.schedule(self.drain_delay(), Action::Close);
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.schedule(self.drain_delay(), Action::ShutdownSend);
+        true
+    }
+}
@@ -0,0 +1,235 @@
+use super::{Listener, Transport};
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use winapi::shared::minwindef::{DWORD, FALSE, TRUE};
+use winapi::shared::winerror::{
+    ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_PIPE_CONNECTED, ERROR_PIPE_NOT_CONNECTED,
+};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::fileapi::{CreateFileW, ReadFile, WriteFile, OPEN_EXISTING};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::ioapiset::{CancelIoEx, GetOverlappedResult};
+use winapi::um::minwinbase::OVERLAPPED;
+use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe};
+use winapi::um::synchapi::CreateEventW;
+use winapi::um::winbase::{
+    FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE,
+    PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use winapi::um::winnt::{GENERIC_READ, GENERIC_WRITE, HANDLE};
+
+const PIPE_BUFFER_SIZE: DWORD = 64 * 1024;
+
+pub fn pipe_name(port: u16) -> String {
+    format!(r"\\.\pipe\online_game_programming-{}", port)
+}
+
+fn wide(name: &str) -> Vec<u16> {
+    OsStr::new(name).encode_wide().chain(Some(0)).collect()
+}
+
+fn last_error() -> std::io::Error {
+    std::io::Error::from_raw_os_error(unsafe { GetLastError() } as i32)
+}
+
+unsafe fn overlapped<F>(handle: HANDLE, start: F) -> std::io::Result<DWORD>
+where
+    F: FnOnce(*mut OVERLAPPED) -> i32,
+{
+    let event = CreateEventW(std::ptr::null_mut(), TRUE, FALSE, std::ptr::null());
+    if event.is_null() {
+        return Err(last_error());
+    }
+    let mut request: OVERLAPPED = std::mem::zeroed();
+    request.hEvent = event;
+    let mut transferred: DWORD = 0;
+    let result = if start(&mut request) != FALSE || GetLastError() == ERROR_IO_PENDING {
+        if GetOverlappedResult(handle, &mut request, &mut transferred, TRUE) == FALSE {
+            Err(last_error())
+        } else {
+            Ok(transferred)
+        }
+    } else {
+        Err(last_error())
+    };
+    CloseHandle(event);
+    result
+}
+
+pub struct NamedPipeTransport {
+    handle: HANDLE,
+    closed: AtomicBool,
+}
+
+unsafe impl Send for NamedPipeTransport {}
+unsafe impl Sync for NamedPipeTransport {}
+
+impl NamedPipeTransport {
+    pub fn connect(name: &str) -> std::io::Result<Self> {
+        let handle = unsafe {
+            CreateFileW(
+                wide(name).as_ptr(),
+                GENERIC_READ | GENERIC_WRITE,
+                0,
+                std::ptr::null_mut(),
+                OPEN_EXISTING,
+                FILE_FLAG_OVERLAPPED,
+                std::ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(last_error());
+        }
+        Ok(NamedPipeTransport {
+            handle,
+            closed: AtomicBool::new(false),
+        })
+    }
+}
+
+impl Transport for NamedPipeTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let result = unsafe {
+            overlapped(self.handle, |request| {
+                ReadFile(
+                    self.handle,
+                    buffer.as_mut_ptr() as *mut _,
+                    buffer.len() as DWORD,
+                    std::ptr::null_mut(),
+                    request,
+                )
+            })
+        };
+        match result {
+            Ok(size) => Ok(size as usize),
+            Err(e)
+                if e.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32)
+                    || e.raw_os_error() == Some(ERROR_PIPE_NOT_CONNECTED as i32) =>
+            {
+                Ok(0)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let mut sent = 0;
+        while sent < data.len() {
+            let remaining = &data[sent..];
+            sent += unsafe {
+                overlapped(self.handle, |request| {
+                    WriteFile(
+                        self.handle,
+                        remaining.as_ptr() as *const _,
+                        remaining.len() as DWORD,
+                        std::ptr::null_mut(),
+                        request,
+                    )
+                })
+            }? as usize;
+        }
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        unsafe {
+            CancelIoEx(self.handle, std::ptr::null_mut());
+            DisconnectNamedPipe(self.handle);
+        }
+    }
+
+    fn close(&self) {
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            self.shutdown();
+            unsafe {
+                CloseHandle(self.handle);
+            }
+        }
+    }
+}
+
+pub struct NamedPipeListener {
+    name: String,
+}
+
+impl NamedPipeListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        NamedPipeListener::bind_name(&pipe_name(port))
+    }
+
+    pub fn bind_name(name: &str) -> std::io::Result<Self> {
+        Ok(NamedPipeListener {
+            name: name.to_string(),
+        })
+    }
+}
+
+impl Listener for NamedPipeListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let handle = unsafe {
+            CreateNamedPipeW(
+                wide(&self.name).as_ptr(),
+                PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+                PIPE_UNLIMITED_INSTANCES,
+                PIPE_BUFFER_SIZE,
+                PIPE_BUFFER_SIZE,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(last_error());
+        }
+        let transport = NamedPipeTransport {
+            handle,
+            closed: AtomicBool::new(false),
+        };
+        let connected = unsafe { overlapped(handle, |request| ConnectNamedPipe(handle, request)) };
+        match connected {
+            Ok(_) => {}
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) => {}
+            Err(e) => {
+                transport.close();
+                return Err(e);
+            }
+        }
+        Ok((Arc::new(transport), "localhost".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_pipe_sessions_deliver_text_both_ways_and_close() {
+        let name = format!(r"\\.\pipe\ogp-test-{}", std::process::id());
+        let listener = NamedPipeListener::bind_name(&name).expect("Failed to bind pipe.");
+        let server = std::thread::spawn(move || {
+            let (transport, address) = listener.accept().expect("Failed to accept pipe.");
+            assert_eq!(address, "localhost");
+            let mut buffer = [0_u8; 64];
+            let size = transport.receive(&mut buffer).expect("Failed to receive.");
+            transport.send(&buffer[..size]).expect("Failed to echo.");
+            transport
+        });
+        let client = loop {
+            match NamedPipeTransport::connect(&name) {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+
+        client.send_text("こんにちは").expect("Failed to send.");
+        let mut buffer = [0_u8; 64];
+        let size = client.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], "こんにちは\0".as_bytes());
+
+        server.join().expect("Server thread panicked.").close();
+        assert_eq!(client.receive(&mut buffer).expect("Failed to read EOF."), 0);
+        client.close();
+    }
+}
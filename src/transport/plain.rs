@@ -0,0 +1,176 @@
+use super::{SendPriority, Transport};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+const RAW_BUFFER_SIZE: usize = 2048;
+
+const IAC: u8 = 255;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const WILL: u8 = 251;
+const DONT: u8 = 254;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum TelnetState {
+    #[default]
+    Data,
+    Command,
+    Option,
+    Subnegotiation,
+    SubnegotiationCommand,
+}
+
+#[derive(Debug, Default)]
+struct LineDecoder {
+    state: TelnetState,
+    decoded: VecDeque<u8>,
+}
+
+impl LineDecoder {
+    fn push(&mut self, data: &[u8]) {
+        for byte in data.iter().copied() {
+            self.state = match (self.state, byte) {
+                (TelnetState::Data, IAC) => TelnetState::Command,
+                (TelnetState::Data, b'\n') => {
+                    self.decoded.push_back(0);
+                    TelnetState::Data
+                }
+                (TelnetState::Data, b'\r' | 0) => TelnetState::Data,
+                (TelnetState::Data, byte) => {
+                    self.decoded.push_back(byte);
+                    TelnetState::Data
+                }
+                (TelnetState::Command, SB) => TelnetState::Subnegotiation,
+                (TelnetState::Command, WILL..=DONT) => TelnetState::Option,
+                (TelnetState::Command | TelnetState::Option, _) => TelnetState::Data,
+                (TelnetState::Subnegotiation, IAC) => TelnetState::SubnegotiationCommand,
+                (TelnetState::Subnegotiation, _) => TelnetState::Subnegotiation,
+                (TelnetState::SubnegotiationCommand, SE) => TelnetState::Data,
+                (TelnetState::SubnegotiationCommand, _) => TelnetState::Subnegotiation,
+            };
+        }
+    }
+
+    fn drain(&mut self, buffer: &mut [u8]) -> usize {
+        let size = buffer.len().min(self.decoded.len());
+        for (slot, byte) in buffer.iter_mut().zip(self.decoded.drain(..size)) {
+            *slot = byte;
+        }
+        size
+    }
+}
+
+fn to_lines(data: &[u8]) -> Vec<u8> {
+    let mut lines = Vec::with_capacity(data.len() + 2);
+    for byte in data.iter().copied() {
+        match byte {
+            0 => lines.extend_from_slice(b"\r\n"),
+            b'\n' => lines.extend_from_slice(b"\r\n"),
+            b'\r' => {}
+            byte => lines.push(byte),
+        }
+    }
+    lines
+}
+
+pub struct PlainTextTransport {
+    inner: Arc<dyn Transport>,
+    decoder: Mutex<LineDecoder>,
+}
+
+impl PlainTextTransport {
+    pub fn new(inner: Arc<dyn Transport>) -> Self {
+        PlainTextTransport {
+            inner,
+            decoder: Mutex::new(LineDecoder::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LineDecoder> {
+        self.decoder.lock().expect("Failed to lock line decoder.")
+    }
+}
+
+impl Transport for PlainTextTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let size = self.lock().drain(buffer);
+            if size > 0 {
+                return Ok(size);
+            }
+            let mut raw = [0_u8; RAW_BUFFER_SIZE];
+            let size = self.inner.receive(&mut raw)?;
+            if size == 0 {
+                return Ok(0);
+            }
+            self.lock().push(&raw[..size]);
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        self.inner.send(&to_lines(data)).map(|_| data.len())
+    }
+
+    fn send_prioritized(&self, data: &[u8], priority: SendPriority) -> std::io::Result<usize> {
+        self.inner
+            .send_prioritized(&to_lines(data), priority)
+            .map(|_| data.len())
+    }
+
+    fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.inner.shutdown_send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn telnet_lines_become_frames_and_frames_become_lines() {
+        let inner = Arc::new(MockTransport::new());
+        inner
+            .script_read(&[IAC, WILL, 31, IAC, SB, 31, 0, 80, 0, 24, IAC, SE])
+            .script_read(b":join red\r\nhel")
+            .script_read("lo 世界\r\0\n".as_bytes());
+        let transport = PlainTextTransport::new(inner.clone());
+
+        let mut received = vec![];
+        let mut buffer = [0_u8; 64];
+        loop {
+            let size = transport.receive(&mut buffer).expect("Failed to receive.");
+            if size == 0 {
+                break;
+            }
+            received.extend_from_slice(&buffer[..size]);
+        }
+        assert_eq!(received, ":join red\0hello 世界\0".as_bytes());
+
+        transport.send_text("OK red").expect("Failed to send.");
+        transport
+            .send(b"alice: hi\0bob: yo\0")
+            .expect("Failed to send.");
+        assert_eq!(
+            inner.writes(),
+            vec![b"OK red\r\n".to_vec(), b"alice: hi\r\nbob: yo\r\n".to_vec()]
+        );
+    }
+}
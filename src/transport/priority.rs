@@ -0,0 +1,209 @@
+use super::{TextEncoding, Transport};
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+const TIER_COUNT: usize = 4;
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SendPriority {
+    Control,
+    GameState,
+    Chat,
+    Bulk,
+}
+
+#[derive(Default)]
+struct Queues {
+    tiers: [VecDeque<Vec<u8>>; TIER_COUNT],
+    closed: bool,
+    finished: bool,
+}
+
+impl Queues {
+    fn push(&mut self, data: Vec<u8>, priority: SendPriority) {
+        self.tiers[priority as usize].push_back(data);
+    }
+
+    fn pop(&mut self) -> Option<Vec<u8>> {
+        self.tiers.iter_mut().find_map(VecDeque::pop_front)
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    queues: Mutex<Queues>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queues> {
+        self.queues.lock().expect("Failed to lock send queues.")
+    }
+
+    fn finish(&self) {
+        let mut queues = self.lock();
+        queues.closed = true;
+        queues.finished = true;
+        queues.tiers.iter_mut().for_each(VecDeque::clear);
+        self.changed.notify_all();
+    }
+}
+
+fn write_loop(inner: Arc<dyn Transport>, shared: Arc<Shared>) {
+    loop {
+        let data = {
+            let mut queues = shared.lock();
+            loop {
+                if let Some(data) = queues.pop() {
+                    break data;
+                }
+                if queues.closed {
+                    drop(queues);
+                    shared.finish();
+                    return;
+                }
+                queues = shared
+                    .changed
+                    .wait(queues)
+                    .expect("Failed to lock send queues.");
+            }
+        };
+        if inner.send(&data).is_err() {
+            shared.finish();
+            return;
+        }
+    }
+}
+
+pub struct PriorityTransport {
+    inner: Arc<dyn Transport>,
+    shared: Arc<Shared>,
+}
+
+impl PriorityTransport {
+    pub fn new(inner: Arc<dyn Transport>) -> Self {
+        let shared = Arc::new(Shared::default());
+        let writer_inner = inner.clone();
+        let writer_shared = shared.clone();
+        std::thread::spawn(move || write_loop(writer_inner, writer_shared));
+        PriorityTransport { inner, shared }
+    }
+
+    pub fn queued(&self, priority: SendPriority) -> usize {
+        self.shared.lock().tiers[priority as usize].len()
+    }
+
+    fn flush(&self) {
+        let mut queues = self.shared.lock();
+        queues.closed = true;
+        self.shared.changed.notify_all();
+        let _ = self
+            .shared
+            .changed
+            .wait_timeout_while(queues, FLUSH_TIMEOUT, |queues| !queues.finished)
+            .expect("Failed to lock send queues.");
+    }
+}
+
+impl Transport for PriorityTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.receive(buffer)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        self.send_prioritized(data, SendPriority::Chat)
+    }
+
+    fn send_prioritized(&self, data: &[u8], priority: SendPriority) -> std::io::Result<usize> {
+        let mut queues = self.shared.lock();
+        if queues.closed {
+            return Err(Error::new(
+                ErrorKind::BrokenPipe,
+                "the send queue is closed",
+            ));
+        }
+        queues.push(data.to_vec(), priority);
+        self.shared.changed.notify_all();
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.flush();
+        self.inner.shutdown();
+    }
+
+    fn close(&self) {
+        self.flush();
+        self.inner.close();
+    }
+
+    fn set_encoding(&self, encoding: TextEncoding) -> bool {
+        self.inner.set_encoding(encoding)
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        self.inner.bind_session(token)
+    }
+
+    fn shutdown_send(&self) -> bool {
+        self.flush();
+        self.inner.shutdown_send()
+    }
+}
+
+impl Drop for PriorityTransport {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn higher_tiers_are_drained_first() {
+        let mut queues = Queues::default();
+        queues.push(b"chunk 1".to_vec(), SendPriority::Bulk);
+        queues.push(b"chunk 2".to_vec(), SendPriority::Bulk);
+        queues.push(b"hello".to_vec(), SendPriority::Chat);
+        queues.push(b"SNAPSHOT".to_vec(), SendPriority::GameState);
+        queues.push(b"PING".to_vec(), SendPriority::Control);
+
+        let order = std::iter::from_fn(|| queues.pop()).collect::<Vec<_>>();
+
+        assert_eq!(
+            order,
+            vec![
+                b"PING".to_vec(),
+                b"SNAPSHOT".to_vec(),
+                b"hello".to_vec(),
+                b"chunk 1".to_vec(),
+                b"chunk 2".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn queued_data_is_flushed_before_shutdown() {
+        let inner = Arc::new(MockTransport::new());
+        let transport = PriorityTransport::new(inner.clone());
+
+        transport
+            .send_text_prioritized("chunk", SendPriority::Bulk)
+            .expect("Failed to send.");
+        transport.send_text("bye").expect("Failed to send.");
+        transport.shutdown();
+
+        let mut sent = inner.sent_text();
+        sent.sort();
+        assert_eq!(sent, vec!["bye", "chunk"]);
+        assert_eq!(transport.queued(SendPriority::Bulk), 0);
+        assert!(transport.send_text("late").is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use super::{Listener, Transport};
+use std::alloc::Layout;
+use std::cell::UnsafeCell;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(feature = "winsock")]
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+#[cfg(feature = "winsock")]
+use winapi::um::memoryapi::{
+    MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+};
+#[cfg(feature = "winsock")]
+use winapi::um::winbase::CreateFileMappingW;
+#[cfg(feature = "winsock")]
+use winapi::um::winnt::{HANDLE, PAGE_READWRITE};
+
+pub const SHM_SLOTS: usize = 8;
+
+const RING_SIZE: usize = 64 * 1024;
+const SPIN_LIMIT: u32 = 64;
+const IDLE_WAIT: Duration = Duration::from_micros(100);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SLOT_FREE: u32 = 0;
+const SLOT_REQUESTED: u32 = 1;
+const SLOT_ACCEPTED: u32 = 2;
+const SLOT_RESETTING: u32 = 3;
+
+#[repr(C)]
+struct Ring {
+    head: AtomicU32,
+    tail: AtomicU32,
+    data: UnsafeCell<[u8; RING_SIZE]>,
+}
+
+impl Ring {
+    fn push(&self, data: &[u8]) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        let free = RING_SIZE - head.wrapping_sub(tail) as usize;
+        let size = data.len().min(free);
+        let buffer = self.data.get() as *mut u8;
+        for (offset, byte) in data[..size].iter().enumerate() {
+            let index = (head as usize + offset) % RING_SIZE;
+            unsafe { buffer.add(index).write(*byte) };
+        }
+        self.head
+            .store(head.wrapping_add(size as u32), Ordering::Release);
+        size
+    }
+
+    fn pop(&self, buffer: &mut [u8]) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        let size = (head.wrapping_sub(tail) as usize).min(buffer.len());
+        let data = self.data.get() as *const u8;
+        for (offset, byte) in buffer[..size].iter_mut().enumerate() {
+            let index = (tail as usize + offset) % RING_SIZE;
+            *byte = unsafe { data.add(index).read() };
+        }
+        self.tail
+            .store(tail.wrapping_add(size as u32), Ordering::Release);
+        size
+    }
+
+    fn reset(&self) {
+        self.head.store(0, Ordering::Release);
+        self.tail.store(0, Ordering::Release);
+    }
+}
+
+#[repr(C)]
+struct Slot {
+    state: AtomicU32,
+    closed: [AtomicU32; 2],
+    rings: [Ring; 2],
+}
+
+#[repr(C)]
+struct Region {
+    slots: [Slot; SHM_SLOTS],
+}
+
+enum Owner {
+    Heap,
+    #[cfg(feature = "winsock")]
+    Mapping(HANDLE),
+}
+
+pub struct SharedMemory {
+    region: NonNull<Region>,
+    owner: Owner,
+}
+
+unsafe impl Send for SharedMemory {}
+unsafe impl Sync for SharedMemory {}
+
+#[cfg(feature = "winsock")]
+fn wide(name: &str) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+    std::ffi::OsStr::new(name)
+        .encode_wide()
+        .chain(Some(0))
+        .collect()
+}
+
+impl SharedMemory {
+    pub fn in_process() -> Arc<Self> {
+        let region = unsafe { std::alloc::alloc_zeroed(Layout::new::<Region>()) } as *mut Region;
+        Arc::new(SharedMemory {
+            region: NonNull::new(region)
+                .unwrap_or_else(|| std::alloc::handle_alloc_error(Layout::new::<Region>())),
+            owner: Owner::Heap,
+        })
+    }
+
+    #[cfg(feature = "winsock")]
+    pub fn create(name: &str) -> std::io::Result<Arc<Self>> {
+        let size = std::mem::size_of::<Region>() as u64;
+        let mapping = unsafe {
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                std::ptr::null_mut(),
+                PAGE_READWRITE,
+                (size >> 32) as u32,
+                size as u32,
+                wide(name).as_ptr(),
+            )
+        };
+        SharedMemory::map(mapping)
+    }
+
+    #[cfg(feature = "winsock")]
+    pub fn open(name: &str) -> std::io::Result<Arc<Self>> {
+        let mapping = unsafe { OpenFileMappingW(FILE_MAP_ALL_ACCESS, 0, wide(name).as_ptr()) };
+        SharedMemory::map(mapping)
+    }
+
+    #[cfg(feature = "winsock")]
+    fn map(mapping: HANDLE) -> std::io::Result<Arc<Self>> {
+        if mapping.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        let view = unsafe {
+            MapViewOfFile(
+                mapping,
+                FILE_MAP_ALL_ACCESS,
+                0,
+                0,
+                std::mem::size_of::<Region>(),
+            )
+        };
+        match NonNull::new(view as *mut Region) {
+            Some(region) => Ok(Arc::new(SharedMemory {
+                region,
+                owner: Owner::Mapping(mapping),
+            })),
+            None => {
+                let error = std::io::Error::last_os_error();
+                unsafe { CloseHandle(mapping) };
+                Err(error)
+            }
+        }
+    }
+
+    fn slot(&self, index: usize) -> &Slot {
+        unsafe { &self.region.as_ref().slots[index] }
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        match self.owner {
+            Owner::Heap => unsafe {
+                std::alloc::dealloc(self.region.as_ptr() as *mut u8, Layout::new::<Region>())
+            },
+            #[cfg(feature = "winsock")]
+            Owner::Mapping(mapping) => unsafe {
+                UnmapViewOfFile(self.region.as_ptr() as *const _);
+                CloseHandle(mapping);
+            },
+        }
+    }
+}
+
+#[cfg(feature = "winsock")]
+pub fn shm_name(port: u16) -> String {
+    format!(r"Local\online_game_programming-shm-{}", port)
+}
+
+fn wait(spins: &mut u32) {
+    if *spins < SPIN_LIMIT {
+        *spins += 1;
+        std::thread::yield_now();
+    } else {
+        std::thread::sleep(IDLE_WAIT);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Side {
+    Server = 0,
+    Client = 1,
+}
+
+impl Side {
+    fn peer(&self) -> Side {
+        match self {
+            Side::Server => Side::Client,
+            Side::Client => Side::Server,
+        }
+    }
+}
+
+pub struct ShmTransport {
+    memory: Arc<SharedMemory>,
+    slot: usize,
+    side: Side,
+}
+
+impl ShmTransport {
+    pub fn connect(memory: Arc<SharedMemory>) -> std::io::Result<Self> {
+        let slot = (0..SHM_SLOTS)
+            .find(|index| {
+                memory
+                    .slot(*index)
+                    .state
+                    .compare_exchange(
+                        SLOT_FREE,
+                        SLOT_REQUESTED,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+            })
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::ConnectionRefused,
+                    "all shared memory slots are in use",
+                )
+            })?;
+        let started = Instant::now();
+        let mut spins = 0;
+        while memory.slot(slot).state.load(Ordering::Acquire) != SLOT_ACCEPTED {
+            if started.elapsed() > CONNECT_TIMEOUT
+                && memory
+                    .slot(slot)
+                    .state
+                    .compare_exchange(
+                        SLOT_REQUESTED,
+                        SLOT_FREE,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "the shared memory listener did not accept the connection",
+                ));
+            }
+            wait(&mut spins);
+        }
+        Ok(ShmTransport {
+            memory,
+            slot,
+            side: Side::Client,
+        })
+    }
+
+    #[cfg(feature = "winsock")]
+    pub fn connect_port(port: u16) -> std::io::Result<Self> {
+        ShmTransport::connect(SharedMemory::open(&shm_name(port))?)
+    }
+
+    fn slot(&self) -> &Slot {
+        self.memory.slot(self.slot)
+    }
+
+    fn is_closed(&self, side: Side) -> bool {
+        self.slot().closed[side as usize].load(Ordering::Acquire) != 0
+    }
+}
+
+impl Transport for ShmTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let inbound = &self.slot().rings[self.side as usize];
+        let mut spins = 0;
+        loop {
+            let size = inbound.pop(buffer);
+            if size > 0 || buffer.is_empty() {
+                return Ok(size);
+            }
+            if self.is_closed(self.side) || self.is_closed(self.side.peer()) {
+                return Ok(inbound.pop(buffer));
+            }
+            wait(&mut spins);
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let outbound = &self.slot().rings[self.side.peer() as usize];
+        let mut sent = 0;
+        let mut spins = 0;
+        while sent < data.len() {
+            if self.is_closed(self.side) || self.is_closed(self.side.peer()) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "the shared memory connection is closed",
+                ));
+            }
+            match outbound.push(&data[sent..]) {
+                0 => wait(&mut spins),
+                size => {
+                    sent += size;
+                    spins = 0;
+                }
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.slot().closed[self.side as usize].store(1, Ordering::Release);
+    }
+
+    fn close(&self) {
+        self.shutdown();
+        let slot = self.slot();
+        if self.is_closed(self.side.peer())
+            && slot
+                .state
+                .compare_exchange(
+                    SLOT_ACCEPTED,
+                    SLOT_RESETTING,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        {
+            for ring in slot.rings.iter() {
+                ring.reset();
+            }
+            for closed in slot.closed.iter() {
+                closed.store(0, Ordering::Release);
+            }
+            slot.state.store(SLOT_FREE, Ordering::Release);
+        }
+    }
+}
+
+pub struct ShmListener {
+    memory: Arc<SharedMemory>,
+}
+
+impl ShmListener {
+    pub fn new(memory: Arc<SharedMemory>) -> Self {
+        ShmListener { memory }
+    }
+
+    #[cfg(feature = "winsock")]
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        Ok(ShmListener::new(SharedMemory::create(&shm_name(port))?))
+    }
+
+    pub fn memory(&self) -> Arc<SharedMemory> {
+        self.memory.clone()
+    }
+}
+
+impl Listener for ShmListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let mut spins = 0;
+        loop {
+            for slot in 0..SHM_SLOTS {
+                if self
+                    .memory
+                    .slot(slot)
+                    .state
+                    .compare_exchange(
+                        SLOT_REQUESTED,
+                        SLOT_ACCEPTED,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+                {
+                    let transport = ShmTransport {
+                        memory: self.memory.clone(),
+                        slot,
+                        side: Side::Server,
+                    };
+                    return Ok((Arc::new(transport), "localhost".to_string()));
+                }
+            }
+            wait(&mut spins);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_memory_sessions_deliver_text_both_ways_and_free_their_slot() {
+        let listener = ShmListener::new(SharedMemory::in_process());
+        let memory = listener.memory();
+        let client =
+            std::thread::spawn(move || ShmTransport::connect(memory).expect("Failed to connect."));
+        let (server, address) = listener.accept().expect("Failed to accept.");
+        let client = client.join().expect("Client thread panicked.");
+        assert_eq!(address, "localhost");
+
+        let large = vec![7_u8; RING_SIZE * 2 + 3];
+        let reader = {
+            let expected = large.len();
+            let server = server.clone();
+            std::thread::spawn(move || {
+                let mut received = 0;
+                let mut buffer = [0_u8; 4096];
+                while received < expected {
+                    received += server.receive(&mut buffer).expect("Failed to receive.");
+                }
+                received
+            })
+        };
+        client.send(&large).expect("Failed to send.");
+        assert_eq!(reader.join().expect("Reader panicked."), large.len());
+
+        server.send_text("こんにちは").expect("Failed to send.");
+        let mut buffer = [0_u8; 64];
+        let size = client.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], "こんにちは\0".as_bytes());
+
+        server.close();
+        assert_eq!(client.receive(&mut buffer).expect("Failed to read EOF."), 0);
+        assert!(client.send(b"late").is_err());
+        client.close();
+        assert_eq!(
+            listener.memory().slot(0).state.load(Ordering::Acquire),
+            SLOT_FREE
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use super::{Listener, Transport, HALF_CLOSE_TIMEOUT};
+use crate::bindings::Windows::Win32::Networking::WinSock::{
+    closesocket, getpeername, getsockname, listen, recv, send, shutdown, WSAGetLastError, SD_BOTH,
+    SD_SEND, SEND_FLAGS, SOCKADDR, SOCKADDR_IN, SOCKET, SOCKET_ERROR, SOMAXCONN,
+};
+use crate::bindings::Windows::Win32::System::SystemServices::PSTR;
+use crate::server::{accept_client, client_ip, create_and_bind_socket, empty_address, socket_addr};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ws2def::{SOL_SOCKET, SO_RCVTIMEO};
+use winapi::um::winsock2::setsockopt;
+
+pub struct SocketTransport {
+    socket: SOCKET,
+}
+
+impl SocketTransport {
+    pub fn new(socket: SOCKET) -> Self {
+        SocketTransport { socket }
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        query_address(|address, size| unsafe { getsockname(self.socket, address, size) })
+    }
+
+    pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        query_address(|address, size| unsafe { getpeername(self.socket, address, size) })
+    }
+}
+
+fn last_error() -> std::io::Error {
+    std::io::Error::from_raw_os_error(unsafe { WSAGetLastError().0 })
+}
+
+fn query_address<F>(query: F) -> std::io::Result<SocketAddr>
+where
+    F: FnOnce(*mut SOCKADDR, *mut i32) -> i32,
+{
+    let mut address = empty_address();
+    let mut size = std::mem::size_of::<SOCKADDR_IN>() as i32;
+    if query(&mut address as *mut _ as *mut SOCKADDR, &mut size) == SOCKET_ERROR {
+        Err(last_error())
+    } else {
+        Ok(socket_addr(&address))
+    }
+}
+
+impl Transport for SocketTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let result = unsafe {
+            recv(
+                self.socket,
+                PSTR(buffer.as_mut_ptr()),
+                buffer.len() as i32,
+                0,
+            )
+        };
+        if result == SOCKET_ERROR {
+            Err(last_error())
+        } else {
+            Ok(result as usize)
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let result = unsafe {
+            send(
+                self.socket,
+                PSTR(data.as_ptr() as *mut u8),
+                data.len() as i32,
+                SEND_FLAGS(0),
+            )
+        };
+        if result == SOCKET_ERROR {
+            Err(last_error())
+        } else {
+            Ok(result as usize)
+        }
+    }
+
+    fn shutdown(&self) {
+        unsafe {
+            shutdown(self.socket, SD_BOTH as i32);
+        }
+    }
+
+    fn close(&self) {
+        unsafe {
+            closesocket(self.socket);
+        }
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        SocketTransport::peer_addr(self).ok()
+    }
+
+    fn shutdown_send(&self) -> bool {
+        let timeout = HALF_CLOSE_TIMEOUT.as_millis() as DWORD;
+        unsafe {
+            setsockopt(
+                self.socket.0,
+                SOL_SOCKET,
+                SO_RCVTIMEO,
+                &timeout as *const DWORD as *const _,
+                std::mem::size_of::<DWORD>() as i32,
+            );
+            shutdown(self.socket, SD_SEND as i32) != SOCKET_ERROR
+        }
+    }
+}
+
+pub struct SocketListener {
+    socket: SOCKET,
+}
+
+impl SocketListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        let socket = unsafe { create_and_bind_socket(port) }.ok_or_else(last_error)?;
+        let result = unsafe { listen(socket, SOMAXCONN as i32) };
+        if result == SOCKET_ERROR {
+            let error = last_error();
+            unsafe {
+                closesocket(socket);
+            }
+            return Err(error);
+        }
+        Ok(SocketListener { socket })
+    }
+}
+
+impl Listener for SocketListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (socket, address) = unsafe { accept_client(&self.socket) }.ok_or_else(last_error)?;
+        Ok((Arc::new(SocketTransport::new(socket)), client_ip(&address)))
+    }
+}
+
+impl Drop for SocketListener {
+    fn drop(&mut self) {
+        unsafe {
+            closesocket(self.socket);
+        }
+    }
+}
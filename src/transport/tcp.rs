@@ -0,0 +1,123 @@
+use super::{Listener, Transport, HALF_CLOSE_TIMEOUT};
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+
+pub struct StdTcpTransport {
+    stream: TcpStream,
+}
+
+impl StdTcpTransport {
+    pub fn new(stream: TcpStream) -> Self {
+        StdTcpTransport { stream }
+    }
+}
+
+impl Transport for StdTcpTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        (&self.stream).read(buffer)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        (&self.stream).write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn close(&self) {
+        self.shutdown();
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.peer_addr().ok()
+    }
+
+    fn shutdown_send(&self) -> bool {
+        let _ = self.stream.set_read_timeout(Some(HALF_CLOSE_TIMEOUT));
+        self.stream.shutdown(Shutdown::Write).is_ok()
+    }
+}
+
+pub struct StdTcpListener {
+    listener: TcpListener,
+}
+
+impl StdTcpListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        Ok(StdTcpListener {
+            listener: TcpListener::bind(("0.0.0.0", port))?,
+        })
+    }
+}
+
+impl Listener for StdTcpListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (stream, address) = self.listener.accept()?;
+        Ok((
+            Arc::new(StdTcpTransport::new(stream)),
+            address.ip().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn std_tcp_sessions_deliver_text_both_ways_and_close() {
+        let port = TcpListener::bind(("127.0.0.1", 0))
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to find a free port.")
+            .port();
+        let listener = StdTcpListener::bind(port).expect("Failed to bind.");
+        let client = StdTcpTransport::new(
+            TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect."),
+        );
+        let (server, address) = listener.accept().expect("Failed to accept.");
+        assert_eq!(address, "127.0.0.1");
+        assert_eq!(server.peer_addr(), client.stream.local_addr().ok());
+
+        client.send_text("ping").expect("Failed to send.");
+        let mut buffer = [0_u8; 64];
+        let size = server.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"ping\0");
+
+        server.send_text("pong").expect("Failed to send.");
+        let size = client.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"pong\0");
+
+        server.close();
+        assert_eq!(client.receive(&mut buffer).expect("Failed to receive."), 0);
+    }
+
+    #[test]
+    fn half_closed_sessions_deliver_pending_text_then_eof() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("Failed to bind.");
+        let client = StdTcpTransport::new(
+            TcpStream::connect(listener.local_addr().expect("Failed to read the address."))
+                .expect("Failed to connect."),
+        );
+        let server = StdTcpTransport::new(listener.accept().expect("Failed to accept.").0);
+
+        server.send_text("Bye!").expect("Failed to send.");
+        assert!(server.shutdown_send());
+        let mut received = vec![];
+        let mut buffer = [0_u8; 64];
+        loop {
+            match client.receive(&mut buffer).expect("Failed to receive.") {
+                0 => break,
+                size => received.extend_from_slice(&buffer[..size]),
+            }
+        }
+        assert_eq!(received, b"Bye!\0");
+
+        client.send_text("late").expect("Failed to send.");
+        client.close();
+        let size = server.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"late\0");
+    }
+}
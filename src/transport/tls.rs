@@ -0,0 +1,99 @@
+use super::{Listener, Transport};
+use crate::config::env_or;
+use native_tls::{Identity, TlsAcceptor, TlsStream};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn tls_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::other(format!("TLS error: {}", e))
+}
+
+pub struct TlsTransport {
+    stream: Mutex<TlsStream<TcpStream>>,
+    socket: TcpStream,
+    closed: AtomicBool,
+}
+
+impl TlsTransport {
+    fn new(stream: TlsStream<TcpStream>) -> std::io::Result<Self> {
+        let socket = stream.get_ref().try_clone()?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        Ok(TlsTransport {
+            stream: Mutex::new(stream),
+            socket,
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TlsStream<TcpStream>> {
+        self.stream.lock().expect("Failed to lock TLS stream.")
+    }
+}
+
+impl Transport for TlsTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        while !self.closed.load(Ordering::SeqCst) {
+            match self.lock().read(buffer) {
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    std::thread::yield_now()
+                }
+                result => return result,
+            }
+        }
+        Ok(0)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        let mut stream = self.lock();
+        stream.write_all(data)?;
+        stream.flush()?;
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let _ = self.socket.shutdown(Shutdown::Both);
+    }
+
+    fn close(&self) {
+        if let Ok(mut stream) = self.stream.try_lock() {
+            let _ = stream.shutdown();
+        }
+        self.shutdown();
+    }
+}
+
+pub struct TlsListener {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+}
+
+impl TlsListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        let identity = std::fs::read(env_or("TLS_IDENTITY", "identity.p12"))?;
+        let identity =
+            Identity::from_pkcs12(&identity, &env_or("TLS_PASSWORD", "")).map_err(tls_error)?;
+        Ok(TlsListener {
+            listener: TcpListener::bind(("0.0.0.0", port))?,
+            acceptor: TlsAcceptor::new(identity).map_err(tls_error)?,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (stream, address) = self.listener.accept()?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let stream = self.acceptor.accept(stream).map_err(tls_error)?;
+        Ok((
+            Arc::new(TlsTransport::new(stream)?),
+            address.ip().to_string(),
+        ))
+    }
+}
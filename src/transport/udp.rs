@@ -0,0 +1,572 @@
+use super::{Listener, Transport};
+use crate::config::env_or;
+use crate::rudp::{
+    append_checksum, record_corrupted_packet, verify_checksum, Packet, ReliableChannel, SessionKey,
+};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+const MAX_PAYLOAD: usize = 1200;
+const MAX_DATAGRAM: usize = 65536;
+const TICK: Duration = Duration::from_millis(20);
+const CLOSE_LINGER: Duration = Duration::from_secs(2);
+const MAX_PENDING_PEERS: usize = 256;
+
+type Peers = Arc<Mutex<HashMap<SocketAddr, Arc<UdpPeer>>>>;
+
+fn checksum_from_env() -> bool {
+    env_or("UDP_CHECKSUM", "false") == "true"
+}
+
+struct UdpPeer {
+    socket: Mutex<Arc<UdpSocket>>,
+    address: Mutex<SocketAddr>,
+    checksum: bool,
+    session: Mutex<Option<SessionKey>>,
+    pending_migration: Mutex<Option<Packet>>,
+    channel: Mutex<ReliableChannel>,
+    inbox: Mutex<VecDeque<u8>>,
+    arrived: Condvar,
+    closed: AtomicBool,
+    closed_at: Mutex<Option<Instant>>,
+}
+
+impl UdpPeer {
+    fn new(socket: Arc<UdpSocket>, address: SocketAddr, checksum: bool) -> Self {
+        UdpPeer {
+            socket: Mutex::new(socket),
+            address: Mutex::new(address),
+            checksum,
+            session: Mutex::new(None),
+            pending_migration: Mutex::new(None),
+            channel: Mutex::new(ReliableChannel::default()),
+            inbox: Mutex::new(VecDeque::new()),
+            arrived: Condvar::new(),
+            closed: AtomicBool::new(false),
+            closed_at: Mutex::new(None),
+        }
+    }
+
+    fn transmit(&self, packet: &Packet) {
+        let datagram = if self.checksum {
+            append_checksum(packet.encode())
+        } else {
+            packet.encode()
+        };
+        let _ = self.socket().send_to(&datagram, self.address());
+    }
+
+    fn socket(&self) -> Arc<UdpSocket> {
+        self.socket
+            .lock()
+            .expect("Failed to lock UDP peer.")
+            .clone()
+    }
+
+    fn address(&self) -> SocketAddr {
+        *self.address.lock().expect("Failed to lock UDP peer.")
+    }
+
+    fn channel(&self) -> MutexGuard<'_, ReliableChannel> {
+        self.channel.lock().expect("Failed to lock UDP channel.")
+    }
+
+    fn is_authenticated(&self) -> bool {
+        self.session
+            .lock()
+            .expect("Failed to lock UDP session.")
+            .is_some()
+    }
+
+    fn accept_migration(&self, packet: &Packet, address: SocketAddr) -> bool {
+        let accepted = self
+            .session
+            .lock()
+            .expect("Failed to lock UDP session.")
+            .as_mut()
+            .is_some_and(|session| session.accept_migration(packet));
+        if accepted {
+            *self.address.lock().expect("Failed to lock UDP peer.") = address;
+        }
+        accepted
+    }
+
+    fn handle(&self, packet: Packet) {
+        self.pending_migration
+            .lock()
+            .expect("Failed to lock UDP session.")
+            .take();
+        if packet == Packet::Close {
+            self.mark_closed();
+            return;
+        }
+        let (delivered, ack) = self.channel().receive(packet);
+        if let Some(ack) = ack {
+            self.transmit(&ack);
+        }
+        if !delivered.is_empty() {
+            let mut inbox = self.inbox.lock().expect("Failed to lock UDP inbox.");
+            for payload in delivered {
+                inbox.extend(payload);
+            }
+            self.arrived.notify_all();
+        }
+    }
+
+    fn mark_closed(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.closed_at
+            .lock()
+            .expect("Failed to lock UDP peer.")
+            .get_or_insert_with(Instant::now);
+        let _guard = self.inbox.lock().expect("Failed to lock UDP inbox.");
+        self.arrived.notify_all();
+    }
+
+    fn tick(&self, now: Instant) -> bool {
+        let (retransmissions, in_flight) = {
+            let mut channel = self.channel();
+            (channel.retransmissions(now), channel.in_flight())
+        };
+        let migration = self
+            .pending_migration
+            .lock()
+            .expect("Failed to lock UDP session.")
+            .clone();
+        for packet in migration.iter().chain(retransmissions.iter()) {
+            self.transmit(packet);
+        }
+        let closed_at = *self.closed_at.lock().expect("Failed to lock UDP peer.");
+        match closed_at {
+            Some(closed_at) if in_flight == 0 || now - closed_at >= CLOSE_LINGER => {
+                self.transmit(&Packet::Close);
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+fn open_datagram(datagram: &[u8], checksum: bool) -> Option<Packet> {
+    if !checksum {
+        return Packet::decode(datagram);
+    }
+    match verify_checksum(datagram) {
+        Some(packet) => Packet::decode(packet),
+        None => {
+            record_corrupted_packet();
+            None
+        }
+    }
+}
+
+fn migrate(
+    peers: &mut HashMap<SocketAddr, Arc<UdpPeer>>,
+    address: SocketAddr,
+    packet: &Packet,
+) -> Option<Arc<UdpPeer>> {
+    let previous = peers
+        .iter()
+        .find(|(_, peer)| peer.accept_migration(packet, address))
+        .map(|(previous, _)| *previous)?;
+    let peer = peers.remove(&previous)?;
+    peers.insert(address, peer.clone());
+    println!("UDPセッションを{}から{}へ移行しました\n", previous, address);
+    Some(peer)
+}
+
+fn pump<F>(
+    socket: Arc<UdpSocket>,
+    peers: Peers,
+    accepted: Option<(Sender<Arc<UdpPeer>>, usize)>,
+    checksum: bool,
+    running: F,
+) where
+    F: Fn(usize) -> bool,
+{
+    let mut buffer = vec![0_u8; MAX_DATAGRAM];
+    let mut next_tick = Instant::now() + TICK;
+    loop {
+        if let Ok((size, address)) = socket.recv_from(&mut buffer) {
+            if let Some(packet) = open_datagram(&buffer[..size], checksum) {
+                let peer = {
+                    let mut peers = peers.lock().expect("Failed to lock UDP peers.");
+                    match (peers.get(&address), accepted.as_ref()) {
+                        (Some(peer), _) => Some(peer.clone()),
+                        (None, _) if matches!(packet, Packet::Migrate { .. }) => {
+                            migrate(&mut peers, address, &packet)
+                        }
+                        (None, Some((accepted, max_pending)))
+                            if matches!(packet, Packet::Data { sequence: 0, .. })
+                                && peers
+                                    .values()
+                                    .filter(|peer| !peer.is_authenticated())
+                                    .count()
+                                    < *max_pending =>
+                        {
+                            let peer = Arc::new(UdpPeer::new(socket.clone(), address, checksum));
+                            peers.insert(address, peer.clone());
+                            let _ = accepted.send(peer.clone());
+                            Some(peer)
+                        }
+                        _ => None,
+                    }
+                };
+                if let Some(peer) = peer {
+                    peer.handle(packet);
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if now < next_tick {
+            continue;
+        }
+        next_tick = now + TICK;
+        let mut peers = peers.lock().expect("Failed to lock UDP peers.");
+        peers.retain(|_, peer| peer.tick(now));
+        if !running(peers.len()) {
+            return;
+        }
+    }
+}
+
+pub struct UdpTransport {
+    peer: Arc<UdpPeer>,
+}
+
+impl UdpTransport {
+    fn spawn_client_pump(peer: Arc<UdpPeer>) -> std::io::Result<()> {
+        let socket = Arc::new(UdpSocket::bind(("0.0.0.0", 0))?);
+        socket.set_read_timeout(Some(TICK))?;
+        *peer.socket.lock().expect("Failed to lock UDP peer.") = socket.clone();
+        let peers = Arc::new(Mutex::new(HashMap::new()));
+        peers
+            .lock()
+            .expect("Failed to lock UDP peers.")
+            .insert(peer.address(), peer.clone());
+        let checksum = peer.checksum;
+        let own_socket = socket.clone();
+        std::thread::spawn(move || {
+            pump(socket, peers, None, checksum, |peers| {
+                peers > 0 && Arc::ptr_eq(&peer.socket(), &own_socket)
+            })
+        });
+        Ok(())
+    }
+
+    pub fn connect(address: SocketAddr, checksum: bool) -> std::io::Result<Self> {
+        let socket = Arc::new(UdpSocket::bind(("0.0.0.0", 0))?);
+        let peer = Arc::new(UdpPeer::new(socket, address, checksum));
+        UdpTransport::spawn_client_pump(peer.clone())?;
+        Ok(UdpTransport { peer })
+    }
+
+    pub fn migrate(&self) -> std::io::Result<()> {
+        let migration = self
+            .peer
+            .session
+            .lock()
+            .expect("Failed to lock UDP session.")
+            .as_mut()
+            .map(SessionKey::migration)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotConnected,
+                    "the UDP session has no session key",
+                )
+            })?;
+        UdpTransport::spawn_client_pump(self.peer.clone())?;
+        self.peer.transmit(&migration);
+        *self
+            .peer
+            .pending_migration
+            .lock()
+            .expect("Failed to lock UDP session.") = Some(migration);
+        Ok(())
+    }
+
+    pub fn send_unreliable(&self, data: &[u8]) -> std::io::Result<usize> {
+        if data.len() > MAX_PAYLOAD {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "unreliable payloads must fit in one datagram",
+            ));
+        }
+        if self.peer.closed.load(Ordering::SeqCst) {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "the UDP session is closed",
+            ));
+        }
+        self.peer.transmit(&Packet::Unreliable {
+            payload: data.to_vec(),
+        });
+        Ok(data.len())
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer.address()
+    }
+}
+
+impl Transport for UdpTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let mut inbox = self.peer.inbox.lock().expect("Failed to lock UDP inbox.");
+        while inbox.is_empty() && !self.peer.closed.load(Ordering::SeqCst) {
+            inbox = self
+                .peer
+                .arrived
+                .wait(inbox)
+                .expect("Failed to lock UDP inbox.");
+        }
+        let size = inbox.len().min(buffer.len());
+        for (slot, byte) in buffer.iter_mut().zip(inbox.drain(..size)) {
+            *slot = byte;
+        }
+        Ok(size)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        if self.peer.closed.load(Ordering::SeqCst) {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "the UDP session is closed",
+            ));
+        }
+        for chunk in data.chunks(MAX_PAYLOAD) {
+            let packet = self.peer.channel().send(chunk.to_vec(), Instant::now());
+            self.peer.transmit(&packet);
+        }
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        self.peer.mark_closed();
+    }
+
+    fn close(&self) {
+        self.peer.mark_closed();
+    }
+
+    fn bind_session(&self, token: &str) -> bool {
+        *self
+            .peer
+            .session
+            .lock()
+            .expect("Failed to lock UDP session.") = Some(SessionKey::new(token));
+        true
+    }
+}
+
+pub struct UdpListener {
+    accepted: Mutex<Receiver<Arc<UdpPeer>>>,
+}
+
+impl UdpListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        UdpListener::bind_with_checksum(port, checksum_from_env())
+    }
+
+    pub fn bind_with_checksum(port: u16, checksum: bool) -> std::io::Result<Self> {
+        UdpListener::bind_with_limit(port, checksum, MAX_PENDING_PEERS)
+    }
+
+    fn bind_with_limit(port: u16, checksum: bool, max_pending: usize) -> std::io::Result<Self> {
+        let socket = Arc::new(UdpSocket::bind(("0.0.0.0", port))?);
+        socket.set_read_timeout(Some(TICK))?;
+        let (accepted, incoming) = channel();
+        let peers = Arc::new(Mutex::new(HashMap::new()));
+        std::thread::spawn(move || {
+            pump(
+                socket,
+                peers,
+                Some((accepted, max_pending)),
+                checksum,
+                |_| true,
+            )
+        });
+        Ok(UdpListener {
+            accepted: Mutex::new(incoming),
+        })
+    }
+}
+
+pub struct UdpEndpoint {
+    socket: Arc<UdpSocket>,
+    peers: Peers,
+    checksum: bool,
+    _alive: Arc<()>,
+}
+
+impl UdpEndpoint {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> std::io::Result<Self> {
+        let socket = Arc::new(UdpSocket::bind(address)?);
+        socket.set_read_timeout(Some(TICK))?;
+        let peers = Arc::new(Mutex::new(HashMap::new()));
+        let alive = Arc::new(());
+        let (pump_socket, pump_peers) = (socket.clone(), peers.clone());
+        let liveness = Arc::downgrade(&alive);
+        let checksum = checksum_from_env();
+        std::thread::spawn(move || {
+            pump(pump_socket, pump_peers, None, checksum, |_| {
+                liveness.strong_count() > 0
+            })
+        });
+        Ok(UdpEndpoint {
+            socket,
+            peers,
+            checksum,
+            _alive: alive,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn connect(&self, address: SocketAddr) -> UdpTransport {
+        let peer = self
+            .peers
+            .lock()
+            .expect("Failed to lock UDP peers.")
+            .entry(address)
+            .or_insert_with(|| Arc::new(UdpPeer::new(self.socket.clone(), address, self.checksum)))
+            .clone();
+        UdpTransport { peer }
+    }
+}
+
+impl Listener for UdpListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let peer = self
+            .accepted
+            .lock()
+            .expect("Failed to lock UDP listener.")
+            .recv()
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "the UDP listener stopped"))?;
+        let address = peer.address().ip().to_string();
+        Ok((Arc::new(UdpTransport { peer }), address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive_text(transport: &dyn Transport, expected: &str) {
+        let mut received = vec![];
+        let mut buffer = [0_u8; 64];
+        while received.len() < expected.len() + 1 {
+            let size = transport.receive(&mut buffer).expect("Failed to receive.");
+            assert!(size > 0, "The UDP session closed early.");
+            received.extend_from_slice(&buffer[..size]);
+        }
+        assert_eq!(received, format!("{}\0", expected).as_bytes());
+    }
+
+    fn free_port() -> u16 {
+        UdpSocket::bind(("127.0.0.1", 0))
+            .and_then(|socket| socket.local_addr())
+            .expect("Failed to find a free port.")
+            .port()
+    }
+
+    #[test]
+    fn udp_sessions_deliver_text_both_ways_and_close() {
+        let port = free_port();
+        let listener =
+            UdpListener::bind_with_checksum(port, false).expect("Failed to bind UDP listener.");
+        let client = UdpTransport::connect(SocketAddr::from(([127, 0, 0, 1], port)), false)
+            .expect("Failed to connect.");
+
+        client.send_text("ping").expect("Failed to send.");
+        let (server, address) = listener.accept().expect("Failed to accept.");
+        receive_text(server.as_ref(), "ping");
+        server.send_text("pong").expect("Failed to send.");
+        receive_text(&client, "pong");
+
+        server.close();
+        assert_eq!(client.receive(&mut [0_u8; 16]).ok(), Some(0));
+        assert_eq!(address, "127.0.0.1");
+    }
+
+    #[test]
+    fn authenticated_sessions_survive_an_address_change() {
+        let port = free_port();
+        let listener =
+            UdpListener::bind_with_checksum(port, false).expect("Failed to bind UDP listener.");
+        let client = UdpTransport::connect(SocketAddr::from(([127, 0, 0, 1], port)), false)
+            .expect("Failed to connect.");
+        let token = "ab".repeat(32);
+
+        client.send_text("hello").expect("Failed to send.");
+        let (server, _) = listener.accept().expect("Failed to accept.");
+        receive_text(server.as_ref(), "hello");
+        assert!(client.migrate().is_err());
+        assert!(server.bind_session(&token));
+        assert!(client.bind_session(&token));
+
+        client.migrate().expect("Failed to migrate.");
+        client.send_text("moved").expect("Failed to send.");
+        receive_text(server.as_ref(), "moved");
+        server.send_text("welcome back").expect("Failed to send.");
+        receive_text(&client, "welcome back");
+    }
+
+    #[test]
+    fn unauthenticated_peers_wait_for_a_free_slot() {
+        let port = free_port();
+        let listener = Arc::new(
+            UdpListener::bind_with_limit(port, false, 1).expect("Failed to bind UDP listener."),
+        );
+        let (accepted, incoming) = channel();
+        {
+            let listener = listener.clone();
+            std::thread::spawn(move || {
+                while let Ok((server, _)) = listener.accept() {
+                    if accepted.send(server).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let address = SocketAddr::from(([127, 0, 0, 1], port));
+        let first = UdpTransport::connect(address, false).expect("Failed to connect.");
+        let second = UdpTransport::connect(address, false).expect("Failed to connect.");
+
+        first.send_text("first").expect("Failed to send.");
+        let server = incoming
+            .recv_timeout(Duration::from_secs(5))
+            .expect("The first peer was not accepted.");
+        receive_text(server.as_ref(), "first");
+        second.send_text("second").expect("Failed to send.");
+        assert!(incoming.recv_timeout(TICK * 10).is_err());
+
+        assert!(server.bind_session(&"ab".repeat(32)));
+        let server = incoming
+            .recv_timeout(Duration::from_secs(5))
+            .expect("The second peer was not accepted.");
+        receive_text(server.as_ref(), "second");
+    }
+
+    #[test]
+    fn checksummed_sessions_exchange_sealed_packets() {
+        let port = free_port();
+        let listener =
+            UdpListener::bind_with_checksum(port, true).expect("Failed to bind UDP listener.");
+        let client = UdpTransport::connect(SocketAddr::from(([127, 0, 0, 1], port)), true)
+            .expect("Failed to connect.");
+
+        client.send_text("sealed").expect("Failed to send.");
+        let (server, _) = listener.accept().expect("Failed to accept.");
+        receive_text(server.as_ref(), "sealed");
+        server.send_text("ok").expect("Failed to send.");
+        receive_text(&client, "ok");
+    }
+}
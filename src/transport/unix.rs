@@ -0,0 +1,116 @@
+use super::{Listener, Transport, HALF_CLOSE_TIMEOUT};
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub struct UnixSocketTransport {
+    stream: UnixStream,
+}
+
+impl UnixSocketTransport {
+    pub fn new(stream: UnixStream) -> Self {
+        UnixSocketTransport { stream }
+    }
+
+    pub fn connect<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(UnixSocketTransport::new(UnixStream::connect(path)?))
+    }
+}
+
+impl Transport for UnixSocketTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        (&self.stream).read(buffer)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        (&self.stream).write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn close(&self) {
+        self.shutdown();
+    }
+
+    fn shutdown_send(&self) -> bool {
+        let _ = self.stream.set_read_timeout(Some(HALF_CLOSE_TIMEOUT));
+        self.stream.shutdown(Shutdown::Write).is_ok()
+    }
+}
+
+pub struct UnixSocketListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl UnixSocketListener {
+    pub fn bind<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let stale = std::fs::symlink_metadata(&path)
+            .map(|metadata| metadata.file_type().is_socket())
+            .unwrap_or(false);
+        if stale {
+            std::fs::remove_file(&path)?;
+        }
+        Ok(UnixSocketListener {
+            listener: UnixListener::bind(&path)?,
+            path,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Listener for UnixSocketListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (stream, _) = self.listener.accept()?;
+        Ok((
+            Arc::new(UnixSocketTransport::new(stream)),
+            "localhost".to_string(),
+        ))
+    }
+}
+
+impl Drop for UnixSocketListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_socket_sessions_deliver_text_both_ways_and_remove_their_path() {
+        let path = std::env::temp_dir().join(format!("ogp-unix-{}.sock", std::process::id()));
+        let stale = UnixListener::bind(&path).expect("Failed to bind a stale socket.");
+        drop(stale);
+        let listener = UnixSocketListener::bind(&path).expect("Failed to rebind.");
+        let client = UnixSocketTransport::connect(&path).expect("Failed to connect.");
+        let (server, address) = listener.accept().expect("Failed to accept.");
+        assert_eq!(address, "localhost");
+
+        client.send_text("ping").expect("Failed to send.");
+        let mut buffer = [0_u8; 64];
+        let size = server.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"ping\0");
+
+        server.send_text("pong").expect("Failed to send.");
+        let size = client.receive(&mut buffer).expect("Failed to receive.");
+        assert_eq!(&buffer[..size], b"pong\0");
+
+        server.close();
+        assert_eq!(client.receive(&mut buffer).expect("Failed to receive."), 0);
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
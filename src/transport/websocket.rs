@@ -0,0 +1,273 @@
+use super::{Listener, Transport};
+use sha1::{Digest, Sha1};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_HANDSHAKE_SIZE: usize = 8 * 1024;
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(value >> (18 - index * 6)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(HANDSHAKE_GUID.as_bytes());
+    base64(&hasher.finalize())
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn handshake(stream: &mut TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut request = vec![];
+    let mut byte = [0_u8; 1];
+    while !request.ends_with(b"\r\n\r\n") {
+        if request.len() >= MAX_HANDSHAKE_SIZE {
+            return Err(invalid("the WebSocket handshake is too large"));
+        }
+        stream.read_exact(&mut byte)?;
+        request.push(byte[0]);
+    }
+    stream.set_read_timeout(None)?;
+
+    let request = String::from_utf8_lossy(&request);
+    let key = request
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("Sec-WebSocket-Key"))
+        .map(|(_, value)| value.trim().to_string())
+        .ok_or_else(|| invalid("the WebSocket handshake has no Sec-WebSocket-Key"))?;
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    );
+    stream.write_all(response.as_bytes())
+}
+
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        size if size < 126 => frame.push(size as u8),
+        size if size <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(size as u16).to_be_bytes());
+        }
+        size => {
+            frame.push(127);
+            frame.extend_from_slice(&(size as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+struct Reader {
+    stream: TcpStream,
+    message: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+impl Reader {
+    fn read_frame(&mut self) -> std::io::Result<(bool, u8, Vec<u8>)> {
+        let mut header = [0_u8; 2];
+        self.stream.read_exact(&mut header)?;
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0f;
+        let masked = header[1] & 0x80 != 0;
+        let size = match header[1] & 0x7f {
+            126 => {
+                let mut size = [0_u8; 2];
+                self.stream.read_exact(&mut size)?;
+                u16::from_be_bytes(size) as usize
+            }
+            127 => {
+                let mut size = [0_u8; 8];
+                self.stream.read_exact(&mut size)?;
+                u64::from_be_bytes(size) as usize
+            }
+            size => size as usize,
+        };
+        if size > MAX_MESSAGE_SIZE || self.message.len() + size > MAX_MESSAGE_SIZE {
+            return Err(invalid("the WebSocket message is too large"));
+        }
+        let mut mask = [0_u8; 4];
+        if masked {
+            self.stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0_u8; size];
+        self.stream.read_exact(&mut payload)?;
+        if masked {
+            for (index, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[index % 4];
+            }
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+
+pub struct WebSocketTransport {
+    reader: Mutex<Reader>,
+    writer: Mutex<TcpStream>,
+}
+
+impl WebSocketTransport {
+    fn new(stream: TcpStream) -> std::io::Result<Self> {
+        let writer = stream.try_clone()?;
+        Ok(WebSocketTransport {
+            reader: Mutex::new(Reader {
+                stream,
+                message: vec![],
+                pending: vec![],
+            }),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    fn write(&self, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .expect("Failed to lock WebSocket writer.");
+        write_frame(&mut writer, opcode, payload)
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn receive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let mut reader = self
+            .reader
+            .lock()
+            .expect("Failed to lock WebSocket reader.");
+        while reader.pending.is_empty() {
+            let (fin, opcode, payload) = match reader.read_frame() {
+                Ok(frame) => frame,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(0),
+                Err(e) => return Err(e),
+            };
+            match opcode {
+                OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                    reader.message.extend_from_slice(&payload);
+                    if fin {
+                        let mut message = std::mem::take(&mut reader.message);
+                        if message.last() != Some(&0) {
+                            message.push(0);
+                        }
+                        reader.pending = message;
+                    }
+                }
+                OPCODE_PING => self.write(OPCODE_PONG, &payload)?,
+                OPCODE_PONG => {}
+                OPCODE_CLOSE => {
+                    let _ = self.write(OPCODE_CLOSE, &payload);
+                    return Ok(0);
+                }
+                _ => return Err(invalid("unknown WebSocket opcode")),
+            }
+        }
+        let size = reader.pending.len().min(buffer.len());
+        buffer[..size].copy_from_slice(&reader.pending[..size]);
+        reader.pending.drain(..size);
+        Ok(size)
+    }
+
+    fn send(&self, data: &[u8]) -> std::io::Result<usize> {
+        for message in data.split_inclusive(|b| *b == 0) {
+            let message = message.strip_suffix(&[0]).unwrap_or(message);
+            let opcode = if std::str::from_utf8(message).is_ok() {
+                OPCODE_TEXT
+            } else {
+                OPCODE_BINARY
+            };
+            self.write(opcode, message)?;
+        }
+        Ok(data.len())
+    }
+
+    fn shutdown(&self) {
+        let _ = self.write(OPCODE_CLOSE, &[]);
+        let _ = self
+            .writer
+            .lock()
+            .expect("Failed to lock WebSocket writer.")
+            .shutdown(Shutdown::Both);
+    }
+
+    fn close(&self) {
+        self.shutdown();
+    }
+}
+
+pub struct WebSocketListener {
+    listener: TcpListener,
+}
+
+impl WebSocketListener {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        Ok(WebSocketListener {
+            listener: TcpListener::bind(("0.0.0.0", port))?,
+        })
+    }
+}
+
+impl Listener for WebSocketListener {
+    fn accept(&self) -> std::io::Result<(Arc<dyn Transport>, String)> {
+        let (mut stream, address) = self.listener.accept()?;
+        handshake(&mut stream)?;
+        Ok((
+            Arc::new(WebSocketTransport::new(stream)?),
+            address.ip().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_the_rfc_6455_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+}
@@ -0,0 +1,33 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ZoneCommand {
+    Move(i32, i32),
+}
+
+impl ZoneCommand {
+    pub fn parse(input: &str) -> Option<ZoneCommand> {
+        let mut parts = input
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            .split_whitespace();
+        match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+            (":move", Some(x), Some(y), None) => {
+                Some(ZoneCommand::Move(x.parse().ok()?, y.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_commands_parse_moves() {
+        assert_eq!(
+            ZoneCommand::parse(":move 120 -4"),
+            Some(ZoneCommand::Move(120, -4))
+        );
+        assert_eq!(ZoneCommand::parse(":move 120"), None);
+        assert_eq!(ZoneCommand::parse(":move east 4"), None);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::config::{env_millis, env_or};
+use std::time::Duration;
+
+pub const DEFAULT_HANDOFF_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZoneBounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl ZoneBounds {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZoneRoute {
+    pub id: String,
+    pub bounds: ZoneBounds,
+    pub client_address: String,
+    pub handoff_address: String,
+}
+
+impl ZoneRoute {
+    pub fn parse(input: &str) -> Option<ZoneRoute> {
+        let (id, rest) = input.trim().split_once('=')?;
+        let (bounds, addresses) = rest.split_once('@')?;
+        let (client_address, handoff_address) = addresses.split_once('|')?;
+        let bounds = bounds
+            .split(',')
+            .map(|value| value.trim().parse().ok())
+            .collect::<Option<Vec<i32>>>()?;
+        match (id.trim(), bounds.as_slice()) {
+            ("", _) => None,
+            (id, [min_x, min_y, max_x, max_y]) if min_x <= max_x && min_y <= max_y => {
+                Some(ZoneRoute {
+                    id: id.to_string(),
+                    bounds: ZoneBounds {
+                        min_x: *min_x,
+                        min_y: *min_y,
+                        max_x: *max_x,
+                        max_y: *max_y,
+                    },
+                    client_address: client_address.trim().to_string(),
+                    handoff_address: handoff_address.trim().to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ZoneConfig {
+    pub zone_id: String,
+    pub bind: String,
+    pub routes: Vec<ZoneRoute>,
+    pub handoff_ttl: Duration,
+}
+
+impl ZoneConfig {
+    pub fn from_env() -> Option<Self> {
+        let zone_id = std::env::var("ZONE_ID").ok()?;
+        let routes = parse_zone_map(&env_or("ZONE_MAP", ""));
+        let bind = match std::env::var("ZONE_HANDOFF_BIND") {
+            Ok(bind) => bind,
+            Err(_) => match routes.iter().find(|route| route.id == zone_id) {
+                Some(route) => route.handoff_address.clone(),
+                None => {
+                    eprintln!("ゾーン{}がZONE_MAPに見つかりません\n", zone_id);
+                    return None;
+                }
+            },
+        };
+        Some(ZoneConfig {
+            zone_id,
+            bind,
+            routes,
+            handoff_ttl: env_millis("ZONE_HANDOFF_TTL_MS").unwrap_or(DEFAULT_HANDOFF_TTL),
+        })
+    }
+
+    pub fn local(&self) -> Option<&ZoneRoute> {
+        self.routes.iter().find(|route| route.id == self.zone_id)
+    }
+
+    pub fn locate(&self, x: i32, y: i32) -> Option<&ZoneRoute> {
+        self.routes.iter().find(|route| route.bounds.contains(x, y))
+    }
+}
+
+pub fn parse_zone_map(input: &str) -> Vec<ZoneRoute> {
+    input
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let route = ZoneRoute::parse(entry);
+            if route.is_none() {
+                eprintln!("ゾーン定義{}を解析できませんでした\n", entry);
+            }
+            route
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_maps_parse_and_locate_positions() {
+        let routes = parse_zone_map(
+            "west=0,0,99,99@127.0.0.1:9000|127.0.0.1:9100; east=100,0,199,99@127.0.0.1:9001|127.0.0.1:9101;broken=1,2@x|y",
+        );
+        assert_eq!(routes.len(), 2);
+        assert_eq!(
+            routes[1],
+            ZoneRoute {
+                id: "east".to_string(),
+                bounds: ZoneBounds {
+                    min_x: 100,
+                    min_y: 0,
+                    max_x: 199,
+                    max_y: 99,
+                },
+                client_address: "127.0.0.1:9001".to_string(),
+                handoff_address: "127.0.0.1:9101".to_string(),
+            }
+        );
+
+        let config = ZoneConfig {
+            zone_id: "west".to_string(),
+            bind: "127.0.0.1:0".to_string(),
+            routes,
+            handoff_ttl: DEFAULT_HANDOFF_TTL,
+        };
+        assert_eq!(config.local().map(|route| route.id.as_str()), Some("west"));
+        assert_eq!(
+            config.locate(99, 50).map(|route| route.id.as_str()),
+            Some("west")
+        );
+        assert_eq!(
+            config.locate(100, 50).map(|route| route.id.as_str()),
+            Some("east")
+        );
+        assert_eq!(config.locate(100, 100), None);
+    }
+}
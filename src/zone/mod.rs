@@ -0,0 +1,6 @@
+mod command;
+mod config;
+mod node;
+pub use command::*;
+pub use config::*;
+pub use node::*;
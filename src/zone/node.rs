@@ -0,0 +1,123 @@
+use super::{ZoneConfig, ZoneRoute};
+use crate::transfer::{PlayerState, TransferConfig, TransferPeer, TransferService};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct ZoneNode {
+    config: Arc<ZoneConfig>,
+    transfer: TransferService,
+}
+
+impl ZoneNode {
+    pub fn start(config: ZoneConfig) -> std::io::Result<Self> {
+        let transfer = TransferService::start(TransferConfig {
+            server_id: config.zone_id.clone(),
+            bind: config.bind.clone(),
+            peers: config
+                .routes
+                .iter()
+                .filter(|route| route.id != config.zone_id)
+                .map(|route| TransferPeer {
+                    id: route.id.clone(),
+                    client_address: route.client_address.clone(),
+                    transfer_address: route.handoff_address.clone(),
+                })
+                .collect(),
+            ticket_ttl: config.handoff_ttl,
+        })?;
+        Ok(ZoneNode {
+            config: Arc::new(config),
+            transfer,
+        })
+    }
+
+    pub fn zone_id(&self) -> &str {
+        &self.config.zone_id
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.transfer.local_addr()
+    }
+
+    pub fn transfer(&self) -> TransferService {
+        self.transfer.clone()
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.config
+            .local()
+            .is_some_and(|route| route.bounds.contains(x, y))
+    }
+
+    pub fn locate(&self, x: i32, y: i32) -> Option<ZoneRoute> {
+        self.config.locate(x, y).cloned()
+    }
+
+    pub fn hand_off(&self, route: &ZoneRoute, player: PlayerState) -> std::io::Result<String> {
+        self.transfer.hand_off(&route.handoff_address, player)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zone::{ZoneBounds, DEFAULT_HANDOFF_TTL};
+
+    fn route(id: &str, min_x: i32, handoff_address: &str) -> ZoneRoute {
+        ZoneRoute {
+            id: id.to_string(),
+            bounds: ZoneBounds {
+                min_x,
+                min_y: 0,
+                max_x: min_x + 99,
+                max_y: 99,
+            },
+            client_address: format!("{}.example:7000", id),
+            handoff_address: handoff_address.to_string(),
+        }
+    }
+
+    fn start(zone_id: &str, routes: Vec<ZoneRoute>) -> ZoneNode {
+        ZoneNode::start(ZoneConfig {
+            zone_id: zone_id.to_string(),
+            bind: "127.0.0.1:0".to_string(),
+            routes,
+            handoff_ttl: DEFAULT_HANDOFF_TTL,
+        })
+        .expect("Failed to start the zone.")
+    }
+
+    #[test]
+    fn zones_route_positions_and_expose_neighbours_as_transfer_peers() {
+        let east = start("east", vec![route("east", 100, "unused")]);
+        let east_route = route("east", 100, &east.local_addr().to_string());
+        let west = start("west", vec![route("west", 0, "unused"), east_route.clone()]);
+        assert!(west.contains(10, 10));
+        assert!(!west.contains(150, 10));
+        assert_eq!(west.locate(150, 10), Some(east_route.clone()));
+        assert_eq!(
+            west.transfer()
+                .peer("east")
+                .map(|peer| peer.transfer_address.clone()),
+            Some(east_route.handoff_address.clone())
+        );
+        assert!(west.transfer().peer("west").is_none());
+
+        let player = PlayerState {
+            name: "alice".to_string(),
+            nickname: None,
+            account_id: None,
+            room: "lobby".to_string(),
+            position: (150, 10),
+        };
+        let token = west
+            .hand_off(&east_route, player.clone())
+            .expect("Failed to hand off.");
+        let ticket = east
+            .transfer()
+            .claim(&token)
+            .expect("The ticket was not transferred.");
+        assert_eq!((ticket.origin.as_str(), ticket.player), ("west", player));
+    }
+}
@@ -0,0 +1,84 @@
+#![cfg(all(windows, feature = "chaos"))]
+
+mod common;
+
+use common::TestServer;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+const CHURNED_CLIENTS: usize = 40;
+const RECOVERY_TIMEOUT: Duration = Duration::from_secs(20);
+
+fn churn(port: u16, client: usize) {
+    let mut stream = match TcpStream::connect(("127.0.0.1", port)) {
+        Ok(stream) => stream,
+        Err(_) => return,
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(200)));
+    let mut buffer = [0_u8; 2048];
+    for message in 0..5 {
+        if stream
+            .write_all(format!("chaos {} {}\0", client, message).as_bytes())
+            .is_err()
+        {
+            return;
+        }
+        if let Ok(0) = stream.read(&mut buffer) {
+            return;
+        }
+    }
+}
+
+fn round_trip(server: &TestServer) -> bool {
+    let mut stream = match TcpStream::connect(("127.0.0.1", server.port())) {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(500)));
+    if stream.write_all(b"still alive\0").is_err() {
+        return false;
+    }
+    let mut received = vec![];
+    let mut buffer = [0_u8; 2048];
+    while let Ok(read) = stream.read(&mut buffer) {
+        if read == 0 {
+            break;
+        }
+        received.extend_from_slice(&buffer[..read]);
+        if received
+            .split(|b| *b == 0)
+            .any(|frame| frame == b"still alive")
+        {
+            return true;
+        }
+    }
+    false
+}
+
+#[test]
+fn server_survives_dropped_connections_send_errors_and_slow_accepts() {
+    let mut server = TestServer::builder("chaos")
+        .env("CHAOS_DROP", "0.2")
+        .env("CHAOS_SEND_ERROR", "0.2")
+        .env("CHAOS_ACCEPT_DELAY_MS", "20")
+        .start();
+    let port = server.port();
+
+    let churners = (0..CHURNED_CLIENTS)
+        .map(|client| std::thread::spawn(move || churn(port, client)))
+        .collect::<Vec<_>>();
+    for churner in churners {
+        churner.join().expect("Churning client panicked.");
+    }
+
+    assert!(server.is_running(), "Server exited under chaos.");
+    let deadline = Instant::now() + RECOVERY_TIMEOUT;
+    while !round_trip(&server) {
+        assert!(
+            Instant::now() < deadline,
+            "Server stopped relaying chat after chaos."
+        );
+    }
+    assert!(server.is_running(), "Server exited under chaos.");
+}
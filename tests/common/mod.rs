@@ -0,0 +1,254 @@
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+pub const QUIET_PERIOD: Duration = Duration::from_millis(300);
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+const INHERITED_ENV: &[&str] = &["SystemRoot", "PATH", "TEMP", "TMP"];
+
+pub enum Step<'a> {
+    Send(&'a str),
+    Expect(&'a str),
+    ExpectWithin(&'a str, Duration),
+    Silent,
+    Closed,
+}
+
+pub struct TestServerBuilder {
+    name: String,
+    env: Vec<(String, String)>,
+    startup_timeout: Duration,
+}
+
+impl TestServerBuilder {
+    pub fn env(mut self, key: &str, value: impl AsRef<str>) -> Self {
+        self.env.push((key.to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    pub fn start(self) -> TestServer {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to find a free port.")
+            .port();
+        let directory = std::env::temp_dir().join(format!(
+            "ogp-test-{}-{}-{}",
+            self.name,
+            std::process::id(),
+            port
+        ));
+        std::fs::create_dir_all(&directory).expect("Failed to create test directory.");
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_online_game_programming"));
+        command.env_clear();
+        for key in INHERITED_ENV {
+            if let Some(value) = std::env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+        command
+            .env("SERVER_PORT", port.to_string())
+            .env("DATABASE_PATH", directory.join("test.db"));
+        for (key, value) in self.env.iter() {
+            command.env(key, value.replace("{dir}", &directory.to_string_lossy()));
+        }
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start server.");
+
+        let stdout = BufReader::new(child.stdout.take().expect("Server has no stdout."));
+        let (started, wait_started) = channel();
+        std::thread::spawn(move || {
+            let mut started = Some(started);
+            for line in stdout.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if line.contains("サーバーが起動しました") {
+                    if let Some(started) = started.take() {
+                        let _ = started.send(());
+                    }
+                }
+            }
+        });
+
+        let mut server = TestServer {
+            child,
+            port,
+            directory,
+        };
+        if wait_started.recv_timeout(self.startup_timeout).is_err() {
+            let status = server.child.try_wait().ok().flatten();
+            panic!(
+                "Server did not start listening within {:?} (exit status: {:?}).",
+                self.startup_timeout, status
+            );
+        }
+        server
+    }
+}
+
+pub struct TestServer {
+    child: Child,
+    port: u16,
+    directory: PathBuf,
+}
+
+impl TestServer {
+    pub fn builder(name: &str) -> TestServerBuilder {
+        TestServerBuilder {
+            name: name.to_string(),
+            env: vec![],
+            startup_timeout: STARTUP_TIMEOUT,
+        }
+    }
+
+    pub fn start(name: &str) -> TestServer {
+        TestServer::builder(name).start()
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn connect_raw(&self) -> TestClient {
+        let stream =
+            TcpStream::connect(("127.0.0.1", self.port)).expect("Failed to connect to server.");
+        stream
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .expect("Failed to set read timeout.");
+        TestClient {
+            stream,
+            pending: vec![],
+        }
+    }
+
+    pub fn connect(&self) -> TestClient {
+        let mut client = self.connect_raw();
+        client.expect("Hello");
+        client
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+pub struct TestClient {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+
+impl TestClient {
+    pub fn send(&mut self, message: &str) {
+        self.stream
+            .write_all(format!("{}\0", message).as_bytes())
+            .expect("Failed to send message.");
+    }
+
+    pub fn send_raw(&mut self, data: &[u8]) {
+        self.stream
+            .write_all(data)
+            .expect("Failed to send raw data.");
+    }
+
+    pub fn try_receive(&mut self, timeout: Duration) -> Option<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(end) = self.pending.iter().position(|b| *b == 0) {
+                let frame = self.pending.drain(..=end).collect::<Vec<_>>();
+                return Some(String::from_utf8_lossy(&frame[..end]).to_string());
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            self.stream
+                .set_read_timeout(Some(remaining.max(Duration::from_millis(1))))
+                .expect("Failed to set read timeout.");
+            let mut buffer = [0_u8; 2048];
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return None,
+                Ok(read) => self.pending.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    return None
+                }
+                Err(e) => panic!("Failed to receive message: {}", e),
+            }
+        }
+    }
+
+    pub fn receive(&mut self) -> String {
+        self.try_receive(READ_TIMEOUT)
+            .expect("Timed out waiting for a message.")
+    }
+
+    pub fn expect_within(&mut self, expected: &str, timeout: Duration) {
+        match self.try_receive(timeout) {
+            Some(message) => assert_eq!(message, expected),
+            None => panic!("Timed out after {:?} waiting for {:?}.", timeout, expected),
+        }
+    }
+
+    pub fn expect(&mut self, expected: &str) {
+        self.expect_within(expected, READ_TIMEOUT);
+    }
+
+    pub fn assert_silent(&mut self) {
+        if let Some(message) = self.try_receive(QUIET_PERIOD) {
+            panic!("Expected no message but received {:?}.", message);
+        }
+    }
+
+    pub fn is_closed(&mut self) -> bool {
+        self.stream
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .expect("Failed to set read timeout.");
+        let mut buffer = [0_u8; 64];
+        matches!(self.stream.read(&mut buffer), Ok(0) | Err(_))
+    }
+
+    pub fn script(&mut self, steps: &[Step]) {
+        for (index, step) in steps.iter().enumerate() {
+            match step {
+                Step::Send(message) => self.send(message),
+                Step::Expect(expected) => self.expect(expected),
+                Step::ExpectWithin(expected, timeout) => self.expect_within(expected, *timeout),
+                Step::Silent => self.assert_silent(),
+                Step::Closed => assert!(
+                    self.is_closed(),
+                    "Step {} expected a closed connection.",
+                    index
+                ),
+            }
+        }
+    }
+
+    pub fn close(self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
@@ -0,0 +1,102 @@
+#![cfg(windows)]
+
+mod common;
+
+use common::{Step, TestServer};
+
+#[test]
+fn chat_is_echoed_and_broadcast_to_every_client() {
+    let server = TestServer::start("broadcast");
+    let mut alice = server.connect();
+    let mut bob = server.connect();
+    let mut carol = server.connect();
+
+    alice.send("hello everyone");
+
+    assert_eq!(alice.receive(), "hello everyone");
+    assert_eq!(bob.receive(), "hello everyone");
+    assert_eq!(carol.receive(), "hello everyone");
+}
+
+#[test]
+fn late_joiner_receives_recent_history() {
+    let server = TestServer::start("history");
+    let mut alice = server.connect();
+    alice.send("first");
+    assert_eq!(alice.receive(), "first");
+
+    let mut bob = server.connect();
+    assert_eq!(bob.receive(), "0：first");
+}
+
+#[test]
+fn rooms_isolate_broadcasts() {
+    let server = TestServer::start("rooms");
+    let mut alice = server.connect();
+    let mut bob = server.connect();
+
+    alice.script(&[Step::Send(":join red"), Step::Expect("OK red")]);
+
+    bob.send("lobby only");
+    assert_eq!(bob.receive(), "lobby only");
+    alice.assert_silent();
+
+    bob.script(&[
+        Step::Send(":join red"),
+        Step::Expect("OK red"),
+        Step::Send("red team"),
+        Step::Expect("red team"),
+    ]);
+    assert_eq!(alice.receive(), "red team");
+}
+
+#[test]
+fn end_command_says_goodbye_and_closes_only_that_client() {
+    let server = TestServer::start("end");
+    let mut alice = server.connect();
+    let mut bob = server.connect();
+
+    alice.script(&[Step::Send(":end"), Step::Expect("Bye!"), Step::Closed]);
+
+    bob.send("still here");
+    assert_eq!(bob.receive(), "still here");
+    alice.assert_silent();
+}
+
+#[test]
+fn room_history_size_limits_the_backlog() {
+    let server = TestServer::builder("history-size")
+        .env("ROOM_HISTORY_SIZE", "1")
+        .start();
+    let mut alice = server.connect();
+    alice.script(&[
+        Step::Send("first"),
+        Step::Expect("first"),
+        Step::Send("second"),
+        Step::Expect("second"),
+    ]);
+
+    let mut bob = server.connect();
+    bob.script(&[Step::Expect("0：second"), Step::Silent]);
+}
+
+#[test]
+fn whispers_reach_only_their_target() {
+    let server = TestServer::start("whisper");
+    let mut alice = server.connect();
+    let mut bob = server.connect();
+    let mut carol = server.connect();
+
+    alice.send(":register alice secret");
+    assert!(alice.receive().starts_with("OK alice "));
+    bob.send(":register bob secret");
+    assert!(bob.receive().starts_with("OK bob "));
+
+    alice.script(&[
+        Step::Send(":w bob psst"),
+        Step::Expect("[Whisper -> bob #1] psst"),
+        Step::Expect("DELIVERED 1 bob"),
+    ]);
+    bob.script(&[Step::Expect("[Whisper #1] alice：psst"), Step::Silent]);
+    carol.assert_silent();
+}